@@ -1,12 +1,18 @@
 #![allow(clippy::return_self_not_must_use)]
 
-use crate::{log::LogMeta, stream::EventStream, ContractError, EthLogDecode};
+use crate::{
+    log::LogMeta,
+    stream::{EventStream, ResumableEventStream},
+    ContractError, EthLogDecode,
+};
 
 use ethers_core::{
     abi::{Detokenize, RawLog},
     types::{BlockNumber, Filter, Log, ValueOrArray, H256},
 };
-use ethers_providers::{FilterWatcher, Middleware, PubsubClient, SubscriptionStream};
+use ethers_providers::{
+    FilterWatcher, LogCheckpoint, Middleware, PubsubClient, StreamExt, SubscriptionStream,
+};
 use std::{borrow::Cow, marker::PhantomData};
 
 /// A trait for implementing event bindings
@@ -128,6 +134,24 @@ where
             self.provider.watch(&self.filter).await.map_err(ContractError::MiddlewareError)?;
         Ok(EventStream::new(filter.id, filter, Box::new(move |log| self.parse_log(log))))
     }
+
+    /// Returns a stream for the event which falls back to `eth_getLogs` and reinstalls its
+    /// filter when the node drops it. The stream starts after the checkpoint if one is given,
+    /// and its [`ResumableEventStream::checkpoint`] can be persisted to resume it later.
+    pub fn stream_resumable(
+        &'a self,
+        checkpoint: Option<LogCheckpoint>,
+    ) -> ResumableEventStream<'a, M, D, ContractError<M>> {
+        let mut stream = self.provider.watch_resumable(&self.filter);
+        if let Some(checkpoint) = checkpoint {
+            stream = stream.from_checkpoint(checkpoint);
+        }
+        ResumableEventStream::new(
+            stream,
+            Box::new(move |log| self.parse_log(log)),
+            ContractError::MiddlewareError,
+        )
+    }
 }
 
 impl<'a, M, D> Event<'a, M, D>
@@ -186,7 +210,155 @@ where
         Ok(events)
     }
 
+    /// Queries the blockchain for the selected filter in chunks of `chunk_size` blocks and
+    /// returns a vector of matching event logs. Use this instead of [`Event::query`] to
+    /// backfill ranges which are too large for a single `eth_getLogs` request, see
+    /// [`LogQuery`](ethers_providers::LogQuery) for details.
+    pub async fn query_paginated(&self, chunk_size: u64) -> Result<Vec<D>, ContractError<M>> {
+        let mut logs = self.provider.get_logs_paginated(&self.filter, chunk_size);
+        let mut events = Vec::new();
+        while let Some(log) = logs.next().await {
+            let log = log.map_err(ContractError::MiddlewareError)?;
+            events.push(self.parse_log(log)?);
+        }
+        Ok(events)
+    }
+
+    /// Queries the blockchain for the selected filter in chunks of `chunk_size` blocks and
+    /// returns a vector of logs along with their metadata, see [`Event::query_paginated`]
+    pub async fn query_paginated_with_meta(
+        &self,
+        chunk_size: u64,
+    ) -> Result<Vec<(D, LogMeta)>, ContractError<M>> {
+        let mut logs = self.provider.get_logs_paginated(&self.filter, chunk_size);
+        let mut events = Vec::new();
+        while let Some(log) = logs.next().await {
+            let log = log.map_err(ContractError::MiddlewareError)?;
+            let meta = LogMeta::from(&log);
+            events.push((self.parse_log(log)?, meta));
+        }
+        Ok(events)
+    }
+
     fn parse_log(&self, log: Log) -> Result<D, ContractError<M>> {
         D::decode_log(&RawLog { topics: log.topics, data: log.data.to_vec() }).map_err(From::from)
     }
 }
+
+#[cfg(test)]
+#[cfg(not(target_arch = "wasm32"))]
+mod tests {
+    use super::*;
+    use ethers_core::types::{Address, Bytes, U256, U64};
+    use ethers_providers::{JsonRpcError, MockProvider, Provider};
+    use serde_json::json;
+    use std::time::Duration;
+
+    /// An event with a single `uint256` in its data
+    #[derive(Debug, PartialEq)]
+    struct Value(U256);
+
+    impl EthLogDecode for Value {
+        fn decode_log(log: &RawLog) -> Result<Self, ethers_core::abi::Error> {
+            if log.data.len() != 32 {
+                return Err(ethers_core::abi::Error::InvalidData)
+            }
+            Ok(Value(U256::from_big_endian(&log.data)))
+        }
+    }
+
+    fn log(block: u64, index: u64, value: u64) -> Log {
+        let mut data = [0u8; 32];
+        U256::from(value).to_big_endian(&mut data);
+        Log {
+            address: Address::zero(),
+            topics: vec![],
+            data: Bytes::from(data.to_vec()),
+            block_hash: Some(H256::repeat_byte(block as u8)),
+            block_number: Some(block.into()),
+            transaction_hash: Some(H256::zero()),
+            transaction_index: Some(0u64.into()),
+            log_index: Some(index.into()),
+            transaction_log_index: None,
+            log_type: None,
+            removed: None,
+        }
+    }
+
+    fn value_event(
+        provider: &Provider<MockProvider>,
+        filter: Filter,
+    ) -> Event<'_, Provider<MockProvider>, Value> {
+        Event { filter, provider, datatype: PhantomData }
+    }
+
+    #[tokio::test]
+    async fn queries_paginated() {
+        let (provider, mock) = Provider::mocked();
+        mock.expect("eth_getLogs")
+            .matching(|params| params[0]["fromBlock"] == json!("0x0"))
+            .returns(vec![log(3, 0, 1)])
+            .unwrap();
+        mock.expect("eth_getLogs")
+            .matching(|params| params[0]["fromBlock"] == json!("0xa"))
+            .returns(vec![log(12, 0, 2)])
+            .unwrap();
+
+        let event = value_event(&provider, Filter::new().from_block(0u64).to_block(15u64));
+        let values = event.query_paginated(10).await.unwrap();
+        assert_eq!(values, vec![Value(1.into()), Value(2.into())]);
+
+        // a log which can not be decoded fails the query
+        mock.clear_expectations();
+        mock.expect("eth_getLogs")
+            .returns(vec![Log { data: Bytes::default(), ..log(3, 0, 1) }])
+            .unwrap();
+        let err = event.query_paginated(10).await.unwrap_err();
+        assert!(matches!(err, ContractError::DecodingError(_)));
+    }
+
+    #[tokio::test]
+    async fn streams_resumable() {
+        let (provider, mock) = Provider::mocked();
+        let provider = provider.interval(Duration::from_millis(1));
+        let filter_not_found =
+            JsonRpcError { code: -32000, message: "filter not found".to_string(), data: None };
+        mock.expect("eth_newFilter").times(1).returns(U256::from(1)).unwrap();
+        mock.expect("eth_newFilter").returns(U256::from(2)).unwrap();
+        mock.expect("eth_blockNumber").times(1).returns(U64::from(12)).unwrap();
+        mock.expect("eth_blockNumber").returns(U64::from(13)).unwrap();
+        mock.expect("eth_getLogs")
+            .matching(|params| params[0]["fromBlock"] == json!("0xa"))
+            .returns(vec![log(10, 1, 1), log(10, 2, 2), log(12, 0, 3)])
+            .unwrap();
+        mock.expect("eth_getLogs").returns(vec![log(13, 0, 4)]).unwrap();
+        mock.expect("eth_getFilterChanges").returns_error(filter_not_found);
+        mock.expect("eth_uninstallFilter").returns(true).unwrap();
+
+        // resumes after the second log of block 10
+        let event = value_event(&provider, Filter::new());
+        let checkpoint = LogCheckpoint { block_number: 10, log_index: Some(1) };
+        let mut stream = event.stream_resumable(Some(checkpoint));
+        let (value, meta) = stream.next().await.unwrap().unwrap();
+        assert_eq!(value, Value(2.into()));
+        assert_eq!(meta.block_number, 10.into());
+        assert_eq!(
+            stream.checkpoint(),
+            Some(LogCheckpoint { block_number: 10, log_index: Some(2) })
+        );
+        assert_eq!(stream.next().await.unwrap().unwrap().0, Value(3.into()));
+
+        // the filter was dropped, the stream catches up with `eth_getLogs`
+        assert_eq!(stream.next().await.unwrap().unwrap().0, Value(4.into()));
+        assert_eq!(
+            stream.checkpoint(),
+            Some(LogCheckpoint { block_number: 13, log_index: Some(0) })
+        );
+        mock.assert_request("eth_newFilter", [Filter::new()]).unwrap();
+        mock.assert_request("eth_blockNumber", ()).unwrap();
+        mock.assert_request("eth_getLogs", [Filter::new().from_block(10u64).to_block(12u64)])
+            .unwrap();
+        mock.assert_request("eth_getFilterChanges", [U256::from(1)]).unwrap();
+        mock.assert_request("eth_uninstallFilter", [U256::from(1)]).unwrap();
+    }
+}
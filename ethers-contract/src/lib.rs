@@ -19,6 +19,7 @@ mod log;
 pub use log::{decode_logs, EthLogDecode, LogMeta};
 
 mod stream;
+pub use stream::ResumableEventStream;
 
 mod multicall;
 pub use multicall::Multicall;
@@ -1,5 +1,6 @@
 use crate::LogMeta;
 use ethers_core::types::{Log, U256};
+use ethers_providers::{LogCheckpoint, Middleware, ResumableLogStream};
 use futures_util::{
     future::Either,
     stream::{Stream, StreamExt},
@@ -206,3 +207,45 @@ where
         }
     }
 }
+
+/// A stream of events with their `LogMeta` which survives the node dropping its filter and can be
+/// resumed from a `LogCheckpoint`, see `ResumableLogStream`.
+pub struct ResumableEventStream<'a, M: Middleware, R, E> {
+    stream: ResumableLogStream<'a, M>,
+    parse: MapEvent<'a, R, E>,
+    map_err: fn(M::Error) -> E,
+}
+
+impl<'a, M: Middleware, R, E> ResumableEventStream<'a, M, R, E> {
+    /// Wraps a log stream, decoding its logs with `parse` and converting the errors of the
+    /// middleware with `map_err`
+    pub fn new(
+        stream: ResumableLogStream<'a, M>,
+        parse: MapEvent<'a, R, E>,
+        map_err: fn(M::Error) -> E,
+    ) -> Self {
+        Self { stream, parse, map_err }
+    }
+
+    /// Returns the position after the last event returned by the stream, which can be persisted
+    /// to resume from it later with `Event::stream_resumable`
+    pub fn checkpoint(&self) -> Option<LogCheckpoint> {
+        self.stream.checkpoint()
+    }
+}
+
+impl<'a, M: Middleware, R, E> Stream for ResumableEventStream<'a, M, R, E> {
+    type Item = Result<(R, LogMeta), E>;
+
+    fn poll_next(self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        match futures_util::ready!(this.stream.poll_next_unpin(ctx)) {
+            Some(Ok(log)) => {
+                let meta = LogMeta::from(&log);
+                Poll::Ready(Some((this.parse)(log).map(|event| (event, meta))))
+            }
+            Some(Err(err)) => Poll::Ready(Some(Err((this.map_err)(err)))),
+            None => Poll::Ready(None),
+        }
+    }
+}
@@ -1,5 +1,12 @@
 // Taken from https://github.com/tomusdrw/rust-web3/blob/master/src/types/block.rs
 use crate::types::{Address, Bloom, Bytes, H256, U256, U64};
+#[cfg(not(feature = "celo"))]
+use crate::{
+    types::{Transaction, H64},
+    utils::{keccak256, ordered_trie_root},
+};
+#[cfg(not(feature = "celo"))]
+use rlp::RlpStream;
 use serde::{ser::SerializeStruct, Deserialize, Deserializer, Serialize, Serializer};
 use std::str::FromStr;
 
@@ -88,6 +95,51 @@ pub struct Block<TX> {
     pub epoch_snark_data: Option<EpochSnarkData>,
 }
 
+#[cfg(not(feature = "celo"))]
+impl<TX> Block<TX> {
+    /// Returns the RLP encoding of the header of the block, whose keccak256 hash is the block
+    /// hash. The base fee is only encoded for blocks after London.
+    pub fn header_rlp(&self) -> Bytes {
+        let mut rlp = RlpStream::new();
+        rlp.begin_unbounded_list();
+        rlp.append(&self.parent_hash);
+        rlp.append(&self.uncles_hash);
+        rlp.append(&self.author);
+        rlp.append(&self.state_root);
+        rlp.append(&self.transactions_root);
+        rlp.append(&self.receipts_root);
+        rlp.append(&self.logs_bloom.unwrap_or_default());
+        rlp.append(&self.difficulty);
+        rlp.append(&self.number.unwrap_or_default());
+        rlp.append(&self.gas_limit);
+        rlp.append(&self.gas_used);
+        rlp.append(&self.timestamp);
+        rlp.append(&self.extra_data.as_ref());
+        rlp.append(&self.mix_hash.unwrap_or_default());
+        rlp.append(&H64::from_low_u64_be(self.nonce.unwrap_or_default().as_u64()));
+        if let Some(ref base_fee) = self.base_fee_per_gas {
+            rlp.append(base_fee);
+        }
+        rlp.finalize_unbounded_list();
+        rlp.out().freeze().into()
+    }
+
+    /// Computes the hash of the block from its header, which matches `hash` unless the block
+    /// was tampered with
+    pub fn header_hash(&self) -> H256 {
+        keccak256(self.header_rlp()).into()
+    }
+}
+
+#[cfg(not(feature = "celo"))]
+impl Block<Transaction> {
+    /// Computes the transactions root of the block from its transactions, which matches
+    /// `transactions_root` unless the transactions were tampered with
+    pub fn compute_transactions_root(&self) -> H256 {
+        ordered_trie_root(self.transactions.iter().map(Transaction::rlp))
+    }
+}
+
 #[derive(Debug, Default, Clone, PartialEq, Deserialize, Serialize)]
 #[cfg(feature = "celo")]
 /// Commit-reveal data for generating randomness in the
@@ -241,6 +293,88 @@ mod tests {
         let _block: Block<Transaction> = serde_json::from_str(block).unwrap();
     }
 
+    #[test]
+    fn header_hash() {
+        let mut block: Block<TxHash> = serde_json::from_value(serde_json::json!({
+            "number": "0x0",
+            "hash": "0xd4e56740f876aef8c010b86a40d5f56745a118d0906a34e69aec8c0db1cb8fa3",
+            "parentHash": "0x0000000000000000000000000000000000000000000000000000000000000000",
+            "mixHash": "0x0000000000000000000000000000000000000000000000000000000000000000",
+            "nonce": "0x0000000000000042",
+            "sha3Uncles": "0x1dcc4de8dec75d7aab85b567b6ccd41ad312451b948a7413f0a142fd40d49347",
+            "logsBloom": format!("0x{}", "00".repeat(256)),
+            "transactionsRoot": "0x56e81f171bcc55a6ff8345e692c0f86e5b48e01b996cadc001622fb5e363b421",
+            "stateRoot": "0xd7f8974fb5ac78d9ac099b9ad5018bedc2ce0a72dad1827a1709da30580f0544",
+            "receiptsRoot": "0x56e81f171bcc55a6ff8345e692c0f86e5b48e01b996cadc001622fb5e363b421",
+            "miner": "0x0000000000000000000000000000000000000000",
+            "difficulty": "0x400000000",
+            "totalDifficulty": "0x400000000",
+            "extraData": "0x11bbe8db4e347b4e8c937c1c8370e4b5ed33adb3db69cbdb7a38e1e50b1b82fa",
+            "size": "0x21c",
+            "gasLimit": "0x1388",
+            "gasUsed": "0x0",
+            "timestamp": "0x0",
+            "transactions": [],
+            "uncles": []
+        }))
+        .unwrap();
+        // mainnet genesis
+        assert_eq!(Some(block.header_hash()), block.hash);
+
+        // the base fee is part of the header
+        block.base_fee_per_gas = Some(7.into());
+        assert_ne!(Some(block.header_hash()), block.hash);
+    }
+
+    #[test]
+    fn post_london_header_hash() {
+        // the example payload of the Engine API spec
+        let block: Block<TxHash> = serde_json::from_value(serde_json::json!({
+            "number": "0x1",
+            "hash": "0x3559e851470f6e7bbed1db474980683e8c315bfce99b2a6ef47c057c04de7858",
+            "parentHash": "0x3b8fb240d288781d4aac94d3fd16809ee413bc99294a085798a589dae51ddd4a",
+            "mixHash": "0x0000000000000000000000000000000000000000000000000000000000000000",
+            "nonce": "0x0000000000000000",
+            "sha3Uncles": "0x1dcc4de8dec75d7aab85b567b6ccd41ad312451b948a7413f0a142fd40d49347",
+            "logsBloom": format!("0x{}", "00".repeat(256)),
+            "transactionsRoot": "0x56e81f171bcc55a6ff8345e692c0f86e5b48e01b996cadc001622fb5e363b421",
+            "stateRoot": "0xca3149fa9e37db08d1cd49c9061db1002ef1cd58db2210f2115c8c989b2bdf45",
+            "receiptsRoot": "0x56e81f171bcc55a6ff8345e692c0f86e5b48e01b996cadc001622fb5e363b421",
+            "miner": "0xa94f5374fce5edbc8e2a8697c15331677e6ebf0b",
+            "difficulty": "0x0",
+            "extraData": "0x",
+            "gasLimit": "0x1c9c380",
+            "gasUsed": "0x0",
+            "timestamp": "0x5",
+            "baseFeePerGas": "0x7",
+            "transactions": [],
+            "uncles": []
+        }))
+        .unwrap();
+        assert_eq!(Some(block.header_hash()), block.hash);
+    }
+
+    #[test]
+    fn transactions_root() {
+        let block = Block::<Transaction>::default();
+        assert_eq!(block.compute_transactions_root(), crate::types::EMPTY_TRIE_ROOT);
+
+        let block = r#"{"number":"0x3","hash":"0xda53da08ef6a3cbde84c33e51c04f68c3853b6a3731f10baa2324968eee63972","parentHash":"0x689c70c080ca22bc0e681694fa803c1aba16a69c8b6368fed5311d279eb9de90","mixHash":"0x0000000000000000000000000000000000000000000000000000000000000000","nonce":"0x0000000000000000","sha3Uncles":"0x1dcc4de8dec75d7aab85b567b6ccd41ad312451b948a7413f0a142fd40d49347","logsBloom":"0x00000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000","transactionsRoot":"0x7270c1c4440180f2bd5215809ee3d545df042b67329499e1ab97eb759d31610d","stateRoot":"0x29f32984517a7d25607da485b23cefabfd443751422ca7e603395e1de9bc8a4b","receiptsRoot":"0x056b23fbba480696b65fe5a59b8f2148a1299103c4f57df839233af2cf4ca2d2","miner":"0x0000000000000000000000000000000000000000","difficulty":"0x0","totalDifficulty":"0x0","extraData":"0x","size":"0x3e8","gasLimit":"0x6691b7","gasUsed":"0x5208","timestamp":"0x5ecedbb9","transactions":[{"hash":"0xc3c5f700243de37ae986082fd2af88d2a7c2752a0c0f7b9d6ac47c729d45e067","nonce":"0x2","blockHash":"0xda53da08ef6a3cbde84c33e51c04f68c3853b6a3731f10baa2324968eee63972","blockNumber":"0x3","transactionIndex":"0x0","from":"0xfdcedc3bfca10ecb0890337fbdd1977aba84807a","to":"0xdca8ce283150ab773bcbeb8d38289bdb5661de1e","value":"0x0","gas":"0x15f90","gasPrice":"0x4a817c800","input":"0x","v":"0x25","r":"0x19f2694eb9113656dbea0b925e2e7ceb43df83e601c4116aee9c0dd99130be88","s":"0x73e5764b324a4f7679d890a198ba658ba1c8cd36983ff9797e10b1b89dbb448e"}],"uncles":[]}"#;
+        let mut block: Block<Transaction> = serde_json::from_str(block).unwrap();
+        assert_eq!(
+            block.compute_transactions_root(),
+            "0x7270c1c4440180f2bd5215809ee3d545df042b67329499e1ab97eb759d31610d"
+                .parse::<H256>()
+                .unwrap()
+        );
+        assert_eq!(block.compute_transactions_root(), block.transactions_root);
+        assert_eq!(Some(block.header_hash()), block.hash);
+
+        // tampering with a transaction changes the root
+        block.transactions[0].value = 1.into();
+        assert_ne!(block.compute_transactions_root(), block.transactions_root);
+    }
+
     #[test]
     // https://github.com/tomusdrw/rust-web3/commit/3a32ee962c0f2f8d50a5e25be9f2dfec7ae0750d
     fn post_london_block() {
@@ -0,0 +1,76 @@
+//! Types for `eth_callMany`, which simulates bundles of calls on top of each other
+use crate::types::{transaction::eip2718::TypedTransaction, BlockId, BlockNumber, Bytes};
+use serde::{Deserialize, Serialize};
+use std::iter::FromIterator;
+
+/// Calls executed in a single block, each on top of the state left by the previous ones
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CallBundle {
+    /// The calls of the bundle
+    pub transactions: Vec<TypedTransaction>,
+}
+
+impl<T: Into<TypedTransaction>> FromIterator<T> for CallBundle {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        Self { transactions: iter.into_iter().map(Into::into).collect() }
+    }
+}
+
+/// The state on top of which the bundles are executed
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StateContext {
+    /// The block whose state is used
+    pub block_number: BlockId,
+    /// Executes the bundles after this many transactions of the block, or after all of them
+    /// if `None`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transaction_index: Option<u64>,
+}
+
+impl From<BlockId> for StateContext {
+    fn from(block: BlockId) -> Self {
+        Self { block_number: block, transaction_index: None }
+    }
+}
+
+impl From<BlockNumber> for StateContext {
+    fn from(block: BlockNumber) -> Self {
+        BlockId::from(block).into()
+    }
+}
+
+/// The outcome of a call of `eth_callMany`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CallResponse {
+    /// The data returned by the call, if it succeeded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<Bytes>,
+    /// The reason the call failed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Address, TransactionRequest};
+    use serde_json::json;
+
+    #[test]
+    fn serde_call_many() {
+        let bundle: CallBundle =
+            vec![TransactionRequest::new().to(Address::zero())].into_iter().collect();
+        let value = serde_json::to_value(&bundle).unwrap();
+        assert_eq!(value["transactions"][0]["to"], json!(Address::zero()));
+
+        let context = StateContext::from(BlockNumber::Latest);
+        assert_eq!(serde_json::to_value(&context).unwrap(), json!({"blockNumber": "latest"}));
+
+        let responses: Vec<CallResponse> =
+            serde_json::from_value(json!([{"value": "0x01"}, {"error": "execution reverted"}]))
+                .unwrap();
+        assert_eq!(responses[0].value, Some(Bytes::from(vec![1])));
+        assert_eq!(responses[1].error.as_deref(), Some("execution reverted"));
+    }
+}
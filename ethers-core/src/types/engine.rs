@@ -0,0 +1,219 @@
+//! Types for the `engine_*` API that consensus clients use to drive execution clients
+//!
+//! https://github.com/ethereum/execution-apis/blob/main/src/engine/specification.md
+use crate::types::{Address, Bloom, Bytes, H256, H64, U256, U64};
+use serde::{Deserialize, Serialize};
+
+/// The identifier of a payload build process, returned by `engine_forkchoiceUpdatedV1`
+pub type PayloadId = H64;
+
+/// An execution block as exchanged over the Engine API (`ExecutionPayloadV1`)
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExecutionPayload {
+    /// Hash of the parent block
+    pub parent_hash: H256,
+    /// Recipient of the priority fees of the block
+    pub fee_recipient: Address,
+    /// State root after executing the block
+    pub state_root: H256,
+    /// Root of the receipts trie of the block
+    pub receipts_root: H256,
+    /// Bloom filter of the logs of the block
+    pub logs_bloom: Bloom,
+    /// The `prevRandao` value of the beacon chain, replaces the `mixHash` of the block
+    pub prev_randao: H256,
+    /// Number of the block
+    pub block_number: U64,
+    /// Gas limit of the block
+    pub gas_limit: U64,
+    /// Gas used by the transactions of the block
+    pub gas_used: U64,
+    /// Timestamp of the block
+    pub timestamp: U64,
+    /// Extra data of the block, at most 32 bytes
+    pub extra_data: Bytes,
+    /// Base fee per unit of gas of the block
+    pub base_fee_per_gas: U256,
+    /// Hash of the block
+    pub block_hash: H256,
+    /// The RLP encoded (typed envelope for EIP-2718 transactions) transactions of the block
+    pub transactions: Vec<Bytes>,
+}
+
+/// The heads of the chain as seen by the consensus client (`ForkchoiceStateV1`)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ForkchoiceState {
+    /// Hash of the head of the canonical chain
+    pub head_block_hash: H256,
+    /// Hash of the most recent safe block, or zero if unknown
+    pub safe_block_hash: H256,
+    /// Hash of the most recent finalized block, or zero before the first finalized block
+    pub finalized_block_hash: H256,
+}
+
+/// The attributes of a payload to be built on top of the new head (`PayloadAttributesV1`)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PayloadAttributes {
+    /// Timestamp of the payload
+    pub timestamp: U64,
+    /// The `prevRandao` value of the payload
+    pub prev_randao: H256,
+    /// Recipient of the priority fees of the payload
+    pub suggested_fee_recipient: Address,
+}
+
+/// The validation status of a payload
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum PayloadStatusKind {
+    /// The payload is valid
+    Valid,
+    /// The payload or one of its ancestors is invalid
+    Invalid,
+    /// The execution client is syncing and can't validate the payload yet
+    Syncing,
+    /// The payload extends a side chain and was not fully validated
+    Accepted,
+    /// The hash of the payload does not match its contents
+    InvalidBlockHash,
+}
+
+/// The result of validating a payload (`PayloadStatusV1`)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PayloadStatus {
+    /// The validation status
+    pub status: PayloadStatusKind,
+    /// Hash of the most recent valid block in the branch of the payload
+    pub latest_valid_hash: Option<H256>,
+    /// Message describing why the payload is invalid
+    #[serde(default)]
+    pub validation_error: Option<String>,
+}
+
+impl PayloadStatus {
+    /// Returns true if the payload was fully validated
+    pub fn is_valid(&self) -> bool {
+        self.status == PayloadStatusKind::Valid
+    }
+
+    /// Returns true if the payload or one of its ancestors is invalid
+    pub fn is_invalid(&self) -> bool {
+        matches!(self.status, PayloadStatusKind::Invalid | PayloadStatusKind::InvalidBlockHash)
+    }
+
+    /// Returns true if the execution client could not validate the payload yet
+    pub fn is_syncing(&self) -> bool {
+        matches!(self.status, PayloadStatusKind::Syncing | PayloadStatusKind::Accepted)
+    }
+}
+
+/// The response of `engine_forkchoiceUpdatedV1`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ForkchoiceUpdated {
+    /// The validation status of the new head
+    pub payload_status: PayloadStatus,
+    /// The identifier of the payload build process, if payload attributes were given
+    pub payload_id: Option<PayloadId>,
+}
+
+/// The merge transition parameters of a client (`TransitionConfigurationV1`)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransitionConfiguration {
+    /// The total difficulty that triggers the merge
+    pub terminal_total_difficulty: U256,
+    /// Hash of the terminal PoW block, or zero if not overridden
+    pub terminal_block_hash: H256,
+    /// Number of the terminal PoW block, or zero if not overridden
+    pub terminal_block_number: U64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn serde_execution_payload() {
+        let value = json!({
+            "parentHash": "0x3b8fb240d288781d4aac94d3fd16809ee413bc99294a085798a589dae51ddd4a",
+            "feeRecipient": "0xa94f5374fce5edbc8e2a8697c15331677e6ebf0b",
+            "stateRoot": "0xca3149fa9e37db08d1cd49c9061db1002ef1cd58db2210f2115c8c989b2bdf45",
+            "receiptsRoot": "0x56e81f171bcc55a6ff8345e692c0f86e5b48e01b996cadc001622fb5e363b421",
+            "logsBloom": format!("0x{}", "00".repeat(256)),
+            "prevRandao": "0x0000000000000000000000000000000000000000000000000000000000000000",
+            "blockNumber": "0x1",
+            "gasLimit": "0x1c9c380",
+            "gasUsed": "0x0",
+            "timestamp": "0x5",
+            "extraData": "0x",
+            "baseFeePerGas": "0x7",
+            "blockHash": "0x6359b8381a370e2f54072a5784ddd78b6ed024991558c511d4452eb4f6ac898c",
+            "transactions": ["0x02f86c"]
+        });
+        let payload: ExecutionPayload = serde_json::from_value(value.clone()).unwrap();
+        assert_eq!(payload.block_number, 1.into());
+        assert_eq!(payload.gas_limit, 30_000_000.into());
+        assert_eq!(payload.base_fee_per_gas, 7.into());
+        assert_eq!(payload.transactions, vec![Bytes::from(vec![0x02, 0xf8, 0x6c])]);
+        assert_eq!(serde_json::to_value(&payload).unwrap(), value);
+    }
+
+    #[test]
+    fn serde_forkchoice() {
+        let state = ForkchoiceState {
+            head_block_hash: H256::repeat_byte(1),
+            safe_block_hash: H256::repeat_byte(2),
+            finalized_block_hash: H256::zero(),
+        };
+        let value = serde_json::to_value(&state).unwrap();
+        assert_eq!(value["headBlockHash"], json!(format!("0x{}", "01".repeat(32))));
+        assert_eq!(value["safeBlockHash"], json!(format!("0x{}", "02".repeat(32))));
+        assert_eq!(value["finalizedBlockHash"], json!(format!("0x{}", "00".repeat(32))));
+
+        let attributes = PayloadAttributes {
+            timestamp: 5.into(),
+            prev_randao: H256::zero(),
+            suggested_fee_recipient: Address::repeat_byte(0xaa),
+        };
+        let value = serde_json::to_value(&attributes).unwrap();
+        assert_eq!(value["timestamp"], json!("0x5"));
+        assert_eq!(value["suggestedFeeRecipient"], json!(format!("0x{}", "aa".repeat(20))));
+
+        let updated: ForkchoiceUpdated = serde_json::from_value(json!({
+            "payloadStatus": {
+                "status": "VALID",
+                "latestValidHash": format!("0x{}", "01".repeat(32)),
+                "validationError": null
+            },
+            "payloadId": "0xa247243752eb10b4"
+        }))
+        .unwrap();
+        assert!(updated.payload_status.is_valid());
+        assert_eq!(updated.payload_status.latest_valid_hash, Some(H256::repeat_byte(1)));
+        assert_eq!(updated.payload_id, Some(PayloadId::from_low_u64_be(0xa247243752eb10b4)));
+    }
+
+    #[test]
+    fn deserialize_payload_status() {
+        let status: PayloadStatus = serde_json::from_value(json!({
+            "status": "INVALID_BLOCK_HASH",
+            "latestValidHash": null,
+            "validationError": "invalid block hash"
+        }))
+        .unwrap();
+        assert_eq!(status.status, PayloadStatusKind::InvalidBlockHash);
+        assert!(status.is_invalid());
+        assert_eq!(status.validation_error.as_deref(), Some("invalid block hash"));
+
+        let status: PayloadStatus =
+            serde_json::from_value(json!({"status": "SYNCING", "latestValidHash": null})).unwrap();
+        assert!(status.is_syncing());
+        assert_eq!(status.validation_error, None);
+    }
+}
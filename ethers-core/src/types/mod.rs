@@ -5,7 +5,7 @@ pub type Selector = [u8; 4];
 /// A transaction Hash
 pub use ethabi::ethereum_types::H256 as TxHash;
 
-pub use ethabi::ethereum_types::{Address, Bloom, H160, H256, H512, U128, U256, U64};
+pub use ethabi::ethereum_types::{Address, Bloom, H160, H256, H512, H64, U128, U256, U64};
 
 pub mod transaction;
 pub use transaction::{
@@ -57,3 +57,12 @@ pub use proof::*;
 
 mod fee;
 pub use fee::*;
+
+mod engine;
+pub use engine::*;
+
+mod syncing;
+pub use syncing::{SyncProgress, SyncingStatus};
+
+mod call_many;
+pub use call_many::{CallBundle, CallResponse, StateContext};
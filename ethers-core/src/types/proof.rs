@@ -1,5 +1,22 @@
-use crate::types::{Bytes, H256, U256};
+use crate::{
+    types::{Address, Bytes, H256, U256},
+    utils::keccak256,
+};
+use rlp::Rlp;
 use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// The code hash of accounts without code, `keccak256([])`
+pub const KECCAK_EMPTY: H256 = H256([
+    0xc5, 0xd2, 0x46, 0x01, 0x86, 0xf7, 0x23, 0x3c, 0x92, 0x7e, 0x7d, 0xb2, 0xdc, 0xc7, 0x03, 0xc0,
+    0xe5, 0x00, 0xb6, 0x53, 0xca, 0x82, 0x27, 0x3b, 0x7b, 0xfa, 0xd8, 0x04, 0x5d, 0x85, 0xa4, 0x70,
+]);
+
+/// The root hash of an empty Merkle-Patricia trie, `keccak256(rlp(""))`
+pub const EMPTY_TRIE_ROOT: H256 = H256([
+    0x56, 0xe8, 0x1f, 0x17, 0x1b, 0xcc, 0x55, 0xa6, 0xff, 0x83, 0x45, 0xe6, 0x92, 0xc0, 0xf8, 0x6e,
+    0x5b, 0x48, 0xe0, 0x1b, 0x99, 0x6c, 0xad, 0xc0, 0x01, 0x62, 0x2f, 0xb5, 0xe3, 0x63, 0xb4, 0x21,
+]);
 
 #[derive(Debug, Default, Clone, PartialEq, Deserialize, Serialize)]
 pub struct StorageProof {
@@ -9,11 +26,429 @@ pub struct StorageProof {
 }
 
 #[derive(Debug, Default, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
 pub struct EIP1186ProofResponse {
-    balance: U256,
-    code_hash: H256,
-    nonce: U256,
-    storage_hash: H256,
-    account_proof: Vec<Bytes>,
-    storage_proof: Vec<StorageProof>,
+    pub balance: U256,
+    pub code_hash: H256,
+    pub nonce: U256,
+    pub storage_hash: H256,
+    pub account_proof: Vec<Bytes>,
+    pub storage_proof: Vec<StorageProof>,
+}
+
+impl EIP1186ProofResponse {
+    /// Verifies the account proof of `address` against the `state_root` of a block and the
+    /// storage proofs against the `storage_hash` of the account.
+    ///
+    /// Accounts missing from the state trie are accepted if the response describes an empty
+    /// account whose storage slots are all zero.
+    pub fn verify(&self, address: Address, state_root: H256) -> Result<(), ProofError> {
+        let account = verify_proof(state_root, &keccak256(address), &self.account_proof)?;
+        match account {
+            Some(account) => {
+                let account =
+                    decode_account(&Rlp::new(&account)).map_err(|_| ProofError::InvalidAccount)?;
+                if account != (self.nonce, self.balance, self.storage_hash, self.code_hash) {
+                    return Err(ProofError::AccountMismatch)
+                }
+            }
+            None => {
+                let is_empty = self.nonce.is_zero() &&
+                    self.balance.is_zero() &&
+                    (self.code_hash == KECCAK_EMPTY || self.code_hash.is_zero()) &&
+                    (self.storage_hash == EMPTY_TRIE_ROOT || self.storage_hash.is_zero());
+                if !is_empty {
+                    return Err(ProofError::AccountMismatch)
+                }
+            }
+        }
+
+        for slot in &self.storage_proof {
+            slot.verify(self.storage_hash)?;
+        }
+        Ok(())
+    }
+}
+
+impl StorageProof {
+    /// Verifies the proof of the storage slot against the `storage_hash` of the account
+    pub fn verify(&self, storage_hash: H256) -> Result<(), ProofError> {
+        let value = if storage_hash.is_zero() && self.proof.is_empty() {
+            // non-existent accounts may report a zero storage hash
+            None
+        } else {
+            verify_proof(storage_hash, &keccak256(self.key), &self.proof)?
+        };
+        let value = match value {
+            Some(value) => {
+                decode_u256(&Rlp::new(&value)).map_err(|_| ProofError::InvalidSlot(self.key))?
+            }
+            None => U256::zero(),
+        };
+        if value != self.value {
+            return Err(ProofError::StorageMismatch(self.key))
+        }
+        Ok(())
+    }
+}
+
+/// Error thrown when verifying Merkle-Patricia trie proofs
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum ProofError {
+    /// Thrown if the proof ends before reaching the value or its absence
+    #[error("proof is missing node {0}")]
+    MissingNode(usize),
+    /// Thrown if a node of the proof does not match the hash referencing it
+    #[error("node {index} of the proof hashes to {actual:?}, expected {expected:?}")]
+    HashMismatch { index: usize, expected: H256, actual: H256 },
+    /// Thrown if a node of the proof is not a valid trie node
+    #[error("node {0} of the proof is malformed")]
+    InvalidNode(usize),
+    /// Thrown if the proven account is not RLP encoded as `[nonce, balance, storageRoot,
+    /// codeHash]`
+    #[error("proven account is malformed")]
+    InvalidAccount,
+    /// Thrown if the account state of the response does not match the proven account
+    #[error("account state does not match the proof")]
+    AccountMismatch,
+    /// Thrown if the proven value of the storage slot is not a RLP encoded integer
+    #[error("proven value of storage slot {0:?} is malformed")]
+    InvalidSlot(H256),
+    /// Thrown if the value of the storage slot does not match the proven value
+    #[error("value of storage slot {0:?} does not match the proof")]
+    StorageMismatch(H256),
+    /// Thrown if the response contains no proof for a requested storage slot
+    #[error("storage slot {0:?} is not proven")]
+    MissingSlot(H256),
+    /// Thrown if the response proves a storage slot which was not requested, or proves it more
+    /// than once
+    #[error("storage slot {0:?} was not requested")]
+    UnexpectedSlot(H256),
+}
+
+/// A reference to the next node of the trie, either its hash or the node itself when its
+/// encoding is shorter than 32 bytes
+enum NodeRef {
+    Hash(H256),
+    Inline(Vec<u8>),
+}
+
+/// Verifies a Merkle-Patricia trie proof, i.e. the nodes on the path from the `root` to `key`,
+/// and returns the value stored at `key`, or `None` if the proof shows that `key` is not part of
+/// the trie.
+///
+/// For the state and storage tries of Ethereum the key is the keccak256 hash of the address or
+/// storage slot.
+pub fn verify_proof(
+    root: H256,
+    key: &[u8],
+    proof: &[Bytes],
+) -> Result<Option<Vec<u8>>, ProofError> {
+    if root == EMPTY_TRIE_ROOT && proof.is_empty() {
+        return Ok(None)
+    }
+
+    let nibbles = to_nibbles(key);
+    let mut path = &nibbles[..];
+    let mut next = NodeRef::Hash(root);
+    let mut index = 0;
+
+    loop {
+        let node = match next {
+            NodeRef::Hash(expected) => {
+                let node = proof.get(index).ok_or(ProofError::MissingNode(index))?;
+                let actual = H256::from(keccak256(node));
+                if actual != expected {
+                    return Err(ProofError::HashMismatch { index, expected, actual })
+                }
+                index += 1;
+                node.to_vec()
+            }
+            NodeRef::Inline(node) => node,
+        };
+        // the index of the proof item holding the current node
+        let current = index - 1;
+        let invalid = |_| ProofError::InvalidNode(current);
+
+        let node = Rlp::new(&node);
+        if node.is_empty() {
+            return Ok(None)
+        }
+        match node.item_count().map_err(invalid)? {
+            // branch node
+            17 => {
+                let (nibble, rest) = match path.split_first() {
+                    Some(split) => split,
+                    None => {
+                        let value = node.at(16).and_then(|value| value.data()).map_err(invalid)?;
+                        return Ok(if value.is_empty() { None } else { Some(value.to_vec()) })
+                    }
+                };
+                path = rest;
+                next = match child(&node.at(*nibble as usize).map_err(invalid)?)
+                    .ok_or(ProofError::InvalidNode(current))?
+                {
+                    Some(child) => child,
+                    None => return Ok(None),
+                };
+            }
+            // extension or leaf node
+            2 => {
+                let encoded = node.at(0).and_then(|path| path.data()).map_err(invalid)?;
+                let (is_leaf, node_path) =
+                    decode_path(encoded).ok_or(ProofError::InvalidNode(current))?;
+                if is_leaf {
+                    if path != &node_path[..] {
+                        return Ok(None)
+                    }
+                    let value = node.at(1).and_then(|value| value.data()).map_err(invalid)?;
+                    return Ok(Some(value.to_vec()))
+                }
+                if !path.starts_with(&node_path) {
+                    return Ok(None)
+                }
+                path = &path[node_path.len()..];
+                next = match child(&node.at(1).map_err(invalid)?)
+                    .ok_or(ProofError::InvalidNode(current))?
+                {
+                    Some(child) => child,
+                    None => return Err(ProofError::InvalidNode(current)),
+                };
+            }
+            _ => return Err(ProofError::InvalidNode(current)),
+        }
+    }
+}
+
+/// Decodes a child reference of a node, `Some(None)` for empty slots of branch nodes
+fn child(item: &Rlp) -> Option<Option<NodeRef>> {
+    if item.is_list() {
+        return Some(Some(NodeRef::Inline(item.as_raw().to_vec())))
+    }
+    match item.data().ok()? {
+        [] => Some(None),
+        hash if hash.len() == 32 => Some(Some(NodeRef::Hash(H256::from_slice(hash)))),
+        _ => None,
+    }
+}
+
+/// Decodes the hex-prefix encoded path of a leaf or extension node
+fn decode_path(encoded: &[u8]) -> Option<(bool, Vec<u8>)> {
+    let (first, rest) = encoded.split_first()?;
+    let flag = first >> 4;
+    if flag > 3 || (flag & 1 == 0 && first & 0x0f != 0) {
+        return None
+    }
+    let mut nibbles = if flag & 1 == 1 { vec![first & 0x0f] } else { Vec::new() };
+    nibbles.extend(to_nibbles(rest));
+    Some((flag & 2 == 2, nibbles))
+}
+
+fn to_nibbles(bytes: &[u8]) -> Vec<u8> {
+    bytes.iter().flat_map(|byte| [byte >> 4, byte & 0x0f]).collect()
+}
+
+/// Decodes an account of the state trie into `(nonce, balance, storageRoot, codeHash)`
+fn decode_account(item: &Rlp) -> Result<(U256, U256, H256, H256), rlp::DecoderError> {
+    if item.item_count()? != 4 {
+        return Err(rlp::DecoderError::RlpIncorrectListLen)
+    }
+    Ok((
+        decode_u256(&item.at(0)?)?,
+        decode_u256(&item.at(1)?)?,
+        decode_h256(&item.at(2)?)?,
+        decode_h256(&item.at(3)?)?,
+    ))
+}
+
+fn decode_u256(item: &Rlp) -> Result<U256, rlp::DecoderError> {
+    let data = item.data()?;
+    if data.len() > 32 {
+        return Err(rlp::DecoderError::RlpIsTooBig)
+    }
+    Ok(U256::from_big_endian(data))
+}
+
+fn decode_h256(item: &Rlp) -> Result<H256, rlp::DecoderError> {
+    let data = item.data()?;
+    if data.len() != 32 {
+        return Err(rlp::DecoderError::RlpInvalidLength)
+    }
+    Ok(H256::from_slice(data))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rlp::RlpStream;
+
+    fn leaf(path: &[u8], value: &[u8]) -> Vec<u8> {
+        let mut encoded = if path.len() % 2 == 1 { vec![0x30 | path[0]] } else { vec![0x20] };
+        let path = if path.len() % 2 == 1 { &path[1..] } else { path };
+        encoded.extend(path.chunks(2).map(|pair| (pair[0] << 4) | pair[1]));
+
+        let mut stream = RlpStream::new_list(2);
+        stream.append(&encoded).append(&value);
+        stream.out().to_vec()
+    }
+
+    fn branch(children: &[(u8, H256)]) -> Vec<u8> {
+        let mut stream = RlpStream::new_list(17);
+        for nibble in 0..16 {
+            match children.iter().find(|(n, _)| *n == nibble) {
+                Some((_, hash)) => stream.append(hash),
+                None => stream.append_empty_data(),
+            };
+        }
+        stream.append_empty_data();
+        stream.out().to_vec()
+    }
+
+    fn account(nonce: u64, balance: u64, storage_hash: H256) -> Vec<u8> {
+        let mut stream = RlpStream::new_list(4);
+        stream
+            .append(&U256::from(nonce))
+            .append(&U256::from(balance))
+            .append(&storage_hash)
+            .append(&KECCAK_EMPTY);
+        stream.out().to_vec()
+    }
+
+    fn hash(node: &[u8]) -> H256 {
+        keccak256(node).into()
+    }
+
+    #[test]
+    fn constants() {
+        assert_eq!(KECCAK_EMPTY, hash(&[]));
+        assert_eq!(EMPTY_TRIE_ROOT, hash(&[0x80]));
+    }
+
+    #[test]
+    fn verifies_single_leaf() {
+        let address = Address::repeat_byte(1);
+        let key = keccak256(address);
+        let node = leaf(&to_nibbles(&key), &account(1, 100, EMPTY_TRIE_ROOT));
+        let root = hash(&node);
+
+        let mut response = EIP1186ProofResponse {
+            balance: 100.into(),
+            code_hash: KECCAK_EMPTY,
+            nonce: 1.into(),
+            storage_hash: EMPTY_TRIE_ROOT,
+            account_proof: vec![node.clone().into()],
+            storage_proof: vec![StorageProof {
+                key: H256::zero(),
+                proof: vec![],
+                value: U256::zero(),
+            }],
+        };
+        response.verify(address, root).unwrap();
+
+        assert_eq!(
+            response.verify(address, H256::zero()).unwrap_err(),
+            ProofError::HashMismatch { index: 0, expected: H256::zero(), actual: root }
+        );
+
+        response.balance = 101.into();
+        assert_eq!(response.verify(address, root).unwrap_err(), ProofError::AccountMismatch);
+
+        // the leaf does not prove anything about other accounts
+        let other = Address::repeat_byte(2);
+        assert_eq!(response.verify(other, root).unwrap_err(), ProofError::AccountMismatch);
+        let empty = EIP1186ProofResponse {
+            code_hash: KECCAK_EMPTY,
+            storage_hash: EMPTY_TRIE_ROOT,
+            account_proof: vec![node.into()],
+            ..Default::default()
+        };
+        empty.verify(other, root).unwrap();
+    }
+
+    #[test]
+    fn verifies_branches() {
+        // find two accounts in different subtries of the root
+        let first = Address::from_low_u64_be(0);
+        let first_key = to_nibbles(&keccak256(first));
+        let second = (1..)
+            .map(Address::from_low_u64_be)
+            .find(|address| keccak256(address)[0] >> 4 != first_key[0])
+            .unwrap();
+        let second_key = to_nibbles(&keccak256(second));
+
+        let first_leaf = leaf(&first_key[1..], &account(0, 1, EMPTY_TRIE_ROOT));
+        let second_leaf = leaf(&second_key[1..], &account(0, 2, EMPTY_TRIE_ROOT));
+        let root_node =
+            branch(&[(first_key[0], hash(&first_leaf)), (second_key[0], hash(&second_leaf))]);
+        let root = hash(&root_node);
+
+        let proof: Vec<Bytes> = vec![root_node.clone().into(), second_leaf.into()];
+        let value = verify_proof(root, &keccak256(second), &proof).unwrap();
+        assert_eq!(value, Some(account(0, 2, EMPTY_TRIE_ROOT)));
+
+        // swapping the leaves breaks the proof
+        let proof: Vec<Bytes> = vec![root_node.clone().into(), first_leaf.clone().into()];
+        assert!(matches!(
+            verify_proof(root, &keccak256(second), &proof),
+            Err(ProofError::HashMismatch { index: 1, .. })
+        ));
+
+        // the proof must reach the leaf
+        let proof: Vec<Bytes> = vec![root_node.clone().into()];
+        assert_eq!(
+            verify_proof(root, &keccak256(first), &proof).unwrap_err(),
+            ProofError::MissingNode(1)
+        );
+
+        // an empty slot of the branch proves the absence of the key
+        let third = (1..)
+            .map(Address::from_low_u64_be)
+            .find(|address| {
+                let nibble = keccak256(address)[0] >> 4;
+                nibble != first_key[0] && nibble != second_key[0]
+            })
+            .unwrap();
+        assert_eq!(verify_proof(root, &keccak256(third), &proof).unwrap(), None);
+    }
+
+    #[test]
+    fn verifies_storage() {
+        let slot = H256::from_low_u64_be(3);
+        let mut value = RlpStream::new();
+        value.append(&U256::from(0x1234));
+        let node = leaf(&to_nibbles(&keccak256(slot)), &value.out());
+        let storage_hash = hash(&node);
+
+        let mut proof =
+            StorageProof { key: slot, proof: vec![node.into()], value: U256::from(0x1234) };
+        proof.verify(storage_hash).unwrap();
+
+        proof.value = U256::from(0x1235);
+        assert_eq!(proof.verify(storage_hash).unwrap_err(), ProofError::StorageMismatch(slot));
+
+        let mut tampered = proof.proof[0].to_vec();
+        *tampered.last_mut().unwrap() ^= 1;
+        proof.proof = vec![tampered.into()];
+        assert!(matches!(
+            proof.verify(storage_hash).unwrap_err(),
+            ProofError::HashMismatch { index: 0, .. }
+        ));
+    }
+
+    #[test]
+    fn deserialize_proof_response() {
+        let response: EIP1186ProofResponse = serde_json::from_value(serde_json::json!({
+            "address": "0x0000000000000000000000000000000000000001",
+            "accountProof": [],
+            "balance": "0x0",
+            "codeHash": "0xc5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470",
+            "nonce": "0x0",
+            "storageHash": "0x56e81f171bcc55a6ff8345e692c0f86e5b48e01b996cadc001622fb5e363b421",
+            "storageProof": [{"key": H256::zero(), "value": "0x0", "proof": []}]
+        }))
+        .unwrap();
+        assert_eq!(response.code_hash, KECCAK_EMPTY);
+        // an empty state trie proves that every account is empty
+        response.verify(Address::zero(), EMPTY_TRIE_ROOT).unwrap();
+    }
 }
@@ -0,0 +1,103 @@
+use crate::types::U64;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+/// The response of `eth_syncing`, which is `false` if the node is not syncing
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SyncingStatus {
+    /// The node is in sync with the network
+    NotSyncing,
+    /// The node is syncing
+    Syncing(SyncProgress),
+}
+
+impl SyncingStatus {
+    /// Returns true if the node is syncing
+    pub fn is_syncing(&self) -> bool {
+        matches!(self, SyncingStatus::Syncing(_))
+    }
+}
+
+/// The progress of a syncing node
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncProgress {
+    /// The block at which the import started
+    pub starting_block: U64,
+    /// The current block
+    pub current_block: U64,
+    /// The estimated highest block
+    pub highest_block: U64,
+    /// The number of state entries processed, if the node reports it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pulled_states: Option<U64>,
+    /// The number of known state entries still to process, if the node reports it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub known_states: Option<U64>,
+}
+
+impl Serialize for SyncingStatus {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            SyncingStatus::NotSyncing => serializer.serialize_bool(false),
+            SyncingStatus::Syncing(progress) => progress.serialize(serializer),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for SyncingStatus {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum BoolOrProgress {
+            Bool(bool),
+            Progress(SyncProgress),
+        }
+
+        match BoolOrProgress::deserialize(deserializer)? {
+            BoolOrProgress::Bool(false) => Ok(SyncingStatus::NotSyncing),
+            BoolOrProgress::Bool(true) => {
+                Err(de::Error::custom("expected `false` or the sync progress"))
+            }
+            BoolOrProgress::Progress(progress) => Ok(SyncingStatus::Syncing(progress)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn serde_syncing_status() {
+        let status: SyncingStatus = serde_json::from_value(json!(false)).unwrap();
+        assert_eq!(status, SyncingStatus::NotSyncing);
+        assert_eq!(serde_json::to_value(&status).unwrap(), json!(false));
+
+        let value = json!({
+            "startingBlock": "0x384",
+            "currentBlock": "0x386",
+            "highestBlock": "0x454",
+            "pulledStates": "0x10"
+        });
+        let status: SyncingStatus = serde_json::from_value(value.clone()).unwrap();
+        assert!(status.is_syncing());
+        match status {
+            SyncingStatus::Syncing(ref progress) => {
+                assert_eq!(progress.current_block, 0x386.into());
+                assert_eq!(progress.pulled_states, Some(0x10.into()));
+                assert_eq!(progress.known_states, None);
+            }
+            _ => unreachable!(),
+        }
+        assert_eq!(serde_json::to_value(&status).unwrap(), value);
+
+        assert!(serde_json::from_value::<SyncingStatus>(json!(true)).is_err());
+    }
+}
@@ -0,0 +1,434 @@
+//! Types for the Geth `debug_trace*` API
+//!
+//! https://geth.ethereum.org/docs/rpc/ns-debug
+use crate::types::{Address, Bytes, H256, U256};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Options for the `debug_traceTransaction`, `debug_traceCall` and `debug_traceBlock*`
+/// methods. The default options return the struct logs of the opcode logger.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GethDebugTracingOptions {
+    /// Disables the storage capture of the struct logger
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub disable_storage: Option<bool>,
+    /// Disables the stack capture of the struct logger
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub disable_stack: Option<bool>,
+    /// Enables the memory capture of the struct logger
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub enable_memory: Option<bool>,
+    /// Enables the return data capture of the struct logger
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub enable_return_data: Option<bool>,
+    /// The tracer to use instead of the struct logger
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tracer: Option<GethDebugTracerType>,
+    /// The configuration of the tracer
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tracer_config: Option<GethDebugTracerConfig>,
+    /// Overrides the default timeout of 5 seconds for JavaScript-based tracers, e.g. `"10s"`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<String>,
+}
+
+impl GethDebugTracingOptions {
+    /// Options for the given built-in tracer
+    pub fn tracer(tracer: GethDebugBuiltInTracerType) -> Self {
+        Self { tracer: Some(GethDebugTracerType::BuiltInTracer(tracer)), ..Default::default() }
+    }
+
+    /// Options for a JavaScript tracer
+    pub fn js_tracer(code: impl Into<String>) -> Self {
+        Self { tracer: Some(GethDebugTracerType::JsTracer(code.into())), ..Default::default() }
+    }
+
+    /// Sets the configuration of the tracer
+    #[must_use]
+    pub fn tracer_config(mut self, config: impl Into<GethDebugTracerConfig>) -> Self {
+        self.tracer_config = Some(config.into());
+        self
+    }
+
+    /// Sets the timeout of the tracer, e.g. `"10s"`
+    #[must_use]
+    pub fn timeout(mut self, timeout: impl Into<String>) -> Self {
+        self.timeout = Some(timeout.into());
+        self
+    }
+}
+
+/// The tracer of a `debug_trace*` call
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum GethDebugTracerType {
+    /// One of the tracers built into geth
+    BuiltInTracer(GethDebugBuiltInTracerType),
+    /// The code of a JavaScript tracer
+    JsTracer(String),
+}
+
+/// The tracers built into geth
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GethDebugBuiltInTracerType {
+    /// Tracks the call frames of the transaction, returns a [`CallFrame`]
+    #[serde(rename = "callTracer")]
+    CallTracer,
+    /// Returns the accounts touched by the transaction as a [`PreStateFrame`]
+    #[serde(rename = "prestateTracer")]
+    PreStateTracer,
+    /// Counts the function selectors and calldata sizes of all calls as a [`FourByteFrame`]
+    #[serde(rename = "4byteTracer")]
+    FourByteTracer,
+    /// Returns an empty object
+    #[serde(rename = "noopTracer")]
+    NoopTracer,
+}
+
+/// The configuration of the tracer
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum GethDebugTracerConfig {
+    /// Configuration of the `callTracer`
+    CallTracer(CallConfig),
+    /// Configuration of the `prestateTracer`
+    PreStateTracer(PreStateConfig),
+}
+
+impl From<CallConfig> for GethDebugTracerConfig {
+    fn from(config: CallConfig) -> Self {
+        GethDebugTracerConfig::CallTracer(config)
+    }
+}
+
+impl From<PreStateConfig> for GethDebugTracerConfig {
+    fn from(config: PreStateConfig) -> Self {
+        GethDebugTracerConfig::PreStateTracer(config)
+    }
+}
+
+/// Configuration of the `callTracer`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CallConfig {
+    /// Only trace the top level call
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub only_top_call: Option<bool>,
+    /// Include the logs emitted by each call
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub with_log: Option<bool>,
+}
+
+/// Configuration of the `prestateTracer`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PreStateConfig {
+    /// Return the state before and after the transaction instead of only the state before
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub diff_mode: Option<bool>,
+}
+
+/// The result of a `debug_trace*` call, depending on the tracer
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum GethTrace {
+    /// The result of the struct logger or one of the built-in tracers
+    Known(GethTraceFrame),
+    /// The result of a JavaScript tracer
+    Unknown(serde_json::Value),
+}
+
+/// The result of the struct logger or one of the built-in tracers
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum GethTraceFrame {
+    /// The result of the struct logger
+    Default(DefaultFrame),
+    /// The result of the `callTracer`
+    CallTracer(CallFrame),
+    /// The result of the `4byteTracer`, or the empty result of the `noopTracer`
+    FourByteTracer(FourByteFrame),
+    /// The result of the `prestateTracer`
+    PreStateTracer(PreStateFrame),
+}
+
+/// The result of tracing a transaction of a block with `debug_traceBlock*`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GethTraceResult {
+    /// The hash of the transaction, only returned by recent versions of geth
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tx_hash: Option<H256>,
+    /// The trace, if the transaction could be traced
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<GethTrace>,
+    /// The error, if the transaction could not be traced
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// The result of the struct logger
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DefaultFrame {
+    /// Whether the transaction failed
+    pub failed: bool,
+    /// The gas used by the transaction
+    pub gas: u64,
+    /// The hex encoded return value, without `0x` prefix
+    pub return_value: String,
+    /// The executed opcodes
+    pub struct_logs: Vec<StructLog>,
+}
+
+/// An executed opcode
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StructLog {
+    /// The program counter
+    pub pc: u64,
+    /// The name of the opcode
+    pub op: String,
+    /// The remaining gas
+    pub gas: u64,
+    /// The cost of the opcode
+    pub gas_cost: u64,
+    /// The call depth
+    pub depth: u64,
+    /// The error of the opcode, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// The stack before the opcode, unless disabled
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stack: Option<Vec<U256>>,
+    /// The return data of the last call, if enabled
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub return_data: Option<Bytes>,
+    /// The memory as hex encoded 32 byte words without `0x` prefix, if enabled
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memory: Option<Vec<String>>,
+    /// The size of the memory
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mem_size: Option<u64>,
+    /// The storage slots accessed so far as hex encoded words without `0x` prefix, unless
+    /// disabled
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub storage: Option<BTreeMap<String, String>>,
+    /// The gas refund counter
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub refund: Option<u64>,
+}
+
+/// A call frame of the `callTracer`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CallFrame {
+    /// The kind of call, e.g. `CALL`, `DELEGATECALL` or `CREATE2`
+    #[serde(rename = "type")]
+    pub typ: String,
+    /// The caller
+    pub from: Address,
+    /// The callee, missing if a contract creation failed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub to: Option<Address>,
+    /// The value transferred
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<U256>,
+    /// The gas provided to the call
+    pub gas: U256,
+    /// The gas used by the call
+    pub gas_used: U256,
+    /// The calldata, or the init code of a contract creation
+    pub input: Bytes,
+    /// The returned data, or the code of a created contract
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output: Option<Bytes>,
+    /// The error, if the call failed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// The decoded revert reason, if the call reverted with a reason string
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub revert_reason: Option<String>,
+    /// The calls made by this call
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub calls: Option<Vec<CallFrame>>,
+    /// The logs emitted by this call, if `withLog` is enabled
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub logs: Option<Vec<CallLogFrame>>,
+}
+
+/// A log emitted in a call frame of the `callTracer`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CallLogFrame {
+    /// The emitter of the log
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub address: Option<Address>,
+    /// The topics of the log
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub topics: Option<Vec<H256>>,
+    /// The data of the log
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data: Option<Bytes>,
+}
+
+/// The number of calls per `<selector>-<calldata size>`, as returned by the `4byteTracer`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FourByteFrame(pub BTreeMap<String, u64>);
+
+/// The result of the `prestateTracer`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum PreStateFrame {
+    /// The state of the touched accounts before the transaction
+    Default(PreStateMode),
+    /// The state of the changed accounts before and after the transaction, if `diffMode` is
+    /// enabled
+    Diff(DiffMode),
+}
+
+/// The state of the touched accounts before the transaction
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PreStateMode(pub BTreeMap<Address, AccountState>);
+
+/// The state of the changed accounts before and after the transaction
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DiffMode {
+    /// The state before the transaction
+    pub pre: BTreeMap<Address, AccountState>,
+    /// The state after the transaction
+    pub post: BTreeMap<Address, AccountState>,
+}
+
+/// The state of an account as returned by the `prestateTracer`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AccountState {
+    /// The balance of the account
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub balance: Option<U256>,
+    /// The code of the account
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<Bytes>,
+    /// The nonce of the account
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<u64>,
+    /// The accessed storage slots
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub storage: Option<BTreeMap<H256, H256>>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn serialize_tracing_options() {
+        let opts = GethDebugTracingOptions::tracer(GethDebugBuiltInTracerType::CallTracer)
+            .tracer_config(CallConfig { only_top_call: Some(true), with_log: None })
+            .timeout("10s");
+        assert_eq!(
+            serde_json::to_value(&opts).unwrap(),
+            json!({"tracer": "callTracer", "tracerConfig": {"onlyTopCall": true}, "timeout": "10s"})
+        );
+
+        let opts = GethDebugTracingOptions { disable_storage: Some(true), ..Default::default() };
+        assert_eq!(serde_json::to_value(&opts).unwrap(), json!({"disableStorage": true}));
+
+        let opts = GethDebugTracingOptions::js_tracer("{}");
+        assert_eq!(serde_json::to_value(&opts).unwrap(), json!({"tracer": "{}"}));
+    }
+
+    #[test]
+    fn deserialize_struct_logs() {
+        let trace: GethTrace = serde_json::from_value(json!({
+            "failed": false,
+            "gas": 21000,
+            "returnValue": "",
+            "structLogs": [{
+                "pc": 0,
+                "op": "PUSH1",
+                "gas": 78868,
+                "gasCost": 3,
+                "depth": 1,
+                "stack": [],
+                "storage": {}
+            }]
+        }))
+        .unwrap();
+        match trace {
+            GethTrace::Known(GethTraceFrame::Default(frame)) => {
+                assert_eq!(frame.gas, 21000);
+                assert_eq!(frame.struct_logs[0].op, "PUSH1");
+            }
+            trace => panic!("unexpected trace {:?}", trace),
+        }
+    }
+
+    #[test]
+    fn deserialize_call_frame() {
+        let trace: GethTrace = serde_json::from_value(json!({
+            "type": "CALL",
+            "from": "0x0000000000000000000000000000000000000001",
+            "to": "0x0000000000000000000000000000000000000002",
+            "value": "0x0",
+            "gas": "0x7148",
+            "gasUsed": "0x5208",
+            "input": "0x",
+            "output": "0x",
+            "calls": [{
+                "type": "STATICCALL",
+                "from": "0x0000000000000000000000000000000000000002",
+                "to": "0x0000000000000000000000000000000000000003",
+                "gas": "0x1000",
+                "gasUsed": "0x100",
+                "input": "0x12345678",
+                "error": "execution reverted"
+            }]
+        }))
+        .unwrap();
+        match trace {
+            GethTrace::Known(GethTraceFrame::CallTracer(frame)) => {
+                assert_eq!(frame.gas_used, 0x5208.into());
+                let calls = frame.calls.unwrap();
+                assert_eq!(calls[0].typ, "STATICCALL");
+                assert_eq!(calls[0].error.as_deref(), Some("execution reverted"));
+            }
+            trace => panic!("unexpected trace {:?}", trace),
+        }
+    }
+
+    #[test]
+    fn deserialize_prestate_and_four_byte_frames() {
+        let trace: GethTrace = serde_json::from_value(json!({
+            "0x0000000000000000000000000000000000000001": {
+                "balance": "0x10",
+                "nonce": 1,
+                "code": "0x",
+                "storage": {
+                    "0x0000000000000000000000000000000000000000000000000000000000000000":
+                        "0x0000000000000000000000000000000000000000000000000000000000000001"
+                }
+            }
+        }))
+        .unwrap();
+        match trace {
+            GethTrace::Known(GethTraceFrame::PreStateTracer(PreStateFrame::Default(state))) => {
+                assert_eq!(state.0.len(), 1);
+            }
+            trace => panic!("unexpected trace {:?}", trace),
+        }
+
+        let trace: GethTrace =
+            serde_json::from_value(json!({"0x27dc297e-128": 1, "0x38cc4831-0": 2})).unwrap();
+        match trace {
+            GethTrace::Known(GethTraceFrame::FourByteTracer(counts)) => {
+                assert_eq!(counts.0["0x38cc4831-0"], 2);
+            }
+            trace => panic!("unexpected trace {:?}", trace),
+        }
+
+        let diff: PreStateFrame = serde_json::from_value(json!({"pre": {}, "post": {}})).unwrap();
+        assert_eq!(diff, PreStateFrame::Diff(DiffMode::default()));
+    }
+}
@@ -248,6 +248,39 @@ pub struct TransactionReceipt {
     pub effective_gas_price: Option<U256>,
 }
 
+impl TransactionReceipt {
+    /// Returns the encoding of the receipt committed to by the `receiptsRoot` of blocks, i.e. the
+    /// EIP-2718 envelope of typed receipts and the RLP encoding of legacy ones
+    pub fn rlp(&self) -> Bytes {
+        let mut rlp = RlpStream::new_list(4);
+        // receipts before Byzantium hold the intermediate state root instead of the status
+        match (self.status, self.root) {
+            (Some(status), _) => rlp.append(&status),
+            (None, Some(root)) => rlp.append(&root),
+            (None, None) => rlp.append_empty_data(),
+        };
+        rlp.append(&self.cumulative_gas_used);
+        rlp.append(&self.logs_bloom);
+        rlp.begin_list(self.logs.len());
+        for log in &self.logs {
+            rlp.begin_list(3);
+            rlp.append(&log.address);
+            rlp.append_list::<H256, _>(&log.topics);
+            rlp.append(&log.data.as_ref());
+        }
+
+        let rlp_bytes: Bytes = rlp.out().freeze().into();
+        match self.transaction_type {
+            Some(x) if !x.is_zero() => {
+                let mut encoded = vec![x.as_u64() as u8];
+                encoded.extend_from_slice(rlp_bytes.as_ref());
+                encoded.into()
+            }
+            _ => rlp_bytes,
+        }
+    }
+}
+
 #[cfg(test)]
 #[cfg(not(feature = "celo"))]
 mod tests {
@@ -401,4 +434,37 @@ mod tests {
             )
         );
     }
+
+    #[test]
+    fn rlp_receipts() {
+        let mut receipt = TransactionReceipt {
+            cumulative_gas_used: 0x5208.into(),
+            status: Some(1.into()),
+            transaction_type: Some(2.into()),
+            logs: vec![serde_json::from_value(serde_json::json!({
+                "address": Address::repeat_byte(0x11),
+                "topics": [H256::repeat_byte(0x22)],
+                "data": "0x0102"
+            }))
+            .unwrap()],
+            ..Default::default()
+        };
+        assert_eq!(
+            hex::encode(receipt.rlp()),
+            format!(
+                "02f9014501825208b90100{}f83cf83a94{}e1a0{}820102",
+                "00".repeat(256),
+                "11".repeat(20),
+                "22".repeat(32)
+            )
+        );
+
+        receipt.status = Some(0.into());
+        receipt.transaction_type = None;
+        receipt.logs = vec![];
+        assert_eq!(
+            hex::encode(receipt.rlp()),
+            format!("f9010880825208b90100{}c0", "00".repeat(256))
+        );
+    }
 }
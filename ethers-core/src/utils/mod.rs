@@ -19,6 +19,9 @@ pub use hash::{hash_message, id, keccak256, serialize};
 mod units;
 pub use units::Units;
 
+mod trie;
+pub use trie::{ordered_trie_root, trie_root};
+
 /// Re-export RLP
 pub use rlp;
 
@@ -0,0 +1,158 @@
+//! Root hash computation of Merkle-Patricia tries
+use super::keccak256;
+use ethabi::ethereum_types::H256;
+use rlp::RlpStream;
+
+/// Computes the root hash of the trie mapping the RLP encoded index of each item to the item,
+/// like the `transactionsRoot` and `receiptsRoot` of blocks.
+///
+/// The items must be the encoded transactions or receipts, i.e. the RLP encoding of legacy
+/// ones and the EIP-2718 envelope of typed ones.
+pub fn ordered_trie_root<I>(items: I) -> H256
+where
+    I: IntoIterator,
+    I::Item: AsRef<[u8]>,
+{
+    trie_root(
+        items.into_iter().enumerate().map(|(index, item)| (rlp::encode(&(index as u64)), item)),
+    )
+}
+
+/// Computes the root hash of the trie holding the key value pairs. Keys must be unique.
+pub fn trie_root<I, K, V>(entries: I) -> H256
+where
+    I: IntoIterator<Item = (K, V)>,
+    K: AsRef<[u8]>,
+    V: AsRef<[u8]>,
+{
+    let mut entries: Vec<(Vec<u8>, V)> =
+        entries.into_iter().map(|(key, value)| (to_nibbles(key.as_ref()), value)).collect();
+    entries.sort_by(|a, b| a.0.cmp(&b.0));
+    let entries: Vec<(&[u8], &[u8])> =
+        entries.iter().map(|(key, value)| (&key[..], value.as_ref())).collect();
+    keccak256(encode_node(&entries)).into()
+}
+
+/// Returns the RLP encoding of the node holding the entries, whose keys are relative to the node
+fn encode_node(entries: &[(&[u8], &[u8])]) -> Vec<u8> {
+    let mut stream = RlpStream::new();
+    match entries {
+        [] => {
+            stream.append_empty_data();
+        }
+        [(key, value)] => {
+            stream.begin_list(2);
+            stream.append(&hex_prefix(key, true));
+            stream.append(value);
+        }
+        _ => {
+            let prefix = common_prefix(entries);
+            if prefix > 0 {
+                let children: Vec<_> =
+                    entries.iter().map(|(key, value)| (&key[prefix..], *value)).collect();
+                stream.begin_list(2);
+                stream.append(&hex_prefix(&entries[0].0[..prefix], false));
+                append_child(&mut stream, &encode_node(&children));
+            } else {
+                stream.begin_list(17);
+                for nibble in 0..16 {
+                    let children: Vec<_> = entries
+                        .iter()
+                        .filter(|(key, _)| key.first() == Some(&nibble))
+                        .map(|(key, value)| (&key[1..], *value))
+                        .collect();
+                    if children.is_empty() {
+                        stream.append_empty_data();
+                    } else {
+                        append_child(&mut stream, &encode_node(&children));
+                    }
+                }
+                // entries are sorted, so a value stored at this node comes first
+                match entries.first() {
+                    Some((key, value)) if key.is_empty() => stream.append(value),
+                    _ => stream.append_empty_data(),
+                };
+            }
+        }
+    }
+    stream.out().to_vec()
+}
+
+/// Appends a reference to a child node, which is inlined if its encoding is shorter than 32 bytes
+fn append_child(stream: &mut RlpStream, node: &[u8]) {
+    if node.len() < 32 {
+        stream.append_raw(node, 1);
+    } else {
+        stream.append(&H256::from(keccak256(node)));
+    }
+}
+
+/// Returns the length of the common prefix of the keys
+fn common_prefix(entries: &[(&[u8], &[u8])]) -> usize {
+    let first = entries[0].0;
+    entries[1..].iter().fold(first.len(), |len, (key, _)| {
+        first[..len].iter().zip(key.iter()).take_while(|(a, b)| a == b).count()
+    })
+}
+
+/// Encodes the nibbles of the path of a leaf or extension node
+fn hex_prefix(nibbles: &[u8], is_leaf: bool) -> Vec<u8> {
+    let flag = if is_leaf { 0x20 } else { 0x00 };
+    let (mut encoded, rest) = if nibbles.len() % 2 == 1 {
+        (vec![flag | 0x10 | nibbles[0]], &nibbles[1..])
+    } else {
+        (vec![flag], nibbles)
+    };
+    encoded.extend(rest.chunks(2).map(|pair| (pair[0] << 4) | pair[1]));
+    encoded
+}
+
+fn to_nibbles(bytes: &[u8]) -> Vec<u8> {
+    bytes.iter().flat_map(|byte| [byte >> 4, byte & 0x0f]).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::EMPTY_TRIE_ROOT;
+    use std::str::FromStr;
+
+    #[test]
+    fn empty_trie() {
+        assert_eq!(trie_root(Vec::<(Vec<u8>, Vec<u8>)>::new()), EMPTY_TRIE_ROOT);
+        assert_eq!(ordered_trie_root(Vec::<Vec<u8>>::new()), EMPTY_TRIE_ROOT);
+    }
+
+    #[test]
+    fn trie_roots() {
+        // https://github.com/ethereum/tests/blob/develop/TrieTests/trieanyorder.json
+        let root = trie_root(vec![
+            ("do", "verb"),
+            ("horse", "stallion"),
+            ("doge", "coin"),
+            ("dog", "puppy"),
+        ]);
+        assert_eq!(
+            root,
+            H256::from_str("5991bb8c6514148a29db676a14ac506cd2cd5775ace63c30a4fe457715e9ac84")
+                .unwrap()
+        );
+
+        let root = trie_root(vec![("doe", "reindeer"), ("dog", "puppy"), ("dogglesworth", "cat")]);
+        assert_eq!(
+            root,
+            H256::from_str("8aad789dff2f538bca5d8ea56e8abe10f4c7ba3a5dea95fea4cd6e7c3a1168d3")
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn ordered_root_matches_keyed_root() {
+        let items: Vec<Vec<u8>> = (0..200u64).map(|i| vec![i as u8; (i % 40) as usize]).collect();
+        let keyed = trie_root(
+            items.iter().enumerate().map(|(index, item)| (rlp::encode(&(index as u64)), item)),
+        );
+        assert_eq!(ordered_trie_root(&items), keyed);
+        assert_ne!(ordered_trie_root(&items[1..]), keyed);
+    }
+}
@@ -34,6 +34,13 @@ pub use policy::PolicyMiddleware;
 pub mod timelag;
 pub use timelag::TimeLag;
 
+/// The [VerifyingMiddleware](crate::VerifyingMiddleware) verifies blocks, transactions and
+/// receipts against their hashes instead of trusting the node
+#[cfg(not(feature = "celo"))]
+pub mod verifying;
+#[cfg(not(feature = "celo"))]
+pub use verifying::VerifyingMiddleware;
+
 #[cfg(feature = "forge")]
 pub mod forge;
 #[cfg(feature = "forge")]
@@ -223,12 +223,13 @@ where
 
         // if we have a nonce manager set, we should try handling the result in
         // case there was a nonce mismatch
-        let signed_tx = self.sign_transaction(tx).await?;
+        let signed_tx = self.sign_transaction(tx.clone()).await?;
 
-        // Submit the raw transaction
+        // Submit the raw transaction, the pending transaction detects replacements by its nonce
         self.inner
             .send_raw_transaction(signed_tx)
             .await
+            .map(|pending| pending.request(&tx))
             .map_err(SignerMiddlewareError::MiddlewareError)
     }
 
@@ -275,13 +275,12 @@ where
         self.inner().fill_transaction(tx, block).await.map_err(ethers_providers::FromErr::from)
     }
 
-    async fn get_block_receipts<T: Into<BlockNumber> + Send + Sync>(
+    async fn get_block_receipts<T: Into<BlockId> + Send + Sync>(
         &self,
         block: T,
-    ) -> Result<Vec<TransactionReceipt>, Self::Error> {
-        let block: BlockNumber = block.into();
+    ) -> Result<Option<Vec<TransactionReceipt>>, Self::Error> {
         let block = self
-            .normalize_block_number(Some(block))
+            .normalize_block_id(Some(block.into()))
             .await?
             .expect("Cannot return None if Some is passed in");
 
@@ -0,0 +1,284 @@
+use async_trait::async_trait;
+use ethers_core::{
+    types::{Block, BlockId, BlockNumber, Transaction, TransactionReceipt, TxHash, H256, U64},
+    utils::ordered_trie_root,
+};
+use ethers_providers::{FromErr, Middleware};
+use thiserror::Error;
+
+/// Middleware which verifies the blocks, transactions and receipts returned by the node
+/// against their hashes instead of trusting the node.
+///
+/// - Block headers are hashed locally and must match the block hash, and the block that was asked
+///   for.
+/// - Transactions are hashed locally and must match their hash. The transactions of blocks fetched
+///   with [`Middleware::get_block_with_txs`] must match the `transactions_root`.
+/// - Block receipts must match the `receipts_root` of their block. Receipts returned by
+///   [`Middleware::get_transaction_receipt`] are checked the same way, which fetches the
+///   transactions and all receipts of the block.
+///
+/// Hashes only prove the integrity of the response, the block hashes themselves should be
+/// checked against a trusted source, e.g. a consensus client.
+///
+/// ```no_run
+/// use ethers_middleware::VerifyingMiddleware;
+/// use ethers_providers::{Middleware, Provider, Http};
+/// use std::convert::TryFrom;
+///
+/// # async fn foo() -> Result<(), Box<dyn std::error::Error>> {
+/// let provider = Provider::<Http>::try_from("http://localhost:8545")?;
+/// let provider = VerifyingMiddleware::new(provider);
+///
+/// let block = provider.get_block_with_txs(1u64).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct VerifyingMiddleware<M> {
+    inner: M,
+}
+
+impl<M> VerifyingMiddleware<M>
+where
+    M: Middleware,
+{
+    /// Verifies the responses of the inner middleware
+    pub fn new(inner: M) -> Self {
+        Self { inner }
+    }
+
+    /// Checks the hash of the header and that it is the requested block
+    fn verify_header<TX>(
+        block: &Block<TX>,
+        id: BlockId,
+    ) -> Result<(), VerifyingMiddlewareError<M>> {
+        let hash = match (block.hash, id) {
+            (Some(hash), _) => hash,
+            // pending blocks have no hash yet
+            (None, BlockId::Number(BlockNumber::Pending)) => return Ok(()),
+            (None, _) => return Err(VerifyingMiddlewareError::UnexpectedBlock(id)),
+        };
+        let computed = block.header_hash();
+        if computed != hash {
+            return Err(VerifyingMiddlewareError::BlockHashMismatch { expected: hash, computed })
+        }
+
+        let expected = match id {
+            BlockId::Hash(expected) => hash == expected,
+            BlockId::Number(BlockNumber::Number(number)) => block.number == Some(number),
+            BlockId::Number(BlockNumber::Earliest) => block.number == Some(U64::zero()),
+            BlockId::Number(_) => true,
+        };
+        if !expected {
+            return Err(VerifyingMiddlewareError::UnexpectedBlock(id))
+        }
+        Ok(())
+    }
+
+    /// Checks that the transaction hashes to its hash
+    fn verify_transaction(tx: &Transaction) -> Result<(), VerifyingMiddlewareError<M>> {
+        let computed = tx.hash();
+        if computed != tx.hash {
+            return Err(VerifyingMiddlewareError::TransactionHashMismatch {
+                expected: tx.hash,
+                computed,
+            })
+        }
+        Ok(())
+    }
+
+    /// Fetches the receipts of the verified block and checks them against its receipts root
+    async fn verified_receipts(
+        &self,
+        block: &Block<Transaction>,
+    ) -> Result<Vec<TransactionReceipt>, VerifyingMiddlewareError<M>> {
+        let hash = block.hash.unwrap_or_default();
+        let number =
+            block.number.ok_or(VerifyingMiddlewareError::BlockNotFound(BlockId::Hash(hash)))?;
+        let receipts = self
+            .inner
+            .get_block_receipts(number)
+            .await
+            .map_err(FromErr::from)?
+            .ok_or(VerifyingMiddlewareError::BlockNotFound(BlockId::Hash(hash)))?;
+        if ordered_trie_root(receipts.iter().map(TransactionReceipt::rlp)) != block.receipts_root {
+            return Err(VerifyingMiddlewareError::ReceiptsRootMismatch(hash))
+        }
+        Ok(receipts)
+    }
+}
+
+#[derive(Error, Debug)]
+/// Thrown when the response of the node could not be verified
+pub enum VerifyingMiddlewareError<M: Middleware> {
+    /// Thrown when the internal middleware errors
+    #[error("{0}")]
+    MiddlewareError(M::Error),
+
+    /// Thrown when the hash of a block does not match the hash of its header
+    #[error("header of block {expected:?} hashes to {computed:?}")]
+    BlockHashMismatch { expected: H256, computed: H256 },
+
+    /// Thrown when the node returned another block than the requested one
+    #[error("node returned another block than {0:?}")]
+    UnexpectedBlock(BlockId),
+
+    /// Thrown when the block needed to verify a response was not found
+    #[error("block {0:?} not found")]
+    BlockNotFound(BlockId),
+
+    /// Thrown when the hash of a transaction does not match its contents
+    #[error("transaction {expected:?} hashes to {computed:?}")]
+    TransactionHashMismatch { expected: H256, computed: H256 },
+
+    /// Thrown when the transactions of a block do not match its transactions root
+    #[error("transactions of block {0:?} do not match its transactions root")]
+    TransactionsRootMismatch(H256),
+
+    /// Thrown when the receipts of a block do not match its receipts root
+    #[error("receipts of block {0:?} do not match its receipts root")]
+    ReceiptsRootMismatch(H256),
+
+    /// Thrown when the receipt of a transaction is not the one included in its block
+    #[error("receipt of transaction {0:?} does not match its block")]
+    ReceiptMismatch(H256),
+}
+
+impl<M: Middleware> FromErr<M::Error> for VerifyingMiddlewareError<M> {
+    fn from(src: M::Error) -> Self {
+        VerifyingMiddlewareError::MiddlewareError(src)
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl<M> Middleware for VerifyingMiddleware<M>
+where
+    M: Middleware,
+{
+    type Error = VerifyingMiddlewareError<M>;
+    type Provider = M::Provider;
+    type Inner = M;
+
+    fn inner(&self) -> &M {
+        &self.inner
+    }
+
+    async fn get_block<T: Into<BlockId> + Send + Sync>(
+        &self,
+        block_hash_or_number: T,
+    ) -> Result<Option<Block<TxHash>>, Self::Error> {
+        let id = block_hash_or_number.into();
+        let block = self.inner.get_block(id).await.map_err(FromErr::from)?;
+        if let Some(ref block) = block {
+            Self::verify_header(block, id)?;
+        }
+        Ok(block)
+    }
+
+    async fn get_block_with_txs<T: Into<BlockId> + Send + Sync>(
+        &self,
+        block_hash_or_number: T,
+    ) -> Result<Option<Block<Transaction>>, Self::Error> {
+        let id = block_hash_or_number.into();
+        let block = self.inner.get_block_with_txs(id).await.map_err(FromErr::from)?;
+        if let Some(ref block) = block {
+            Self::verify_header(block, id)?;
+            for tx in &block.transactions {
+                Self::verify_transaction(tx)?;
+            }
+            if block.compute_transactions_root() != block.transactions_root {
+                return Err(VerifyingMiddlewareError::TransactionsRootMismatch(
+                    block.hash.unwrap_or_default(),
+                ))
+            }
+        }
+        Ok(block)
+    }
+
+    async fn get_uncle<T: Into<BlockId> + Send + Sync>(
+        &self,
+        block_hash_or_number: T,
+        idx: U64,
+    ) -> Result<Option<Block<H256>>, Self::Error> {
+        let uncle = self.inner.get_uncle(block_hash_or_number, idx).await.map_err(FromErr::from)?;
+        if let Some(ref uncle) = uncle {
+            let hash = uncle.hash.unwrap_or_default();
+            Self::verify_header(uncle, BlockId::Hash(hash))?;
+        }
+        Ok(uncle)
+    }
+
+    async fn get_transaction<T: Send + Sync + Into<TxHash>>(
+        &self,
+        transaction_hash: T,
+    ) -> Result<Option<Transaction>, Self::Error> {
+        let hash = transaction_hash.into();
+        let tx = self.inner.get_transaction(hash).await.map_err(FromErr::from)?;
+        if let Some(ref tx) = tx {
+            if tx.hash != hash {
+                return Err(VerifyingMiddlewareError::TransactionHashMismatch {
+                    expected: hash,
+                    computed: tx.hash,
+                })
+            }
+            Self::verify_transaction(tx)?;
+        }
+        Ok(tx)
+    }
+
+    async fn get_transaction_receipt<T: Send + Sync + Into<TxHash>>(
+        &self,
+        transaction_hash: T,
+    ) -> Result<Option<TransactionReceipt>, Self::Error> {
+        let hash = transaction_hash.into();
+        let receipt = match self.inner.get_transaction_receipt(hash).await.map_err(FromErr::from)? {
+            Some(receipt) => receipt,
+            None => return Ok(None),
+        };
+        let block_hash =
+            receipt.block_hash.ok_or(VerifyingMiddlewareError::ReceiptMismatch(hash))?;
+        let block = self
+            .get_block_with_txs(block_hash)
+            .await?
+            .ok_or(VerifyingMiddlewareError::BlockNotFound(BlockId::Hash(block_hash)))?;
+
+        // the receipts root only commits to the position of the receipt in the block
+        let index = receipt.transaction_index.as_usize();
+        if block.transactions.get(index).map(|tx| tx.hash) != Some(hash) {
+            return Err(VerifyingMiddlewareError::ReceiptMismatch(hash))
+        }
+        let receipts = self.verified_receipts(&block).await?;
+        if receipts.get(index).map(TransactionReceipt::rlp) != Some(receipt.rlp()) {
+            return Err(VerifyingMiddlewareError::ReceiptMismatch(hash))
+        }
+        Ok(Some(receipt))
+    }
+
+    async fn get_block_receipts<T: Into<BlockId> + Send + Sync>(
+        &self,
+        block: T,
+    ) -> Result<Option<Vec<TransactionReceipt>>, Self::Error> {
+        let block = block.into();
+        let receipts = match self.inner.get_block_receipts(block).await.map_err(FromErr::from)? {
+            Some(receipts) => receipts,
+            None => return Ok(None),
+        };
+
+        // check the receipts against the block they claim to be from
+        let id = match receipts.first().and_then(|receipt| receipt.block_hash) {
+            Some(hash) => BlockId::Hash(hash),
+            None => block,
+        };
+        let header =
+            self.get_block(id).await?.ok_or(VerifyingMiddlewareError::BlockNotFound(id))?;
+        Self::verify_header(&header, block)?;
+
+        if ordered_trie_root(receipts.iter().map(TransactionReceipt::rlp)) != header.receipts_root {
+            return Err(VerifyingMiddlewareError::ReceiptsRootMismatch(
+                header.hash.unwrap_or_default(),
+            ))
+        }
+        Ok(Some(receipts))
+    }
+}
@@ -0,0 +1,146 @@
+#![cfg(not(target_arch = "wasm32"))]
+#![cfg(not(feature = "celo"))]
+use ethers_core::{
+    types::{Block, Transaction, TransactionReceipt, H256, U256},
+    utils::ordered_trie_root,
+};
+use ethers_middleware::{verifying::VerifyingMiddlewareError, VerifyingMiddleware};
+use ethers_providers::{Middleware, MockProvider, Provider};
+use serde_json::json;
+
+/// Returns a block with a single transaction and its receipts, with valid hashes and roots
+fn block() -> (Block<Transaction>, Vec<TransactionReceipt>) {
+    let mut tx = Transaction {
+        nonce: 1.into(),
+        gas: 21_000.into(),
+        gas_price: Some(1.into()),
+        value: 100.into(),
+        v: 27.into(),
+        r: 1.into(),
+        s: 1.into(),
+        transaction_index: Some(0.into()),
+        ..Default::default()
+    };
+    tx.hash = tx.hash();
+    let mut receipt = TransactionReceipt {
+        transaction_hash: tx.hash,
+        cumulative_gas_used: 21_000.into(),
+        status: Some(1.into()),
+        ..Default::default()
+    };
+
+    let mut block = Block {
+        number: Some(1.into()),
+        gas_limit: 30_000_000.into(),
+        gas_used: 21_000.into(),
+        transactions: vec![tx],
+        ..Default::default()
+    };
+    block.transactions_root = block.compute_transactions_root();
+    block.receipts_root = ordered_trie_root(&[receipt.rlp()]);
+    block.hash = Some(block.header_hash());
+
+    block.transactions[0].block_hash = block.hash;
+    receipt.block_hash = block.hash;
+    receipt.block_number = block.number;
+    (block, vec![receipt])
+}
+
+/// Responds to the requests of `method` with the block, with or without its transactions
+fn serve_block(mock: &MockProvider, method: &str, block: &Block<Transaction>) {
+    let mut header = serde_json::to_value(block).unwrap();
+    let hashes: Vec<H256> = block.transactions.iter().map(|tx| tx.hash).collect();
+    header["transactions"] = json!(hashes);
+    mock.expect(method).matching(|params| params[1] == json!(false)).returns(header).unwrap();
+    mock.expect(method).matching(|params| params[1] == json!(true)).returns(block).unwrap();
+}
+
+#[tokio::test]
+async fn verifies_blocks() {
+    let (provider, mock) = Provider::mocked();
+    let provider = VerifyingMiddleware::new(provider);
+    let (block, _) = block();
+    let hash = block.hash.unwrap();
+
+    serve_block(&mock, "eth_getBlockByHash", &block);
+    assert_eq!(provider.get_block_with_txs(hash).await.unwrap(), Some(block.clone()));
+    assert_eq!(provider.get_block(hash).await.unwrap().unwrap().hash, Some(hash));
+
+    // the node returns a block with another state root
+    let mut tampered = block.clone();
+    tampered.state_root = H256::repeat_byte(1);
+    mock.clear_expectations();
+    serve_block(&mock, "eth_getBlockByHash", &tampered);
+    let err = provider.get_block(hash).await.unwrap_err();
+    match err {
+        VerifyingMiddlewareError::BlockHashMismatch { expected, .. } => assert_eq!(expected, hash),
+        err => panic!("unexpected error {:?}", err),
+    }
+
+    // the node returns another valid block
+    mock.clear_expectations();
+    serve_block(&mock, "eth_getBlockByNumber", &block);
+    let err = provider.get_block(2u64).await.unwrap_err();
+    assert!(matches!(err, VerifyingMiddlewareError::UnexpectedBlock(_)));
+}
+
+#[tokio::test]
+async fn verifies_transactions() {
+    let (provider, mock) = Provider::mocked();
+    let provider = VerifyingMiddleware::new(provider);
+    let (block, _) = block();
+    let hash = block.hash.unwrap();
+
+    // the transaction does not match its hash
+    let mut tampered = block.clone();
+    tampered.transactions[0].value = U256::from(1_000_000);
+    serve_block(&mock, "eth_getBlockByHash", &tampered);
+    let err = provider.get_block_with_txs(hash).await.unwrap_err();
+    assert!(matches!(err, VerifyingMiddlewareError::TransactionHashMismatch { .. }));
+
+    // the transaction matches its hash, but not the transactions root of the block
+    tampered.transactions[0].hash = tampered.transactions[0].hash();
+    mock.clear_expectations();
+    serve_block(&mock, "eth_getBlockByHash", &tampered);
+    let err = provider.get_block_with_txs(hash).await.unwrap_err();
+    assert!(matches!(err, VerifyingMiddlewareError::TransactionsRootMismatch(h) if h == hash));
+
+    // the node returns another transaction
+    let tx = tampered.transactions[0].clone();
+    mock.expect("eth_getTransactionByHash").returns(&tx).unwrap();
+    let err = provider.get_transaction(block.transactions[0].hash).await.unwrap_err();
+    assert!(matches!(err, VerifyingMiddlewareError::TransactionHashMismatch { .. }));
+}
+
+#[tokio::test]
+async fn verifies_receipts() {
+    let (provider, mock) = Provider::mocked();
+    let provider = VerifyingMiddleware::new(provider);
+    let (block, receipts) = block();
+    let tx_hash = block.transactions[0].hash;
+
+    serve_block(&mock, "eth_getBlockByHash", &block);
+    mock.expect("eth_getBlockReceipts").returns(&receipts).unwrap();
+    mock.expect("eth_getTransactionReceipt").times(1).returns(&receipts[0]).unwrap();
+    assert_eq!(provider.get_block_receipts(1u64).await.unwrap(), Some(receipts.clone()));
+    let receipt = provider.get_transaction_receipt(tx_hash).await.unwrap();
+    assert_eq!(receipt.as_ref(), receipts.first());
+
+    // the node returns a failed receipt, which is not the one committed to by the block
+    let mut tampered = receipts[0].clone();
+    tampered.status = Some(0.into());
+    mock.expect("eth_getTransactionReceipt").returns(&tampered).unwrap();
+    let err = provider.get_transaction_receipt(tx_hash).await.unwrap_err();
+    assert!(matches!(err, VerifyingMiddlewareError::ReceiptMismatch(h) if h == tx_hash));
+
+    mock.clear_expectations();
+    serve_block(&mock, "eth_getBlockByHash", &block);
+    mock.expect("eth_getBlockReceipts").returns(vec![tampered]).unwrap();
+    let err = provider.get_block_receipts(1u64).await.unwrap_err();
+    assert!(matches!(err, VerifyingMiddlewareError::ReceiptsRootMismatch(_)));
+
+    // unknown blocks have nothing to verify
+    mock.clear_expectations();
+    mock.expect("eth_getBlockReceipts").returns(()).unwrap();
+    assert_eq!(provider.get_block_receipts(2u64).await.unwrap(), None);
+}
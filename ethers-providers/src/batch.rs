@@ -0,0 +1,377 @@
+use crate::{JsonRpcClient, JsonRpcError, Provider, ProviderError};
+
+use ethers_core::{
+    types::{
+        transaction::eip2718::TypedTransaction, Address, Block, BlockId, BlockNumber, Bytes,
+        Transaction, TransactionReceipt, TxHash, H256, U256, U64,
+    },
+    utils,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+use std::{fmt, marker::PhantomData};
+use tracing::trace;
+use tracing_futures::Instrument;
+
+/// A builder for a batch of JSON-RPC requests which are sent to the node in a single round
+/// trip, if the transport supports it.
+///
+/// Every request added to the batch returns a [`BatchItem`], which is used to retrieve the
+/// typed result of that request from the [`BatchResponse`].
+///
+/// # Example
+///
+/// ```no_run
+/// use ethers_providers::{Http, Provider};
+/// use ethers_core::types::{Address, BlockNumber};
+/// use std::convert::TryFrom;
+///
+/// # async fn foo() -> Result<(), Box<dyn std::error::Error>> {
+/// let provider = Provider::<Http>::try_from("http://localhost:8545")?;
+/// let accounts: Vec<Address> = vec![Address::zero(), Address::repeat_byte(1)];
+///
+/// let mut batch = provider.batch();
+/// let block = batch.get_block_number()?;
+/// let balances = accounts
+///     .iter()
+///     .map(|account| batch.get_balance(*account, None))
+///     .collect::<Result<Vec<_>, _>>()?;
+/// let response = batch.send().await?;
+///
+/// let block = response.get(&block)?;
+/// for (account, balance) in accounts.iter().zip(balances) {
+///     println!("{:?} has {} wei at block {}", account, response.get(&balance)?, block);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[must_use = "batches do nothing unless sent"]
+pub struct BatchRequest<'a, P> {
+    provider: &'a Provider<P>,
+    requests: Vec<(String, Value)>,
+}
+
+impl<'a, P: JsonRpcClient> BatchRequest<'a, P> {
+    /// Creates an empty batch for the provider
+    pub fn new(provider: &'a Provider<P>) -> Self {
+        Self { provider, requests: Vec::new() }
+    }
+
+    /// Returns the number of requests in the batch
+    pub fn len(&self) -> usize {
+        self.requests.len()
+    }
+
+    /// Returns true if no requests were added to the batch
+    pub fn is_empty(&self) -> bool {
+        self.requests.is_empty()
+    }
+
+    /// Adds a request with the provided JSON-RPC method and parameters to the batch. The
+    /// response of the request will be deserialized as `R`.
+    pub fn add_request<T, R>(
+        &mut self,
+        method: &str,
+        params: T,
+    ) -> Result<BatchItem<R>, ProviderError>
+    where
+        T: Serialize,
+        R: DeserializeOwned,
+    {
+        let params = match serde_json::to_value(params)? {
+            // requests without params are sent with an empty array, since not all nodes
+            // accept `null`
+            Value::Null => Value::Array(Vec::new()),
+            params => params,
+        };
+        self.requests.push((method.to_string(), params));
+        Ok(BatchItem { idx: self.requests.len() - 1, ret: PhantomData })
+    }
+
+    /// Adds an `eth_blockNumber` request to the batch
+    pub fn get_block_number(&mut self) -> Result<BatchItem<U64>, ProviderError> {
+        self.add_request("eth_blockNumber", ())
+    }
+
+    /// Adds a request for the block at `block_hash_or_number` (transaction hashes only)
+    pub fn get_block<T: Into<BlockId>>(
+        &mut self,
+        block_hash_or_number: T,
+    ) -> Result<BatchItem<Option<Block<TxHash>>>, ProviderError> {
+        self.get_block_gen(block_hash_or_number.into(), false)
+    }
+
+    /// Adds a request for the block at `block_hash_or_number` (full transactions included)
+    pub fn get_block_with_txs<T: Into<BlockId>>(
+        &mut self,
+        block_hash_or_number: T,
+    ) -> Result<BatchItem<Option<Block<Transaction>>>, ProviderError> {
+        self.get_block_gen(block_hash_or_number.into(), true)
+    }
+
+    fn get_block_gen<Tx: DeserializeOwned>(
+        &mut self,
+        id: BlockId,
+        include_txs: bool,
+    ) -> Result<BatchItem<Option<Block<Tx>>>, ProviderError> {
+        let include_txs = utils::serialize(&include_txs);
+        match id {
+            BlockId::Hash(hash) => {
+                let hash = utils::serialize(&hash);
+                self.add_request("eth_getBlockByHash", [hash, include_txs])
+            }
+            BlockId::Number(num) => {
+                let num = utils::serialize(&num);
+                self.add_request("eth_getBlockByNumber", [num, include_txs])
+            }
+        }
+    }
+
+    /// Adds a request for the transaction with `transaction_hash`
+    pub fn get_transaction<T: Into<TxHash>>(
+        &mut self,
+        transaction_hash: T,
+    ) -> Result<BatchItem<Option<Transaction>>, ProviderError> {
+        self.add_request("eth_getTransactionByHash", [transaction_hash.into()])
+    }
+
+    /// Adds a request for the receipt of the transaction with `transaction_hash`
+    pub fn get_transaction_receipt<T: Into<TxHash>>(
+        &mut self,
+        transaction_hash: T,
+    ) -> Result<BatchItem<Option<TransactionReceipt>>, ProviderError> {
+        self.add_request("eth_getTransactionReceipt", [transaction_hash.into()])
+    }
+
+    /// Adds a request for the balance of `from`
+    pub fn get_balance<T: Into<Address>>(
+        &mut self,
+        from: T,
+        block: Option<BlockId>,
+    ) -> Result<BatchItem<U256>, ProviderError> {
+        let from = utils::serialize(&from.into());
+        let block = utils::serialize(&block.unwrap_or_else(|| BlockNumber::Latest.into()));
+        self.add_request("eth_getBalance", [from, block])
+    }
+
+    /// Adds a request for the nonce of `from`
+    pub fn get_transaction_count<T: Into<Address>>(
+        &mut self,
+        from: T,
+        block: Option<BlockId>,
+    ) -> Result<BatchItem<U256>, ProviderError> {
+        let from = utils::serialize(&from.into());
+        let block = utils::serialize(&block.unwrap_or_else(|| BlockNumber::Latest.into()));
+        self.add_request("eth_getTransactionCount", [from, block])
+    }
+
+    /// Adds a request for the deployed code at `at`
+    pub fn get_code<T: Into<Address>>(
+        &mut self,
+        at: T,
+        block: Option<BlockId>,
+    ) -> Result<BatchItem<Bytes>, ProviderError> {
+        let at = utils::serialize(&at.into());
+        let block = utils::serialize(&block.unwrap_or_else(|| BlockNumber::Latest.into()));
+        self.add_request("eth_getCode", [at, block])
+    }
+
+    /// Adds a request for the storage of `from` at the slot `location`
+    pub fn get_storage_at<T: Into<Address>>(
+        &mut self,
+        from: T,
+        location: H256,
+        block: Option<BlockId>,
+    ) -> Result<BatchItem<H256>, ProviderError> {
+        let from = utils::serialize(&from.into());
+        let location = utils::serialize(&location);
+        let block = utils::serialize(&block.unwrap_or_else(|| BlockNumber::Latest.into()));
+        self.add_request("eth_getStorageAt", [from, location, block])
+    }
+
+    /// Adds an `eth_call` of the read-only transaction to the batch
+    pub fn call(
+        &mut self,
+        tx: &TypedTransaction,
+        block: Option<BlockId>,
+    ) -> Result<BatchItem<Bytes>, ProviderError> {
+        let tx = utils::serialize(tx);
+        let block = utils::serialize(&block.unwrap_or_else(|| BlockNumber::Latest.into()));
+        self.add_request("eth_call", [tx, block])
+    }
+
+    /// Sends all requests of the batch
+    pub async fn send(self) -> Result<BatchResponse, ProviderError> {
+        let span = tracing::trace_span!("rpc_batch", len = self.requests.len());
+        let provider = self.provider;
+        let requests = self.requests;
+        async move {
+            trace!("tx");
+            let responses = provider
+                .as_ref()
+                .request_batch(requests)
+                .await
+                .map_err(Into::<ProviderError>::into)?;
+            trace!(rx = ?responses);
+            Ok(BatchResponse { responses })
+        }
+        .instrument(span)
+        .await
+    }
+}
+
+impl<'a, P> fmt::Debug for BatchRequest<'a, P> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BatchRequest").field("requests", &self.requests).finish()
+    }
+}
+
+/// A handle to the result of a request within a [`BatchRequest`], which deserializes to `R`.
+pub struct BatchItem<R> {
+    idx: usize,
+    ret: PhantomData<fn() -> R>,
+}
+
+impl<R> BatchItem<R> {
+    /// The position of the request within the batch
+    pub fn index(&self) -> usize {
+        self.idx
+    }
+}
+
+impl<R> Clone for BatchItem<R> {
+    fn clone(&self) -> Self {
+        Self { idx: self.idx, ret: PhantomData }
+    }
+}
+
+impl<R> Copy for BatchItem<R> {}
+
+impl<R> fmt::Debug for BatchItem<R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BatchItem").field("idx", &self.idx).finish()
+    }
+}
+
+/// The responses to a [`BatchRequest`], in the order the requests were added.
+#[derive(Debug, Clone)]
+pub struct BatchResponse {
+    responses: Vec<Result<Value, JsonRpcError>>,
+}
+
+impl BatchResponse {
+    /// Returns the deserialized result of the request, or the JSON-RPC error the node returned
+    /// for it
+    pub fn get<R: DeserializeOwned>(&self, item: &BatchItem<R>) -> Result<R, ProviderError> {
+        let response = self
+            .responses
+            .get(item.idx)
+            .ok_or_else(|| ProviderError::CustomError(format!("no response for {:?}", item)))?;
+        match response {
+            Ok(value) => Ok(R::deserialize(value)?),
+            Err(err) => Err(ProviderError::JsonRpcClientError(Box::new(err.clone()))),
+        }
+    }
+
+    /// Returns the number of responses
+    pub fn len(&self) -> usize {
+        self.responses.len()
+    }
+
+    /// Returns true if the batch was empty
+    pub fn is_empty(&self) -> bool {
+        self.responses.is_empty()
+    }
+
+    /// Returns the raw JSON responses
+    pub fn into_values(self) -> Vec<Result<Value, JsonRpcError>> {
+        self.responses
+    }
+}
+
+#[cfg(test)]
+#[cfg(not(target_arch = "wasm32"))]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn batch_with_mock() {
+        let (provider, mock) = Provider::mocked();
+        // the mock transport sends the batch one request after another, the responses are
+        // popped from the back
+        mock.push(U256::from(1000)).unwrap();
+        mock.push(U64::from(12)).unwrap();
+
+        let mut batch = provider.batch();
+        let block = batch.get_block_number().unwrap();
+        let balance = batch.get_balance(Address::zero(), None).unwrap();
+        assert_eq!(batch.len(), 2);
+        let response = batch.send().await.unwrap();
+
+        assert_eq!(response.get(&block).unwrap(), U64::from(12));
+        assert_eq!(response.get(&balance).unwrap(), U256::from(1000));
+        mock.assert_request("eth_blockNumber", json!([])).unwrap();
+        mock.assert_request("eth_getBalance", [json!(Address::zero()), json!("latest")]).unwrap();
+    }
+
+    /// Only implements `request`, to exercise the default `request_batch`
+    #[derive(Debug)]
+    struct Sequential(crate::MockProvider);
+
+    #[async_trait::async_trait]
+    impl JsonRpcClient for Sequential {
+        type Error = crate::MockError;
+
+        async fn request<T, R>(&self, method: &str, params: T) -> Result<R, Self::Error>
+        where
+            T: fmt::Debug + Serialize + Send + Sync,
+            R: DeserializeOwned,
+        {
+            self.0.request(method, params).await
+        }
+    }
+
+    #[tokio::test]
+    async fn default_batch_returns_errors_per_entry() {
+        let mock = crate::MockProvider::new();
+        mock.push(U64::from(12)).unwrap();
+        mock.push_error(JsonRpcError {
+            code: -32000,
+            message: "header not found".into(),
+            data: None,
+        });
+        let client = Sequential(mock.clone());
+
+        let responses = client
+            .request_batch(vec![
+                ("eth_getBlockByNumber".to_string(), json!(["0x1", false])),
+                ("eth_blockNumber".to_string(), json!([])),
+                ("eth_chainId".to_string(), json!([])),
+            ])
+            .await
+            .unwrap();
+        assert_eq!(responses[0].as_ref().unwrap_err().message, "header not found");
+        assert_eq!(responses[1].as_ref().unwrap(), &json!("0xc"));
+        // errors of the transport are returned as internal errors
+        let err = responses[2].as_ref().unwrap_err();
+        assert_eq!(err.code, -32603);
+        assert_eq!(err.message, crate::MockError::EmptyResponses.to_string());
+    }
+
+    #[test]
+    fn returns_errors_per_entry() {
+        let response = BatchResponse {
+            responses: vec![
+                Ok(json!("0xc")),
+                Err(JsonRpcError { code: -32000, message: "header not found".into(), data: None }),
+            ],
+        };
+        let ok: BatchItem<U64> = BatchItem { idx: 0, ret: PhantomData };
+        let err: BatchItem<U64> = BatchItem { idx: 1, ret: PhantomData };
+        let missing: BatchItem<U64> = BatchItem { idx: 2, ret: PhantomData };
+        assert_eq!(response.get(&ok).unwrap(), U64::from(12));
+        assert!(response.get(&err).is_err());
+        assert!(response.get(&missing).is_err());
+    }
+}
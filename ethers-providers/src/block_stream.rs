@@ -0,0 +1,476 @@
+use crate::{stream::interval, JsonRpcClient, Provider, ProviderError};
+
+use ethers_core::types::{
+    Block, BlockId, BlockNumber, Transaction, TransactionReceipt, TxHash, H256,
+};
+
+use futures_core::{stream::Stream, Future};
+use futures_util::StreamExt;
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+    collections::VecDeque,
+    fmt::Debug,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+    vec::IntoIter,
+};
+
+/// The number of blocks a [`BlockStream`] keeps by default, which is the deepest reorg it can
+/// follow
+pub const DEFAULT_REORG_DEPTH: usize = 64;
+
+/// The transaction representation of the blocks of a [`BlockStream`], either the hashes of the
+/// transactions or the full [`Transaction`]s.
+pub trait BlockTransaction:
+    Default + Clone + Serialize + DeserializeOwned + Debug + Send + Sync + 'static
+{
+    /// Whether blocks are requested with their full transactions
+    const FULL: bool;
+
+    /// The hash of the transaction
+    fn tx_hash(&self) -> TxHash;
+}
+
+impl BlockTransaction for TxHash {
+    const FULL: bool = false;
+
+    fn tx_hash(&self) -> TxHash {
+        *self
+    }
+}
+
+impl BlockTransaction for Transaction {
+    const FULL: bool = true;
+
+    fn tx_hash(&self) -> TxHash {
+        self.hash
+    }
+}
+
+/// A block of the canonical chain, with the receipts of its transactions if requested with
+/// [`BlockStream::with_receipts`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct StreamedBlock<TX> {
+    /// The block
+    pub block: Block<TX>,
+    /// The receipts of the transactions of the block, in the same order
+    pub receipts: Option<Vec<TransactionReceipt>>,
+}
+
+/// A change of the canonical chain as observed by a [`BlockStream`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum BlockEvent<TX> {
+    /// A block was appended to the canonical chain
+    NewBlock(StreamedBlock<TX>),
+    /// The blocks after the common ancestor were replaced by another branch
+    Reorg {
+        /// The blocks which are no longer canonical, oldest first
+        removed: Vec<Block<TX>>,
+        /// The blocks of the new canonical branch, oldest first
+        added: Vec<StreamedBlock<TX>>,
+    },
+}
+
+/// The recent blocks of the canonical chain, oldest first
+#[derive(Debug)]
+struct ChainWindow<TX> {
+    blocks: VecDeque<Block<TX>>,
+    depth: usize,
+}
+
+impl<TX: BlockTransaction> ChainWindow<TX> {
+    fn new(depth: usize) -> Self {
+        Self { blocks: VecDeque::with_capacity(depth), depth }
+    }
+
+    fn contains(&self, hash: H256) -> bool {
+        self.blocks.iter().any(|block| block.hash == Some(hash))
+    }
+
+    /// Fetches the latest block and returns the changes to the canonical chain since the last
+    /// update. The window is left untouched if an error occurs, so the update is retried with
+    /// the next head.
+    async fn update<P: JsonRpcClient>(
+        &mut self,
+        provider: &Provider<P>,
+        receipts: bool,
+    ) -> Result<Vec<BlockEvent<TX>>, ProviderError> {
+        let head = get_block::<P, TX>(provider, BlockNumber::Latest.into()).await?;
+        let head_hash = block_hash(&head)?;
+        if self.contains(head_hash) {
+            // no new block, or a lagging node behind a load balancer
+            return Ok(Vec::new())
+        }
+
+        // walk back from the new head until we reach a block of the window
+        let mut branch = vec![head];
+        let ancestor = loop {
+            let oldest = branch.last().expect("branch is never empty");
+            let oldest_known = match self.blocks.front() {
+                Some(block) => block.number.unwrap_or_default(),
+                None => break None,
+            };
+            if let Some(pos) =
+                self.blocks.iter().rposition(|block| block.hash == Some(oldest.parent_hash))
+            {
+                break Some(pos)
+            }
+            if oldest.number.unwrap_or_default() <= oldest_known {
+                // the common ancestor is older than the window, start over from the new head
+                self.blocks.clear();
+                return Err(ProviderError::CustomError(format!(
+                    "reorg deeper than {} blocks at block {:?}",
+                    self.depth, head_hash
+                )))
+            }
+            let parent = get_block::<P, TX>(provider, oldest.parent_hash.into()).await?;
+            branch.push(parent);
+        };
+        branch.reverse();
+
+        let mut added = Vec::with_capacity(branch.len());
+        for block in branch {
+            let receipts =
+                if receipts { Some(get_receipts(provider, &block).await?) } else { None };
+            added.push(StreamedBlock { block, receipts });
+        }
+
+        let removed = match ancestor {
+            Some(pos) => self.blocks.drain(pos + 1..).collect(),
+            None => Vec::new(),
+        };
+        self.blocks.extend(added.iter().map(|block| block.block.clone()));
+        while self.blocks.len() > self.depth {
+            self.blocks.pop_front();
+        }
+
+        if removed.is_empty() {
+            Ok(added.into_iter().map(BlockEvent::NewBlock).collect())
+        } else {
+            Ok(vec![BlockEvent::Reorg { removed, added }])
+        }
+    }
+}
+
+async fn get_block<P: JsonRpcClient, TX: BlockTransaction>(
+    provider: &Provider<P>,
+    id: BlockId,
+) -> Result<Block<TX>, ProviderError> {
+    provider
+        .get_block_gen(id, TX::FULL)
+        .await?
+        .ok_or_else(|| ProviderError::CustomError(format!("block {:?} not found", id)))
+}
+
+fn block_hash<TX>(block: &Block<TX>) -> Result<H256, ProviderError> {
+    block.hash.ok_or_else(|| ProviderError::CustomError("block without hash".to_string()))
+}
+
+/// Fetches the receipts of all transactions of the block in a single batch
+async fn get_receipts<P: JsonRpcClient, TX: BlockTransaction>(
+    provider: &Provider<P>,
+    block: &Block<TX>,
+) -> Result<Vec<TransactionReceipt>, ProviderError> {
+    if block.transactions.is_empty() {
+        return Ok(Vec::new())
+    }
+    let hash = block_hash(block)?;
+    let mut batch = provider.batch();
+    let items = block
+        .transactions
+        .iter()
+        .map(|tx| batch.get_transaction_receipt(tx.tx_hash()))
+        .collect::<Result<Vec<_>, _>>()?;
+    let response = batch.send().await?;
+
+    let mut receipts = Vec::with_capacity(items.len());
+    for item in items {
+        // the receipt belongs to another block if the block was reorged in the meantime
+        match response.get(&item)? {
+            Some(receipt) if receipt.block_hash == Some(hash) => receipts.push(receipt),
+            _ => {
+                return Err(ProviderError::CustomError(format!(
+                    "receipts of block {:?} not found",
+                    hash
+                )))
+            }
+        }
+    }
+    Ok(receipts)
+}
+
+/// The window is moved into the update and handed back along with its result
+type UpdateOutput<TX> = (ChainWindow<TX>, Result<Vec<BlockEvent<TX>>, ProviderError>);
+#[cfg(not(target_arch = "wasm32"))]
+type UpdateFut<'a, TX> = Pin<Box<dyn Future<Output = UpdateOutput<TX>> + Send + 'a>>;
+#[cfg(target_arch = "wasm32")]
+type UpdateFut<'a, TX> = Pin<Box<dyn Future<Output = UpdateOutput<TX>> + 'a>>;
+
+enum BlockStreamState<'a, TX> {
+    Start,
+    WaitForInterval,
+    Update(UpdateFut<'a, TX>),
+    NextEvent(IntoIter<BlockEvent<TX>>),
+}
+
+/// Streams the changes to the canonical chain by polling the latest block.
+///
+/// The stream keeps a window of the most recent blocks. Blocks which extend the chain are
+/// yielded as [`BlockEvent::NewBlock`], including any blocks that were skipped between two
+/// polls. If the parent of a new block is not the previous head, the stream walks back to the
+/// common ancestor and yields a single [`BlockEvent::Reorg`] with the removed and added
+/// blocks.
+///
+/// Errors are yielded without ending the stream, the update is retried at the next interval.
+/// If the common ancestor of a reorg is older than the window, an error is yielded and the
+/// stream starts over from the new head.
+///
+/// # Example
+///
+/// ```no_run
+/// use ethers_providers::{BlockEvent, Http, Provider, StreamExt};
+/// use std::convert::TryFrom;
+///
+/// # async fn foo() -> Result<(), Box<dyn std::error::Error>> {
+/// let provider = Provider::<Http>::try_from("http://localhost:8545")?;
+/// let mut stream = provider.block_stream().with_transactions().with_receipts();
+/// while let Some(event) = stream.next().await {
+///     match event? {
+///         BlockEvent::NewBlock(block) => println!("new block {:?}", block.block.number),
+///         BlockEvent::Reorg { removed, added } => {
+///             println!("reorg: {} blocks removed, {} added", removed.len(), added.len())
+///         }
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[must_use = "streams do nothing unless polled"]
+pub struct BlockStream<'a, P, TX = TxHash> {
+    provider: &'a Provider<P>,
+    interval: Box<dyn Stream<Item = ()> + Send + Unpin>,
+    receipts: bool,
+    depth: usize,
+    /// `None` while an update is in flight
+    window: Option<ChainWindow<TX>>,
+    state: BlockStreamState<'a, TX>,
+}
+
+impl<'a, P: JsonRpcClient> BlockStream<'a, P, TxHash> {
+    /// Creates a stream of blocks with transaction hashes, which is polled at the interval of
+    /// the provider
+    pub fn new(provider: &'a Provider<P>) -> Self {
+        Self {
+            provider,
+            interval: Box::new(interval(provider.get_interval())),
+            receipts: false,
+            depth: DEFAULT_REORG_DEPTH,
+            window: Some(ChainWindow::new(DEFAULT_REORG_DEPTH)),
+            state: BlockStreamState::Start,
+        }
+    }
+
+    /// Streams blocks with their full transactions instead of the transaction hashes
+    pub fn with_transactions(self) -> BlockStream<'a, P, Transaction> {
+        BlockStream {
+            provider: self.provider,
+            interval: self.interval,
+            receipts: self.receipts,
+            depth: self.depth,
+            window: Some(ChainWindow::new(self.depth)),
+            state: BlockStreamState::Start,
+        }
+    }
+}
+
+impl<'a, P: JsonRpcClient, TX: BlockTransaction> BlockStream<'a, P, TX> {
+    /// Sets the polling interval
+    pub fn interval(mut self, duration: Duration) -> Self {
+        self.interval = Box::new(interval(duration));
+        self
+    }
+
+    /// Sets the number of recent blocks which are kept to detect reorgs (default: 64)
+    pub fn reorg_depth(mut self, depth: usize) -> Self {
+        self.depth = depth.max(1);
+        if let Some(window) = self.window.as_mut() {
+            window.depth = self.depth;
+        }
+        self
+    }
+
+    /// Fetches the receipts of the transactions of every new block
+    pub fn with_receipts(mut self) -> Self {
+        self.receipts = true;
+        self
+    }
+}
+
+fn update<'a, P: JsonRpcClient, TX: BlockTransaction>(
+    provider: &'a Provider<P>,
+    receipts: bool,
+    mut window: ChainWindow<TX>,
+) -> UpdateFut<'a, TX> {
+    Box::pin(async move {
+        let res = window.update(provider, receipts).await;
+        (window, res)
+    })
+}
+
+impl<'a, P, TX> Stream for BlockStream<'a, P, TX>
+where
+    P: JsonRpcClient,
+    TX: BlockTransaction,
+{
+    type Item = Result<BlockEvent<TX>, ProviderError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            this.state = match &mut this.state {
+                BlockStreamState::Start => {
+                    let window = this.window.take().expect("window is returned by every update");
+                    BlockStreamState::Update(update(this.provider, this.receipts, window))
+                }
+                BlockStreamState::WaitForInterval => {
+                    // Wait the polling period
+                    let _ready = futures_util::ready!(this.interval.poll_next_unpin(cx));
+                    let window = this.window.take().expect("window is returned by every update");
+                    BlockStreamState::Update(update(this.provider, this.receipts, window))
+                }
+                BlockStreamState::Update(fut) => {
+                    let (window, res) = futures_util::ready!(fut.as_mut().poll(cx));
+                    this.window = Some(window);
+                    match res {
+                        Ok(events) => BlockStreamState::NextEvent(events.into_iter()),
+                        Err(err) => {
+                            this.state = BlockStreamState::WaitForInterval;
+                            return Poll::Ready(Some(Err(err)))
+                        }
+                    }
+                }
+                BlockStreamState::NextEvent(events) => match events.next() {
+                    Some(event) => return Poll::Ready(Some(Ok(event))),
+                    None => BlockStreamState::WaitForInterval,
+                },
+            };
+        }
+    }
+}
+
+impl<'a, P, TX> Debug for BlockStream<'a, P, TX> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BlockStream").field("receipts", &self.receipts).finish()
+    }
+}
+
+#[cfg(test)]
+#[cfg(not(target_arch = "wasm32"))]
+mod tests {
+    use super::*;
+
+    fn block(number: u64, hash: u8, parent: u8) -> Block<TxHash> {
+        Block {
+            hash: Some(H256::repeat_byte(hash)),
+            parent_hash: H256::repeat_byte(parent),
+            number: Some(number.into()),
+            ..Default::default()
+        }
+    }
+
+    fn new_block(block: Block<TxHash>) -> BlockEvent<TxHash> {
+        BlockEvent::NewBlock(StreamedBlock { block, receipts: None })
+    }
+
+    #[tokio::test]
+    async fn tracks_canonical_chain() {
+        let (provider, mock) = Provider::mocked();
+        let mut window = ChainWindow::<TxHash>::new(DEFAULT_REORG_DEPTH);
+
+        mock.push(block(1, 0xa1, 0xa0)).unwrap();
+        let events = window.update(&provider, false).await.unwrap();
+        assert_eq!(events, vec![new_block(block(1, 0xa1, 0xa0))]);
+
+        mock.push(block(2, 0xa2, 0xa1)).unwrap();
+        let events = window.update(&provider, false).await.unwrap();
+        assert_eq!(events, vec![new_block(block(2, 0xa2, 0xa1))]);
+
+        // the head did not change
+        mock.push(block(2, 0xa2, 0xa1)).unwrap();
+        assert!(window.update(&provider, false).await.unwrap().is_empty());
+
+        // a competing branch replaces block 2, responses are popped from the back
+        mock.push(block(2, 0xb2, 0xa1)).unwrap();
+        mock.push(block(3, 0xb3, 0xb2)).unwrap();
+        let events = window.update(&provider, false).await.unwrap();
+        assert_eq!(
+            events,
+            vec![BlockEvent::Reorg {
+                removed: vec![block(2, 0xa2, 0xa1)],
+                added: vec![
+                    StreamedBlock { block: block(2, 0xb2, 0xa1), receipts: None },
+                    StreamedBlock { block: block(3, 0xb3, 0xb2), receipts: None },
+                ],
+            }]
+        );
+
+        // skipped blocks are filled in
+        mock.push(block(4, 0xb4, 0xb3)).unwrap();
+        mock.push(block(5, 0xb5, 0xb4)).unwrap();
+        let events = window.update(&provider, false).await.unwrap();
+        assert_eq!(events, vec![new_block(block(4, 0xb4, 0xb3)), new_block(block(5, 0xb5, 0xb4))]);
+    }
+
+    #[tokio::test]
+    async fn fails_on_reorg_deeper_than_window() {
+        let (provider, mock) = Provider::mocked();
+        let mut window = ChainWindow::<TxHash>::new(2);
+
+        mock.push(block(1, 0xa1, 0xa0)).unwrap();
+        window.update(&provider, false).await.unwrap();
+        mock.push(block(2, 0xa2, 0xa1)).unwrap();
+        window.update(&provider, false).await.unwrap();
+
+        mock.push(block(1, 0xc1, 0xc0)).unwrap();
+        mock.push(block(2, 0xc2, 0xc1)).unwrap();
+        mock.push(block(3, 0xc3, 0xc2)).unwrap();
+        assert!(window.update(&provider, false).await.is_err());
+
+        // the stream starts over from the new head
+        mock.push(block(3, 0xc3, 0xc2)).unwrap();
+        let events = window.update(&provider, false).await.unwrap();
+        assert_eq!(events, vec![new_block(block(3, 0xc3, 0xc2))]);
+    }
+
+    #[tokio::test]
+    async fn streams_blocks_with_receipts() {
+        let (provider, mock) = Provider::mocked();
+        let tx_hash = TxHash::repeat_byte(0xff);
+        let mut head = block(1, 0xa1, 0xa0);
+        head.transactions = vec![tx_hash];
+        let receipt = TransactionReceipt {
+            transaction_hash: tx_hash,
+            block_hash: head.hash,
+            ..Default::default()
+        };
+        mock.push(block(2, 0xa2, 0xa1)).unwrap();
+        mock.push(receipt.clone()).unwrap();
+        mock.push(head.clone()).unwrap();
+
+        let mut stream = provider.block_stream().interval(Duration::from_millis(1)).with_receipts();
+        let event = stream.next().await.unwrap().unwrap();
+        assert_eq!(
+            event,
+            BlockEvent::NewBlock(StreamedBlock { block: head, receipts: Some(vec![receipt]) })
+        );
+        let event = stream.next().await.unwrap().unwrap();
+        assert_eq!(
+            event,
+            BlockEvent::NewBlock(StreamedBlock {
+                block: block(2, 0xa2, 0xa1),
+                receipts: Some(Vec::new())
+            })
+        );
+    }
+}
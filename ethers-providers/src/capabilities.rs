@@ -0,0 +1,150 @@
+//! Detection of the node client and the JSON-RPC features it supports
+use crate::ProviderError;
+use std::str::FromStr;
+
+/// The node implementation, as reported by `web3_clientVersion`
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum NodeClient {
+    Geth,
+    Erigon,
+    OpenEthereum,
+    Nethermind,
+    Besu,
+    /// Foundry's development node
+    Anvil,
+    /// The Hardhat Network development node
+    Hardhat,
+    /// The Ganache development node, formerly EthereumJS TestRPC
+    Ganache,
+}
+
+impl NodeClient {
+    /// Returns true for development nodes, which support dev-only RPCs such as `evm_snapshot`
+    pub fn is_dev(&self) -> bool {
+        matches!(self, NodeClient::Anvil | NodeClient::Hardhat | NodeClient::Ganache)
+    }
+}
+
+impl FromStr for NodeClient {
+    type Err = ProviderError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split('/').next().unwrap().to_lowercase().as_str() {
+            "geth" => Ok(NodeClient::Geth),
+            "erigon" => Ok(NodeClient::Erigon),
+            "openethereum" | "parity-ethereum" => Ok(NodeClient::OpenEthereum),
+            "nethermind" => Ok(NodeClient::Nethermind),
+            "besu" => Ok(NodeClient::Besu),
+            "anvil" => Ok(NodeClient::Anvil),
+            "hardhatnetwork" => Ok(NodeClient::Hardhat),
+            "ganache" | "ethereumjs testrpc" => Ok(NodeClient::Ganache),
+            _ => Err(ProviderError::UnsupportedNodeClient),
+        }
+    }
+}
+
+/// The JSON-RPC features of a node, detected by
+/// [`Provider::capabilities`](crate::Provider::capabilities) or configured with
+/// [`Provider::with_capabilities`](crate::Provider::with_capabilities).
+///
+/// ```
+/// use ethers_providers::{NodeCapabilities, NodeClient};
+///
+/// // a hosted Geth endpoint which limits the range of `eth_getLogs`
+/// let capabilities = NodeCapabilities {
+///     max_get_logs_range: Some(2_000),
+///     ..NodeCapabilities::for_client(Some(NodeClient::Geth))
+/// };
+/// assert!(!capabilities.trace);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NodeCapabilities {
+    /// The node client, `None` if it is unknown
+    pub client: Option<NodeClient>,
+    /// Whether `eth_feeHistory` is supported, which is required to estimate EIP-1559 fees
+    pub fee_history: bool,
+    /// Whether the `trace_*` methods are supported
+    pub trace: bool,
+    /// Whether the `debug_trace*` methods are supported
+    pub debug: bool,
+    /// Whether `eth_getBlockReceipts` is supported
+    pub block_receipts: bool,
+    /// Whether the transport supports `eth_subscribe` subscriptions
+    pub pubsub: bool,
+    /// The maximum number of blocks a single `eth_getLogs` request may span, `None` if the
+    /// node does not limit it
+    pub max_get_logs_range: Option<u64>,
+}
+
+impl NodeCapabilities {
+    /// Returns the features supported by the client. Unknown clients are assumed to support
+    /// everything, so that requests are sent to the node instead of being rejected locally.
+    pub fn for_client(client: Option<NodeClient>) -> Self {
+        let (fee_history, trace, debug, block_receipts) = match client {
+            Some(NodeClient::Geth) => (true, false, true, true),
+            Some(NodeClient::OpenEthereum) => (true, true, false, false),
+            Some(NodeClient::Besu) => (true, true, true, false),
+            Some(NodeClient::Hardhat) => (true, false, true, false),
+            Some(NodeClient::Ganache) => (false, false, true, false),
+            Some(NodeClient::Erigon) |
+            Some(NodeClient::Nethermind) |
+            Some(NodeClient::Anvil) |
+            None => (true, true, true, true),
+        };
+        Self {
+            client,
+            fee_history,
+            trace,
+            debug,
+            block_receipts,
+            pubsub: false,
+            max_get_logs_range: None,
+        }
+    }
+
+    /// Returns true if the node is a development node, see [`NodeClient::is_dev`]
+    pub fn is_dev(&self) -> bool {
+        self.client.map_or(false, |client| client.is_dev())
+    }
+}
+
+impl Default for NodeCapabilities {
+    fn default() -> Self {
+        Self::for_client(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_client_versions() {
+        let versions = [
+            ("Geth/v1.10.17-stable/linux-amd64/go1.18", NodeClient::Geth),
+            ("erigon/2022.04.3/linux-amd64/go1.18.1", NodeClient::Erigon),
+            ("anvil/v0.1.0", NodeClient::Anvil),
+            ("HardhatNetwork/2.9.3/@ethereumjs/vm/5.9.0", NodeClient::Hardhat),
+            ("Ganache/v7.0.4/EthereumJS TestRPC/v7.0.4/ethereum-js", NodeClient::Ganache),
+            ("EthereumJS TestRPC/v2.13.2/ethereum-js", NodeClient::Ganache),
+        ];
+        for (version, client) in versions {
+            assert_eq!(version.parse::<NodeClient>().unwrap(), client);
+        }
+        assert!("reth/v0.1.0".parse::<NodeClient>().is_err());
+        assert!(NodeClient::Hardhat.is_dev() && !NodeClient::Geth.is_dev());
+    }
+
+    #[test]
+    fn client_capabilities() {
+        let geth = NodeCapabilities::for_client(Some(NodeClient::Geth));
+        assert!(geth.debug && !geth.trace && !geth.is_dev());
+
+        let ganache = NodeCapabilities::for_client(Some(NodeClient::Ganache));
+        assert!(!ganache.fee_history && !ganache.block_receipts && ganache.is_dev());
+
+        let unknown = NodeCapabilities::default();
+        assert!(unknown.fee_history && unknown.trace && unknown.debug && unknown.block_receipts);
+        assert_eq!(unknown.max_get_logs_range, None);
+    }
+}
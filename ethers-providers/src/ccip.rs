@@ -0,0 +1,360 @@
+//! [EIP-3668](https://eips.ethereum.org/EIPS/eip-3668) CCIP-Read support
+//!
+//! Contracts which store their data offchain revert `eth_call`s with an `OffchainLookup` error,
+//! which tells the client which gateways to fetch the data from and which function of the
+//! contract to call back with the response.
+use crate::{JsonRpcError, ProviderError};
+use ethers_core::{
+    abi::{self, ParamType, Token},
+    types::{Address, Bytes, Selector},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// OffchainLookup(address,string[],bytes,bytes4,bytes)
+pub const OFFCHAIN_LOOKUP_SELECTOR: Selector = [85, 111, 24, 48];
+
+/// The number of offchain lookups which are followed for a single call by default, the same as
+/// ethers.js
+pub const DEFAULT_MAX_REDIRECTS: usize = 4;
+
+/// The `OffchainLookup` error a contract reverts with to request data from offchain gateways
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OffchainLookup {
+    /// The contract which reverted, which must be the contract that was called
+    pub sender: Address,
+    /// The gateway URL templates, which are tried in order
+    pub urls: Vec<String>,
+    /// The data which is sent to the gateway
+    pub call_data: Bytes,
+    /// The function of the contract which is called with the response of the gateway
+    pub callback_function: Selector,
+    /// The data which is passed to the callback along with the response
+    pub extra_data: Bytes,
+}
+
+impl OffchainLookup {
+    /// Decodes the revert data of a call, returns `None` if it is not an `OffchainLookup`
+    pub fn decode(revert_data: &[u8]) -> Option<Self> {
+        if revert_data.len() < 4 || revert_data[..4] != OFFCHAIN_LOOKUP_SELECTOR {
+            return None
+        }
+        let params = [
+            ParamType::Address,
+            ParamType::Array(Box::new(ParamType::String)),
+            ParamType::Bytes,
+            ParamType::FixedBytes(4),
+            ParamType::Bytes,
+        ];
+        let mut tokens = abi::decode(&params, &revert_data[4..]).ok()?.into_iter();
+        let sender = tokens.next()?.into_address()?;
+        let urls = tokens
+            .next()?
+            .into_array()?
+            .into_iter()
+            .map(Token::into_string)
+            .collect::<Option<Vec<_>>>()?;
+        let call_data = tokens.next()?.into_bytes()?.into();
+        let mut callback_function = [0u8; 4];
+        callback_function.copy_from_slice(&tokens.next()?.into_fixed_bytes()?);
+        let extra_data = tokens.next()?.into_bytes()?.into();
+        Some(Self { sender, urls, call_data, callback_function, extra_data })
+    }
+
+    /// Decodes the revert data of the JSON-RPC error of a call, returns `None` if the call did
+    /// not revert with an `OffchainLookup`
+    pub fn from_error(err: &JsonRpcError) -> Option<Self> {
+        // the revert data is usually a hex string, some nodes nest it in an object
+        let data = match err.data.as_ref()? {
+            Value::String(data) => data,
+            Value::Object(obj) => obj.get("data")?.as_str()?,
+            _ => return None,
+        };
+        let data = hex::decode(data.strip_prefix("0x").unwrap_or(data)).ok()?;
+        Self::decode(&data)
+    }
+
+    /// Returns the calldata of the callback for the response of the gateway
+    pub fn callback_data(&self, response: &[u8]) -> Bytes {
+        let args =
+            [Token::Bytes(response.to_vec()), Token::Bytes(self.extra_data.as_ref().to_vec())];
+        [&self.callback_function[..], &abi::encode(&args)].concat().into()
+    }
+
+    /// Returns the URL and, for gateways which do not take the data in their URL, the body of
+    /// the request to the gateway `url`
+    pub fn gateway_request(&self, url: &str) -> (String, Option<GatewayRequest>) {
+        let sender = format!("{:?}", self.sender);
+        let data = self.call_data.to_string();
+        let request_url = url.replace("{sender}", &sender).replace("{data}", &data);
+        // gateways which do not take the data in their URL are queried with a POST request
+        let body = (!url.contains("{data}")).then(|| GatewayRequest { data, sender });
+        (request_url, body)
+    }
+
+    /// Queries the gateways in order and returns the first response.
+    ///
+    /// Per EIP-3668, a gateway which responds with a 4xx status fails the lookup, while other
+    /// errors move on to the next gateway.
+    pub async fn fetch(&self) -> Result<Bytes, ProviderError> {
+        let client = reqwest::Client::new();
+        let mut errors = Vec::new();
+        for url in &self.urls {
+            let (request_url, body) = self.gateway_request(url);
+            let request = match body {
+                Some(body) => client.post(&request_url).json(&body),
+                None => client.get(&request_url),
+            };
+            let response = match request.send().await {
+                Ok(response) => response,
+                Err(err) => {
+                    errors.push(format!("{}: {}", url, err));
+                    continue
+                }
+            };
+            let status = response.status();
+            if status.is_client_error() {
+                let message = match response.json::<GatewayError>().await {
+                    Ok(err) => err.message,
+                    Err(_) => status.to_string(),
+                };
+                return Err(ProviderError::CcipReadError(format!("{}: {}", url, message)))
+            }
+            if !status.is_success() {
+                errors.push(format!("{}: {}", url, status));
+                continue
+            }
+            match response.json::<GatewayResponse>().await {
+                Ok(response) => return Ok(response.data),
+                Err(err) => errors.push(format!("{}: {}", url, err)),
+            }
+        }
+        Err(ProviderError::CcipReadError(format!("all gateways failed: [{}]", errors.join(", "))))
+    }
+}
+
+/// The body of a POST request to a gateway
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct GatewayRequest {
+    /// The hex encoded call data
+    pub data: String,
+    /// The hex encoded address of the contract
+    pub sender: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct GatewayResponse {
+    data: Bytes,
+}
+
+#[derive(Debug, Deserialize)]
+struct GatewayError {
+    message: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn lookup() -> OffchainLookup {
+        OffchainLookup {
+            sender: Address::repeat_byte(0x11),
+            urls: vec![
+                "https://example.com/gateway/{sender}/{data}.json".to_string(),
+                "https://example.com/gateway".to_string(),
+            ],
+            call_data: vec![1, 2, 3].into(),
+            callback_function: [0xaa, 0xbb, 0xcc, 0xdd],
+            extra_data: vec![4, 5].into(),
+        }
+    }
+
+    fn encode(lookup: &OffchainLookup) -> Vec<u8> {
+        let tokens = [
+            Token::Address(lookup.sender),
+            Token::Array(lookup.urls.iter().cloned().map(Token::String).collect()),
+            Token::Bytes(lookup.call_data.as_ref().to_vec()),
+            Token::FixedBytes(lookup.callback_function.to_vec()),
+            Token::Bytes(lookup.extra_data.as_ref().to_vec()),
+        ];
+        [&OFFCHAIN_LOOKUP_SELECTOR[..], &abi::encode(&tokens)].concat()
+    }
+
+    #[test]
+    fn decodes_offchain_lookup() {
+        let lookup = lookup();
+        let data = encode(&lookup);
+        assert_eq!(OffchainLookup::decode(&data), Some(lookup.clone()));
+        assert_eq!(OffchainLookup::decode(&data[..40]), None);
+        assert_eq!(OffchainLookup::decode(&[0x08, 0xc3, 0x79, 0xa0]), None);
+
+        let err = JsonRpcError {
+            code: 3,
+            message: "execution reverted".to_string(),
+            data: Some(json!(format!("0x{}", hex::encode(&data)))),
+        };
+        assert_eq!(OffchainLookup::from_error(&err), Some(lookup));
+    }
+
+    #[test]
+    fn builds_gateway_requests() {
+        let lookup = lookup();
+        let (url, body) = lookup.gateway_request(&lookup.urls[0]);
+        assert_eq!(
+            url,
+            "https://example.com/gateway/0x1111111111111111111111111111111111111111/0x010203.json"
+        );
+        assert_eq!(body, None);
+
+        let (url, body) = lookup.gateway_request(&lookup.urls[1]);
+        assert_eq!(url, "https://example.com/gateway");
+        assert_eq!(
+            body,
+            Some(GatewayRequest {
+                data: "0x010203".to_string(),
+                sender: "0x1111111111111111111111111111111111111111".to_string()
+            })
+        );
+    }
+
+    /// Serves the `eth_call`s of the provider at `/rpc` and the gateway at `/gateway`, the
+    /// callback returns its own calldata
+    #[cfg(not(target_arch = "wasm32"))]
+    fn serve_node_and_gateway(
+        listener: std::net::TcpListener,
+        lookup: OffchainLookup,
+        requests: usize,
+    ) {
+        use std::io::{Read, Write};
+
+        for stream in listener.incoming().take(requests) {
+            let mut stream = stream.unwrap();
+            let mut buf = Vec::new();
+            let mut chunk = [0u8; 4096];
+            let (head, body) = loop {
+                let n = stream.read(&mut chunk).unwrap();
+                buf.extend_from_slice(&chunk[..n]);
+                let end = match buf.windows(4).position(|w| w == b"\r\n\r\n") {
+                    Some(end) => end,
+                    None => continue,
+                };
+                let head = String::from_utf8_lossy(&buf[..end]).to_string();
+                let len = head
+                    .lines()
+                    .find_map(|line| {
+                        let (key, value) = line.split_once(':')?;
+                        key.eq_ignore_ascii_case("content-length").then(|| value.trim().parse())
+                    })
+                    .map(Result::unwrap)
+                    .unwrap_or(0);
+                while buf.len() < end + 4 + len {
+                    let n = stream.read(&mut chunk).unwrap();
+                    buf.extend_from_slice(&chunk[..n]);
+                }
+                break (head, buf[end + 4..end + 4 + len].to_vec())
+            };
+
+            let path = head.split_whitespace().nth(1).unwrap();
+            let response = if path.starts_with("/gateway/") {
+                assert_eq!(path, format!("/gateway/{:?}/{}.json", lookup.sender, lookup.call_data));
+                json!({ "data": "0xcafe" })
+            } else {
+                let request: Value = serde_json::from_slice(&body).unwrap();
+                let data = request["params"][0]["data"].as_str().unwrap();
+                if data == "0x12345678" {
+                    let revert = format!("0x{}", hex::encode(encode(&lookup)));
+                    json!({
+                        "jsonrpc": "2.0",
+                        "id": request["id"],
+                        "error": { "code": 3, "message": "execution reverted", "data": revert }
+                    })
+                } else {
+                    json!({ "jsonrpc": "2.0", "id": request["id"], "result": data })
+                }
+            };
+            let response = response.to_string();
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                response.len(),
+                response
+            )
+            .unwrap();
+        }
+    }
+
+    #[tokio::test]
+    #[cfg(not(target_arch = "wasm32"))]
+    async fn follows_offchain_lookup() {
+        use crate::{Http, Middleware, Provider};
+        use ethers_core::types::TransactionRequest;
+        use std::convert::TryFrom;
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mut lookup = lookup();
+        lookup.urls = vec![format!("http://{}/gateway/{{sender}}/{{data}}.json", addr)];
+        let server = {
+            let lookup = lookup.clone();
+            std::thread::spawn(move || serve_node_and_gateway(listener, lookup, 5))
+        };
+
+        let tx = TransactionRequest::new().to(lookup.sender).data(vec![0x12, 0x34, 0x56, 0x78]);
+        let url = format!("http://{}/rpc", addr);
+
+        // CCIP-Read is disabled by default
+        let provider = Provider::<Http>::try_from(url.as_str()).unwrap();
+        let err = provider.call(&tx.clone().into(), None).await.unwrap_err();
+        assert!(OffchainLookup::from_error(err.as_error_response().unwrap()).is_some());
+
+        let provider = provider.ccip_read(DEFAULT_MAX_REDIRECTS);
+        let data = provider.call(&tx.clone().into(), None).await.unwrap();
+        assert_eq!(data, lookup.callback_data(&[0xca, 0xfe]));
+
+        // the lookup has to be answered by the called contract
+        let tx = tx.to(Address::repeat_byte(0x22));
+        let err = provider.call(&tx.into(), None).await.unwrap_err();
+        assert!(matches!(err, ProviderError::CcipReadError(_)));
+
+        server.join().unwrap();
+    }
+
+    #[tokio::test]
+    #[cfg(not(target_arch = "wasm32"))]
+    async fn follows_offchain_lookup_behind_wrapping_transport() {
+        use crate::{Http, Middleware, Provider, Quorum, QuorumProvider, WeightedProvider};
+        use ethers_core::types::TransactionRequest;
+        use std::str::FromStr;
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mut lookup = lookup();
+        lookup.urls = vec![format!("http://{}/gateway/{{sender}}/{{data}}.json", addr)];
+        let server = {
+            let lookup = lookup.clone();
+            std::thread::spawn(move || serve_node_and_gateway(listener, lookup, 3))
+        };
+
+        // the revert is wrapped in the error of the quorum
+        let http = Http::from_str(&format!("http://{}/rpc", addr)).unwrap();
+        let quorum = QuorumProvider::new(Quorum::All, vec![WeightedProvider::new(http)]);
+        let provider = Provider::new(quorum).ccip_read(DEFAULT_MAX_REDIRECTS);
+
+        let tx = TransactionRequest::new().to(lookup.sender).data(vec![0x12, 0x34, 0x56, 0x78]);
+        // pinned to a block, the quorum would otherwise ask for the latest block number first
+        let data = provider.call(&tx.into(), Some(1u64.into())).await.unwrap();
+        assert_eq!(data, lookup.callback_data(&[0xca, 0xfe]));
+
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn encodes_callback() {
+        let data = lookup().callback_data(&[9, 9]);
+        assert_eq!(data.as_ref()[..4], [0xaa, 0xbb, 0xcc, 0xdd]);
+        let tokens =
+            abi::decode(&[ParamType::Bytes, ParamType::Bytes], &data.as_ref()[4..]).unwrap();
+        assert_eq!(tokens, vec![Token::Bytes(vec![9, 9]), Token::Bytes(vec![4, 5])]);
+    }
+}
@@ -1,9 +1,13 @@
 //! [Ethereum Name Service](https://docs.ens.domains/) support
 //! Adapted from https://github.com/hhatto/rust-ens/blob/master/src/lib.rs
+use crate::ProviderError;
 use ethers_core::{
-    types::{Address, NameOrAddress, Selector, TransactionRequest, H160, H256},
+    abi::{self, Token},
+    types::{Address, Bytes, NameOrAddress, Selector, TransactionRequest, H160, H256, U256},
     utils::keccak256,
 };
+use serde::Deserialize;
+use std::{fmt, str::FromStr};
 
 /// ENS registry address (`0x00000000000C2E074eC69A0dFb2997BA6C7d2e1e`)
 pub const ENS_ADDRESS: Address = H160([
@@ -23,15 +27,39 @@ pub const ADDR_SELECTOR: Selector = [59, 59, 87, 222];
 /// name(bytes32)
 pub const NAME_SELECTOR: Selector = [105, 31, 52, 49];
 
+/// text(bytes32,string)
+pub const FIELD_SELECTOR: Selector = [89, 209, 212, 60];
+
+/// contenthash(bytes32)
+pub const CONTENTHASH_SELECTOR: Selector = [188, 28, 88, 209];
+
+/// resolve(bytes,bytes), which is also the [ENSIP-10](https://docs.ens.domains/ens-improvement-proposals/ensip-10-wildcard-resolution)
+/// interface id of extended resolvers
+pub const EXTENDED_RESOLVER_SELECTOR: Selector = [144, 97, 185, 35];
+
+/// supportsInterface(bytes4)
+pub const SUPPORTS_INTERFACE_SELECTOR: Selector = [1, 255, 201, 167];
+
+/// tokenURI(uint256)
+pub const ERC721_TOKEN_URI_SELECTOR: Selector = [200, 123, 86, 221];
+
+/// ownerOf(uint256)
+pub const ERC721_OWNER_SELECTOR: Selector = [99, 82, 33, 30];
+
+/// uri(uint256)
+pub const ERC1155_URI_SELECTOR: Selector = [14, 137, 52, 28];
+
+/// balanceOf(address,uint256)
+pub const ERC1155_BALANCE_SELECTOR: Selector = [0, 253, 213, 142];
+
+/// The gateway used to fetch `ipfs://` URIs of avatars and NFT metadata
+pub const IPFS_GATEWAY: &str = "https://ipfs.io/ipfs/";
+
 /// Returns a transaction request for calling the `resolver` method on the ENS server
 pub fn get_resolver<T: Into<Address>>(ens_address: T, name: &str) -> TransactionRequest {
     // keccak256('resolver(bytes32)')
     let data = [&RESOLVER[..], &namehash(name).0].concat();
-    TransactionRequest {
-        data: Some(data.into()),
-        to: Some(NameOrAddress::Address(ens_address.into())),
-        ..Default::default()
-    }
+    call(ens_address.into(), data)
 }
 
 /// Returns a transaction request for calling
@@ -40,10 +68,56 @@ pub fn resolve<T: Into<Address>>(
     selector: Selector,
     name: &str,
 ) -> TransactionRequest {
-    let data = [&selector[..], &namehash(name).0].concat();
+    resolve_with_args(resolver_address, selector, name, &[])
+}
+
+/// Returns a transaction request for calling a resolver method which takes further arguments
+/// after the namehash, e.g. `text(bytes32,string)`
+pub fn resolve_with_args<T: Into<Address>>(
+    resolver_address: T,
+    selector: Selector,
+    name: &str,
+    args: &[Token],
+) -> TransactionRequest {
+    call(resolver_address.into(), resolver_call_data(selector, name, args))
+}
+
+/// Returns the calldata of a resolver method which takes the namehash of `name` followed by
+/// `args`, e.g. `text(bytes32,string)`
+pub fn resolver_call_data(selector: Selector, name: &str, args: &[Token]) -> Vec<u8> {
+    let mut tokens = vec![Token::FixedBytes(namehash(name).0.to_vec())];
+    tokens.extend_from_slice(args);
+    [&selector[..], &abi::encode(&tokens)].concat()
+}
+
+/// Returns a transaction request for calling `resolve(bytes,bytes)` on an
+/// [ENSIP-10](https://docs.ens.domains/ens-improvement-proposals/ensip-10-wildcard-resolution)
+/// extended resolver, which returns the result of `data` as `bytes`
+pub fn resolve_extended<T: Into<Address>>(
+    resolver_address: T,
+    name: &str,
+    data: Vec<u8>,
+) -> Result<TransactionRequest, ProviderError> {
+    let tokens = [Token::Bytes(dns_encode(name)?), Token::Bytes(data)];
+    let data = [&EXTENDED_RESOLVER_SELECTOR[..], &abi::encode(&tokens)].concat();
+    Ok(call(resolver_address.into(), data))
+}
+
+/// Returns a transaction request for calling the ERC-165 `supportsInterface` method
+pub fn supports_interface<T: Into<Address>>(
+    address: T,
+    interface_id: Selector,
+) -> TransactionRequest {
+    let mut data = SUPPORTS_INTERFACE_SELECTOR.to_vec();
+    data.extend_from_slice(&interface_id);
+    data.resize(4 + 32, 0);
+    call(address.into(), data)
+}
+
+fn call(to: Address, data: Vec<u8>) -> TransactionRequest {
     TransactionRequest {
         data: Some(data.into()),
-        to: Some(NameOrAddress::Address(resolver_address.into())),
+        to: Some(NameOrAddress::Address(to)),
         ..Default::default()
     }
 }
@@ -65,6 +139,314 @@ pub fn namehash(name: &str) -> H256 {
         .into()
 }
 
+/// Returns the DNS wire format encoding of `name`, as required by `resolve(bytes,bytes)`
+pub fn dns_encode(name: &str) -> Result<Vec<u8>, ProviderError> {
+    let mut encoded = Vec::with_capacity(name.len() + 2);
+    for label in name.split('.').filter(|label| !label.is_empty()) {
+        if label.len() > 255 {
+            return Err(ProviderError::EnsError(name.to_owned()))
+        }
+        encoded.push(label.len() as u8);
+        encoded.extend_from_slice(label.as_bytes());
+    }
+    encoded.push(0);
+    Ok(encoded)
+}
+
+/// The decoded `contenthash` record of a name, as specified in
+/// [EIP-1577](https://eips.ethereum.org/EIPS/eip-1577)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ContentHash {
+    /// An IPFS CID
+    Ipfs(String),
+    /// An IPNS key or DNSLink name
+    Ipns(String),
+    /// A Swarm manifest hash
+    Swarm(H256),
+    /// A content hash with an unknown or malformed codec
+    Unknown(Bytes),
+}
+
+impl ContentHash {
+    /// Decodes the raw `contenthash` record, returns `None` if no record is set
+    pub fn decode(bytes: &[u8]) -> Option<Self> {
+        if bytes.is_empty() {
+            return None
+        }
+        let unknown = || ContentHash::Unknown(bytes.to_vec().into());
+        let (codec, cid) = match read_varint(bytes) {
+            Some(res) => res,
+            None => return Some(unknown()),
+        };
+        let decoded = match codec {
+            IPFS_NS => decode_ipfs(cid).map(ContentHash::Ipfs),
+            IPNS_NS => decode_ipns(cid).map(ContentHash::Ipns),
+            SWARM_NS => decode_swarm(cid).map(ContentHash::Swarm),
+            _ => None,
+        };
+        Some(decoded.unwrap_or_else(unknown))
+    }
+
+    /// Returns the URL of the content, e.g. `ipfs://<cid>` or `bzz://<hash>`
+    pub fn to_url(&self) -> Option<String> {
+        match self {
+            ContentHash::Unknown(_) => None,
+            _ => Some(self.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for ContentHash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ContentHash::Ipfs(cid) => write!(f, "ipfs://{}", cid),
+            ContentHash::Ipns(name) => write!(f, "ipns://{}", name),
+            ContentHash::Swarm(hash) => write!(f, "bzz://{}", hex::encode(hash)),
+            ContentHash::Unknown(bytes) => write!(f, "{}", bytes),
+        }
+    }
+}
+
+// multicodecs of the content hash namespaces and CIDs
+const IPFS_NS: u64 = 0xe3;
+const SWARM_NS: u64 = 0xe4;
+const IPNS_NS: u64 = 0xe5;
+const DAG_PB: u64 = 0x70;
+const SWARM_MANIFEST: u64 = 0xfa;
+const IDENTITY: u64 = 0x00;
+const SHA2_256: u64 = 0x12;
+const KECCAK_256: u64 = 0x1b;
+
+/// Reads an unsigned varint, returns the value and the remaining bytes
+fn read_varint(bytes: &[u8]) -> Option<(u64, &[u8])> {
+    let mut value = 0u64;
+    for (idx, byte) in bytes.iter().enumerate().take(9) {
+        value |= u64::from(byte & 0x7f) << (7 * idx);
+        if byte & 0x80 == 0 {
+            return Some((value, &bytes[idx + 1..]))
+        }
+    }
+    None
+}
+
+/// Splits a CIDv1 into its content codec and multihash
+fn read_cid_v1(cid: &[u8]) -> Option<(u64, &[u8])> {
+    match read_varint(cid)? {
+        (1, rest) => read_varint(rest),
+        _ => None,
+    }
+}
+
+/// Splits a multihash into its hash function and digest
+fn read_multihash(multihash: &[u8]) -> Option<(u64, &[u8])> {
+    let (code, rest) = read_varint(multihash)?;
+    let (len, digest) = read_varint(rest)?;
+    (digest.len() as u64 == len).then(|| (code, digest))
+}
+
+fn decode_ipfs(cid: &[u8]) -> Option<String> {
+    // a CIDv0 is a bare sha2-256 multihash
+    if let Some((SHA2_256, digest)) = read_multihash(cid) {
+        if digest.len() == 32 {
+            return Some(base58_encode(cid))
+        }
+    }
+    let (codec, multihash) = read_cid_v1(cid)?;
+    let (code, digest) = read_multihash(multihash)?;
+    if codec == DAG_PB && code == SHA2_256 && digest.len() == 32 {
+        // the CIDv0 representation is the one most users are familiar with
+        Some(base58_encode(multihash))
+    } else {
+        Some(format!("b{}", base32_encode(cid)))
+    }
+}
+
+fn decode_ipns(cid: &[u8]) -> Option<String> {
+    let (_, multihash) = read_cid_v1(cid)?;
+    let (code, digest) = read_multihash(multihash)?;
+    // DNSLink names are stored as the identity hash of the name
+    if code == IDENTITY {
+        if let Ok(name) = std::str::from_utf8(digest) {
+            if !name.is_empty() &&
+                name.chars().all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-')
+            {
+                return Some(name.to_string())
+            }
+        }
+    }
+    Some(format!("b{}", base32_encode(cid)))
+}
+
+fn decode_swarm(cid: &[u8]) -> Option<H256> {
+    let (codec, multihash) = read_cid_v1(cid)?;
+    match read_multihash(multihash)? {
+        (KECCAK_256, digest) if codec == SWARM_MANIFEST && digest.len() == 32 => {
+            Some(H256::from_slice(digest))
+        }
+        _ => None,
+    }
+}
+
+/// Encodes the bytes with the base58 bitcoin alphabet
+fn base58_encode(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+    // little endian base58 digits
+    let mut digits: Vec<u8> = Vec::with_capacity(bytes.len() * 138 / 100 + 1);
+    for byte in bytes {
+        let mut carry = u32::from(*byte);
+        for digit in digits.iter_mut() {
+            carry += u32::from(*digit) << 8;
+            *digit = (carry % 58) as u8;
+            carry /= 58;
+        }
+        while carry > 0 {
+            digits.push((carry % 58) as u8);
+            carry /= 58;
+        }
+    }
+    let zeros = bytes.iter().take_while(|byte| **byte == 0).count();
+    std::iter::repeat('1')
+        .take(zeros)
+        .chain(digits.iter().rev().map(|digit| ALPHABET[*digit as usize] as char))
+        .collect()
+}
+
+/// Encodes the bytes with the lowercase RFC 4648 base32 alphabet, without padding
+fn base32_encode(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"abcdefghijklmnopqrstuvwxyz234567";
+    let mut encoded = String::with_capacity((bytes.len() * 8 + 4) / 5);
+    let (mut buffer, mut bits) = (0u32, 0u32);
+    for byte in bytes {
+        buffer = ((buffer << 8) | u32::from(*byte)) & 0xfff;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            encoded.push(ALPHABET[((buffer >> bits) & 31) as usize] as char);
+        }
+    }
+    if bits > 0 {
+        encoded.push(ALPHABET[((buffer << (5 - bits)) & 31) as usize] as char);
+    }
+    encoded
+}
+
+/// The NFT standards supported as [ENSIP-12](https://docs.ens.domains/ens-improvement-proposals/ensip-12-avatar-text-records)
+/// avatars
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErcNftType {
+    /// An ERC-721 token
+    Erc721,
+    /// An ERC-1155 token
+    Erc1155,
+}
+
+/// An NFT referenced by an avatar record, e.g.
+/// `eip155:1/erc721:0xb7F7F6C52F2e2fdb1963Eab30438024864c313F6/2430`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ErcNft {
+    /// The id of the chain the token lives on
+    pub chain_id: u64,
+    /// The token standard
+    pub typ: ErcNftType,
+    /// The token contract
+    pub contract: Address,
+    /// The token id
+    pub id: U256,
+}
+
+impl ErcNft {
+    /// Returns a transaction request for the metadata URI of the token
+    pub fn uri_request(&self) -> TransactionRequest {
+        let selector = match self.typ {
+            ErcNftType::Erc721 => ERC721_TOKEN_URI_SELECTOR,
+            ErcNftType::Erc1155 => ERC1155_URI_SELECTOR,
+        };
+        call(self.contract, [&selector[..], &abi::encode(&[Token::Uint(self.id)])].concat())
+    }
+
+    /// Returns a transaction request for checking whether `owner` owns the token. ERC-721
+    /// tokens return their owner, ERC-1155 tokens return the balance of `owner`.
+    pub fn owner_request(&self, owner: Address) -> TransactionRequest {
+        let data = match self.typ {
+            ErcNftType::Erc721 => {
+                [&ERC721_OWNER_SELECTOR[..], &abi::encode(&[Token::Uint(self.id)])].concat()
+            }
+            ErcNftType::Erc1155 => [
+                &ERC1155_BALANCE_SELECTOR[..],
+                &abi::encode(&[Token::Address(owner), Token::Uint(self.id)]),
+            ]
+            .concat(),
+        };
+        call(self.contract, data)
+    }
+
+    /// Substitutes the `{id}` placeholder of ERC-1155 metadata URIs
+    pub fn metadata_uri(&self, uri: &str) -> String {
+        match self.typ {
+            ErcNftType::Erc721 => uri.to_string(),
+            ErcNftType::Erc1155 => {
+                let mut id = [0u8; 32];
+                self.id.to_big_endian(&mut id);
+                uri.replace("{id}", &hex::encode(id))
+            }
+        }
+    }
+}
+
+impl FromStr for ErcNft {
+    type Err = ProviderError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || ProviderError::CustomError(format!("invalid NFT avatar: {}", s));
+        let rest = s.strip_prefix("eip155:").ok_or_else(err)?;
+        let mut parts = rest.split('/');
+        let (chain_id, asset, id) = match (parts.next(), parts.next(), parts.next(), parts.next()) {
+            (Some(chain_id), Some(asset), Some(id), None) => (chain_id, asset, id),
+            _ => return Err(err()),
+        };
+        let (typ, contract) = asset.split_once(':').ok_or_else(err)?;
+        let typ = match typ.to_ascii_lowercase().as_str() {
+            "erc721" => ErcNftType::Erc721,
+            "erc1155" => ErcNftType::Erc1155,
+            _ => return Err(err()),
+        };
+        Ok(ErcNft {
+            chain_id: chain_id.parse().map_err(|_| err())?,
+            typ,
+            contract: contract.parse().map_err(|_| err())?,
+            id: U256::from_dec_str(id).map_err(|_| err())?,
+        })
+    }
+}
+
+/// The fields of the ERC-721/ERC-1155 metadata JSON which may hold the image of the token
+#[derive(Debug, Clone, Default, Deserialize)]
+pub(crate) struct NftMetadata {
+    #[serde(default)]
+    image: Option<String>,
+    #[serde(default)]
+    image_url: Option<String>,
+    #[serde(default)]
+    image_data: Option<String>,
+}
+
+impl NftMetadata {
+    /// Returns the image URI, inline SVG images are returned as a `data:` URI
+    pub(crate) fn image(self) -> Option<String> {
+        self.image.or(self.image_url).or_else(|| {
+            self.image_data.map(|svg| format!("data:image/svg+xml;base64,{}", base64::encode(svg)))
+        })
+    }
+}
+
+/// Rewrites `ipfs://` URIs to the [`IPFS_GATEWAY`], other URIs are returned as is
+pub fn ipfs_gateway(uri: &str) -> String {
+    match uri.strip_prefix("ipfs://") {
+        Some(path) => format!("{}{}", IPFS_GATEWAY, path.trim_start_matches("ipfs/")),
+        None => uri.to_string(),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -86,4 +468,91 @@ mod tests {
             assert_hex(namehash(name), expected);
         }
     }
+
+    #[test]
+    fn test_dns_encode() {
+        assert_eq!(dns_encode("").unwrap(), vec![0]);
+        assert_eq!(dns_encode("foo.eth").unwrap(), b"\x03foo\x03eth\x00".to_vec());
+        assert!(dns_encode(&format!("{}.eth", "a".repeat(256))).is_err());
+    }
+
+    #[test]
+    fn test_resolver_call_data() {
+        let data = resolver_call_data(FIELD_SELECTOR, "foo.eth", &[Token::String("url".into())]);
+        assert_eq!(data[..4], FIELD_SELECTOR);
+        assert_eq!(data[4..36], namehash("foo.eth").0);
+        assert_eq!(
+            abi::decode(&[abi::ParamType::FixedBytes(32), abi::ParamType::String], &data[4..])
+                .unwrap()[1],
+            Token::String("url".into())
+        );
+    }
+
+    #[test]
+    fn test_decode_contenthash() {
+        // test vectors from EIP-1577
+        let ipfs = hex::decode(
+            "e3010170122029f2d17be6139079dc48696d1f582a8530eb9805b561eda517e22a892c7e3f1f",
+        )
+        .unwrap();
+        assert_eq!(
+            ContentHash::decode(&ipfs).unwrap().to_string(),
+            "ipfs://QmRAQB6YaCyidP37UdDnjFY5vQuiBrcqdyoW1CuDgwxkD4"
+        );
+
+        let swarm = hex::decode(
+            "e40101fa011b20d1de9994b4d039f6548d191eb26786769f580809256b4685ef316805265ea162",
+        )
+        .unwrap();
+        assert_eq!(
+            ContentHash::decode(&swarm).unwrap().to_string(),
+            "bzz://d1de9994b4d039f6548d191eb26786769f580809256b4685ef316805265ea162"
+        );
+
+        let ipns = [&[0xe5, 0x01, 0x01, 0x72, 0x00, 0x0b][..], b"example.com"].concat();
+        assert_eq!(ContentHash::decode(&ipns), Some(ContentHash::Ipns("example.com".into())));
+
+        assert_eq!(ContentHash::decode(&[]), None);
+        assert_eq!(
+            ContentHash::decode(&[0x01, 0x02]),
+            Some(ContentHash::Unknown(vec![0x01, 0x02].into()))
+        );
+    }
+
+    #[test]
+    fn test_base32_encode() {
+        assert_eq!(base32_encode(b""), "");
+        assert_eq!(base32_encode(b"f"), "my");
+        assert_eq!(base32_encode(b"foobar"), "mzxw6ytboi");
+    }
+
+    #[test]
+    fn test_parse_nft_avatar() {
+        let nft: ErcNft =
+            "eip155:1/erc721:0xb7F7F6C52F2e2fdb1963Eab30438024864c313F6/2430".parse().unwrap();
+        assert_eq!(nft.chain_id, 1);
+        assert_eq!(nft.typ, ErcNftType::Erc721);
+        assert_eq!(nft.contract, "0xb7F7F6C52F2e2fdb1963Eab30438024864c313F6".parse().unwrap());
+        assert_eq!(nft.id, U256::from(2430));
+
+        let nft: ErcNft =
+            "eip155:1/erc1155:0x495f947276749ce646f68ac8c248420045cb7b5e/1".parse().unwrap();
+        assert_eq!(nft.typ, ErcNftType::Erc1155);
+        assert_eq!(
+            nft.metadata_uri("https://api.example.com/{id}.json"),
+            format!("https://api.example.com/{}1.json", "0".repeat(63))
+        );
+
+        assert!("https://example.com/avatar.png".parse::<ErcNft>().is_err());
+        assert!("eip155:1/erc20:0xb7F7F6C52F2e2fdb1963Eab30438024864c313F6/1"
+            .parse::<ErcNft>()
+            .is_err());
+    }
+
+    #[test]
+    fn test_ipfs_gateway() {
+        assert_eq!(ipfs_gateway("ipfs://QmFoo"), "https://ipfs.io/ipfs/QmFoo");
+        assert_eq!(ipfs_gateway("ipfs://ipfs/QmFoo"), "https://ipfs.io/ipfs/QmFoo");
+        assert_eq!(ipfs_gateway("https://example.com/a.png"), "https://example.com/a.png");
+    }
 }
@@ -0,0 +1,232 @@
+//! Strategies estimating EIP-1559 fees from the `eth_feeHistory` of recent blocks
+use ethers_core::{
+    types::{FeeHistory, U256},
+    utils,
+};
+use std::fmt::Debug;
+
+/// Estimates the `max_fee_per_gas` and `max_priority_fee_per_gas` of EIP-1559 transactions
+/// from the fee history of recent blocks.
+///
+/// Configure an estimator with
+/// [`Provider::with_fee_estimator`](crate::Provider::with_fee_estimator) to use it for
+/// [`Middleware::estimate_eip1559_fees`](crate::Middleware::estimate_eip1559_fees) and when
+/// filling transactions.
+pub trait FeeEstimator: Debug + Send + Sync {
+    /// The number of recent blocks to fetch the fee history of
+    fn block_count(&self) -> u64;
+
+    /// The percentiles of the priority fees paid in each block to fetch
+    fn reward_percentiles(&self) -> Vec<f64>;
+
+    /// Returns the estimated `(max_fee_per_gas, max_priority_fee_per_gas)` given the fee history
+    /// of the latest blocks, fetched with the block count and percentiles of the estimator.
+    fn estimate(&self, fee_history: &FeeHistory) -> (U256, U256);
+}
+
+/// The default estimator, see [`utils::eip1559_default_estimator`]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DefaultFeeEstimator;
+
+impl FeeEstimator for DefaultFeeEstimator {
+    fn block_count(&self) -> u64 {
+        utils::EIP1559_FEE_ESTIMATION_PAST_BLOCKS
+    }
+
+    fn reward_percentiles(&self) -> Vec<f64> {
+        vec![utils::EIP1559_FEE_ESTIMATION_REWARD_PERCENTILE]
+    }
+
+    fn estimate(&self, fee_history: &FeeHistory) -> (U256, U256) {
+        // the last entry is the base fee of the next block, the estimator expects the latest one
+        let fees = &fee_history.base_fee_per_gas;
+        let base_fee_per_gas = fees.iter().rev().nth(1).or_else(|| fees.last()).copied();
+        utils::eip1559_default_estimator(
+            base_fee_per_gas.unwrap_or_default(),
+            fee_history.reward.clone(),
+        )
+    }
+}
+
+/// Estimates the priority fee as the median of the priority fees paid at a percentile in the
+/// recent blocks, and the max fee as the base fee it can afford for a number of blocks ahead.
+///
+/// ```
+/// use ethers_providers::{FeeUrgency, PercentileFeeEstimator};
+///
+/// // pays the 75th percentile, and stays includable if the next 5 blocks are full
+/// let estimator = PercentileFeeEstimator::new(75.0).blocks_ahead(5);
+///
+/// // presets
+/// let fast = PercentileFeeEstimator::from(FeeUrgency::Fast);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PercentileFeeEstimator {
+    block_count: u64,
+    percentile: f64,
+    blocks_ahead: u64,
+    min_priority_fee: U256,
+}
+
+impl PercentileFeeEstimator {
+    /// Pays the `percentile` of the priority fees paid in the last 10 blocks, with a max fee
+    /// covering the base fee of the next block
+    pub fn new(percentile: f64) -> Self {
+        Self {
+            block_count: utils::EIP1559_FEE_ESTIMATION_PAST_BLOCKS,
+            percentile,
+            blocks_ahead: 1,
+            min_priority_fee: U256::zero(),
+        }
+    }
+
+    /// Sets the number of recent blocks to sample the priority fees of (default: 10)
+    #[must_use]
+    pub fn past_blocks(mut self, block_count: u64) -> Self {
+        self.block_count = block_count;
+        self
+    }
+
+    /// Sets the number of blocks for which the max fee covers the base fee, assuming every
+    /// block in between is full and raises the base fee by the maximum of 12.5% (default: 1)
+    #[must_use]
+    pub fn blocks_ahead(mut self, blocks_ahead: u64) -> Self {
+        self.blocks_ahead = blocks_ahead;
+        self
+    }
+
+    /// Sets the lower bound of the estimated priority fee (default: 0)
+    #[must_use]
+    pub fn min_priority_fee(mut self, min_priority_fee: impl Into<U256>) -> Self {
+        self.min_priority_fee = min_priority_fee.into();
+        self
+    }
+}
+
+impl Default for PercentileFeeEstimator {
+    fn default() -> Self {
+        FeeUrgency::Standard.into()
+    }
+}
+
+impl FeeEstimator for PercentileFeeEstimator {
+    fn block_count(&self) -> u64 {
+        self.block_count
+    }
+
+    fn reward_percentiles(&self) -> Vec<f64> {
+        vec![self.percentile]
+    }
+
+    fn estimate(&self, fee_history: &FeeHistory) -> (U256, U256) {
+        // blocks without transactions report a reward of zero
+        let mut rewards: Vec<U256> = fee_history
+            .reward
+            .iter()
+            .filter_map(|rewards| rewards.first().copied())
+            .filter(|reward| !reward.is_zero())
+            .collect();
+        rewards.sort();
+        let median = rewards.get(rewards.len() / 2).copied().unwrap_or_default();
+        let max_priority_fee_per_gas = std::cmp::max(median, self.min_priority_fee);
+
+        let next_base_fee = fee_history.base_fee_per_gas.last().copied().unwrap_or_default();
+        let base_fee = project_base_fee(next_base_fee, self.blocks_ahead.saturating_sub(1));
+        (base_fee.saturating_add(max_priority_fee_per_gas), max_priority_fee_per_gas)
+    }
+}
+
+/// Presets of [`PercentileFeeEstimator`] trading off the fees paid against the time until
+/// inclusion
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FeeUrgency {
+    /// Pays the 10th percentile of priority fees, with a max fee covering 2 blocks
+    Slow,
+    /// Pays the median priority fee, with a max fee covering 4 blocks
+    Standard,
+    /// Pays the 90th percentile of priority fees, with a max fee covering 8 blocks
+    Fast,
+}
+
+impl From<FeeUrgency> for PercentileFeeEstimator {
+    fn from(urgency: FeeUrgency) -> Self {
+        let (percentile, blocks_ahead) = match urgency {
+            FeeUrgency::Slow => (10.0, 2),
+            FeeUrgency::Standard => (50.0, 4),
+            FeeUrgency::Fast => (90.0, 8),
+        };
+        PercentileFeeEstimator::new(percentile).blocks_ahead(blocks_ahead)
+    }
+}
+
+impl FeeEstimator for FeeUrgency {
+    fn block_count(&self) -> u64 {
+        PercentileFeeEstimator::from(*self).block_count()
+    }
+
+    fn reward_percentiles(&self) -> Vec<f64> {
+        PercentileFeeEstimator::from(*self).reward_percentiles()
+    }
+
+    fn estimate(&self, fee_history: &FeeHistory) -> (U256, U256) {
+        PercentileFeeEstimator::from(*self).estimate(fee_history)
+    }
+}
+
+/// Returns the highest base fee `blocks` blocks after a block with `base_fee`, reached if all
+/// blocks in between are full
+fn project_base_fee(base_fee: U256, blocks: u64) -> U256 {
+    (0..blocks).fold(base_fee, |fee, _| fee.saturating_add(fee / 8))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fee_history(base_fees: &[u64], rewards: &[u64]) -> FeeHistory {
+        FeeHistory {
+            base_fee_per_gas: base_fees.iter().map(|fee| U256::from(*fee)).collect(),
+            gas_used_ratio: vec![0.5; rewards.len()],
+            oldest_block: U256::one(),
+            reward: rewards.iter().map(|reward| vec![U256::from(*reward)]).collect(),
+        }
+    }
+
+    #[test]
+    fn percentile_estimator() {
+        let history = fee_history(&[100, 100, 100, 120], &[3, 0, 1, 2]);
+
+        // the median of the non-zero rewards, with the max fee covering the next block
+        let estimator = PercentileFeeEstimator::new(50.0);
+        assert_eq!(estimator.reward_percentiles(), vec![50.0]);
+        assert_eq!(estimator.estimate(&history), (122.into(), 2.into()));
+
+        // 120 -> 135 -> 151
+        let estimator = estimator.blocks_ahead(3).min_priority_fee(5);
+        assert_eq!(estimator.estimate(&history), (156.into(), 5.into()));
+
+        // empty blocks only
+        let history = fee_history(&[8, 7], &[0]);
+        assert_eq!(PercentileFeeEstimator::new(50.0).estimate(&history), (7.into(), 0.into()));
+    }
+
+    #[test]
+    fn urgency_presets() {
+        let history = fee_history(&[1_000, 1_000], &[10]);
+        let (slow, _) = FeeUrgency::Slow.estimate(&history);
+        let (standard, _) = FeeUrgency::Standard.estimate(&history);
+        let (fast, _) = FeeUrgency::Fast.estimate(&history);
+        assert!(slow < standard && standard < fast);
+        assert_eq!(FeeUrgency::Fast.reward_percentiles(), vec![90.0]);
+        assert_eq!(PercentileFeeEstimator::default(), FeeUrgency::Standard.into());
+    }
+
+    #[test]
+    fn default_estimator_uses_latest_base_fee() {
+        let history = fee_history(&[10_000_000_000, 20_000_000_000, 30_000_000_000], &[1, 1]);
+        let (max_fee, priority_fee) = DefaultFeeEstimator.estimate(&history);
+        let expected =
+            utils::eip1559_default_estimator(20_000_000_000u64.into(), history.reward.clone());
+        assert_eq!((max_fee, priority_fee), expected);
+    }
+}
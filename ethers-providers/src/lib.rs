@@ -11,8 +11,13 @@ mod provider;
 // ENS support
 pub mod ens;
 
+// EIP-3668 offchain lookup support
+pub mod ccip;
+
 mod pending_transaction;
-pub use pending_transaction::{PendingTransaction, PendingTxState};
+pub use pending_transaction::{
+    PendingTransaction, PendingTxState, ReplacementReason, TxResolution,
+};
 
 mod pending_escalator;
 pub use pending_escalator::EscalatingPending;
@@ -24,17 +29,41 @@ pub use stream::{interval, FilterWatcher, TransactionStream, DEFAULT_POLL_INTERV
 mod pubsub;
 pub use pubsub::{PubsubClient, SubscriptionStream};
 
+mod batch;
+pub use batch::{BatchItem, BatchRequest, BatchResponse};
+
+mod block_stream;
+pub use block_stream::{
+    BlockEvent, BlockStream, BlockTransaction, StreamedBlock, DEFAULT_REORG_DEPTH,
+};
+
+mod log_query;
+pub use log_query::{LogQuery, DEFAULT_LOG_QUERY_CHUNK_SIZE, DEFAULT_LOG_QUERY_CONCURRENCY};
+
+mod log_stream;
+pub use log_stream::{LogCheckpoint, ResumableLogStream};
+
+mod fee_estimator;
+pub use fee_estimator::{DefaultFeeEstimator, FeeEstimator, FeeUrgency, PercentileFeeEstimator};
+
+mod watch_stream;
+pub use watch_stream::{WatchSource, WatchStream};
+
+mod capabilities;
+pub use capabilities::{NodeCapabilities, NodeClient};
+
 use async_trait::async_trait;
 use auto_impl::auto_impl;
 use ethers_core::types::transaction::{eip2718::TypedTransaction, eip2930::AccessListWithGasUsed};
 use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
 use std::{error::Error, fmt::Debug, future::Future, pin::Pin};
 
 pub use provider::{FilterKind, Provider, ProviderError};
 
 // feature-enabled support for dev-rpc methods
 #[cfg(feature = "dev-rpc")]
-pub use provider::dev_rpc::DevRpcMiddleware;
+pub use provider::dev_rpc::{DevRpcMiddleware, Forking};
 
 /// A simple gas escalation policy
 pub type EscalationPolicy = Box<dyn Fn(U256, usize) -> U256 + Send + Sync>;
@@ -60,8 +89,59 @@ pub trait JsonRpcClient: Debug + Send + Sync {
     where
         T: Debug + Serialize + Send + Sync,
         R: DeserializeOwned;
+
+    /// Sends a batch of `(method, params)` requests, returning the responses in the same order
+    /// as the requests. JSON-RPC errors of the individual requests are returned per entry.
+    ///
+    /// Transports which support JSON-RPC batches send all requests in a single round trip. The
+    /// default implementation sends the requests one after another. Errors of the transport are
+    /// returned as internal JSON-RPC errors (code -32603) of the failed request.
+    async fn request_batch(
+        &self,
+        requests: Vec<(String, Value)>,
+    ) -> Result<Vec<Result<Value, JsonRpcError>>, Self::Error> {
+        let mut responses = Vec::with_capacity(requests.len());
+        for (method, params) in requests {
+            let response: Result<Value, Self::Error> = self.request(&method, params).await;
+            responses.push(response.map_err(|err| {
+                let err: ProviderError = err.into();
+                match err.as_error_response() {
+                    Some(err) => err.clone(),
+                    None => JsonRpcError { code: -32603, message: err.to_string(), data: None },
+                }
+            }));
+        }
+        Ok(responses)
+    }
+
+    /// Returns true if the transport supports `eth_subscribe` subscriptions, in which case
+    /// [`subscribe_notifications`](Self::subscribe_notifications) yields their notifications.
+    ///
+    /// Used by [`Provider::stream_blocks`] and friends to choose between a subscription and
+    /// polling a filter. The default implementation returns false, transports implementing
+    /// [`PubsubClient`] override it.
+    fn supports_pubsub(&self) -> bool {
+        false
+    }
+
+    /// Returns the notifications of the subscription with the node side `id`, or `None` if the
+    /// transport does not support subscriptions
+    fn subscribe_notifications(&self, id: U256) -> Option<Result<NotificationStream, Self::Error>> {
+        let _ = id;
+        None
+    }
+
+    /// Stops forwarding the notifications of the subscription with `id`
+    fn unsubscribe_notifications(&self, id: U256) -> Result<(), Self::Error> {
+        let _ = id;
+        Ok(())
+    }
 }
 
+/// The notifications of a subscription, as returned by
+/// [`JsonRpcClient::subscribe_notifications`]
+pub type NotificationStream = Pin<Box<dyn futures_core::Stream<Item = Value> + Send>>;
+
 use ethers_core::types::*;
 pub trait FromErr<T> {
     fn from(src: T) -> Self;
@@ -159,6 +239,11 @@ pub trait Middleware: Sync + Send + Debug {
         self.inner().client_version().await.map_err(FromErr::from)
     }
 
+    /// Returns the Keccak-256 hash of the data, as computed by the node
+    async fn sha3<T: Into<Bytes> + Send + Sync>(&self, data: T) -> Result<H256, Self::Error> {
+        self.inner().sha3(data).await.map_err(FromErr::from)
+    }
+
     /// Fill necessary details of a transaction for dispatch
     ///
     /// This function is defined on providers to behave as follows:
@@ -254,6 +339,25 @@ pub trait Middleware: Sync + Send + Debug {
         self.inner().lookup_address(address).await.map_err(FromErr::from)
     }
 
+    async fn resolve_field(&self, ens_name: &str, field: &str) -> Result<String, Self::Error> {
+        self.inner().resolve_field(ens_name, field).await.map_err(FromErr::from)
+    }
+
+    async fn resolve_contenthash(
+        &self,
+        ens_name: &str,
+    ) -> Result<Option<ens::ContentHash>, Self::Error> {
+        self.inner().resolve_contenthash(ens_name).await.map_err(FromErr::from)
+    }
+
+    async fn resolve_avatar(&self, ens_name: &str) -> Result<url::Url, Self::Error> {
+        self.inner().resolve_avatar(ens_name).await.map_err(FromErr::from)
+    }
+
+    async fn resolve_nft(&self, token: ens::ErcNft) -> Result<url::Url, Self::Error> {
+        self.inner().resolve_nft(token).await.map_err(FromErr::from)
+    }
+
     async fn get_block<T: Into<BlockId> + Send + Sync>(
         &self,
         block_hash_or_number: T,
@@ -303,6 +407,16 @@ pub trait Middleware: Sync + Send + Debug {
         self.inner().call(tx, block).await.map_err(FromErr::from)
     }
 
+    /// Executes bundles of calls on top of each other, starting from the state of `context`,
+    /// without creating transactions. Returns the outcome of each call of each bundle.
+    async fn call_many<T: Into<StateContext> + Send + Sync>(
+        &self,
+        bundles: Vec<CallBundle>,
+        context: T,
+    ) -> Result<Vec<Vec<CallResponse>>, Self::Error> {
+        self.inner().call_many(bundles, context).await.map_err(FromErr::from)
+    }
+
     async fn get_chainid(&self) -> Result<U256, Self::Error> {
         self.inner().get_chainid().await.map_err(FromErr::from)
     }
@@ -311,6 +425,14 @@ pub trait Middleware: Sync + Send + Debug {
         self.inner().get_net_version().await.map_err(FromErr::from)
     }
 
+    async fn get_peer_count(&self) -> Result<U64, Self::Error> {
+        self.inner().get_peer_count().await.map_err(FromErr::from)
+    }
+
+    async fn syncing(&self) -> Result<SyncingStatus, Self::Error> {
+        self.inner().syncing().await.map_err(FromErr::from)
+    }
+
     async fn get_balance<T: Into<NameOrAddress> + Send + Sync>(
         &self,
         from: T,
@@ -326,6 +448,14 @@ pub trait Middleware: Sync + Send + Debug {
         self.inner().get_transaction(transaction_hash).await.map_err(FromErr::from)
     }
 
+    async fn get_transaction_by_sender_and_nonce<T: Into<NameOrAddress> + Send + Sync>(
+        &self,
+        sender: T,
+        nonce: U256,
+    ) -> Result<Option<Transaction>, Self::Error> {
+        self.inner().get_transaction_by_sender_and_nonce(sender, nonce).await.map_err(FromErr::from)
+    }
+
     async fn get_transaction_receipt<T: Send + Sync + Into<TxHash>>(
         &self,
         transaction_hash: T,
@@ -333,10 +463,10 @@ pub trait Middleware: Sync + Send + Debug {
         self.inner().get_transaction_receipt(transaction_hash).await.map_err(FromErr::from)
     }
 
-    async fn get_block_receipts<T: Into<BlockNumber> + Send + Sync>(
+    async fn get_block_receipts<T: Into<BlockId> + Send + Sync>(
         &self,
         block: T,
-    ) -> Result<Vec<TransactionReceipt>, Self::Error> {
+    ) -> Result<Option<Vec<TransactionReceipt>>, Self::Error> {
         self.inner().get_block_receipts(block).await.map_err(FromErr::from)
     }
 
@@ -344,6 +474,10 @@ pub trait Middleware: Sync + Send + Debug {
         self.inner().get_gas_price().await.map_err(FromErr::from)
     }
 
+    async fn get_max_priority_fee_per_gas(&self) -> Result<U256, Self::Error> {
+        self.inner().get_max_priority_fee_per_gas().await.map_err(FromErr::from)
+    }
+
     async fn estimate_eip1559_fees(
         &self,
         estimator: Option<fn(U256, Vec<Vec<U256>>) -> (U256, U256)>,
@@ -392,6 +526,26 @@ pub trait Middleware: Sync + Send + Debug {
         self.inner().get_logs(filter).await.map_err(FromErr::from)
     }
 
+    /// Returns a stream of the logs matching the filter, which queries its block range in
+    /// chunks of `chunk_size` blocks and splits chunks further when the node rejects them as
+    /// too large. See [`LogQuery`] for the available options.
+    fn get_logs_paginated<'a>(&'a self, filter: &Filter, chunk_size: u64) -> LogQuery<'a, Self>
+    where
+        Self: Sized,
+    {
+        LogQuery::new(self, filter).chunk_size(chunk_size)
+    }
+
+    /// Returns a stream of the logs matching the filter which falls back to `eth_getLogs` and
+    /// reinstalls the filter when the node drops it, and can be resumed from a checkpoint. See
+    /// [`ResumableLogStream`] for details.
+    fn watch_resumable<'a>(&'a self, filter: &Filter) -> ResumableLogStream<'a, Self>
+    where
+        Self: Sized,
+    {
+        ResumableLogStream::new(self, filter).interval(self.provider().get_interval())
+    }
+
     async fn new_filter(&self, filter: FilterKind<'_>) -> Result<U256, Self::Error> {
         self.inner().new_filter(filter).await.map_err(FromErr::from)
     }
@@ -454,6 +608,21 @@ pub trait Middleware: Sync + Send + Debug {
         self.inner().get_proof(from, locations, block).await.map_err(FromErr::from)
     }
 
+    /// Returns the EIP-1186 proof of the account and storage slots at `block`, verified against
+    /// the state root of the block. The response must prove every requested slot exactly once.
+    ///
+    /// The state root is taken from the header returned by the node, so the proof is only as
+    /// trustworthy as the block. Use [`EIP1186ProofResponse::verify`] to check proofs against a
+    /// state root obtained from a trusted source.
+    async fn get_verified_proof<T: Into<NameOrAddress> + Send + Sync>(
+        &self,
+        from: T,
+        locations: Vec<H256>,
+        block: Option<BlockId>,
+    ) -> Result<EIP1186ProofResponse, Self::Error> {
+        self.inner().get_verified_proof(from, locations, block).await.map_err(FromErr::from)
+    }
+
     // Mempool inspection for Geth's API
 
     async fn txpool_content(&self) -> Result<TxpoolContent, Self::Error> {
@@ -539,6 +708,94 @@ pub trait Middleware: Sync + Send + Debug {
         self.inner().trace_transaction(hash).await.map_err(FromErr::from)
     }
 
+    // Geth `debug_trace*` support
+
+    /// Replays the transaction with the given tracer, see
+    /// [`debug_traceTransaction`](https://geth.ethereum.org/docs/rpc/ns-debug#debug_tracetransaction)
+    async fn debug_trace_transaction(
+        &self,
+        tx_hash: TxHash,
+        trace_options: GethDebugTracingOptions,
+    ) -> Result<GethTrace, Self::Error> {
+        self.inner().debug_trace_transaction(tx_hash, trace_options).await.map_err(FromErr::from)
+    }
+
+    /// Executes the given call on top of `block` with the given tracer, see
+    /// [`debug_traceCall`](https://geth.ethereum.org/docs/rpc/ns-debug#debug_tracecall)
+    async fn debug_trace_call<T: Into<TypedTransaction> + Send + Sync>(
+        &self,
+        req: T,
+        block: Option<BlockId>,
+        trace_options: GethDebugTracingOptions,
+    ) -> Result<GethTrace, Self::Error> {
+        self.inner().debug_trace_call(req, block, trace_options).await.map_err(FromErr::from)
+    }
+
+    /// Replays all transactions of the block with the given tracer, see
+    /// [`debug_traceBlockByNumber`](https://geth.ethereum.org/docs/rpc/ns-debug#debug_traceblockbynumber)
+    async fn debug_trace_block_by_number(
+        &self,
+        block: Option<BlockNumber>,
+        trace_options: GethDebugTracingOptions,
+    ) -> Result<Vec<GethTraceResult>, Self::Error> {
+        self.inner().debug_trace_block_by_number(block, trace_options).await.map_err(FromErr::from)
+    }
+
+    /// Replays all transactions of the block with the given tracer, see
+    /// [`debug_traceBlockByHash`](https://geth.ethereum.org/docs/rpc/ns-debug#debug_traceblockbyhash)
+    async fn debug_trace_block_by_hash(
+        &self,
+        block: H256,
+        trace_options: GethDebugTracingOptions,
+    ) -> Result<Vec<GethTraceResult>, Self::Error> {
+        self.inner().debug_trace_block_by_hash(block, trace_options).await.map_err(FromErr::from)
+    }
+
+    // Engine API support
+    //
+    // The `engine_*` methods are served on the authenticated port of execution clients, use a
+    // transport with JWT authentication, e.g. `Http::builder(url).auth(Authorization::jwt(key))`
+
+    /// Sends a new payload to the execution client for validation, see
+    /// [`engine_newPayloadV1`](https://github.com/ethereum/execution-apis/blob/main/src/engine/specification.md#engine_newpayloadv1)
+    async fn engine_new_payload_v1(
+        &self,
+        payload: ExecutionPayload,
+    ) -> Result<PayloadStatus, Self::Error> {
+        self.inner().engine_new_payload_v1(payload).await.map_err(FromErr::from)
+    }
+
+    /// Updates the head of the chain and optionally starts building a payload on top of it, see
+    /// [`engine_forkchoiceUpdatedV1`](https://github.com/ethereum/execution-apis/blob/main/src/engine/specification.md#engine_forkchoiceupdatedv1)
+    async fn engine_forkchoice_updated_v1(
+        &self,
+        state: ForkchoiceState,
+        attributes: Option<PayloadAttributes>,
+    ) -> Result<ForkchoiceUpdated, Self::Error> {
+        self.inner().engine_forkchoice_updated_v1(state, attributes).await.map_err(FromErr::from)
+    }
+
+    /// Returns the payload built by the build process `payload_id`, see
+    /// [`engine_getPayloadV1`](https://github.com/ethereum/execution-apis/blob/main/src/engine/specification.md#engine_getpayloadv1)
+    async fn engine_get_payload_v1(
+        &self,
+        payload_id: PayloadId,
+    ) -> Result<ExecutionPayload, Self::Error> {
+        self.inner().engine_get_payload_v1(payload_id).await.map_err(FromErr::from)
+    }
+
+    /// Exchanges the merge transition parameters with the execution client, see
+    /// [`engine_exchangeTransitionConfigurationV1`](https://github.com/ethereum/execution-apis/blob/main/src/engine/specification.md#engine_exchangetransitionconfigurationv1)
+    async fn engine_exchange_transition_configuration_v1(
+        &self,
+        config: TransitionConfiguration,
+    ) -> Result<TransitionConfiguration, Self::Error> {
+        self.inner()
+            .engine_exchange_transition_configuration_v1(config)
+            .await
+            .map_err(FromErr::from)
+    }
+
     // Parity namespace
 
     /// Returns all receipts for that block. Must be done on a parity node.
@@ -0,0 +1,383 @@
+use crate::Middleware;
+
+use ethers_core::types::{BlockNumber, Filter, FilterBlockOption, Log};
+
+use futures_core::stream::Stream;
+use futures_util::{stream, StreamExt, TryStreamExt};
+use std::{
+    fmt,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+/// The number of blocks a [`LogQuery`] requests with a single `eth_getLogs` by default
+pub const DEFAULT_LOG_QUERY_CHUNK_SIZE: u64 = 10_000;
+
+/// The number of `eth_getLogs` requests a [`LogQuery`] keeps in flight by default
+pub const DEFAULT_LOG_QUERY_CONCURRENCY: usize = 4;
+
+/// Error messages returned by nodes which reject a query over too many blocks or with too many
+/// results, in lowercase
+const RANGE_TOO_LARGE_ERRORS: [&str; 7] = [
+    "query returned more than",
+    "response size exceeded",
+    "response size is larger",
+    "block range",
+    "range too large",
+    "too many results",
+    "too many logs",
+];
+
+#[cfg(target_arch = "wasm32")]
+type LogStream<'a, E> = Pin<Box<dyn Stream<Item = Result<Log, E>> + 'a>>;
+#[cfg(not(target_arch = "wasm32"))]
+type LogStream<'a, E> = Pin<Box<dyn Stream<Item = Result<Log, E>> + Send + 'a>>;
+
+/// A stream of the logs matching a [`Filter`], for backfilling long block ranges.
+///
+/// The range of the filter is split into chunks of [`LogQuery::chunk_size`] blocks which are
+/// requested with up to [`LogQuery::concurrency`] concurrent `eth_getLogs` calls. When the node
+/// rejects a chunk because it spans too many blocks or matches too many logs (e.g. "query
+/// returned more than 10000 results"), the chunk is bisected until the node accepts it. The logs
+/// are yielded in the order of the chain regardless of the order in which the chunks complete.
+///
+/// A `latest` (or missing) end of the range is resolved once when the stream is first polled,
+/// minus [`LogQuery::confirmations`], so that the scanned range is fixed and logs of blocks
+/// which may still be reorged out can be excluded.
+///
+/// ```no_run
+/// # async fn foo() -> Result<(), Box<dyn std::error::Error>> {
+/// use ethers_core::types::{Address, Filter};
+/// use ethers_providers::{Middleware, Provider, Http, StreamExt};
+/// use std::convert::TryFrom;
+///
+/// let provider = Provider::<Http>::try_from("http://localhost:8545")?;
+/// let filter = Filter::new().address(Address::zero()).from_block(0u64);
+/// let mut logs = provider.get_logs_paginated(&filter, 2_000).confirmations(12);
+/// while let Some(log) = logs.next().await {
+///     println!("{:?}", log?);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[must_use = "log queries do nothing unless polled"]
+pub struct LogQuery<'a, M: Middleware> {
+    client: &'a M,
+    filter: Filter,
+    chunk_size: u64,
+    concurrency: usize,
+    confirmations: u64,
+    stream: Option<LogStream<'a, M::Error>>,
+}
+
+impl<'a, M: Middleware> LogQuery<'a, M> {
+    /// Creates a new query for the logs matching the filter
+    pub fn new(client: &'a M, filter: &Filter) -> Self {
+        Self {
+            client,
+            filter: filter.clone(),
+            chunk_size: DEFAULT_LOG_QUERY_CHUNK_SIZE,
+            concurrency: DEFAULT_LOG_QUERY_CONCURRENCY,
+            confirmations: 0,
+            stream: None,
+        }
+    }
+
+    /// Sets the number of blocks requested with a single `eth_getLogs` (default: 10000)
+    pub fn chunk_size(mut self, chunk_size: u64) -> Self {
+        self.chunk_size = chunk_size.max(1);
+        self
+    }
+
+    /// Sets the maximum number of concurrent `eth_getLogs` requests (default: 4)
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Sets the number of blocks below the chain head which are considered final. Blocks which
+    /// are not final are excluded from a range ending at `latest` (default: 0)
+    pub fn confirmations(mut self, confirmations: u64) -> Self {
+        self.confirmations = confirmations;
+        self
+    }
+
+    fn create_stream(&self) -> LogStream<'a, M::Error> {
+        let client = self.client;
+        let filter = self.filter.clone();
+        let chunk_size = self.chunk_size;
+        let concurrency = self.concurrency;
+        let confirmations = self.confirmations;
+
+        let chunks = async move { chunks(client, filter, chunk_size, confirmations).await };
+        Box::pin(
+            stream::once(chunks)
+                .map_ok(move |filters| {
+                    stream::iter(filters)
+                        .map(move |chunk| get_logs_bisecting(client, chunk))
+                        .buffered(concurrency)
+                })
+                .try_flatten()
+                .map_ok(|logs| stream::iter(logs.into_iter().map(Ok::<_, M::Error>)))
+                .try_flatten(),
+        )
+    }
+}
+
+impl<'a, M: Middleware> Stream for LogQuery<'a, M> {
+    type Item = Result<Log, M::Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        if self.stream.is_none() {
+            let stream = self.create_stream();
+            self.stream = Some(stream);
+        }
+        self.stream.as_mut().expect("stream is set").as_mut().poll_next(cx)
+    }
+}
+
+impl<'a, M: Middleware> fmt::Debug for LogQuery<'a, M> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LogQuery")
+            .field("client", &self.client)
+            .field("filter", &self.filter)
+            .field("chunk_size", &self.chunk_size)
+            .field("concurrency", &self.concurrency)
+            .field("confirmations", &self.confirmations)
+            .finish()
+    }
+}
+
+/// Splits the range of the filter into filters over at most `chunk_size` blocks
+async fn chunks<M: Middleware>(
+    client: &M,
+    filter: Filter,
+    chunk_size: u64,
+    confirmations: u64,
+) -> Result<Vec<Filter>, M::Error> {
+    let (from_block, to_block) = match filter.block_option {
+        FilterBlockOption::Range { from_block, to_block } => (from_block, to_block),
+        // a single block can not be split
+        FilterBlockOption::AtBlockHash(_) => return Ok(vec![filter]),
+    };
+
+    let explicit = |block: Option<BlockNumber>| match block {
+        Some(BlockNumber::Number(number)) => Some(number.as_u64()),
+        Some(BlockNumber::Earliest) => Some(0),
+        _ => None,
+    };
+    let (from, to) = match (explicit(from_block), explicit(to_block)) {
+        (Some(from), Some(to)) if confirmations == 0 => (from, to),
+        (from, to) => {
+            let head = client.get_block_number().await?.as_u64().saturating_sub(confirmations);
+            (from.unwrap_or(head), to.map(|to| to.min(head)).unwrap_or(head))
+        }
+    };
+
+    let mut chunks = Vec::new();
+    let mut start = from;
+    while start <= to {
+        let end = start.saturating_add(chunk_size - 1).min(to);
+        chunks.push(filter.clone().from_block(start).to_block(end));
+        if end == u64::MAX {
+            break
+        }
+        start = end + 1;
+    }
+    Ok(chunks)
+}
+
+/// Returns the logs matching the filter, bisecting its range while the node rejects it as too
+/// large
+async fn get_logs_bisecting<M: Middleware>(
+    client: &M,
+    filter: Filter,
+) -> Result<Vec<Log>, M::Error> {
+    let mut logs = Vec::new();
+    // ranges are popped from the back, so the lower half is queried first
+    let mut pending = vec![filter];
+    while let Some(filter) = pending.pop() {
+        let err = match client.get_logs(&filter).await {
+            Ok(mut chunk) => {
+                logs.append(&mut chunk);
+                continue
+            }
+            Err(err) => err,
+        };
+
+        match numeric_range(&filter) {
+            Some((from, to)) if from < to && is_range_too_large(&err.to_string()) => {
+                let mid = from + (to - from) / 2;
+                pending.push(filter.clone().from_block(mid + 1).to_block(to));
+                pending.push(filter.from_block(from).to_block(mid));
+            }
+            _ => return Err(err),
+        }
+    }
+    Ok(logs)
+}
+
+fn numeric_range(filter: &Filter) -> Option<(u64, u64)> {
+    match filter.block_option {
+        FilterBlockOption::Range {
+            from_block: Some(BlockNumber::Number(from)),
+            to_block: Some(BlockNumber::Number(to)),
+        } => Some((from.as_u64(), to.as_u64())),
+        _ => None,
+    }
+}
+
+/// Returns true if the error indicates that the node rejected a query over too many blocks or
+/// with too many results
+fn is_range_too_large(err: &str) -> bool {
+    let err = err.to_lowercase();
+    RANGE_TOO_LARGE_ERRORS.iter().any(|pattern| err.contains(pattern))
+}
+
+#[cfg(test)]
+#[cfg(not(target_arch = "wasm32"))]
+mod tests {
+    use super::*;
+    use crate::{JsonRpcClient, Provider, ProviderError};
+    use async_trait::async_trait;
+    use ethers_core::types::{Address, Bytes, U64};
+    use serde::{de::DeserializeOwned, Serialize};
+    use std::{
+        fmt::Debug,
+        sync::{Arc, Mutex},
+    };
+
+    /// A node with one log per block which rejects queries matching more than `max_logs` logs
+    #[derive(Debug, Clone, Default)]
+    struct LimitedNode {
+        head: u64,
+        max_logs: u64,
+        requests: Arc<Mutex<Vec<(u64, u64)>>>,
+    }
+
+    #[derive(Debug, thiserror::Error)]
+    #[error("query returned more than {0} results")]
+    struct TooManyResults(u64);
+
+    impl From<TooManyResults> for ProviderError {
+        fn from(err: TooManyResults) -> Self {
+            ProviderError::JsonRpcClientError(Box::new(err))
+        }
+    }
+
+    #[async_trait]
+    impl JsonRpcClient for LimitedNode {
+        type Error = TooManyResults;
+
+        async fn request<T, R>(&self, method: &str, params: T) -> Result<R, TooManyResults>
+        where
+            T: Debug + Serialize + Send + Sync,
+            R: DeserializeOwned,
+        {
+            let res = match method {
+                "eth_blockNumber" => serde_json::to_value(U64::from(self.head)).unwrap(),
+                "eth_getLogs" => {
+                    let params = serde_json::to_value(params).unwrap();
+                    let block = |key: &str| {
+                        serde_json::from_value::<U64>(params[0][key].clone()).unwrap().as_u64()
+                    };
+                    let (from, to) = (block("fromBlock"), block("toBlock"));
+                    self.requests.lock().unwrap().push((from, to));
+                    if to - from + 1 > self.max_logs {
+                        return Err(TooManyResults(self.max_logs))
+                    }
+                    serde_json::to_value((from..=to).map(log).collect::<Vec<_>>()).unwrap()
+                }
+                _ => unreachable!("unexpected request {}", method),
+            };
+            Ok(serde_json::from_value(res).unwrap())
+        }
+    }
+
+    fn log(block: u64) -> Log {
+        Log {
+            address: Address::zero(),
+            topics: vec![],
+            data: Bytes::default(),
+            block_hash: None,
+            block_number: Some(block.into()),
+            transaction_hash: None,
+            transaction_index: None,
+            log_index: None,
+            transaction_log_index: None,
+            log_type: None,
+            removed: None,
+        }
+    }
+
+    async fn blocks<M: Middleware>(query: LogQuery<'_, M>) -> Vec<u64> {
+        query.map(|log| log.unwrap().block_number.unwrap().as_u64()).collect().await
+    }
+
+    #[tokio::test]
+    async fn splits_range_into_chunks() {
+        let node = LimitedNode { head: 25, max_logs: 100, ..Default::default() };
+        let provider = Provider::new(node.clone());
+
+        let filter = Filter::new().from_block(0u64);
+        let query = provider.get_logs_paginated(&filter, 10).concurrency(2);
+        assert_eq!(blocks(query).await, (0..=25).collect::<Vec<_>>());
+
+        let mut requests = node.requests.lock().unwrap().clone();
+        requests.sort_unstable();
+        assert_eq!(requests, vec![(0, 9), (10, 19), (20, 25)]);
+    }
+
+    #[tokio::test]
+    async fn bisects_rejected_ranges() {
+        let node = LimitedNode { head: 200, max_logs: 30, ..Default::default() };
+        let provider = Provider::new(node.clone());
+
+        let filter = Filter::new().from_block(0u64).to_block(99u64);
+        let query = provider.get_logs_paginated(&filter, 100);
+        assert_eq!(blocks(query).await, (0..=99).collect::<Vec<_>>());
+
+        // the rejected ranges are split in halves, lower half first
+        let requests = node.requests.lock().unwrap().clone();
+        assert_eq!(
+            requests,
+            vec![(0, 99), (0, 49), (0, 24), (25, 49), (50, 99), (50, 74), (75, 99)]
+        );
+    }
+
+    #[tokio::test]
+    async fn excludes_unconfirmed_blocks() {
+        let node = LimitedNode { head: 100, max_logs: 100, ..Default::default() };
+        let provider = Provider::new(node);
+
+        let filter = Filter::new().from_block(85u64);
+        let query = provider.get_logs_paginated(&filter, 10).confirmations(10);
+        assert_eq!(blocks(query).await, (85..=90).collect::<Vec<_>>());
+
+        let filter = Filter::new().from_block(95u64).to_block(100u64);
+        let query = provider.get_logs_paginated(&filter, 10).confirmations(10);
+        assert!(blocks(query).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn fails_when_a_single_block_is_rejected() {
+        let node = LimitedNode { head: 10, max_logs: 0, ..Default::default() };
+        let provider = Provider::new(node);
+
+        let filter = Filter::new().from_block(0u64).to_block(3u64);
+        let mut query = provider.get_logs_paginated(&filter, 10);
+        let err = query.next().await.unwrap().unwrap_err();
+        assert_eq!(err.to_string(), "query returned more than 0 results");
+    }
+
+    #[test]
+    fn detects_range_errors() {
+        assert!(is_range_too_large("query returned more than 10000 results"));
+        assert!(is_range_too_large(
+            "Log response size exceeded. You can make eth_getLogs requests with up to a 2K block \
+             range and no limit on the response size"
+        ));
+        assert!(is_range_too_large("block range is too wide"));
+        assert!(!is_range_too_large("execution reverted"));
+    }
+}
@@ -0,0 +1,557 @@
+use crate::{
+    stream::{interval, DEFAULT_POLL_INTERVAL},
+    FilterKind, Middleware,
+};
+
+use ethers_core::types::{BlockNumber, Filter, FilterBlockOption, Log, U256};
+
+use futures_core::{stream::Stream, Future};
+use futures_util::{StreamExt, TryStreamExt};
+use serde::{Deserialize, Serialize};
+use std::{
+    cmp::Ordering,
+    fmt::Debug,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+    vec::IntoIter,
+};
+use tracing::trace;
+
+/// Error messages returned by nodes which no longer know a filter, in lowercase
+const FILTER_NOT_FOUND_ERRORS: [&str; 3] = ["filter not found", "filter with id", "unknown filter"];
+
+/// The position of a [`ResumableLogStream`] in the chain: every log of the blocks before
+/// `block_number` and every log of `block_number` up to and including `log_index` has been
+/// processed. A missing `log_index` marks the whole block as processed.
+///
+/// Checkpoints are ordered by their position, so a log comes after a checkpoint if its own
+/// checkpoint is greater.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogCheckpoint {
+    /// The number of the last processed block
+    pub block_number: u64,
+    /// The index of the last processed log in the block, `None` if all of its logs were
+    /// processed
+    pub log_index: Option<u64>,
+}
+
+impl LogCheckpoint {
+    /// Returns the checkpoint after all logs of the block
+    pub fn block(block_number: u64) -> Self {
+        Self { block_number, log_index: None }
+    }
+
+    /// Returns the checkpoint of a mined log, `None` for pending logs
+    pub fn from_log(log: &Log) -> Option<Self> {
+        Some(Self {
+            block_number: log.block_number?.as_u64(),
+            log_index: Some(log.log_index?.low_u64()),
+        })
+    }
+
+    /// Returns the checkpoint right before this one, `None` if it is the start of the chain
+    fn previous(&self) -> Option<Self> {
+        match self.log_index {
+            Some(0) => self.block_number.checked_sub(1).map(Self::block),
+            Some(index) => {
+                Some(Self { block_number: self.block_number, log_index: Some(index - 1) })
+            }
+            None => Some(Self { block_number: self.block_number, log_index: Some(u64::MAX) }),
+        }
+    }
+
+    /// The first block which may contain unprocessed logs
+    fn next_block(&self) -> u64 {
+        match self.log_index {
+            Some(_) => self.block_number,
+            None => self.block_number + 1,
+        }
+    }
+}
+
+impl Ord for LogCheckpoint {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.block_number.cmp(&other.block_number).then_with(|| {
+            match (self.log_index, other.log_index) {
+                (Some(a), Some(b)) => a.cmp(&b),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => Ordering::Equal,
+            }
+        })
+    }
+}
+
+impl PartialOrd for LogCheckpoint {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Fetches the logs of a filter, with an installed filter if the node keeps it and with
+/// `eth_getLogs` from its position otherwise
+#[derive(Debug)]
+struct LogCursor {
+    filter: Filter,
+    position: Option<LogCheckpoint>,
+    filter_id: Option<U256>,
+    chunk_size: u64,
+}
+
+impl LogCursor {
+    /// Returns the logs after the position of the cursor and advances it
+    async fn update<M: Middleware>(&mut self, provider: &M) -> Result<Vec<Log>, M::Error> {
+        let (logs, head) = match self.filter_id {
+            Some(id) => match provider.get_filter_changes(id).await {
+                Ok(logs) => (logs, None),
+                Err(err) if is_filter_not_found(&err.to_string()) => {
+                    trace!(?err, "log filter was dropped, catching up with eth_getLogs");
+                    self.filter_id = None;
+                    // a node behind a load balancer may still know the filter
+                    let _ = provider.uninstall_filter(id).await;
+                    self.catch_up(provider).await?
+                }
+                Err(err) => return Err(err),
+            },
+            None => self.catch_up(provider).await?,
+        };
+
+        // rewinds to the first log removed by a reorg, so that the logs of the new chain are
+        // returned even if they are at or before the position
+        let removed = logs
+            .iter()
+            .filter(|log| log.removed == Some(true))
+            .filter_map(LogCheckpoint::from_log)
+            .min();
+        if let Some(removed) = removed {
+            if self.position.map_or(false, |position| removed <= position) {
+                trace!(?removed, "logs were removed by a reorg, rewinding");
+                self.position = removed.previous();
+            }
+        }
+
+        let mut new_logs = Vec::with_capacity(logs.len());
+        for log in logs {
+            if log.removed == Some(true) {
+                continue
+            }
+            let checkpoint = match LogCheckpoint::from_log(&log) {
+                Some(checkpoint) => checkpoint,
+                None => continue,
+            };
+            // skips the logs which were already returned, by `eth_getLogs` or the filter
+            if self.position.map_or(true, |position| position < checkpoint) {
+                self.position = Some(checkpoint);
+                new_logs.push(log);
+            }
+        }
+
+        if let Some(head) = head.map(LogCheckpoint::block) {
+            self.position = Some(self.position.map_or(head, |position| position.max(head)));
+        }
+        Ok(new_logs)
+    }
+
+    /// Installs a new filter and fetches the logs since the position of the cursor with
+    /// `eth_getLogs`. Returns the logs with the block they were fetched up to.
+    async fn catch_up<M: Middleware>(
+        &mut self,
+        provider: &M,
+    ) -> Result<(Vec<Log>, Option<u64>), M::Error> {
+        // the filter is installed before querying the logs so that no block falls between
+        // them, the logs returned by both are skipped by the position
+        let mut changes = self.filter.clone();
+        changes.block_option = FilterBlockOption::default();
+        let id = provider.new_filter(FilterKind::Logs(&changes)).await?;
+
+        match self.logs_since_position(provider).await {
+            Ok((logs, head)) => {
+                self.filter_id = Some(id);
+                Ok((logs, Some(head)))
+            }
+            Err(err) => {
+                let _ = provider.uninstall_filter(id).await;
+                Err(err)
+            }
+        }
+    }
+
+    /// Fetches the logs from the position of the cursor to the current head with `eth_getLogs`
+    async fn logs_since_position<M: Middleware>(
+        &self,
+        provider: &M,
+    ) -> Result<(Vec<Log>, u64), M::Error> {
+        let head = provider.get_block_number().await?.as_u64();
+
+        let from = match self.position {
+            Some(position) => Some(position.next_block()),
+            None => match self.filter.block_option {
+                FilterBlockOption::Range {
+                    from_block: Some(BlockNumber::Number(from)), ..
+                } => Some(from.as_u64()),
+                FilterBlockOption::Range { from_block: Some(BlockNumber::Earliest), .. } => Some(0),
+                // starts with the blocks after the current head
+                _ => None,
+            },
+        };
+        let logs = match from {
+            Some(from) if from <= head => {
+                let filter = self.filter.clone().from_block(from).to_block(head);
+                provider.get_logs_paginated(&filter, self.chunk_size).try_collect().await?
+            }
+            _ => Vec::new(),
+        };
+        Ok((logs, head))
+    }
+}
+
+/// Returns true if the error indicates that the node does not know the filter
+fn is_filter_not_found(err: &str) -> bool {
+    let err = err.to_lowercase();
+    FILTER_NOT_FOUND_ERRORS.iter().any(|pattern| err.contains(pattern))
+}
+
+#[cfg(target_arch = "wasm32")]
+type UpdateFut<'a, E> = Pin<Box<dyn Future<Output = (LogCursor, Result<Vec<Log>, E>)> + 'a>>;
+#[cfg(not(target_arch = "wasm32"))]
+type UpdateFut<'a, E> = Pin<Box<dyn Future<Output = (LogCursor, Result<Vec<Log>, E>)> + Send + 'a>>;
+
+enum ResumableLogStreamState<'a, E> {
+    Start,
+    WaitForInterval,
+    Update(UpdateFut<'a, E>),
+    NextLog(IntoIter<Log>),
+}
+
+/// A stream of the logs matching a filter which survives the node dropping the filter.
+///
+/// The stream installs a filter and polls its changes, like [`FilterWatcher`]. When the node
+/// no longer knows the filter, e.g. because it expired or the node restarted, the logs since
+/// the last returned log are fetched with `eth_getLogs` and a new filter is installed. Logs
+/// returned twice are skipped by their position. Logs which the filter reports as removed by a
+/// reorg are not returned, instead the stream rewinds before them so that the logs replacing
+/// them are returned, even if they are at or before the last checkpoint.
+///
+/// The [`LogCheckpoint`] of the stream can be persisted, and a new stream resumed from it with
+/// [`ResumableLogStream::from_checkpoint`]. Without a checkpoint, the stream starts at the
+/// `from_block` of the filter, or with the blocks after the current head if it is not a number.
+///
+/// ```no_run
+/// # async fn foo() -> Result<(), Box<dyn std::error::Error>> {
+/// use ethers_core::types::{Address, Filter};
+/// use ethers_providers::{LogCheckpoint, Middleware, Provider, Http, StreamExt};
+/// use std::convert::TryFrom;
+///
+/// let provider = Provider::<Http>::try_from("http://localhost:8545")?;
+/// let filter = Filter::new().address(Address::zero());
+/// let checkpoint = LogCheckpoint { block_number: 14_000_000, log_index: Some(3) };
+/// let mut stream = provider.watch_resumable(&filter).from_checkpoint(checkpoint);
+/// while let Some(log) = stream.next().await {
+///     println!("{:?}", log?);
+///     // persist `stream.checkpoint()`
+/// }
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`FilterWatcher`]: crate::FilterWatcher
+#[must_use = "streams do nothing unless polled"]
+pub struct ResumableLogStream<'a, M: Middleware> {
+    provider: &'a M,
+    interval: Box<dyn Stream<Item = ()> + Send + Unpin>,
+    cursor: Option<LogCursor>,
+    /// The position after the last log returned by the stream
+    checkpoint: Option<LogCheckpoint>,
+    /// The position of the cursor after the logs which are being returned
+    end: Option<LogCheckpoint>,
+    state: ResumableLogStreamState<'a, M::Error>,
+}
+
+impl<'a, M: Middleware> ResumableLogStream<'a, M> {
+    /// Creates a stream of the logs matching the filter, which is polled every 7 seconds
+    /// unless an [`interval`](Self::interval) is set
+    pub fn new(provider: &'a M, filter: &Filter) -> Self {
+        Self {
+            provider,
+            interval: Box::new(interval(DEFAULT_POLL_INTERVAL)),
+            cursor: Some(LogCursor {
+                filter: filter.clone(),
+                position: None,
+                filter_id: None,
+                chunk_size: crate::DEFAULT_LOG_QUERY_CHUNK_SIZE,
+            }),
+            checkpoint: None,
+            end: None,
+            state: ResumableLogStreamState::Start,
+        }
+    }
+
+    /// Resumes the stream after the checkpoint
+    pub fn from_checkpoint(mut self, checkpoint: LogCheckpoint) -> Self {
+        if let Some(cursor) = self.cursor.as_mut() {
+            cursor.position = Some(checkpoint);
+        }
+        self.checkpoint = Some(checkpoint);
+        self
+    }
+
+    /// Sets the polling interval
+    pub fn interval(mut self, duration: Duration) -> Self {
+        self.interval = Box::new(interval(duration));
+        self
+    }
+
+    /// Sets the number of blocks requested with a single `eth_getLogs` while catching up
+    /// (default: 10000)
+    pub fn chunk_size(mut self, chunk_size: u64) -> Self {
+        if let Some(cursor) = self.cursor.as_mut() {
+            cursor.chunk_size = chunk_size.max(1);
+        }
+        self
+    }
+
+    /// Returns the position after the last log returned by the stream, which can be persisted
+    /// to resume from it later
+    pub fn checkpoint(&self) -> Option<LogCheckpoint> {
+        self.checkpoint
+    }
+}
+
+fn update<M: Middleware>(provider: &M, mut cursor: LogCursor) -> UpdateFut<'_, M::Error> {
+    Box::pin(async move {
+        let res = cursor.update(provider).await;
+        (cursor, res)
+    })
+}
+
+impl<'a, M: Middleware> Stream for ResumableLogStream<'a, M> {
+    type Item = Result<Log, M::Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            this.state = match &mut this.state {
+                ResumableLogStreamState::Start => {
+                    let cursor = this.cursor.take().expect("cursor is returned by every update");
+                    ResumableLogStreamState::Update(update(this.provider, cursor))
+                }
+                ResumableLogStreamState::WaitForInterval => {
+                    // Wait the polling period
+                    let _ready = futures_util::ready!(this.interval.poll_next_unpin(cx));
+                    let cursor = this.cursor.take().expect("cursor is returned by every update");
+                    ResumableLogStreamState::Update(update(this.provider, cursor))
+                }
+                ResumableLogStreamState::Update(fut) => {
+                    let (cursor, res) = futures_util::ready!(fut.as_mut().poll(cx));
+                    this.end = cursor.position;
+                    this.cursor = Some(cursor);
+                    match res {
+                        Ok(logs) => ResumableLogStreamState::NextLog(logs.into_iter()),
+                        Err(err) => {
+                            this.state = ResumableLogStreamState::WaitForInterval;
+                            return Poll::Ready(Some(Err(err)))
+                        }
+                    }
+                }
+                ResumableLogStreamState::NextLog(logs) => match logs.next() {
+                    Some(log) => {
+                        this.checkpoint = LogCheckpoint::from_log(&log);
+                        return Poll::Ready(Some(Ok(log)))
+                    }
+                    None => {
+                        this.checkpoint = this.end;
+                        ResumableLogStreamState::WaitForInterval
+                    }
+                },
+            };
+        }
+    }
+}
+
+impl<'a, M: Middleware> Debug for ResumableLogStream<'a, M> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ResumableLogStream").field("checkpoint", &self.checkpoint).finish()
+    }
+}
+
+#[cfg(test)]
+#[cfg(not(target_arch = "wasm32"))]
+mod tests {
+    use super::*;
+    use crate::{JsonRpcError, Provider};
+    use ethers_core::types::{Address, Bytes, U256, U64};
+
+    fn log(block: u64, index: u64) -> Log {
+        Log {
+            address: Address::zero(),
+            topics: vec![],
+            data: Bytes::default(),
+            block_hash: None,
+            block_number: Some(block.into()),
+            transaction_hash: None,
+            transaction_index: None,
+            log_index: Some(index.into()),
+            transaction_log_index: None,
+            log_type: None,
+            removed: None,
+        }
+    }
+
+    fn filter_not_found() -> JsonRpcError {
+        JsonRpcError { code: -32000, message: "filter not found".to_string(), data: None }
+    }
+
+    fn checkpoint(block_number: u64, log_index: u64) -> Option<LogCheckpoint> {
+        Some(LogCheckpoint { block_number, log_index: Some(log_index) })
+    }
+
+    #[test]
+    fn orders_checkpoints() {
+        assert!(checkpoint(1, 5) < checkpoint(2, 0));
+        assert!(checkpoint(2, 0) < checkpoint(2, 1));
+        assert!(checkpoint(2, 1) < Some(LogCheckpoint::block(2)));
+        assert!(Some(LogCheckpoint::block(2)) < checkpoint(3, 0));
+
+        let json = serde_json::to_string(&LogCheckpoint::block(7)).unwrap();
+        assert_eq!(json, r#"{"blockNumber":7,"logIndex":null}"#);
+    }
+
+    #[tokio::test]
+    async fn resumes_after_dropped_filter() {
+        let (provider, mock) = Provider::mocked();
+
+        // responses are popped from the back
+        // the filter was dropped, catch up from block 13 to 14
+        mock.push(vec![log(13, 0), log(14, 0)]).unwrap();
+        mock.push(U64::from(14)).unwrap();
+        mock.push(U256::from(2)).unwrap();
+        mock.push(false).unwrap();
+        mock.push_error(filter_not_found());
+        // changes of the filter
+        mock.push(vec![log(12, 0), log(13, 0)]).unwrap();
+        // catch up from the checkpoint in block 10 to 12
+        mock.push(vec![log(10, 1), log(10, 2), log(12, 0)]).unwrap();
+        mock.push(U64::from(12)).unwrap();
+        mock.push(U256::from(1)).unwrap();
+
+        let mut stream = provider
+            .watch_resumable(&Filter::new())
+            .from_checkpoint(checkpoint(10, 1).unwrap())
+            .interval(Duration::from_millis(1));
+
+        let mut logs = Vec::new();
+        for _ in 0..4 {
+            let log = stream.next().await.unwrap().unwrap();
+            assert_eq!(stream.checkpoint(), LogCheckpoint::from_log(&log));
+            logs.push(log);
+        }
+        assert_eq!(logs, vec![log(10, 2), log(12, 0), log(13, 0), log(14, 0)]);
+
+        let filter = Filter::new().from_block(10u64).to_block(12u64);
+        mock.assert_request("eth_newFilter", [Filter::new()]).unwrap();
+        mock.assert_request("eth_blockNumber", ()).unwrap();
+        mock.assert_request("eth_getLogs", [filter]).unwrap();
+        mock.assert_request("eth_getFilterChanges", [U256::from(1)]).unwrap();
+        mock.assert_request("eth_getFilterChanges", [U256::from(1)]).unwrap();
+        mock.assert_request("eth_uninstallFilter", [U256::from(1)]).unwrap();
+        mock.assert_request("eth_newFilter", [Filter::new()]).unwrap();
+        mock.assert_request("eth_blockNumber", ()).unwrap();
+        let filter = Filter::new().from_block(13u64).to_block(14u64);
+        mock.assert_request("eth_getLogs", [filter]).unwrap();
+    }
+
+    #[tokio::test]
+    async fn starts_after_head_without_checkpoint() {
+        let (provider, mock) = Provider::mocked();
+
+        // responses are popped from the back
+        mock.push(vec![log(20, 0), log(21, 0)]).unwrap();
+        mock.push(U64::from(20)).unwrap();
+        mock.push(U256::from(1)).unwrap();
+
+        let mut stream =
+            provider.watch_resumable(&Filter::new()).interval(Duration::from_millis(1));
+        let log = stream.next().await.unwrap().unwrap();
+        assert_eq!(log, self::log(21, 0));
+        assert_eq!(stream.checkpoint(), checkpoint(21, 0));
+    }
+
+    #[tokio::test]
+    async fn returns_other_filter_errors() {
+        let (provider, mock) = Provider::mocked();
+        let timeout =
+            JsonRpcError { code: -32603, message: "request timed out".into(), data: None };
+
+        // responses are popped from the back
+        mock.push(vec![log(13, 0)]).unwrap();
+        mock.push_error(timeout);
+        mock.push(U64::from(12)).unwrap();
+        mock.push(U256::from(1)).unwrap();
+
+        let mut stream = provider
+            .watch_resumable(&Filter::new())
+            .from_checkpoint(LogCheckpoint::block(12))
+            .interval(Duration::from_millis(1));
+
+        // the error is returned and the filter is polled again
+        let err = stream.next().await.unwrap().unwrap_err();
+        assert!(err.to_string().contains("request timed out"));
+        assert_eq!(stream.next().await.unwrap().unwrap(), log(13, 0));
+
+        mock.assert_request("eth_newFilter", [Filter::new()]).unwrap();
+        mock.assert_request("eth_blockNumber", ()).unwrap();
+        mock.assert_request("eth_getFilterChanges", [U256::from(1)]).unwrap();
+        mock.assert_request("eth_getFilterChanges", [U256::from(1)]).unwrap();
+        assert!(mock.assert_request("eth_newFilter", [Filter::new()]).is_err());
+    }
+
+    #[tokio::test]
+    async fn uninstalls_filter_if_catch_up_fails() {
+        let (provider, mock) = Provider::mocked();
+
+        // responses are popped from the back
+        mock.push(true).unwrap();
+        mock.push_error(JsonRpcError { code: -32603, message: "internal".into(), data: None });
+        mock.push(U256::from(1)).unwrap();
+
+        let mut stream = provider.watch_resumable(&Filter::new());
+        assert!(stream.next().await.unwrap().is_err());
+
+        mock.assert_request("eth_newFilter", [Filter::new()]).unwrap();
+        mock.assert_request("eth_blockNumber", ()).unwrap();
+        mock.assert_request("eth_uninstallFilter", [U256::from(1)]).unwrap();
+    }
+
+    #[tokio::test]
+    async fn rewinds_after_reorg() {
+        let (provider, mock) = Provider::mocked();
+        let removed = Log { removed: Some(true), ..log(12, 0) };
+
+        // responses are popped from the back
+        // block 12 was reorged, its new logs are returned again
+        mock.push(vec![removed, log(12, 0), log(12, 1)]).unwrap();
+        mock.push(U64::from(12)).unwrap();
+        mock.push(U256::from(1)).unwrap();
+
+        let mut stream = provider
+            .watch_resumable(&Filter::new())
+            .from_checkpoint(LogCheckpoint::block(12))
+            .interval(Duration::from_millis(1));
+
+        assert_eq!(stream.next().await.unwrap().unwrap(), log(12, 0));
+        assert_eq!(stream.checkpoint(), checkpoint(12, 0));
+        assert_eq!(stream.next().await.unwrap().unwrap(), log(12, 1));
+        assert_eq!(stream.checkpoint(), checkpoint(12, 1));
+    }
+
+    #[test]
+    fn detects_filter_not_found_errors() {
+        assert!(is_filter_not_found("filter not found"));
+        assert!(is_filter_not_found("Filter with id: '0x1' does not exist."));
+        assert!(!is_filter_not_found("request timed out"));
+        assert_eq!(checkpoint(12, 0).unwrap().previous(), Some(LogCheckpoint::block(11)));
+        assert_eq!(checkpoint(12, 3).unwrap().previous(), checkpoint(12, 2));
+    }
+}
@@ -2,7 +2,10 @@ use crate::{
     stream::{interval, DEFAULT_POLL_INTERVAL},
     JsonRpcClient, Middleware, PinBoxFut, Provider, ProviderError,
 };
-use ethers_core::types::{Transaction, TransactionReceipt, TxHash, U64};
+use ethers_core::types::{
+    transaction::eip2718::TypedTransaction, Address, BlockNumber, NameOrAddress, Transaction,
+    TransactionReceipt, TxHash, U256, U64,
+};
 use futures_core::stream::Stream;
 use futures_util::stream::StreamExt;
 use pin_project::pin_project;
@@ -17,14 +20,20 @@ use std::{
 
 #[cfg(not(target_arch = "wasm32"))]
 use futures_timer::Delay;
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Instant;
 #[cfg(target_arch = "wasm32")]
-use wasm_timer::Delay;
+use wasm_timer::{Delay, Instant};
 
 /// A pending transaction is a transaction which has been submitted but is not yet mined.
 /// `await`'ing on a pending transaction will resolve to a transaction receipt
 /// once the transaction has enough `confirmations`. The default number of confirmations
 /// is 1, but may be adjusted with the `confirmations` method. If the transaction does not
-/// have enough confirmations or is not mined, the future will stay in the pending state.
+/// have enough confirmations or is not mined, the future will stay in the pending state,
+/// unless a [`timeout`](Self::timeout) is set.
+///
+/// Use [`resolve`](Self::resolve) to also detect transactions which are replaced by another
+/// transaction of the sender with the same nonce.
 #[pin_project]
 pub struct PendingTransaction<'a, P> {
     tx_hash: TxHash,
@@ -32,6 +41,11 @@ pub struct PendingTransaction<'a, P> {
     provider: &'a Provider<P>,
     state: PendingTxState<'a>,
     interval: Box<dyn Stream<Item = ()> + Send + Unpin>,
+    /// Gives up on transactions which are not mined this long after the creation
+    timeout: Option<Duration>,
+    started: Instant,
+    /// The transaction as it was sent, if its sender and nonce are known
+    request: Option<Transaction>,
 }
 
 impl<'a, P: JsonRpcClient> PendingTransaction<'a, P> {
@@ -44,6 +58,9 @@ impl<'a, P: JsonRpcClient> PendingTransaction<'a, P> {
             provider,
             state: PendingTxState::InitialDelay(delay),
             interval: Box::new(interval(DEFAULT_POLL_INTERVAL)),
+            timeout: None,
+            started: Instant::now(),
+            request: None,
         }
     }
 
@@ -80,6 +97,261 @@ impl<'a, P: JsonRpcClient> PendingTransaction<'a, P> {
 
         self
     }
+
+    /// Sets the time after the creation of the pending transaction at which it is considered
+    /// dropped if it is not mined yet (default: none). Awaiting the pending transaction then
+    /// resolves to `None`.
+    #[must_use]
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Sets the transaction which was sent, so that [`resolve`](Self::resolve) detects its
+    /// replacement or drop from the nonce of the sender even if the node never returns the
+    /// transaction. Ignored if the sender or the nonce of the transaction is not set.
+    #[must_use]
+    pub fn request(mut self, tx: &TypedTransaction) -> Self {
+        if let (Some(from), Some(nonce)) = (tx.from(), tx.nonce()) {
+            let to = match tx.to() {
+                Some(NameOrAddress::Address(to)) => Some(*to),
+                _ => None,
+            };
+            self.request = Some(Transaction {
+                hash: self.tx_hash,
+                from: *from,
+                nonce: *nonce,
+                to,
+                value: tx.value().cloned().unwrap_or_default(),
+                input: tx.data().cloned().unwrap_or_default(),
+                ..Default::default()
+            });
+        }
+        self
+    }
+
+    /// Tracks the transaction until it is mined with enough confirmations, replaced by another
+    /// transaction of the sender or dropped, similar to `TransactionReplaced` in ethers.js.
+    ///
+    /// Once the transaction is not found and the nonce of its sender advanced past it, the
+    /// replacement is searched for in the block in which the nonce was used, and awaited with
+    /// the same number of confirmations. The transaction is dropped if it leaves the mempool
+    /// without another pending transaction taking its nonce, or if it is not mined before the
+    /// [`timeout`](Self::timeout).
+    ///
+    /// The sender and nonce are known once the node returned the transaction, or from the
+    /// [`request`](Self::request), which is set by [`Middleware::send_transaction`] if the
+    /// request has a nonce. Transactions which the node never returns can only be resolved
+    /// with it, or by the timeout.
+    pub async fn resolve(mut self) -> Result<TxResolution, ProviderError> {
+        let provider = self.provider;
+        // the transaction as last seen in the mempool, or as it was sent
+        let mut pending = self.request.clone();
+
+        loop {
+            match provider.get_transaction(self.tx_hash).await {
+                Ok(Some(tx)) if tx.block_number.is_some() => {
+                    // the receipt may be reorged out while waiting for the confirmations
+                    if let Some(receipt) = self.confirmed_receipt(self.tx_hash).await? {
+                        return Ok(TxResolution::Mined(receipt))
+                    }
+                }
+                Ok(Some(tx)) => pending = Some(tx),
+                // the node has not seen the transaction yet, or it left the mempool
+                Ok(None) => {
+                    if let Some(tx) = pending.as_ref() {
+                        if let Some(resolution) = self.check_nonce(tx).await? {
+                            return Ok(resolution)
+                        }
+                    }
+                }
+                // try again after the interval
+                Err(err) => {
+                    tracing::debug!("Could not get pending tx {:?}: {}", self.tx_hash, err)
+                }
+            }
+
+            if timed_out(&self.started, &self.timeout) {
+                tracing::debug!("Timed out waiting for pending tx {:?}", self.tx_hash);
+                return Ok(TxResolution::Dropped)
+            }
+            self.interval.next().await;
+        }
+    }
+
+    /// Resolves a transaction which is not found from the nonce of its sender, or returns `None`
+    /// if it may still be mined
+    async fn check_nonce(
+        &mut self,
+        tx: &Transaction,
+    ) -> Result<Option<TxResolution>, ProviderError> {
+        let provider = self.provider;
+        let nonce =
+            provider.get_transaction_count(tx.from, Some(BlockNumber::Latest.into())).await?;
+        if nonce <= tx.nonce {
+            let pending_nonce =
+                provider.get_transaction_count(tx.from, Some(BlockNumber::Pending.into())).await?;
+            // the transaction or a replacement is still pending
+            if pending_nonce > tx.nonce {
+                return Ok(None)
+            }
+            tracing::debug!("Dropped from mempool, pending tx {:?}", self.tx_hash);
+            return Ok(Some(TxResolution::Dropped))
+        }
+
+        // the transaction may have been mined since it was last polled
+        if provider.get_transaction_receipt(self.tx_hash).await?.is_some() {
+            let receipt = self.confirmed_receipt(self.tx_hash).await?;
+            return Ok(receipt.map(TxResolution::Mined))
+        }
+
+        let latest = provider.get_block_number().await?;
+        let block = self.nonce_block(tx.from, tx.nonce, latest).await?;
+        let replacement = provider.get_block_with_txs(block).await?.and_then(|block| {
+            block.transactions.into_iter().find(|other| {
+                other.from == tx.from && other.nonce == tx.nonce && other.hash != tx.hash
+            })
+        });
+        let replacement = match replacement {
+            Some(replacement) => replacement,
+            // the block was reorged since the nonce was checked
+            None => return Ok(None),
+        };
+
+        tracing::debug!("Pending tx {:?} replaced by {:?}", self.tx_hash, replacement.hash);
+        let reason = ReplacementReason::new(tx, &replacement);
+        let receipt = self.confirmed_receipt(replacement.hash).await?;
+        Ok(receipt.map(|receipt| TxResolution::Replaced { by: replacement.hash, reason, receipt }))
+    }
+
+    /// Returns the block in which the sender used `nonce`, the first block after which its nonce
+    /// is past `nonce`, given that it is past it after the `latest` block
+    async fn nonce_block(
+        &self,
+        from: Address,
+        nonce: U256,
+        latest: U64,
+    ) -> Result<U64, ProviderError> {
+        let provider = self.provider;
+        let nonce_at =
+            |block: u64| provider.get_transaction_count(from, Some(U64::from(block).into()));
+
+        // steps back from the latest block with doubling distance until the nonce is unused,
+        // so that recent replacements are found with few requests
+        let (mut low, mut high) = (0, latest.as_u64());
+        let mut step = 1;
+        while step <= high {
+            let block = high - step;
+            if nonce_at(block).await? <= nonce {
+                low = block;
+                break
+            }
+            high = block;
+            step *= 2;
+        }
+
+        // the nonce is unused after `low` and used after `high`
+        while high - low > 1 {
+            let mid = low + (high - low) / 2;
+            if nonce_at(mid).await? > nonce {
+                high = mid;
+            } else {
+                low = mid;
+            }
+        }
+        Ok(high.into())
+    }
+
+    /// Polls the receipt of a mined transaction until it has enough confirmations. Returns
+    /// `None` if the receipt disappears because its block was reorged, or after the timeout.
+    async fn confirmed_receipt(
+        &mut self,
+        tx_hash: TxHash,
+    ) -> Result<Option<TransactionReceipt>, ProviderError> {
+        let mut found = false;
+        loop {
+            match self.provider.get_transaction_receipt(tx_hash).await {
+                Ok(Some(receipt)) => {
+                    found = true;
+                    if let Some(inclusion_block) = receipt.block_number {
+                        if self.confirmations <= 1 {
+                            return Ok(Some(receipt))
+                        }
+                        let current_block = self.provider.get_block_number().await?;
+                        if current_block > inclusion_block + self.confirmations - 1 {
+                            return Ok(Some(receipt))
+                        }
+                    }
+                }
+                Ok(None) if found => {
+                    tracing::debug!("Receipt of tx {:?} was reorged out", tx_hash);
+                    return Ok(None)
+                }
+                // the node may not have indexed the receipt yet, or the provider errored, just
+                // try again after the interval
+                _ => {}
+            }
+
+            if timed_out(&self.started, &self.timeout) {
+                tracing::debug!("Timed out waiting for the receipt of tx {:?}", tx_hash);
+                return Ok(None)
+            }
+            self.interval.next().await;
+        }
+    }
+}
+
+/// The outcome of a transaction tracked by [`PendingTransaction::resolve`]
+#[derive(Clone, Debug, PartialEq)]
+pub enum TxResolution {
+    /// The transaction was mined, with the requested number of confirmations
+    Mined(TransactionReceipt),
+    /// Another transaction of the sender with the same nonce was mined instead
+    Replaced {
+        /// The hash of the replacement
+        by: TxHash,
+        /// How the replacement differs from the transaction
+        reason: ReplacementReason,
+        /// The receipt of the replacement, with the requested number of confirmations
+        receipt: TransactionReceipt,
+    },
+    /// The transaction left the mempool, or it was not mined with the requested number of
+    /// confirmations before the timeout
+    Dropped,
+}
+
+/// Why a transaction was replaced, see [`TxResolution::Replaced`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReplacementReason {
+    /// The replacement is the same call with a different gas price
+    Repriced,
+    /// The replacement is an empty transfer to the sender itself
+    Cancelled,
+    /// The replacement is another call
+    Other,
+}
+
+impl ReplacementReason {
+    fn new(tx: &Transaction, replacement: &Transaction) -> Self {
+        if replacement.to == Some(tx.from) &&
+            replacement.value.is_zero() &&
+            replacement.input.as_ref().is_empty()
+        {
+            ReplacementReason::Cancelled
+        } else if replacement.to == tx.to &&
+            replacement.value == tx.value &&
+            replacement.input == tx.input
+        {
+            ReplacementReason::Repriced
+        } else {
+            ReplacementReason::Other
+        }
+    }
+}
+
+/// Returns true if the timeout elapsed since `started`
+fn timed_out(started: &Instant, timeout: &Option<Duration>) -> bool {
+    timeout.map_or(false, |timeout| started.elapsed() >= timeout)
 }
 
 macro_rules! rewake_with_new_state {
@@ -115,6 +387,11 @@ impl<'a, P: JsonRpcClient> Future for PendingTransaction<'a, P> {
                 // Wait the polling period so that we do not spam the chain when no
                 // new block has been mined
                 let _ready = futures_util::ready!(this.interval.poll_next_unpin(ctx));
+                if timed_out(this.started, this.timeout) {
+                    tracing::debug!("Timed out waiting for pending tx {:?}", *this.tx_hash);
+                    *this.state = PendingTxState::Completed;
+                    return Poll::Ready(Ok(None))
+                }
                 let fut = Box::pin(this.provider.get_transaction(*this.tx_hash));
                 *this.state = PendingTxState::GettingTx(fut);
                 ctx.waker().wake_by_ref();
@@ -242,6 +519,7 @@ impl<'a, P> fmt::Debug for PendingTransaction<'a, P> {
         f.debug_struct("PendingTransaction")
             .field("tx_hash", &self.tx_hash)
             .field("confirmations", &self.confirmations)
+            .field("timeout", &self.timeout)
             .field("state", &self.state)
             .finish()
     }
@@ -318,3 +596,160 @@ impl<'a> fmt::Debug for PendingTxState<'a> {
         f.debug_struct("PendingTxState").field("state", &state).finish()
     }
 }
+
+#[cfg(test)]
+#[cfg(not(target_arch = "wasm32"))]
+mod tests {
+    use super::*;
+    use ethers_core::types::{Block, TransactionRequest, H256};
+    use serde_json::json;
+
+    fn pending_tx(hash: TxHash, from: Address, nonce: u64) -> Transaction {
+        Transaction {
+            hash,
+            from,
+            nonce: nonce.into(),
+            to: Some(Address::repeat_byte(9)),
+            value: 100.into(),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn resolves_replaced_tx() {
+        let (provider, mock) = Provider::mocked();
+        let from = Address::repeat_byte(1);
+        let tx = pending_tx(H256::repeat_byte(2), from, 5);
+        let request: TypedTransaction = TransactionRequest::new()
+            .from(from)
+            .nonce(5u64)
+            .to(Address::repeat_byte(9))
+            .value(100u64)
+            .into();
+        // the same call with a higher gas price, mined before the tracking started
+        let replacement = Transaction {
+            hash: H256::repeat_byte(3),
+            block_number: Some(3.into()),
+            gas_price: Some(2.into()),
+            ..tx.clone()
+        };
+        let block = Block::<Transaction> {
+            number: Some(3.into()),
+            transactions: vec![replacement.clone()],
+            ..Default::default()
+        };
+        let receipt = TransactionReceipt {
+            transaction_hash: replacement.hash,
+            block_number: Some(3.into()),
+            ..Default::default()
+        };
+
+        // the node never returns the transaction, the nonce was used in block 3
+        mock.expect("eth_getTransactionByHash").returns(()).unwrap();
+        mock.expect("eth_blockNumber").returns(U64::from(11)).unwrap();
+        mock.expect("eth_getTransactionCount")
+            .matching(|params| {
+                let block = params[1].as_str().unwrap_or_default().trim_start_matches("0x");
+                u64::from_str_radix(block, 16).map_or(false, |block| block < 3)
+            })
+            .returns(U256::from(5))
+            .unwrap();
+        mock.expect("eth_getTransactionCount").returns(U256::from(6)).unwrap();
+        mock.expect("eth_getTransactionReceipt")
+            .with_params([tx.hash])
+            .unwrap()
+            .returns(())
+            .unwrap();
+        mock.expect("eth_getTransactionReceipt").returns(&receipt).unwrap();
+        mock.expect("eth_getBlockByNumber")
+            .with_params(json!(["0x3", true]))
+            .unwrap()
+            .returns(&block)
+            .unwrap();
+
+        let pending = PendingTransaction::new(tx.hash, &provider)
+            .interval(Duration::from_millis(1))
+            .request(&request);
+        let resolution = pending.resolve().await.unwrap();
+        assert_eq!(
+            resolution,
+            TxResolution::Replaced {
+                by: replacement.hash,
+                reason: ReplacementReason::Repriced,
+                receipt
+            }
+        );
+        mock.assert_request("eth_getTransactionByHash", [tx.hash]).unwrap();
+        mock.assert_request("eth_getTransactionCount", json!([from, "latest"])).unwrap();
+        mock.assert_request("eth_getTransactionReceipt", [tx.hash]).unwrap();
+        mock.assert_request("eth_blockNumber", ()).unwrap();
+        // steps back from block 11 to block 4 and bisects the blocks before it
+        for block in ["0xa", "0x8", "0x4", "0x2", "0x3"] {
+            mock.assert_request("eth_getTransactionCount", json!([from, block])).unwrap();
+        }
+        mock.assert_request("eth_getBlockByNumber", json!(["0x3", true])).unwrap();
+    }
+
+    #[tokio::test]
+    async fn resolves_dropped_tx() {
+        let (provider, mock) = Provider::mocked();
+        let from = Address::repeat_byte(1);
+        let tx = pending_tx(H256::repeat_byte(2), from, 5);
+
+        // the transaction leaves the mempool without another one taking its nonce
+        mock.expect("eth_getTransactionByHash").times(1).returns(&tx).unwrap();
+        mock.expect("eth_getTransactionByHash").returns(()).unwrap();
+        mock.expect("eth_getTransactionCount").returns(U256::from(5)).unwrap();
+        let pending =
+            PendingTransaction::new(tx.hash, &provider).interval(Duration::from_millis(1));
+        assert_eq!(pending.resolve().await.unwrap(), TxResolution::Dropped);
+
+        // the node never returns the transaction, its nonce is known from the request
+        let request: TypedTransaction = TransactionRequest::new().from(from).nonce(5u64).into();
+        let pending = PendingTransaction::new(tx.hash, &provider)
+            .interval(Duration::from_millis(1))
+            .request(&request);
+        assert_eq!(pending.resolve().await.unwrap(), TxResolution::Dropped);
+
+        // the transaction stays pending until the timeout
+        mock.clear_expectations();
+        mock.expect("eth_getTransactionByHash").returns(&tx).unwrap();
+        let pending = PendingTransaction::new(tx.hash, &provider)
+            .interval(Duration::from_millis(1))
+            .timeout(Duration::from_millis(20));
+        assert_eq!(pending.resolve().await.unwrap(), TxResolution::Dropped);
+        let pending = PendingTransaction::new(tx.hash, &provider)
+            .interval(Duration::from_millis(1))
+            .timeout(Duration::from_millis(20));
+        assert_eq!(pending.await.unwrap(), None);
+
+        // the transaction is mined but does not get enough confirmations before the timeout
+        mock.clear_expectations();
+        let mined = Transaction { block_number: Some(10.into()), ..tx.clone() };
+        let receipt = TransactionReceipt { block_number: Some(10.into()), ..Default::default() };
+        mock.expect("eth_getTransactionByHash").returns(&mined).unwrap();
+        mock.expect("eth_getTransactionReceipt").returns(&receipt).unwrap();
+        mock.expect("eth_blockNumber").returns(U64::from(10)).unwrap();
+        let pending = PendingTransaction::new(tx.hash, &provider)
+            .interval(Duration::from_millis(1))
+            .confirmations(3)
+            .timeout(Duration::from_millis(20));
+        assert_eq!(pending.resolve().await.unwrap(), TxResolution::Dropped);
+    }
+
+    #[test]
+    fn replacement_reason() {
+        let from = Address::repeat_byte(1);
+        let tx = pending_tx(H256::repeat_byte(2), from, 5);
+        let cancel = Transaction {
+            hash: H256::repeat_byte(3),
+            to: Some(from),
+            value: U256::zero(),
+            ..tx.clone()
+        };
+        let other = Transaction { hash: H256::repeat_byte(4), value: 1.into(), ..tx.clone() };
+        assert_eq!(ReplacementReason::new(&tx, &cancel), ReplacementReason::Cancelled);
+        assert_eq!(ReplacementReason::new(&tx, &tx), ReplacementReason::Repriced);
+        assert_eq!(ReplacementReason::new(&tx, &other), ReplacementReason::Other);
+    }
+}
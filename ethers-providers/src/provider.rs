@@ -1,9 +1,17 @@
 use crate::{
-    ens, maybe,
+    batch::BatchRequest,
+    block_stream::BlockStream,
+    capabilities::{NodeCapabilities, NodeClient},
+    ccip, ens,
+    fee_estimator::FeeEstimator,
+    json_rpc_error,
+    log_query::LogQuery,
+    maybe,
     pubsub::{PubsubClient, SubscriptionStream},
     stream::{FilterWatcher, DEFAULT_POLL_INTERVAL},
-    FromErr, Http as HttpProvider, JsonRpcClient, JsonRpcClientWrapper, MockProvider,
-    PendingTransaction, QuorumProvider,
+    watch_stream::{WatchSource, WatchStream},
+    FallbackProvider, FromErr, Http as HttpProvider, JsonRpcClient, JsonRpcClientWrapper,
+    JsonRpcError, MockProvider, PendingTransaction, QuorumProvider,
 };
 
 #[cfg(feature = "celo")]
@@ -12,13 +20,16 @@ use crate::Middleware;
 use async_trait::async_trait;
 
 use ethers_core::{
-    abi::{self, Detokenize, ParamType},
+    abi::{self, Detokenize, ParamType, Token},
     types::{
         transaction::{eip2718::TypedTransaction, eip2930::AccessListWithGasUsed},
-        Address, Block, BlockId, BlockNumber, BlockTrace, Bytes, EIP1186ProofResponse, FeeHistory,
-        Filter, Log, NameOrAddress, Selector, Signature, Trace, TraceFilter, TraceType,
-        Transaction, TransactionReceipt, TxHash, TxpoolContent, TxpoolInspect, TxpoolStatus, H256,
-        U256, U64,
+        Address, Block, BlockId, BlockNumber, BlockTrace, Bytes, CallBundle, CallResponse,
+        EIP1186ProofResponse, ExecutionPayload, FeeHistory, Filter, ForkchoiceState,
+        ForkchoiceUpdated, GethDebugTracingOptions, GethTrace, GethTraceResult, Log, NameOrAddress,
+        PayloadAttributes, PayloadId, PayloadStatus, ProofError, Selector, Signature, StateContext,
+        SyncingStatus, Trace, TraceFilter, TraceType, Transaction, TransactionReceipt,
+        TransactionRequest, TransitionConfiguration, TxHash, TxpoolContent, TxpoolInspect,
+        TxpoolStatus, H256, U256, U64,
     },
     utils,
 };
@@ -27,35 +38,17 @@ use serde::{de::DeserializeOwned, Serialize};
 use thiserror::Error;
 use url::{ParseError, Url};
 
-use futures_util::lock::Mutex;
-use std::{convert::TryFrom, fmt::Debug, str::FromStr, sync::Arc, time::Duration};
+use futures_util::{lock::Mutex, StreamExt};
+use std::{
+    borrow::Cow,
+    convert::TryFrom,
+    fmt::Debug,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
 use tracing::trace;
 use tracing_futures::Instrument;
 
-#[derive(Copy, Clone)]
-pub enum NodeClient {
-    Geth,
-    Erigon,
-    OpenEthereum,
-    Nethermind,
-    Besu,
-}
-
-impl FromStr for NodeClient {
-    type Err = ProviderError;
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s.split('/').next().unwrap().to_lowercase().as_str() {
-            "geth" => Ok(NodeClient::Geth),
-            "erigon" => Ok(NodeClient::Erigon),
-            "openethereum" => Ok(NodeClient::OpenEthereum),
-            "nethermind" => Ok(NodeClient::Nethermind),
-            "besu" => Ok(NodeClient::Besu),
-            _ => Err(ProviderError::UnsupportedNodeClient),
-        }
-    }
-}
-
 /// An abstract provider for interacting with the [Ethereum JSON RPC
 /// API](https://github.com/ethereum/wiki/wiki/JSON-RPC). Must be instantiated
 /// with a data transport which implements the [`JsonRpcClient`](trait@crate::JsonRpcClient) trait
@@ -83,10 +76,18 @@ pub struct Provider<P> {
     ens: Option<Address>,
     interval: Option<Duration>,
     from: Option<Address>,
+    /// The number of EIP-3668 offchain lookups followed by `call`, disabled if zero
+    max_ccip_redirects: usize,
+    /// Estimates the EIP-1559 fees, the default estimator is used if unset
+    fee_estimator: Option<Arc<dyn FeeEstimator>>,
+    /// Fetches offchain data, e.g. the metadata of NFT avatars
+    http: reqwest::Client,
     /// Node client hasn't been checked yet = `None`
     /// Unsupported node client = `Some(None)`
     /// Supported node client = `Some(Some(NodeClient))`
     _node_client: Arc<Mutex<Option<NodeClient>>>,
+    /// The features of the node, `None` until detected or configured
+    capabilities: Arc<RwLock<Option<NodeCapabilities>>>,
 }
 
 impl<P> AsRef<P> for Provider<P> {
@@ -112,12 +113,31 @@ pub enum ProviderError {
     #[error("ens name not found: {0}")]
     EnsError(String),
 
+    /// Invalid reverse ENS name or NFT avatar which is not owned by the name
+    #[error("ens name not owned: {0}")]
+    EnsNotOwned(String),
+
     #[error(transparent)]
     SerdeJson(#[from] serde_json::Error),
 
     #[error(transparent)]
     HexError(#[from] hex::FromHexError),
 
+    #[error(transparent)]
+    HTTPError(#[from] reqwest::Error),
+
+    /// An error while following an EIP-3668 offchain lookup
+    #[error("CCIP-Read error: {0}")]
+    CcipReadError(String),
+
+    /// The proof returned by `eth_getProof` does not match the state root of the block
+    #[error(transparent)]
+    ProofError(#[from] ProofError),
+
+    /// The chain does not support EIP-1559 transactions
+    #[error("EIP-1559 not activated")]
+    Eip1559Unsupported,
+
     #[error("custom error: {0}")]
     CustomError(String),
 
@@ -131,6 +151,16 @@ pub enum ProviderError {
     SignerUnavailable,
 }
 
+impl ProviderError {
+    /// Returns the JSON-RPC error the node responded with, if any
+    pub fn as_error_response(&self) -> Option<&JsonRpcError> {
+        match self {
+            ProviderError::JsonRpcClientError(err) => json_rpc_error(err.as_ref()),
+            _ => None,
+        }
+    }
+}
+
 /// Types of filters supported by the JSON-RPC.
 #[derive(Clone, Debug)]
 pub enum FilterKind<'a> {
@@ -153,7 +183,11 @@ impl<P: JsonRpcClient> Provider<P> {
             ens: None,
             interval: None,
             from: None,
+            max_ccip_redirects: 0,
+            fee_estimator: None,
+            http: reqwest::Client::new(),
             _node_client: Arc::new(Mutex::new(None)),
+            capabilities: Arc::new(RwLock::new(None)),
         }
     }
 
@@ -175,12 +209,141 @@ impl<P: JsonRpcClient> Provider<P> {
         }
     }
 
+    /// Returns the features of the node, which are detected from `web3_clientVersion` and the
+    /// transport on the first call and cached afterwards.
+    ///
+    /// Once the capabilities are known, either detected or configured with
+    /// [`Provider::with_capabilities`], the provider consults them: `trace_*` and `debug_*`
+    /// calls the node does not support fail with [`ProviderError::UnsupportedRPC`] without a
+    /// request, transactions are filled with a gas price if `eth_feeHistory` is missing, block
+    /// receipts are fetched with `parity_getBlockReceipts` from OpenEthereum, the chunks of
+    /// [`Middleware::get_logs_paginated`] are capped to the `eth_getLogs` range of the node and
+    /// the `stream_*` methods poll filters if subscriptions are disabled.
+    pub async fn capabilities(&self) -> Result<NodeCapabilities, ProviderError> {
+        if let Some(capabilities) = self.known_capabilities() {
+            return Ok(capabilities)
+        }
+
+        let client = match self.node_client().await {
+            Ok(client) => Some(client),
+            Err(ProviderError::UnsupportedNodeClient) => None,
+            Err(err) => return Err(err),
+        };
+        let capabilities = NodeCapabilities {
+            pubsub: self.inner.supports_pubsub(),
+            ..NodeCapabilities::for_client(client)
+        };
+        *self.capabilities.write().unwrap() = Some(capabilities);
+        Ok(capabilities)
+    }
+
+    /// Sets the features of the node instead of detecting them
+    #[must_use]
+    pub fn with_capabilities(mut self, capabilities: NodeCapabilities) -> Self {
+        self.capabilities = Arc::new(RwLock::new(Some(capabilities)));
+        self
+    }
+
+    /// Returns the capabilities if they were detected or configured already
+    fn known_capabilities(&self) -> Option<NodeCapabilities> {
+        *self.capabilities.read().unwrap()
+    }
+
+    /// Fails if the known capabilities rule out the feature
+    fn ensure_capability(
+        &self,
+        supported: fn(&NodeCapabilities) -> bool,
+    ) -> Result<(), ProviderError> {
+        match self.known_capabilities() {
+            Some(capabilities) if !supported(&capabilities) => Err(ProviderError::UnsupportedRPC),
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns true if the `stream_*` methods should subscribe instead of polling a filter
+    fn use_pubsub(&self) -> bool {
+        self.inner.supports_pubsub() &&
+            self.known_capabilities().map_or(true, |capabilities| capabilities.pubsub)
+    }
+
     #[must_use]
     pub fn with_sender(mut self, address: impl Into<Address>) -> Self {
         self.from = Some(address.into());
         self
     }
 
+    /// Returns a builder for a batch of requests, which are sent to the node in a single round
+    /// trip if the transport supports JSON-RPC batches.
+    pub fn batch(&self) -> BatchRequest<'_, P> {
+        BatchRequest::new(self)
+    }
+
+    /// Returns a stream of the changes to the canonical chain, which detects reorgs by
+    /// tracking the parent hashes of recent blocks
+    pub fn block_stream(&self) -> BlockStream<'_, P> {
+        BlockStream::new(self)
+    }
+
+    /// Streams new blocks, from a `newHeads` subscription if the transport supports
+    /// `eth_subscribe`, or by polling a block filter and fetching each new block otherwise
+    pub async fn stream_blocks(&self) -> Result<WatchStream<'_, P, Block<TxHash>>, ProviderError> {
+        if self.use_pubsub() {
+            return self.stream_subscription(["newHeads"]).await
+        }
+        let watcher = self.watch_blocks().await?;
+        let id = watcher.id;
+        // the filter only yields the block hashes, blocks which cannot be fetched are skipped
+        let blocks = watcher
+            .then(move |hash| self.get_block(hash))
+            .filter_map(|block| futures_util::future::ready(block.ok().flatten()));
+        Ok(WatchStream::new(WatchSource::Filter(id), self, Box::pin(blocks)))
+    }
+
+    /// Streams the hashes of new pending transactions, from a `newPendingTransactions`
+    /// subscription if the transport supports `eth_subscribe`, or by polling a pending
+    /// transaction filter otherwise
+    pub async fn stream_pending_txs(&self) -> Result<WatchStream<'_, P, TxHash>, ProviderError> {
+        if self.use_pubsub() {
+            return self.stream_subscription(["newPendingTransactions"]).await
+        }
+        let watcher = self.watch_pending_transactions().await?;
+        Ok(WatchStream::new(WatchSource::Filter(watcher.id), self, Box::pin(watcher)))
+    }
+
+    /// Streams the logs matching the filter, from a `logs` subscription if the transport
+    /// supports `eth_subscribe`, or by polling a log filter otherwise
+    pub async fn stream_logs<'a>(
+        &'a self,
+        filter: &Filter,
+    ) -> Result<WatchStream<'a, P, Log>, ProviderError> {
+        if self.use_pubsub() {
+            let logs = utils::serialize(&"logs");
+            let filter = utils::serialize(filter);
+            return self.stream_subscription([logs, filter]).await
+        }
+        let watcher = self.watch(filter).await?;
+        Ok(WatchStream::new(WatchSource::Filter(watcher.id), self, Box::pin(watcher)))
+    }
+
+    async fn stream_subscription<'a, T, R>(
+        &'a self,
+        params: T,
+    ) -> Result<WatchStream<'a, P, R>, ProviderError>
+    where
+        T: Debug + Serialize + Send + Sync,
+        R: DeserializeOwned + Send + 'a,
+    {
+        let id: U256 = self.request("eth_subscribe", params).await?;
+        let notifications = match self.inner.subscribe_notifications(id) {
+            Some(notifications) => notifications.map_err(Into::<ProviderError>::into)?,
+            None => return Err(ProviderError::UnsupportedRPC),
+        };
+        // notifications which do not deserialize are skipped, like in `SubscriptionStream`
+        let items = notifications
+            .filter_map(|item| futures_util::future::ready(serde_json::from_value(item).ok()));
+        Ok(WatchStream::new(WatchSource::Subscription(id), self, Box::pin(items)))
+    }
+
     async fn request<T, R>(&self, method: &str, params: T) -> Result<R, ProviderError>
     where
         T: Debug + Serialize + Send + Sync,
@@ -261,6 +424,11 @@ impl<P: JsonRpcClient> Middleware for Provider<P> {
         self.request("web3_clientVersion", ()).await
     }
 
+    /// Returns the Keccak-256 hash of the data using the `web3_sha3` RPC.
+    async fn sha3<T: Into<Bytes> + Send + Sync>(&self, data: T) -> Result<H256, Self::Error> {
+        self.request("web3_sha3", [data.into()]).await
+    }
+
     async fn fill_transaction(
         &self,
         tx: &mut TypedTransaction,
@@ -304,6 +472,7 @@ impl<P: JsonRpcClient> Middleware for Provider<P> {
             tx.set_gas(gas);
         }
 
+        let mut fallback = None;
         match tx {
             TypedTransaction::Eip2930(_) | TypedTransaction::Legacy(_) => {
                 let gas_price = maybe(tx.gas_price(), self.get_gas_price()).await?;
@@ -311,14 +480,33 @@ impl<P: JsonRpcClient> Middleware for Provider<P> {
             }
             TypedTransaction::Eip1559(ref mut inner) => {
                 if inner.max_fee_per_gas.is_none() || inner.max_priority_fee_per_gas.is_none() {
-                    let (max_fee_per_gas, max_priority_fee_per_gas) =
-                        self.estimate_eip1559_fees(None).await?;
-                    inner.max_fee_per_gas = Some(max_fee_per_gas);
-                    inner.max_priority_fee_per_gas = Some(max_priority_fee_per_gas);
+                    match self.estimate_eip1559_fees(None).await {
+                        Ok((max_fee_per_gas, max_priority_fee_per_gas)) => {
+                            inner.max_fee_per_gas = Some(max_fee_per_gas);
+                            inner.max_priority_fee_per_gas = Some(max_priority_fee_per_gas);
+                        }
+                        // chains without EIP-1559 only accept transactions with a gas price
+                        Err(ProviderError::Eip1559Unsupported) => {
+                            let gas_price = self.get_gas_price().await?;
+                            let access_list = inner.access_list.clone();
+                            let mut legacy: TransactionRequest = inner.clone().into();
+                            legacy.gas_price = Some(gas_price);
+                            fallback = Some(if access_list.0.is_empty() {
+                                TypedTransaction::Legacy(legacy)
+                            } else {
+                                TypedTransaction::Eip2930(legacy.with_access_list(access_list))
+                            });
+                        }
+                        Err(err) => return Err(err),
+                    }
                 };
             }
         }
 
+        if let Some(fallback) = fallback {
+            *tx = fallback;
+        }
+
         Ok(())
     }
 
@@ -390,6 +578,23 @@ impl<P: JsonRpcClient> Middleware for Provider<P> {
         self.request("eth_getTransactionByHash", [hash]).await
     }
 
+    /// Gets the transaction sent by `sender` with `nonce`, using the
+    /// `eth_getTransactionBySenderAndNonce` RPC supported by Erigon and Reth
+    async fn get_transaction_by_sender_and_nonce<T: Into<NameOrAddress> + Send + Sync>(
+        &self,
+        sender: T,
+        nonce: U256,
+    ) -> Result<Option<Transaction>, ProviderError> {
+        let sender = match sender.into() {
+            NameOrAddress::Name(ens_name) => self.resolve_name(&ens_name).await?,
+            NameOrAddress::Address(addr) => addr,
+        };
+
+        let sender = utils::serialize(&sender);
+        let nonce = utils::serialize(&nonce);
+        self.request("eth_getTransactionBySenderAndNonce", [sender, nonce]).await
+    }
+
     /// Gets the transaction receipt with `transaction_hash`
     async fn get_transaction_receipt<T: Send + Sync + Into<TxHash>>(
         &self,
@@ -399,15 +604,29 @@ impl<P: JsonRpcClient> Middleware for Provider<P> {
         self.request("eth_getTransactionReceipt", [hash]).await
     }
 
-    /// Returns all receipts for a block.
+    /// Returns all receipts for a block, or `None` if the block is not found.
     ///
-    /// Note that this uses the `eth_getBlockReceipts` RPC, which is
-    /// non-standard and currently supported by Erigon.
-    async fn get_block_receipts<T: Into<BlockNumber> + Send + Sync>(
+    /// Note that this uses the `eth_getBlockReceipts` RPC, which is not part of the original
+    /// JSON-RPC specification. Nodes which predate its standardization may only accept block
+    /// numbers.
+    async fn get_block_receipts<T: Into<BlockId> + Send + Sync>(
         &self,
         block: T,
-    ) -> Result<Vec<TransactionReceipt>, Self::Error> {
-        self.request("eth_getBlockReceipts", [block.into()]).await
+    ) -> Result<Option<Vec<TransactionReceipt>>, Self::Error> {
+        let block = block.into();
+        if let Some(capabilities) = self.known_capabilities() {
+            if !capabilities.block_receipts {
+                // OpenEthereum serves the receipts of blocks by number via its own RPC
+                return match (capabilities.client, block) {
+                    (Some(NodeClient::OpenEthereum), BlockId::Number(number)) => {
+                        self.request("parity_getBlockReceipts", [number]).await
+                    }
+                    _ => Err(ProviderError::UnsupportedRPC),
+                }
+            }
+        }
+
+        self.request("eth_getBlockReceipts", [block]).await
     }
 
     /// Returns all receipts for that block. Must be done on a parity node.
@@ -423,18 +642,40 @@ impl<P: JsonRpcClient> Middleware for Provider<P> {
         self.request("eth_gasPrice", ()).await
     }
 
+    /// Gets the priority fee per gas which the node suggests for EIP-1559 transactions
+    async fn get_max_priority_fee_per_gas(&self) -> Result<U256, ProviderError> {
+        self.request("eth_maxPriorityFeePerGas", ()).await
+    }
+
     /// Gets a heuristic recommendation of max fee per gas and max priority fee per gas for
     /// EIP-1559 compatible transactions.
     async fn estimate_eip1559_fees(
         &self,
         estimator: Option<fn(U256, Vec<Vec<U256>>) -> (U256, U256)>,
     ) -> Result<(U256, U256), Self::Error> {
+        // the fee history is required to estimate the priority fee
+        if self.known_capabilities().map_or(false, |capabilities| !capabilities.fee_history) {
+            return Err(ProviderError::Eip1559Unsupported)
+        }
+
         let base_fee_per_gas = self
             .get_block(BlockNumber::Latest)
             .await?
             .ok_or_else(|| ProviderError::CustomError("Latest block not found".into()))?
             .base_fee_per_gas
-            .ok_or_else(|| ProviderError::CustomError("EIP-1559 not activated".into()))?;
+            .ok_or(ProviderError::Eip1559Unsupported)?;
+
+        // use the fee estimator of the provider, unless an estimator function is provided
+        if let (None, Some(fee_estimator)) = (estimator, self.fee_estimator.clone()) {
+            let fee_history = self
+                .fee_history(
+                    fee_estimator.block_count(),
+                    BlockNumber::Latest,
+                    &fee_estimator.reward_percentiles(),
+                )
+                .await?;
+            return Ok(fee_estimator.estimate(&fee_history))
+        }
 
         let fee_history = self
             .fee_history(
@@ -502,6 +743,16 @@ impl<P: JsonRpcClient> Middleware for Provider<P> {
         self.request("net_version", ()).await
     }
 
+    /// Returns the number of peers connected to the node.
+    async fn get_peer_count(&self) -> Result<U64, ProviderError> {
+        self.request("net_peerCount", ()).await
+    }
+
+    /// Returns the sync progress of the node, or `NotSyncing` if it is in sync.
+    async fn syncing(&self) -> Result<SyncingStatus, ProviderError> {
+        self.request("eth_syncing", ()).await
+    }
+
     ////// Contract Execution
     //
     // These are relatively low-level calls. The Contracts API should usually be used instead.
@@ -509,14 +760,27 @@ impl<P: JsonRpcClient> Middleware for Provider<P> {
     /// Sends the read-only (constant) transaction to a single Ethereum node and return the result
     /// (as bytes) of executing it. This is free, since it does not change any state on the
     /// blockchain.
+    ///
+    /// If CCIP-Read is enabled with [`Provider::ccip_read`], calls which revert with an EIP-3668
+    /// `OffchainLookup` are answered by the gateways of the contract.
     async fn call(
         &self,
         tx: &TypedTransaction,
         block: Option<BlockId>,
     ) -> Result<Bytes, ProviderError> {
-        let tx = utils::serialize(tx);
-        let block = utils::serialize(&block.unwrap_or_else(|| BlockNumber::Latest.into()));
-        self.request("eth_call", [tx, block]).await
+        self.call_with_ccip_read(tx, block, self.max_ccip_redirects).await
+    }
+
+    /// Executes bundles of calls on top of each other using the `eth_callMany` RPC supported by
+    /// Erigon and Reth. Calls which fail do not abort the following ones.
+    async fn call_many<T: Into<StateContext> + Send + Sync>(
+        &self,
+        bundles: Vec<CallBundle>,
+        context: T,
+    ) -> Result<Vec<Vec<CallResponse>>, ProviderError> {
+        let bundles = utils::serialize(&bundles);
+        let context = utils::serialize(&context.into());
+        self.request("eth_callMany", [bundles, context]).await
     }
 
     /// Sends a transaction to a single Ethereum node and return the estimated amount of gas
@@ -546,9 +810,9 @@ impl<P: JsonRpcClient> Middleware for Provider<P> {
     ) -> Result<PendingTransaction<'_, P>, ProviderError> {
         let mut tx = tx.into();
         self.fill_transaction(&mut tx, block).await?;
-        let tx_hash = self.request("eth_sendTransaction", [tx]).await?;
+        let tx_hash = self.request("eth_sendTransaction", [&tx]).await?;
 
-        Ok(PendingTransaction::new(tx_hash, self).interval(self.get_interval()))
+        Ok(PendingTransaction::new(tx_hash, self).interval(self.get_interval()).request(&tx))
     }
 
     /// Send the raw RLP encoded transaction to the entire Ethereum network and returns the
@@ -606,6 +870,19 @@ impl<P: JsonRpcClient> Middleware for Provider<P> {
         self.request("eth_getLogs", [filter]).await
     }
 
+    /// Returns a stream of the logs matching the filter, with chunks of at most the
+    /// `eth_getLogs` range of the node if its capabilities are known
+    fn get_logs_paginated<'a>(&'a self, filter: &Filter, chunk_size: u64) -> LogQuery<'a, Self>
+    where
+        Self: Sized,
+    {
+        let chunk_size = match self.known_capabilities().and_then(|caps| caps.max_get_logs_range) {
+            Some(max_range) => chunk_size.min(max_range),
+            None => chunk_size,
+        };
+        LogQuery::new(self, filter).chunk_size(chunk_size)
+    }
+
     /// Streams matching filter logs
     async fn watch<'a>(
         &'a self,
@@ -734,6 +1011,43 @@ impl<P: JsonRpcClient> Middleware for Provider<P> {
         self.request("eth_getProof", [from, locations, block]).await
     }
 
+    async fn get_verified_proof<T: Into<NameOrAddress> + Send + Sync>(
+        &self,
+        from: T,
+        locations: Vec<H256>,
+        block: Option<BlockId>,
+    ) -> Result<EIP1186ProofResponse, ProviderError> {
+        let from = match from.into() {
+            NameOrAddress::Name(ens_name) => self.resolve_name(&ens_name).await?,
+            NameOrAddress::Address(addr) => addr,
+        };
+
+        let block = block.unwrap_or_else(|| BlockNumber::Latest.into());
+        let header = self
+            .get_block(block)
+            .await?
+            .ok_or_else(|| ProviderError::CustomError(format!("block {:?} not found", block)))?;
+        // pin the proof to the block we got the state root from
+        let at = header.hash.map(BlockId::Hash).unwrap_or(block);
+
+        let mut unproven = locations.clone();
+        let proof = self.get_proof(from, locations, Some(at)).await?;
+        // the proof must cover exactly the requested slots
+        for slot in &proof.storage_proof {
+            match unproven.iter().position(|location| *location == slot.key) {
+                Some(idx) => {
+                    unproven.swap_remove(idx);
+                }
+                None => return Err(ProofError::UnexpectedSlot(slot.key).into()),
+            }
+        }
+        if let Some(location) = unproven.first() {
+            return Err(ProofError::MissingSlot(*location).into())
+        }
+        proof.verify(from, header.state_root)?;
+        Ok(proof)
+    }
+
     ////// Ethereum Naming Service
     // The Ethereum Naming Service (ENS) allows easy to remember and use names to
     // be assigned to Ethereum addresses. Any provider operation which takes an address
@@ -744,22 +1058,94 @@ impl<P: JsonRpcClient> Middleware for Provider<P> {
 
     /// Returns the address that the `ens_name` resolves to (or None if not configured).
     ///
-    /// # Panics
-    ///
-    /// If the bytes returned from the ENS registrar/resolver cannot be interpreted as
-    /// an address. This should theoretically never happen.
+    /// Names without a resolver of their own are resolved by the resolver of their closest
+    /// parent, if it supports [ENSIP-10](https://docs.ens.domains/ens-improvement-proposals/ensip-10-wildcard-resolution)
+    /// wildcard resolution.
     async fn resolve_name(&self, ens_name: &str) -> Result<Address, ProviderError> {
         self.query_resolver(ParamType::Address, ens_name, ens::ADDR_SELECTOR).await
     }
 
     /// Returns the ENS name the `address` resolves to (or None if not configured).
-    /// # Panics
     ///
-    /// If the bytes returned from the ENS registrar/resolver cannot be interpreted as
-    /// a string. This should theoretically never happen.
+    /// The reverse record can be set to any name by the owner of the address, so the name is
+    /// only returned if it resolves back to `address`.
     async fn lookup_address(&self, address: Address) -> Result<String, ProviderError> {
         let ens_name = ens::reverse_address(address);
-        self.query_resolver(ParamType::String, &ens_name, ens::NAME_SELECTOR).await
+        let name: String =
+            self.query_resolver(ParamType::String, &ens_name, ens::NAME_SELECTOR).await?;
+        if self.resolve_name(&name).await? != address {
+            return Err(ProviderError::EnsNotOwned(name))
+        }
+        Ok(name)
+    }
+
+    /// Returns the text record `field` of the `ens_name`, e.g. `url` or `com.twitter`
+    async fn resolve_field(&self, ens_name: &str, field: &str) -> Result<String, ProviderError> {
+        let field = Token::String(field.to_string());
+        self.query_resolver_with_args(ParamType::String, ens_name, ens::FIELD_SELECTOR, &[field])
+            .await
+    }
+
+    /// Returns the decoded `contenthash` record of the `ens_name` (or None if not configured)
+    async fn resolve_contenthash(
+        &self,
+        ens_name: &str,
+    ) -> Result<Option<ens::ContentHash>, ProviderError> {
+        let hash: Bytes =
+            self.query_resolver(ParamType::Bytes, ens_name, ens::CONTENTHASH_SELECTOR).await?;
+        Ok(ens::ContentHash::decode(hash.as_ref()))
+    }
+
+    /// Returns the URL of the image of the `avatar` text record of the `ens_name`, as specified
+    /// in [ENSIP-12](https://docs.ens.domains/ens-improvement-proposals/ensip-12-avatar-text-records).
+    ///
+    /// NFT avatars are only resolved if the token is owned by the address of the name.
+    async fn resolve_avatar(&self, ens_name: &str) -> Result<Url, ProviderError> {
+        let avatar = self.resolve_field(ens_name, "avatar").await?;
+        if avatar.is_empty() {
+            return Err(ProviderError::EnsError(ens_name.to_owned()))
+        }
+        if !avatar.starts_with("eip155:") {
+            return parse_url(&ens::ipfs_gateway(&avatar))
+        }
+
+        // only NFT avatars need the name to resolve to an address
+        let token: ens::ErcNft = avatar.parse()?;
+        let owner = self.resolve_name(ens_name).await?;
+        let data = self.call(&token.owner_request(owner).into(), None).await?;
+        let owned = match token.typ {
+            ens::ErcNftType::Erc721 => {
+                try_decode_bytes::<Address>(ParamType::Address, data)? == owner
+            }
+            ens::ErcNftType::Erc1155 => {
+                !try_decode_bytes::<U256>(ParamType::Uint(256), data)?.is_zero()
+            }
+        };
+        if !owned {
+            return Err(ProviderError::EnsNotOwned(avatar))
+        }
+        self.resolve_nft(token).await
+    }
+
+    /// Returns the URL of the image of the ERC-721 or ERC-1155 `token`, as found in its
+    /// metadata
+    async fn resolve_nft(&self, token: ens::ErcNft) -> Result<Url, ProviderError> {
+        let data = self.call(&token.uri_request().into(), None).await?;
+        let uri: String = try_decode_bytes(ParamType::String, data)?;
+        let uri = ens::ipfs_gateway(&token.metadata_uri(&uri));
+
+        let metadata: ens::NftMetadata = match uri.strip_prefix("data:application/json;base64,") {
+            Some(json) => {
+                let json = base64::decode(json)
+                    .map_err(|err| ProviderError::CustomError(err.to_string()))?;
+                serde_json::from_slice(&json)?
+            }
+            None => self.http.get(uri.as_str()).send().await?.error_for_status()?.json().await?,
+        };
+        let image = metadata.image().ok_or_else(|| {
+            ProviderError::CustomError(format!("no image in metadata of {}", uri))
+        })?;
+        parse_url(&ens::ipfs_gateway(&image))
     }
 
     /// Returns the details of all transactions currently pending for inclusion in the next
@@ -790,6 +1176,7 @@ impl<P: JsonRpcClient> Middleware for Provider<P> {
         trace_type: Vec<TraceType>,
         block: Option<BlockNumber>,
     ) -> Result<BlockTrace, ProviderError> {
+        self.ensure_capability(|capabilities| capabilities.trace)?;
         let req = req.into();
         let req = utils::serialize(&req);
         let block = utils::serialize(&block.unwrap_or(BlockNumber::Latest));
@@ -803,6 +1190,7 @@ impl<P: JsonRpcClient> Middleware for Provider<P> {
         req: Vec<(T, Vec<TraceType>)>,
         block: Option<BlockNumber>,
     ) -> Result<Vec<BlockTrace>, ProviderError> {
+        self.ensure_capability(|capabilities| capabilities.trace)?;
         let req: Vec<(TypedTransaction, Vec<TraceType>)> =
             req.into_iter().map(|(tx, trace_type)| (tx.into(), trace_type)).collect();
         let req = utils::serialize(&req);
@@ -816,6 +1204,7 @@ impl<P: JsonRpcClient> Middleware for Provider<P> {
         data: Bytes,
         trace_type: Vec<TraceType>,
     ) -> Result<BlockTrace, ProviderError> {
+        self.ensure_capability(|capabilities| capabilities.trace)?;
         let data = utils::serialize(&data);
         let trace_type = utils::serialize(&trace_type);
         self.request("trace_rawTransaction", [data, trace_type]).await
@@ -827,6 +1216,7 @@ impl<P: JsonRpcClient> Middleware for Provider<P> {
         hash: H256,
         trace_type: Vec<TraceType>,
     ) -> Result<BlockTrace, ProviderError> {
+        self.ensure_capability(|capabilities| capabilities.trace)?;
         let hash = utils::serialize(&hash);
         let trace_type = utils::serialize(&trace_type);
         self.request("trace_replayTransaction", [hash, trace_type]).await
@@ -838,6 +1228,7 @@ impl<P: JsonRpcClient> Middleware for Provider<P> {
         block: BlockNumber,
         trace_type: Vec<TraceType>,
     ) -> Result<Vec<BlockTrace>, ProviderError> {
+        self.ensure_capability(|capabilities| capabilities.trace)?;
         let block = utils::serialize(&block);
         let trace_type = utils::serialize(&trace_type);
         self.request("trace_replayBlockTransactions", [block, trace_type]).await
@@ -845,12 +1236,14 @@ impl<P: JsonRpcClient> Middleware for Provider<P> {
 
     /// Returns traces created at given block
     async fn trace_block(&self, block: BlockNumber) -> Result<Vec<Trace>, ProviderError> {
+        self.ensure_capability(|capabilities| capabilities.trace)?;
         let block = utils::serialize(&block);
         self.request("trace_block", [block]).await
     }
 
     /// Return traces matching the given filter
     async fn trace_filter(&self, filter: TraceFilter) -> Result<Vec<Trace>, ProviderError> {
+        self.ensure_capability(|capabilities| capabilities.trace)?;
         let filter = utils::serialize(&filter);
         self.request("trace_filter", vec![filter]).await
     }
@@ -861,6 +1254,7 @@ impl<P: JsonRpcClient> Middleware for Provider<P> {
         hash: H256,
         index: Vec<T>,
     ) -> Result<Trace, ProviderError> {
+        self.ensure_capability(|capabilities| capabilities.trace)?;
         let hash = utils::serialize(&hash);
         let index: Vec<U64> = index.into_iter().map(|i| i.into()).collect();
         let index = utils::serialize(&index);
@@ -869,10 +1263,92 @@ impl<P: JsonRpcClient> Middleware for Provider<P> {
 
     /// Returns all traces of a given transaction
     async fn trace_transaction(&self, hash: H256) -> Result<Vec<Trace>, ProviderError> {
+        self.ensure_capability(|capabilities| capabilities.trace)?;
         let hash = utils::serialize(&hash);
         self.request("trace_transaction", vec![hash]).await
     }
 
+    async fn debug_trace_transaction(
+        &self,
+        tx_hash: TxHash,
+        trace_options: GethDebugTracingOptions,
+    ) -> Result<GethTrace, ProviderError> {
+        self.ensure_capability(|capabilities| capabilities.debug)?;
+        let tx_hash = utils::serialize(&tx_hash);
+        let trace_options = utils::serialize(&trace_options);
+        self.request("debug_traceTransaction", [tx_hash, trace_options]).await
+    }
+
+    async fn debug_trace_call<T: Into<TypedTransaction> + Send + Sync>(
+        &self,
+        req: T,
+        block: Option<BlockId>,
+        trace_options: GethDebugTracingOptions,
+    ) -> Result<GethTrace, ProviderError> {
+        self.ensure_capability(|capabilities| capabilities.debug)?;
+        let req = req.into();
+        let req = utils::serialize(&req);
+        let block = utils::serialize(&block.unwrap_or_else(|| BlockNumber::Latest.into()));
+        let trace_options = utils::serialize(&trace_options);
+        self.request("debug_traceCall", [req, block, trace_options]).await
+    }
+
+    async fn debug_trace_block_by_number(
+        &self,
+        block: Option<BlockNumber>,
+        trace_options: GethDebugTracingOptions,
+    ) -> Result<Vec<GethTraceResult>, ProviderError> {
+        self.ensure_capability(|capabilities| capabilities.debug)?;
+        let block = utils::serialize(&block.unwrap_or(BlockNumber::Latest));
+        let trace_options = utils::serialize(&trace_options);
+        self.request("debug_traceBlockByNumber", [block, trace_options]).await
+    }
+
+    async fn debug_trace_block_by_hash(
+        &self,
+        block: H256,
+        trace_options: GethDebugTracingOptions,
+    ) -> Result<Vec<GethTraceResult>, ProviderError> {
+        self.ensure_capability(|capabilities| capabilities.debug)?;
+        let block = utils::serialize(&block);
+        let trace_options = utils::serialize(&trace_options);
+        self.request("debug_traceBlockByHash", [block, trace_options]).await
+    }
+
+    async fn engine_new_payload_v1(
+        &self,
+        payload: ExecutionPayload,
+    ) -> Result<PayloadStatus, ProviderError> {
+        let payload = utils::serialize(&payload);
+        self.request("engine_newPayloadV1", [payload]).await
+    }
+
+    async fn engine_forkchoice_updated_v1(
+        &self,
+        state: ForkchoiceState,
+        attributes: Option<PayloadAttributes>,
+    ) -> Result<ForkchoiceUpdated, ProviderError> {
+        let state = utils::serialize(&state);
+        let attributes = utils::serialize(&attributes);
+        self.request("engine_forkchoiceUpdatedV1", [state, attributes]).await
+    }
+
+    async fn engine_get_payload_v1(
+        &self,
+        payload_id: PayloadId,
+    ) -> Result<ExecutionPayload, ProviderError> {
+        let payload_id = utils::serialize(&payload_id);
+        self.request("engine_getPayloadV1", [payload_id]).await
+    }
+
+    async fn engine_exchange_transition_configuration_v1(
+        &self,
+        config: TransitionConfiguration,
+    ) -> Result<TransitionConfiguration, ProviderError> {
+        let config = utils::serialize(&config);
+        self.request("engine_exchangeTransitionConfigurationV1", [config]).await
+    }
+
     async fn subscribe<T, R>(
         &self,
         params: T,
@@ -958,23 +1434,137 @@ impl<P: JsonRpcClient> Provider<P> {
         ens_name: &str,
         selector: Selector,
     ) -> Result<T, ProviderError> {
-        // Get the ENS address, prioritize the local override variable
-        let ens_addr = self.ens.unwrap_or(ens::ENS_ADDRESS);
+        self.query_resolver_with_args(param, ens_name, selector, &[]).await
+    }
 
-        // first get the resolver responsible for this name
-        // the call will return a Bytes array which we convert to an address
-        let data = self.call(&ens::get_resolver(ens_addr, ens_name).into(), None).await?;
+    async fn query_resolver_with_args<T: Detokenize>(
+        &self,
+        param: ParamType,
+        ens_name: &str,
+        selector: Selector,
+        args: &[Token],
+    ) -> Result<T, ProviderError> {
+        let (resolver_address, wildcard) = self.find_resolver(ens_name).await?;
 
-        let resolver_address: Address = decode_bytes(ParamType::Address, data);
-        if resolver_address == Address::zero() {
-            return Err(ProviderError::EnsError(ens_name.to_owned()))
+        // resolve
+        let data = if wildcard {
+            // only extended resolvers may resolve the names below them
+            if !self.supports_extended_resolver(resolver_address).await {
+                return Err(ProviderError::EnsError(ens_name.to_owned()))
+            }
+            self.resolve_extended(resolver_address, selector, ens_name, args).await?
+        } else {
+            let tx = ens::resolve_with_args(resolver_address, selector, ens_name, args);
+            match self.ens_call(tx).await {
+                Ok(data) if !data.as_ref().is_empty() => data,
+                // resolvers which only implement ENSIP-10 `resolve(bytes,bytes)` revert or return
+                // nothing, so the interface is only probed then
+                res => {
+                    if self.supports_extended_resolver(resolver_address).await {
+                        self.resolve_extended(resolver_address, selector, ens_name, args).await?
+                    } else {
+                        res?
+                    }
+                }
+            }
+        };
+
+        try_decode_bytes(param, data)
+    }
+
+    /// Calls the resolver method through ENSIP-10 `resolve(bytes,bytes)`
+    async fn resolve_extended(
+        &self,
+        resolver_address: Address,
+        selector: Selector,
+        ens_name: &str,
+        args: &[Token],
+    ) -> Result<Bytes, ProviderError> {
+        let data = ens::resolver_call_data(selector, ens_name, args);
+        let tx = ens::resolve_extended(resolver_address, ens_name, data)?;
+        // the result of the resolver method is wrapped in `bytes`
+        try_decode_bytes::<Bytes>(ParamType::Bytes, self.ens_call(tx).await?)
+    }
+
+    /// Calls a resolver, offchain resolvers are always followed, independent of whether
+    /// CCIP-Read is enabled for `call`
+    async fn ens_call(&self, tx: TransactionRequest) -> Result<Bytes, ProviderError> {
+        let max_redirects = match self.max_ccip_redirects {
+            0 => ccip::DEFAULT_MAX_REDIRECTS,
+            max_redirects => max_redirects,
+        };
+        self.call_with_ccip_read(&tx.into(), None, max_redirects).await
+    }
+
+    /// Sends an `eth_call` and follows at most `max_redirects` EIP-3668 offchain lookups
+    async fn call_with_ccip_read(
+        &self,
+        tx: &TypedTransaction,
+        block: Option<BlockId>,
+        max_redirects: usize,
+    ) -> Result<Bytes, ProviderError> {
+        let block = utils::serialize(&block.unwrap_or_else(|| BlockNumber::Latest.into()));
+        let mut tx = Cow::Borrowed(tx);
+        let mut redirects = 0;
+        loop {
+            let err = match self.request("eth_call", [utils::serialize(&*tx), block.clone()]).await
+            {
+                Ok(data) => return Ok(data),
+                Err(err) => err,
+            };
+            let lookup = match err.as_error_response().and_then(ccip::OffchainLookup::from_error) {
+                Some(lookup) if max_redirects > 0 => lookup,
+                _ => return Err(err),
+            };
+            if redirects == max_redirects {
+                return Err(ProviderError::CcipReadError(format!(
+                    "exceeded {} redirects",
+                    max_redirects
+                )))
+            }
+            redirects += 1;
+
+            // the lookup has to be answered by the called contract itself
+            if tx.to() != Some(&NameOrAddress::Address(lookup.sender)) {
+                return Err(ProviderError::CcipReadError(format!(
+                    "lookup sender {:?} is not the called contract",
+                    lookup.sender
+                )))
+            }
+            let response = lookup.fetch().await?;
+            tx.to_mut().set_data(lookup.callback_data(response.as_ref()));
         }
+    }
 
-        // resolve
-        let data =
-            self.call(&ens::resolve(resolver_address, selector, ens_name).into(), None).await?;
+    /// Returns the resolver of the name, or the resolver of its closest parent which has one
+    /// (see ENSIP-10). The flag is set if the resolver belongs to a parent.
+    async fn find_resolver(&self, ens_name: &str) -> Result<(Address, bool), ProviderError> {
+        // Get the ENS address, prioritize the local override variable
+        let ens_addr = self.ens.unwrap_or(ens::ENS_ADDRESS);
+
+        let mut name = ens_name;
+        loop {
+            // the call will return a Bytes array which we convert to an address
+            let data = self.call(&ens::get_resolver(ens_addr, name).into(), None).await?;
+            let resolver_address: Address = decode_bytes(ParamType::Address, data);
+            if resolver_address != Address::zero() {
+                return Ok((resolver_address, name != ens_name))
+            }
+            name = match name.split_once('.') {
+                Some((_, parent)) if !parent.is_empty() => parent,
+                _ => return Err(ProviderError::EnsError(ens_name.to_owned())),
+            };
+        }
+    }
 
-        Ok(decode_bytes(param, data))
+    /// Returns true if the resolver supports ENSIP-10 `resolve(bytes,bytes)`
+    async fn supports_extended_resolver(&self, resolver_address: Address) -> bool {
+        let tx = ens::supports_interface(resolver_address, ens::EXTENDED_RESOLVER_SELECTOR);
+        // resolvers which predate ERC-165 revert or return nothing
+        match self.call(&tx.into(), None).await {
+            Ok(data) => try_decode_bytes(ParamType::Bool, data).unwrap_or(false),
+            Err(_) => false,
+        }
     }
 
     #[cfg(test)]
@@ -993,6 +1583,45 @@ impl<P: JsonRpcClient> Provider<P> {
         self
     }
 
+    /// Sets the HTTP client which fetches offchain data, e.g. the metadata of NFT avatars, so
+    /// that it can be configured with timeouts, proxies or default headers (default: a
+    /// `reqwest::Client` with the default configuration)
+    #[must_use]
+    pub fn http_client(mut self, client: reqwest::Client) -> Self {
+        self.http = client;
+        self
+    }
+
+    /// Enables [EIP-3668](https://eips.ethereum.org/EIPS/eip-3668) CCIP-Read for `call`, which
+    /// follows at most `max_redirects` offchain lookups per call (default: disabled).
+    ///
+    /// ENS resolution always follows offchain lookups, up to
+    /// [`ccip::DEFAULT_MAX_REDIRECTS`](crate::ccip::DEFAULT_MAX_REDIRECTS) unless configured
+    /// otherwise.
+    #[must_use]
+    pub fn ccip_read(mut self, max_redirects: usize) -> Self {
+        self.max_ccip_redirects = max_redirects;
+        self
+    }
+
+    /// Sets the strategy estimating the fees of EIP-1559 transactions, used by
+    /// `estimate_eip1559_fees` and `fill_transaction` (default:
+    /// [`DefaultFeeEstimator`](crate::DefaultFeeEstimator))
+    ///
+    /// ```
+    /// use ethers_providers::{FeeUrgency, Provider, Http};
+    /// use std::convert::TryFrom;
+    ///
+    /// let provider = Provider::<Http>::try_from("http://localhost:8545")
+    ///     .unwrap()
+    ///     .with_fee_estimator(FeeUrgency::Fast);
+    /// ```
+    #[must_use]
+    pub fn with_fee_estimator(mut self, fee_estimator: impl FeeEstimator + 'static) -> Self {
+        self.fee_estimator = Some(Arc::new(fee_estimator));
+        self
+    }
+
     /// Sets the default polling interval for event filters and pending transactions
     /// (default: 7 seconds)
     #[must_use]
@@ -1043,6 +1672,13 @@ impl<T: JsonRpcClientWrapper> Provider<QuorumProvider<T>> {
     }
 }
 
+impl<T: JsonRpcClientWrapper> Provider<FallbackProvider<T>> {
+    /// Provider that fails over between endpoints in priority order
+    pub fn fallback(inner: FallbackProvider<T>) -> Self {
+        Self::new(inner)
+    }
+}
+
 impl Provider<MockProvider> {
     /// Returns a `Provider` instantiated with an internal "mock" transport.
     ///
@@ -1083,6 +1719,16 @@ fn decode_bytes<T: Detokenize>(param: ParamType, bytes: Bytes) -> T {
     T::from_tokens(tokens).expect("could not parse tokens as address")
 }
 
+fn try_decode_bytes<T: Detokenize>(param: ParamType, bytes: Bytes) -> Result<T, ProviderError> {
+    let tokens = abi::decode(&[param], bytes.as_ref())
+        .map_err(|err| ProviderError::CustomError(err.to_string()))?;
+    T::from_tokens(tokens).map_err(|err| ProviderError::CustomError(err.to_string()))
+}
+
+fn parse_url(url: &str) -> Result<Url, ProviderError> {
+    Url::parse(url).map_err(|err| ProviderError::CustomError(format!("{}: {}", err, url)))
+}
+
 impl TryFrom<&str> for Provider<HttpProvider> {
     type Error = ParseError;
 
@@ -1101,6 +1747,11 @@ impl TryFrom<String> for Provider<HttpProvider> {
 
 /// A middleware supporting development-specific JSON RPC methods
 ///
+/// Methods which are named differently by Anvil, Hardhat and Ganache are dispatched to the
+/// node detected by [`Provider::capabilities`](crate::Provider::capabilities), e.g.
+/// [`set_balance`](crate::DevRpcMiddleware::set_balance) calls `anvil_setBalance`,
+/// `hardhat_setBalance` or `evm_setAccountBalance`.
+///
 /// # Example
 ///
 ///```
@@ -1138,12 +1789,13 @@ impl TryFrom<String> for Provider<HttpProvider> {
 /// ```
 #[cfg(feature = "dev-rpc")]
 pub mod dev_rpc {
-    use crate::{FromErr, Middleware, ProviderError};
+    use crate::{FromErr, Middleware, NodeClient, ProviderError};
     use async_trait::async_trait;
-    use ethers_core::types::U256;
+    use ethers_core::types::{Address, Bytes, H256, U256};
+    use serde::Serialize;
     use thiserror::Error;
 
-    use std::fmt::Debug;
+    use std::{fmt::Debug, time::Duration};
 
     #[derive(Clone, Debug)]
     pub struct DevRpcMiddleware<M>(M);
@@ -1158,6 +1810,37 @@ pub mod dev_rpc {
 
         #[error("Could not revert to snapshot")]
         NoSnapshot,
+
+        #[error("not connected to a development node")]
+        NotDevNode,
+
+        #[error("{client:?} does not support {method}")]
+        UnsupportedMethod { client: NodeClient, method: &'static str },
+    }
+
+    /// The chain to fork when resetting the node, see [`DevRpcMiddleware::reset`]
+    #[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct Forking {
+        /// The endpoint of the node to fork from
+        pub json_rpc_url: String,
+        /// The block to fork at, the latest block if `None`
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub block_number: Option<u64>,
+    }
+
+    impl Forking {
+        /// Forks the latest block of the chain at `json_rpc_url`
+        pub fn new(json_rpc_url: impl Into<String>) -> Self {
+            Self { json_rpc_url: json_rpc_url.into(), block_number: None }
+        }
+
+        /// Sets the block to fork at
+        #[must_use]
+        pub fn block_number(mut self, block_number: u64) -> Self {
+            self.block_number = Some(block_number);
+            self
+        }
     }
 
     #[async_trait]
@@ -1208,6 +1891,186 @@ pub mod dev_rpc {
                 Err(DevRpcMiddlewareError::NoSnapshot)
             }
         }
+
+        /// Allows sending transactions from `address` without its private key
+        pub async fn impersonate_account(
+            &self,
+            address: Address,
+        ) -> Result<(), DevRpcMiddlewareError<M>> {
+            let method = self.dev_method("impersonateAccount").await?;
+            self.dev_request(&method, [address]).await
+        }
+
+        /// Stops impersonating `address`
+        pub async fn stop_impersonating_account(
+            &self,
+            address: Address,
+        ) -> Result<(), DevRpcMiddlewareError<M>> {
+            let method = self.dev_method("stopImpersonatingAccount").await?;
+            self.dev_request(&method, [address]).await
+        }
+
+        /// Sets the balance of `address`
+        pub async fn set_balance(
+            &self,
+            address: Address,
+            balance: U256,
+        ) -> Result<(), DevRpcMiddlewareError<M>> {
+            let method = self.dev_method("setBalance").await?;
+            self.dev_request(&method, (address, balance)).await
+        }
+
+        /// Sets the code of the account at `address`
+        pub async fn set_code(
+            &self,
+            address: Address,
+            code: Bytes,
+        ) -> Result<(), DevRpcMiddlewareError<M>> {
+            let method = self.dev_method("setCode").await?;
+            self.dev_request(&method, (address, code)).await
+        }
+
+        /// Sets the storage `slot` of the account at `address`
+        pub async fn set_storage_at(
+            &self,
+            address: Address,
+            slot: U256,
+            value: H256,
+        ) -> Result<(), DevRpcMiddlewareError<M>> {
+            let method = self.dev_method("setStorageAt").await?;
+            self.dev_request(&method, (address, slot, value)).await
+        }
+
+        /// Sets the nonce of `address`
+        pub async fn set_nonce(
+            &self,
+            address: Address,
+            nonce: U256,
+        ) -> Result<(), DevRpcMiddlewareError<M>> {
+            let method = self.dev_method("setNonce").await?;
+            self.dev_request(&method, (address, nonce)).await
+        }
+
+        /// Mines `blocks` blocks
+        pub async fn mine(&self, blocks: u64) -> Result<(), DevRpcMiddlewareError<M>> {
+            match self.dev_node().await? {
+                NodeClient::Ganache => {
+                    self.dev_request("evm_mine", [serde_json::json!({ "blocks": blocks })]).await
+                }
+                NodeClient::Anvil => self.dev_request("anvil_mine", [U256::from(blocks)]).await,
+                _ => self.dev_request("hardhat_mine", [U256::from(blocks)]).await,
+            }
+        }
+
+        /// Moves the time of the next blocks forward by `seconds`
+        pub async fn increase_time(&self, seconds: u64) -> Result<(), DevRpcMiddlewareError<M>> {
+            match self.dev_node().await? {
+                NodeClient::Anvil => {
+                    self.dev_request("anvil_increaseTime", [U256::from(seconds)]).await
+                }
+                _ => self.dev_request("evm_increaseTime", [seconds]).await,
+            }
+        }
+
+        /// Sets the timestamp of the next block, which is not supported by Ganache
+        pub async fn set_next_block_timestamp(
+            &self,
+            timestamp: u64,
+        ) -> Result<(), DevRpcMiddlewareError<M>> {
+            match self.dev_node().await? {
+                NodeClient::Anvil => {
+                    self.dev_request("anvil_setNextBlockTimestamp", [U256::from(timestamp)]).await
+                }
+                NodeClient::Hardhat => {
+                    self.dev_request("evm_setNextBlockTimestamp", [timestamp]).await
+                }
+                client => Err(DevRpcMiddlewareError::UnsupportedMethod {
+                    client,
+                    method: "evm_setNextBlockTimestamp",
+                }),
+            }
+        }
+
+        /// Enables or disables mining a block for every transaction
+        pub async fn set_automine(&self, enabled: bool) -> Result<(), DevRpcMiddlewareError<M>> {
+            match self.dev_node().await? {
+                // ganache starts and stops its miner instead
+                NodeClient::Ganache => {
+                    let method = if enabled { "miner_start" } else { "miner_stop" };
+                    self.dev_request(method, ()).await
+                }
+                _ => self.dev_request("evm_setAutomine", [enabled]).await,
+            }
+        }
+
+        /// Mines a block every `interval`, disabled if the interval is zero
+        pub async fn set_interval_mining(
+            &self,
+            interval: Duration,
+        ) -> Result<(), DevRpcMiddlewareError<M>> {
+            // anvil expects seconds, hardhat milliseconds
+            let interval = match self.dev_node().await? {
+                NodeClient::Anvil => interval.as_secs(),
+                NodeClient::Hardhat => interval.as_millis() as u64,
+                client => {
+                    return Err(DevRpcMiddlewareError::UnsupportedMethod {
+                        client,
+                        method: "evm_setIntervalMining",
+                    })
+                }
+            };
+            self.dev_request("evm_setIntervalMining", [interval]).await
+        }
+
+        /// Resets the node to its initial state, or to a fork of another chain
+        pub async fn reset(
+            &self,
+            forking: Option<Forking>,
+        ) -> Result<(), DevRpcMiddlewareError<M>> {
+            let method = self.dev_method("reset").await?;
+            match forking {
+                Some(forking) => {
+                    self.dev_request(&method, [serde_json::json!({ "forking": forking })]).await
+                }
+                None => self.dev_request(&method, ()).await,
+            }
+        }
+
+        /// Returns the detected development node
+        async fn dev_node(&self) -> Result<NodeClient, DevRpcMiddlewareError<M>> {
+            match self.provider().capabilities().await?.client {
+                Some(client) if client.is_dev() => Ok(client),
+                _ => Err(DevRpcMiddlewareError::NotDevNode),
+            }
+        }
+
+        /// Returns the name of the detected node for a method named `anvil_<name>` by Anvil
+        async fn dev_method(&self, name: &'static str) -> Result<String, DevRpcMiddlewareError<M>> {
+            match (self.dev_node().await?, name) {
+                (NodeClient::Anvil, _) => Ok(format!("anvil_{}", name)),
+                (NodeClient::Hardhat, _) => Ok(format!("hardhat_{}", name)),
+                // ganache prefixes its account setters with `evm_setAccount`
+                (NodeClient::Ganache, "setBalance" | "setCode" | "setStorageAt" | "setNonce") => {
+                    Ok(format!("evm_setAccount{}", name.trim_start_matches("set")))
+                }
+                (client, _) => {
+                    Err(DevRpcMiddlewareError::UnsupportedMethod { client, method: name })
+                }
+            }
+        }
+
+        /// Sends a request whose result is ignored, since nodes respond with `true` or `null`
+        async fn dev_request<T>(
+            &self,
+            method: &str,
+            params: T,
+        ) -> Result<(), DevRpcMiddlewareError<M>>
+        where
+            T: Debug + Serialize + Send + Sync,
+        {
+            self.provider().request::<T, serde_json::Value>(method, params).await?;
+            Ok(())
+        }
     }
     #[cfg(test)]
     // Celo blocks can not get parsed when used with Ganache
@@ -1273,6 +2136,73 @@ pub mod dev_rpc {
             assert_eq!(block, block0);
             assert_eq!(time, time0);
         }
+
+        #[tokio::test]
+        async fn dispatches_to_detected_node() {
+            use crate::NodeCapabilities;
+            use serde_json::json;
+
+            let address = Address::repeat_byte(1);
+            let dev_client = |client| {
+                let (provider, mock) = Provider::mocked();
+                let provider =
+                    provider.with_capabilities(NodeCapabilities::for_client(Some(client)));
+                (DevRpcMiddleware::new(provider), mock)
+            };
+
+            let (client, mock) = dev_client(NodeClient::Hardhat);
+            mock.expect("hardhat_setBalance").returns(true).unwrap();
+            mock.expect("evm_setIntervalMining").returns(true).unwrap();
+            mock.expect("hardhat_reset").returns(true).unwrap();
+            mock.expect("evm_increaseTime").returns(60u64).unwrap();
+            client.set_balance(address, 100.into()).await.unwrap();
+            client.set_interval_mining(Duration::from_secs(2)).await.unwrap();
+            let forking = Forking::new("http://localhost:8545").block_number(10);
+            client.reset(Some(forking)).await.unwrap();
+            mock.assert_request("hardhat_setBalance", (address, U256::from(100))).unwrap();
+            mock.assert_request("evm_setIntervalMining", [2_000u64]).unwrap();
+            let forking = json!({"jsonRpcUrl": "http://localhost:8545", "blockNumber": 10});
+            mock.assert_request("hardhat_reset", [json!({ "forking": forking })]).unwrap();
+            client.increase_time(60).await.unwrap();
+            mock.assert_request("evm_increaseTime", [60u64]).unwrap();
+
+            let (client, mock) = dev_client(NodeClient::Anvil);
+            mock.expect("anvil_impersonateAccount").returns(()).unwrap();
+            mock.expect("evm_setIntervalMining").returns(()).unwrap();
+            client.impersonate_account(address).await.unwrap();
+            client.set_interval_mining(Duration::from_secs(2)).await.unwrap();
+            mock.assert_request("anvil_impersonateAccount", [address]).unwrap();
+            mock.assert_request("evm_setIntervalMining", [2u64]).unwrap();
+            mock.expect("anvil_increaseTime").returns(U256::from(60)).unwrap();
+            mock.expect("anvil_setNextBlockTimestamp").returns(()).unwrap();
+            client.increase_time(60).await.unwrap();
+            client.set_next_block_timestamp(1_700_000_000).await.unwrap();
+            mock.assert_request("anvil_increaseTime", [U256::from(60)]).unwrap();
+            mock.assert_request("anvil_setNextBlockTimestamp", [U256::from(1_700_000_000)])
+                .unwrap();
+
+            let (client, mock) = dev_client(NodeClient::Ganache);
+            mock.expect("evm_setAccountNonce").returns(true).unwrap();
+            mock.expect("evm_mine").returns("0x0").unwrap();
+            client.set_nonce(address, 5.into()).await.unwrap();
+            client.mine(3).await.unwrap();
+            mock.assert_request("evm_setAccountNonce", (address, U256::from(5))).unwrap();
+            mock.assert_request("evm_mine", [json!({ "blocks": 3 })]).unwrap();
+            let err = client.impersonate_account(address).await.unwrap_err();
+            assert!(matches!(
+                err,
+                DevRpcMiddlewareError::UnsupportedMethod { client: NodeClient::Ganache, .. }
+            ));
+            let err = client.set_next_block_timestamp(1_700_000_000).await.unwrap_err();
+            assert!(matches!(
+                err,
+                DevRpcMiddlewareError::UnsupportedMethod { client: NodeClient::Ganache, .. }
+            ));
+
+            let (client, _) = dev_client(NodeClient::Geth);
+            let err = client.set_automine(false).await.unwrap_err();
+            assert!(matches!(err, DevRpcMiddlewareError::NotDevNode));
+        }
     }
 }
 
@@ -1322,6 +2252,132 @@ mod tests {
             .unwrap_err();
     }
 
+    #[tokio::test]
+    async fn resolve_field_with_mock() {
+        let (provider, mock) = Provider::mocked();
+        let resolver = Address::repeat_byte(1);
+        // responses are popped from the back
+        mock.push(Bytes::from(abi::encode(&[Token::String("https://example.com".into())])))
+            .unwrap();
+        mock.push(Bytes::from(abi::encode(&[Token::Address(resolver)]))).unwrap();
+
+        // the resolver answers directly, so its interface is not probed
+        let url = provider.resolve_field("foo.eth", "url").await.unwrap();
+        assert_eq!(url, "https://example.com");
+    }
+
+    #[tokio::test]
+    async fn resolve_avatar_without_address() {
+        let (provider, mock) = Provider::mocked();
+        let resolver = Address::repeat_byte(1);
+        mock.push(Bytes::from(abi::encode(&[Token::String("ipfs://QmAvatar".into())]))).unwrap();
+        mock.push(Bytes::from(abi::encode(&[Token::Address(resolver)]))).unwrap();
+
+        // the name has no `addr` record, which is only needed for NFT avatars
+        let avatar = provider.resolve_avatar("foo.eth").await.unwrap();
+        assert_eq!(avatar.as_str(), "https://ipfs.io/ipfs/QmAvatar");
+    }
+
+    #[tokio::test]
+    async fn resolve_name_with_extended_resolver() {
+        let (provider, mock) = Provider::mocked();
+        let resolver = Address::repeat_byte(1);
+        let addr = Address::repeat_byte(2);
+        // the resolver only implements `resolve(bytes,bytes)`, so `addr(bytes32)` returns nothing
+        let result = abi::encode(&[Token::Address(addr)]);
+        mock.push(Bytes::from(abi::encode(&[Token::Bytes(result)]))).unwrap();
+        mock.push(Bytes::from(abi::encode(&[Token::Bool(true)]))).unwrap();
+        mock.push(Bytes::default()).unwrap();
+        mock.push(Bytes::from(abi::encode(&[Token::Address(resolver)]))).unwrap();
+
+        assert_eq!(provider.resolve_name("foo.eth").await.unwrap(), addr);
+    }
+
+    #[tokio::test]
+    async fn resolve_nft_with_http_client() {
+        use std::io::{Read, Write};
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let uri = format!("http://{}/{{id}}", listener.local_addr().unwrap());
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut head = Vec::new();
+            let mut chunk = [0u8; 1024];
+            while !head.windows(4).any(|w| w == b"\r\n\r\n") {
+                let n = stream.read(&mut chunk).unwrap();
+                head.extend_from_slice(&chunk[..n]);
+            }
+            let body = r#"{"image":"ipfs://QmImage"}"#;
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            )
+            .unwrap();
+            String::from_utf8(head).unwrap().to_lowercase()
+        });
+
+        let mut headers = reqwest::header::HeaderMap::new();
+        headers.insert("x-api-key", "my-api-key".parse().unwrap());
+        let client = reqwest::Client::builder().default_headers(headers).build().unwrap();
+        let (provider, mock) = Provider::mocked();
+        let provider = provider.http_client(client);
+        mock.push(Bytes::from(abi::encode(&[Token::String(uri)]))).unwrap();
+
+        let token: ens::ErcNft =
+            "eip155:1/erc1155:0x0000000000000000000000000000000000000001/1".parse().unwrap();
+        let image = provider.resolve_nft(token).await.unwrap();
+        assert_eq!(image.as_str(), "https://ipfs.io/ipfs/QmImage");
+
+        // the metadata was fetched by the configured client
+        let head = server.join().unwrap();
+        let id = "0000000000000000000000000000000000000000000000000000000000000001";
+        assert!(head.starts_with(&format!("get /{} http/1.1", id)));
+        assert!(head.contains("x-api-key: my-api-key"));
+    }
+
+    #[tokio::test]
+    async fn resolve_name_with_wildcard_resolver() {
+        let (provider, mock) = Provider::mocked();
+        let resolver = Address::repeat_byte(1);
+        let addr = Address::repeat_byte(2);
+        // `resolve(bytes,bytes)` wraps the result of `addr(bytes32)` in `bytes`
+        let result = abi::encode(&[Token::Address(addr)]);
+        mock.push(Bytes::from(abi::encode(&[Token::Bytes(result)]))).unwrap();
+        mock.push(Bytes::from(abi::encode(&[Token::Bool(true)]))).unwrap();
+        mock.push(Bytes::from(abi::encode(&[Token::Address(resolver)]))).unwrap();
+        mock.push(Bytes::from(abi::encode(&[Token::Address(Address::zero())]))).unwrap();
+
+        assert_eq!(provider.resolve_name("sub.foo.eth").await.unwrap(), addr);
+    }
+
+    #[tokio::test]
+    async fn resolve_name_without_wildcard_support() {
+        let (provider, mock) = Provider::mocked();
+        mock.push(Bytes::from(abi::encode(&[Token::Bool(false)]))).unwrap();
+        mock.push(Bytes::from(abi::encode(&[Token::Address(Address::repeat_byte(1))]))).unwrap();
+        mock.push(Bytes::from(abi::encode(&[Token::Address(Address::zero())]))).unwrap();
+
+        let err = provider.resolve_name("sub.foo.eth").await.unwrap_err();
+        assert!(matches!(err, ProviderError::EnsError(_)));
+    }
+
+    #[tokio::test]
+    async fn lookup_address_verifies_forward_resolution() {
+        let (provider, mock) = Provider::mocked();
+        let resolver = Address::repeat_byte(1);
+        let address = Address::repeat_byte(2);
+        // the name resolves to a different address
+        mock.push(Bytes::from(abi::encode(&[Token::Address(Address::repeat_byte(3))]))).unwrap();
+        mock.push(Bytes::from(abi::encode(&[Token::Address(resolver)]))).unwrap();
+        mock.push(Bytes::from(abi::encode(&[Token::String("foo.eth".into())]))).unwrap();
+        mock.push(Bytes::from(abi::encode(&[Token::Address(resolver)]))).unwrap();
+
+        let err = provider.lookup_address(address).await.unwrap_err();
+        assert!(matches!(err, ProviderError::EnsNotOwned(name) if name == "foo.eth"));
+    }
+
     #[tokio::test]
     #[cfg_attr(feature = "celo", ignore)]
     async fn test_new_block_filter() {
@@ -1448,6 +2504,47 @@ mod tests {
         dbg!(&history);
     }
 
+    #[tokio::test]
+    async fn fee_estimator() {
+        use crate::PercentileFeeEstimator;
+        use serde_json::json;
+
+        let (provider, mock) = Provider::mocked();
+        let provider = provider.with_fee_estimator(PercentileFeeEstimator::new(50.0));
+        mock.expect("eth_getBlockByNumber").returns(json!({"baseFeePerGas": "0x64"})).unwrap();
+        mock.expect("eth_feeHistory")
+            .returns(json!({
+                "baseFeePerGas": ["0x64", "0x78"],
+                "gasUsedRatio": [1.0],
+                "oldestBlock": "0x1",
+                "reward": [["0x2"]]
+            }))
+            .unwrap();
+
+        let fees = provider.estimate_eip1559_fees(None).await.unwrap();
+        assert_eq!(fees, (122.into(), 2.into()));
+        mock.assert_request("eth_getBlockByNumber", json!(["latest", false])).unwrap();
+        mock.assert_request("eth_feeHistory", json!(["0xa", "latest", [50.0]])).unwrap();
+    }
+
+    #[tokio::test]
+    async fn fill_transaction_without_eip1559() {
+        use ethers_core::types::Eip1559TransactionRequest;
+        use serde_json::json;
+
+        let (provider, mock) = Provider::mocked();
+        mock.expect("eth_getBlockByNumber").returns(json!({"number": "0x1"})).unwrap();
+        mock.expect("eth_gasPrice").returns(U256::from(5)).unwrap();
+
+        let mut tx: TypedTransaction =
+            Eip1559TransactionRequest::new().to(Address::zero()).gas(21_000).into();
+        provider.fill_transaction(&mut tx, None).await.unwrap();
+        match tx {
+            TypedTransaction::Legacy(tx) => assert_eq!(tx.gas_price, Some(5.into())),
+            tx => panic!("expected a legacy transaction, got {:?}", tx),
+        }
+    }
+
     #[tokio::test]
     #[ignore]
     #[cfg(feature = "ws")]
@@ -1488,4 +2585,265 @@ mod tests {
             .unwrap();
         dbg!(traces);
     }
+
+    #[tokio::test]
+    async fn debug_trace_transaction_with_call_tracer() {
+        use ethers_core::types::{CallConfig, GethDebugBuiltInTracerType, GethTraceFrame};
+        use serde_json::json;
+
+        let (provider, mock) = Provider::mocked();
+        mock.push(json!({
+            "type": "CALL",
+            "from": "0x0000000000000000000000000000000000000001",
+            "to": "0x0000000000000000000000000000000000000002",
+            "gas": "0x7148",
+            "gasUsed": "0x5208",
+            "input": "0x"
+        }))
+        .unwrap();
+
+        let options = GethDebugTracingOptions::tracer(GethDebugBuiltInTracerType::CallTracer)
+            .tracer_config(CallConfig { only_top_call: Some(true), with_log: None });
+        let trace = provider.debug_trace_transaction(H256::zero(), options).await.unwrap();
+        match trace {
+            GethTrace::Known(GethTraceFrame::CallTracer(frame)) => {
+                assert_eq!(frame.gas_used, U256::from(0x5208))
+            }
+            trace => panic!("unexpected trace {:?}", trace),
+        }
+        mock.assert_request(
+            "debug_traceTransaction",
+            [
+                json!(H256::zero()),
+                json!({"tracer": "callTracer", "tracerConfig": {"onlyTopCall": true}}),
+            ],
+        )
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn get_verified_proof() {
+        use ethers_core::types::{StorageProof, EMPTY_TRIE_ROOT, KECCAK_EMPTY};
+        use serde_json::json;
+
+        let (provider, mock) = Provider::mocked();
+        let block_hash = H256::repeat_byte(1);
+        mock.expect("eth_getBlockByNumber")
+            .returns(json!({"hash": block_hash, "number": "0x1", "stateRoot": EMPTY_TRIE_ROOT}))
+            .unwrap();
+        let mut proof = EIP1186ProofResponse {
+            code_hash: KECCAK_EMPTY,
+            storage_hash: EMPTY_TRIE_ROOT,
+            ..Default::default()
+        };
+        mock.expect("eth_getProof").times(1).returns(&proof).unwrap();
+
+        let address = Address::zero();
+        let verified = provider.get_verified_proof(address, vec![], None).await.unwrap();
+        assert_eq!(verified, proof);
+        mock.assert_request("eth_getBlockByNumber", json!(["latest", false])).unwrap();
+        mock.assert_request("eth_getProof", json!([address, [], { "blockHash": block_hash }]))
+            .unwrap();
+
+        // every requested slot must be proven exactly once
+        let slot = H256::repeat_byte(2);
+        mock.expect("eth_getProof").times(1).returns(&proof).unwrap();
+        let err = provider.get_verified_proof(address, vec![slot], None).await.unwrap_err();
+        assert!(
+            matches!(err, ProviderError::ProofError(ProofError::MissingSlot(key)) if key == slot)
+        );
+        proof.storage_proof = vec![StorageProof { key: slot, ..Default::default() }; 2];
+        mock.expect("eth_getProof").times(1).returns(&proof).unwrap();
+        let err = provider.get_verified_proof(address, vec![slot], None).await.unwrap_err();
+        assert!(
+            matches!(err, ProviderError::ProofError(ProofError::UnexpectedSlot(key)) if key == slot)
+        );
+        proof.storage_proof.truncate(1);
+        mock.expect("eth_getProof").times(1).returns(&proof).unwrap();
+        let verified = provider.get_verified_proof(address, vec![slot], None).await.unwrap();
+        assert_eq!(verified, proof);
+
+        // the state trie is empty, so the account can't have a balance
+        proof.balance = 1.into();
+        mock.expect("eth_getProof").returns(&proof).unwrap();
+        let err = provider.get_verified_proof(address, vec![slot], None).await.unwrap_err();
+        assert!(matches!(err, ProviderError::ProofError(ProofError::AccountMismatch)));
+    }
+
+    #[tokio::test]
+    async fn typed_rpc_methods() {
+        use ethers_core::types::{CallBundle, SyncingStatus};
+        use serde_json::json;
+
+        let (provider, mock) = Provider::mocked();
+        mock.expect("eth_maxPriorityFeePerGas").returns(U256::from(2)).unwrap();
+        mock.expect("net_peerCount").returns(U64::from(25)).unwrap();
+        mock.expect("eth_syncing").returns(false).unwrap();
+        mock.expect("web3_sha3").returns(H256::from(utils::keccak256([0x68]))).unwrap();
+        mock.expect("eth_getBlockReceipts").returns(()).unwrap();
+        mock.expect("eth_getTransactionBySenderAndNonce").returns(()).unwrap();
+        mock.expect("eth_callMany")
+            .returns(json!([[{"value": "0x01"}, {"error": "execution reverted"}]]))
+            .unwrap();
+
+        assert_eq!(provider.get_max_priority_fee_per_gas().await.unwrap(), 2.into());
+        assert_eq!(provider.get_peer_count().await.unwrap(), 25.into());
+        assert_eq!(provider.syncing().await.unwrap(), SyncingStatus::NotSyncing);
+        let hash = provider.sha3(vec![0x68]).await.unwrap();
+        assert_eq!(hash, H256::from(utils::keccak256([0x68])));
+        mock.assert_request("eth_maxPriorityFeePerGas", ()).unwrap();
+        mock.assert_request("net_peerCount", ()).unwrap();
+        mock.assert_request("eth_syncing", ()).unwrap();
+        mock.assert_request("web3_sha3", ["0x68"]).unwrap();
+
+        // unknown blocks are not mistaken for blocks without transactions
+        let hash = H256::repeat_byte(1);
+        assert_eq!(provider.get_block_receipts(hash).await.unwrap(), None);
+        mock.assert_request("eth_getBlockReceipts", [json!({ "blockHash": hash })]).unwrap();
+
+        let sender = Address::repeat_byte(2);
+        let tx = provider.get_transaction_by_sender_and_nonce(sender, 1.into()).await.unwrap();
+        assert_eq!(tx, None);
+        mock.assert_request("eth_getTransactionBySenderAndNonce", json!([sender, "0x1"])).unwrap();
+
+        let bundle: CallBundle = vec![TransactionRequest::new().to(sender)].into_iter().collect();
+        let responses = provider.call_many(vec![bundle], BlockNumber::Latest).await.unwrap();
+        assert_eq!(responses[0][0].value, Some(Bytes::from(vec![1])));
+        assert_eq!(responses[0][1].error.as_deref(), Some("execution reverted"));
+    }
+
+    #[tokio::test]
+    async fn node_capabilities() {
+        use ethers_core::types::Eip1559TransactionRequest;
+
+        let (provider, mock) = Provider::mocked();
+        mock.expect("web3_clientVersion")
+            .returns("Geth/v1.10.17-stable-25c9b49f/linux-amd64/go1.18")
+            .unwrap();
+
+        // detected once, geth has no trace namespace
+        let capabilities = provider.capabilities().await.unwrap();
+        assert_eq!(capabilities.client, Some(NodeClient::Geth));
+        assert!(!capabilities.pubsub);
+        assert_eq!(provider.capabilities().await.unwrap(), capabilities);
+        mock.assert_request("web3_clientVersion", ()).unwrap();
+        let err = provider.trace_block(BlockNumber::Latest).await.unwrap_err();
+        assert!(matches!(err, ProviderError::UnsupportedRPC));
+        assert!(mock.assert_request("trace_block", [BlockNumber::Latest]).is_err());
+
+        // ganache has no fee history, transactions get a gas price instead
+        let (provider, mock) = Provider::mocked();
+        let provider =
+            provider.with_capabilities(NodeCapabilities::for_client(Some(NodeClient::Ganache)));
+        mock.expect("eth_gasPrice").returns(U256::from(5)).unwrap();
+        let err = provider.estimate_eip1559_fees(None).await.unwrap_err();
+        assert!(matches!(err, ProviderError::Eip1559Unsupported));
+        let mut tx: TypedTransaction =
+            Eip1559TransactionRequest::new().to(Address::zero()).gas(21_000).into();
+        provider.fill_transaction(&mut tx, None).await.unwrap();
+        assert_eq!(tx.gas_price(), Some(5.into()));
+
+        // openethereum serves block receipts by number only
+        let (provider, mock) = Provider::mocked();
+        let provider = provider
+            .with_capabilities(NodeCapabilities::for_client(Some(NodeClient::OpenEthereum)));
+        mock.expect("parity_getBlockReceipts").returns(Vec::<TransactionReceipt>::new()).unwrap();
+        assert_eq!(provider.get_block_receipts(1u64).await.unwrap(), Some(Vec::new()));
+        mock.assert_request("parity_getBlockReceipts", ["0x1"]).unwrap();
+        let err = provider.get_block_receipts(H256::zero()).await.unwrap_err();
+        assert!(matches!(err, ProviderError::UnsupportedRPC));
+    }
+
+    #[tokio::test]
+    async fn streams_poll_filters_without_pubsub() {
+        use serde_json::json;
+
+        let (provider, mock) = Provider::mocked();
+        let provider = provider.interval(Duration::from_millis(1));
+        let block = Block::<TxHash> {
+            hash: Some(H256::repeat_byte(1)),
+            number: Some(1.into()),
+            ..Default::default()
+        };
+        let tx_hash = H256::repeat_byte(2);
+        mock.expect("eth_newBlockFilter").returns(U256::from(7)).unwrap();
+        mock.expect("eth_newPendingTransactionFilter").returns(U256::from(8)).unwrap();
+        mock.expect("eth_getFilterChanges")
+            .matching(|params| params[0] == json!("0x7"))
+            .times(1)
+            .returns(vec![block.hash.unwrap()])
+            .unwrap();
+        mock.expect("eth_getFilterChanges")
+            .matching(|params| params[0] == json!("0x8"))
+            .times(1)
+            .returns(vec![tx_hash])
+            .unwrap();
+        mock.expect("eth_getBlockByHash").returns(&block).unwrap();
+        mock.expect("eth_uninstallFilter").returns(true).unwrap();
+
+        // the block filter yields hashes, the stream fetches the blocks
+        let mut blocks = provider.stream_blocks().await.unwrap();
+        assert_eq!(blocks.source(), WatchSource::Filter(7.into()));
+        assert_eq!(blocks.next().await.unwrap(), block);
+        mock.assert_request("eth_newBlockFilter", Vec::<()>::new()).unwrap();
+        mock.assert_request("eth_getFilterChanges", ["0x7"]).unwrap();
+        mock.assert_request("eth_getBlockByHash", json!([block.hash, false])).unwrap();
+
+        let mut txs = provider.stream_pending_txs().await.unwrap();
+        assert_eq!(txs.source(), WatchSource::Filter(8.into()));
+        assert_eq!(txs.next().await.unwrap(), tx_hash);
+        assert!(txs.unsubscribe().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn engine_api() {
+        use ethers_core::types::PayloadStatusKind;
+        use serde_json::json;
+
+        let (provider, mock) = Provider::mocked();
+        let head = H256::repeat_byte(1);
+        let state = ForkchoiceState {
+            head_block_hash: head,
+            safe_block_hash: head,
+            finalized_block_hash: H256::zero(),
+        };
+        let attributes = PayloadAttributes {
+            timestamp: 5.into(),
+            prev_randao: H256::zero(),
+            suggested_fee_recipient: Address::zero(),
+        };
+        mock.expect("engine_forkchoiceUpdatedV1")
+            .returns(json!({
+                "payloadStatus": {"status": "VALID", "latestValidHash": head},
+                "payloadId": "0x0000000000000001"
+            }))
+            .unwrap();
+        mock.expect("engine_newPayloadV1")
+            .returns(json!({"status": "SYNCING", "latestValidHash": null}))
+            .unwrap();
+
+        let updated = provider.engine_forkchoice_updated_v1(state, Some(attributes)).await.unwrap();
+        assert!(updated.payload_status.is_valid());
+        assert_eq!(updated.payload_id, Some(PayloadId::from_low_u64_be(1)));
+        mock.assert_request(
+            "engine_forkchoiceUpdatedV1",
+            [
+                json!({
+                    "headBlockHash": head,
+                    "safeBlockHash": head,
+                    "finalizedBlockHash": H256::zero()
+                }),
+                json!({
+                    "timestamp": "0x5",
+                    "prevRandao": H256::zero(),
+                    "suggestedFeeRecipient": Address::zero()
+                }),
+            ],
+        )
+        .unwrap();
+
+        let status = provider.engine_new_payload_v1(ExecutionPayload::default()).await.unwrap();
+        assert_eq!(status.status, PayloadStatusKind::Syncing);
+        mock.assert_request("engine_newPayloadV1", [ExecutionPayload::default()]).unwrap();
+    }
 }
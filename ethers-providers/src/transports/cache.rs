@@ -0,0 +1,599 @@
+//! A [`JsonRpcClient`] implementation that caches the responses to requests whose result can
+//! never change.
+use super::common::JsonRpcError;
+use crate::{provider::ProviderError, JsonRpcClient, NotificationStream};
+
+use async_trait::async_trait;
+use ethers_core::types::{U256, U64};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Debug,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+};
+use tracing::trace;
+
+/// A storage backend for the responses cached by a [`CachingClient`]
+pub trait ResponseCache: Send + Sync + Debug {
+    /// Returns the cached response for the request `key`
+    fn get(&self, key: &str) -> Option<Value>;
+
+    /// Stores the response for the request `key`
+    fn insert(&self, key: &str, value: &Value);
+}
+
+/// An in-memory [`ResponseCache`] which evicts the least recently used response once it holds
+/// `capacity` responses
+#[derive(Debug)]
+pub struct MemoryCache {
+    capacity: usize,
+    inner: Mutex<LruState>,
+}
+
+#[derive(Debug, Default)]
+struct LruState {
+    /// Incremented on every access
+    tick: u64,
+    /// The cached values and the tick of their last access, by key
+    entries: HashMap<String, (Value, u64)>,
+    /// The keys by the tick of their last access
+    by_access: BTreeMap<u64, String>,
+}
+
+impl MemoryCache {
+    /// Creates a cache holding at most `capacity` responses
+    pub fn new(capacity: usize) -> Self {
+        Self { capacity: capacity.max(1), inner: Default::default() }
+    }
+
+    /// Returns the number of cached responses
+    pub fn len(&self) -> usize {
+        self.inner.lock().unwrap().entries.len()
+    }
+
+    /// Returns true if no responses are cached
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl ResponseCache for MemoryCache {
+    fn get(&self, key: &str) -> Option<Value> {
+        let mut state = self.inner.lock().unwrap();
+        state.tick += 1;
+        let tick = state.tick;
+        let LruState { entries, by_access, .. } = &mut *state;
+        let (value, last_access) = entries.get_mut(key)?;
+        by_access.remove(&*last_access);
+        by_access.insert(tick, key.to_string());
+        *last_access = tick;
+        Some(value.clone())
+    }
+
+    fn insert(&self, key: &str, value: &Value) {
+        let mut state = self.inner.lock().unwrap();
+        state.tick += 1;
+        let tick = state.tick;
+        if let Some((_, last_access)) = state.entries.insert(key.to_string(), (value.clone(), tick))
+        {
+            state.by_access.remove(&last_access);
+        }
+        state.by_access.insert(tick, key.to_string());
+
+        while state.entries.len() > self.capacity {
+            let oldest = *state.by_access.keys().next().expect("entries are not empty");
+            if let Some(key) = state.by_access.remove(&oldest) {
+                state.entries.remove(&key);
+            }
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub use disk::DiskCache;
+
+#[cfg(not(target_arch = "wasm32"))]
+mod disk {
+    use super::ResponseCache;
+    use ethers_core::utils::keccak256;
+    use serde_json::Value;
+    use std::{
+        fs, io,
+        path::{Path, PathBuf},
+        sync::atomic::{AtomicU64, Ordering},
+    };
+    use tracing::trace;
+
+    /// Makes the temporary file of every write unique within the process
+    static TMP_ID: AtomicU64 = AtomicU64::new(0);
+
+    /// A [`ResponseCache`] which stores every response as a JSON file in a directory, so that
+    /// the cache survives restarts of the process
+    #[derive(Debug, Clone)]
+    pub struct DiskCache {
+        dir: PathBuf,
+    }
+
+    impl DiskCache {
+        /// Creates a cache in `dir`, which is created if it does not exist
+        pub fn new(dir: impl AsRef<Path>) -> io::Result<Self> {
+            fs::create_dir_all(dir.as_ref())?;
+            Ok(Self { dir: dir.as_ref().to_path_buf() })
+        }
+
+        /// Returns the directory of the cache
+        pub fn dir(&self) -> &Path {
+            &self.dir
+        }
+
+        fn path(&self, key: &str) -> PathBuf {
+            self.dir.join(format!("{}.json", hex::encode(keccak256(key.as_bytes()))))
+        }
+    }
+
+    impl ResponseCache for DiskCache {
+        fn get(&self, key: &str) -> Option<Value> {
+            let file = fs::File::open(self.path(key)).ok()?;
+            serde_json::from_reader(io::BufReader::new(file)).ok()
+        }
+
+        fn insert(&self, key: &str, value: &Value) {
+            let path = self.path(key);
+            // write to a temporary file first, so that readers never see partial writes, and
+            // concurrent writers of the same key never write to the same file
+            let tmp = path.with_extension(format!(
+                "{}.{}.tmp",
+                std::process::id(),
+                TMP_ID.fetch_add(1, Ordering::Relaxed)
+            ));
+            let res = serde_json::to_vec(value)
+                .map_err(io::Error::from)
+                .and_then(|bytes| fs::write(&tmp, bytes))
+                .and_then(|_| fs::rename(&tmp, &path));
+            if let Err(err) = res {
+                trace!(path = ?path, "could not cache response: {}", err);
+            }
+        }
+    }
+}
+
+/// Whether the response to a request can be cached
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Immutability {
+    /// The response never changes
+    Always,
+    /// The response never changes once the block is finalized
+    AtBlock(u64),
+    /// The response never changes once the block of the returned object is finalized
+    AtResultBlock,
+    /// The response may change
+    Never,
+}
+
+/// Returns whether the response to the request can be cached
+fn immutability(method: &str, params: Option<&Value>) -> Immutability {
+    let param = |idx: usize| params.and_then(|params| params.get(idx));
+    match method {
+        "eth_chainId" | "net_version" | "eth_getBlockByHash" => Immutability::Always,
+        "eth_getTransactionReceipt" => Immutability::AtResultBlock,
+        "eth_getBlockByNumber" => block_immutability(param(0)),
+        "eth_getBalance" | "eth_getCode" | "eth_getTransactionCount" | "eth_call" => {
+            block_immutability(param(1))
+        }
+        "eth_getStorageAt" => block_immutability(param(2)),
+        _ => Immutability::Never,
+    }
+}
+
+/// Requests at a block hash never change, requests at a block number once the block is
+/// finalized and requests at a block tag may always change.
+fn block_immutability(block: Option<&Value>) -> Immutability {
+    match block {
+        Some(Value::String(number)) => match parse_quantity(number) {
+            Some(number) => Immutability::AtBlock(number),
+            None => Immutability::Never,
+        },
+        // EIP-1898 block parameter
+        Some(Value::Object(block)) => {
+            if block.contains_key("blockHash") {
+                Immutability::Always
+            } else {
+                match block.get("blockNumber").and_then(Value::as_str).and_then(parse_quantity) {
+                    Some(number) => Immutability::AtBlock(number),
+                    None => Immutability::Never,
+                }
+            }
+        }
+        _ => Immutability::Never,
+    }
+}
+
+/// Parses a hex encoded `QUANTITY`, returns `None` for block tags like `latest`
+fn parse_quantity(quantity: &str) -> Option<u64> {
+    u64::from_str_radix(quantity.strip_prefix("0x")?, 16).ok()
+}
+
+/// A [`JsonRpcClient`] which wraps another transport and caches the responses to requests
+/// whose result can never change:
+///
+/// - `eth_chainId`, `net_version` and `eth_getBlockByHash`
+/// - `eth_getTransactionReceipt` if the receipt's block is finalized
+/// - `eth_getBlockByNumber`, `eth_getBalance`, `eth_getCode`, `eth_getTransactionCount`,
+///   `eth_getStorageAt` and `eth_call` at a block hash or at the number of a finalized block
+///
+/// A block is considered finalized once it is `confirmations` blocks deep. Empty (`null`)
+/// responses and errors are never cached.
+///
+/// The caches are consulted in the order they were added, a hit in a later cache is written
+/// to all caches before it. This allows to keep the hot responses in memory in front of a
+/// persistent [`DiskCache`].
+///
+/// # Example
+///
+/// ```no_run
+/// use ethers_providers::{CachingClient, DiskCache, Http, MemoryCache, Middleware, Provider};
+/// use std::str::FromStr;
+///
+/// # async fn foo() -> Result<(), Box<dyn std::error::Error>> {
+/// let http = Http::from_str("http://localhost:8545")?;
+/// let client = CachingClient::new(http, MemoryCache::new(10_000))
+///     .with_cache(DiskCache::new("./rpc-cache")?)
+///     .confirmations(64);
+/// let provider = Provider::new(client);
+/// // served from the cache when the script runs again
+/// let block = provider.get_block(13_000_000u64).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct CachingClient<T> {
+    inner: T,
+    caches: Vec<Box<dyn ResponseCache>>,
+    /// The depth at which a block is considered finalized
+    confirmations: u64,
+    /// The highest block number seen so far
+    head: AtomicU64,
+}
+
+impl<T: JsonRpcClient> CachingClient<T> {
+    /// Creates a new `CachingClient` which stores the responses in `cache`
+    pub fn new(inner: T, cache: impl ResponseCache + 'static) -> Self {
+        Self { inner, caches: vec![Box::new(cache)], confirmations: 64, head: AtomicU64::new(0) }
+    }
+
+    /// Adds a cache which is consulted after all previously added caches
+    #[must_use]
+    pub fn with_cache(mut self, cache: impl ResponseCache + 'static) -> Self {
+        self.caches.push(Box::new(cache));
+        self
+    }
+
+    /// Sets the number of blocks after which a block is considered finalized (default: 64)
+    #[must_use]
+    pub fn confirmations(mut self, confirmations: u64) -> Self {
+        self.confirmations = confirmations;
+        self
+    }
+
+    /// Returns a reference to the wrapped transport
+    pub fn inner(&self) -> &T {
+        &self.inner
+    }
+
+    fn get(&self, key: &str) -> Option<Value> {
+        for (idx, cache) in self.caches.iter().enumerate() {
+            if let Some(value) = cache.get(key) {
+                for cache in &self.caches[..idx] {
+                    cache.insert(key, &value);
+                }
+                return Some(value)
+            }
+        }
+        None
+    }
+
+    fn insert(&self, key: &str, value: &Value) {
+        for cache in &self.caches {
+            cache.insert(key, value);
+        }
+    }
+
+    /// Returns true if the block is at least `confirmations` blocks deep, fetching the latest
+    /// block number if the last known head is not recent enough
+    async fn is_finalized(&self, block: u64) -> bool {
+        let finalized = |head: u64| block.saturating_add(self.confirmations) <= head;
+        if finalized(self.head.load(Ordering::SeqCst)) {
+            return true
+        }
+        match self.inner.request::<_, U64>("eth_blockNumber", ()).await {
+            Ok(head) => finalized(self.update_head(head.as_u64())),
+            Err(_) => false,
+        }
+    }
+
+    /// Stores the block number if it is the highest seen so far, returns the highest one
+    fn update_head(&self, head: u64) -> u64 {
+        self.head.fetch_max(head, Ordering::SeqCst).max(head)
+    }
+
+    /// Returns true if the response to a request with the given immutability can be cached
+    async fn is_cacheable(&self, immutability: Immutability, response: &Value) -> bool {
+        if response.is_null() {
+            return false
+        }
+        match immutability {
+            Immutability::Always => true,
+            Immutability::AtBlock(block) => self.is_finalized(block).await,
+            Immutability::AtResultBlock => {
+                match response.get("blockNumber").and_then(Value::as_str).and_then(parse_quantity) {
+                    Some(block) => self.is_finalized(block).await,
+                    None => false,
+                }
+            }
+            Immutability::Never => false,
+        }
+    }
+}
+
+/// Returns the cache key of a request
+fn cache_key(method: &str, params: Option<&Value>) -> String {
+    match params {
+        // requests without params have no params, `null` or `[]` depending on how they are sent
+        None | Some(Value::Null) => method.to_string(),
+        Some(Value::Array(params)) if params.is_empty() => method.to_string(),
+        Some(params) => format!("{}:{}", method, params),
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl<T: JsonRpcClient> JsonRpcClient for CachingClient<T> {
+    type Error = ProviderError;
+
+    async fn request<A, R>(&self, method: &str, params: A) -> Result<R, Self::Error>
+    where
+        A: Debug + Serialize + Send + Sync,
+        R: DeserializeOwned,
+    {
+        // Zero-sized params are not serialized at all by the transports, so we keep them as
+        // `None` instead of `null`
+        let params =
+            if std::mem::size_of::<A>() == 0 { None } else { Some(serde_json::to_value(params)?) };
+        let immutability = immutability(method, params.as_ref());
+        let key = cache_key(method, params.as_ref());
+
+        if immutability != Immutability::Never {
+            if let Some(value) = self.get(&key) {
+                trace!(method = method, "cache hit");
+                return Ok(serde_json::from_value(value)?)
+            }
+        }
+
+        let value: Value = match &params {
+            Some(params) => self.inner.request(method, params).await,
+            None => self.inner.request(method, ()).await,
+        }
+        .map_err(Into::<ProviderError>::into)?;
+
+        if method == "eth_blockNumber" {
+            if let Ok(head) = serde_json::from_value::<U64>(value.clone()) {
+                self.update_head(head.as_u64());
+            }
+        }
+        if self.is_cacheable(immutability, &value).await {
+            self.insert(&key, &value);
+        }
+        Ok(serde_json::from_value(value)?)
+    }
+
+    /// Serves the cached requests of the batch from the cache, the remaining requests are sent
+    /// as a single batch by the inner transport
+    async fn request_batch(
+        &self,
+        requests: Vec<(String, Value)>,
+    ) -> Result<Vec<Result<Value, JsonRpcError>>, Self::Error> {
+        let mut responses = Vec::with_capacity(requests.len());
+        let mut misses = Vec::new();
+        for (idx, (method, params)) in requests.iter().enumerate() {
+            let immutability = immutability(method, Some(params));
+            let cached = if immutability != Immutability::Never {
+                self.get(&cache_key(method, Some(params)))
+            } else {
+                None
+            };
+            if cached.is_none() {
+                misses.push(idx);
+            }
+            responses.push(cached.map(Ok));
+        }
+
+        if !misses.is_empty() {
+            let batch = misses.iter().map(|idx| requests[*idx].clone()).collect();
+            let fetched =
+                self.inner.request_batch(batch).await.map_err(Into::<ProviderError>::into)?;
+            for (idx, response) in misses.into_iter().zip(fetched) {
+                let (method, params) = &requests[idx];
+                if let Ok(value) = &response {
+                    if self.is_cacheable(immutability(method, Some(params)), value).await {
+                        self.insert(&cache_key(method, Some(params)), value);
+                    }
+                }
+                responses[idx] = Some(response);
+            }
+        }
+
+        responses
+            .into_iter()
+            .zip(&requests)
+            .map(|(response, (method, _))| {
+                response.ok_or_else(|| {
+                    ProviderError::CustomError(format!("missing batch response for {}", method))
+                })
+            })
+            .collect()
+    }
+
+    fn supports_pubsub(&self) -> bool {
+        self.inner.supports_pubsub()
+    }
+
+    fn subscribe_notifications(&self, id: U256) -> Option<Result<NotificationStream, Self::Error>> {
+        self.inner.subscribe_notifications(id).map(|res| res.map_err(Into::into))
+    }
+
+    fn unsubscribe_notifications(&self, id: U256) -> Result<(), Self::Error> {
+        self.inner.unsubscribe_notifications(id).map_err(Into::into)
+    }
+}
+
+#[cfg(test)]
+#[cfg(not(target_arch = "wasm32"))]
+mod tests {
+    use super::*;
+    use crate::{Middleware, MockProvider, Provider};
+    use ethers_core::types::{Address, BlockNumber, Bytes, U256};
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn caches_immutable_responses() {
+        let mock = MockProvider::new();
+        let provider = Provider::new(CachingClient::new(mock.clone(), MemoryCache::new(100)));
+
+        mock.push(U256::from(1)).unwrap();
+        assert_eq!(provider.get_chainid().await.unwrap(), U256::from(1));
+        assert_eq!(provider.get_chainid().await.unwrap(), U256::from(1));
+        mock.assert_request("eth_chainId", ()).unwrap();
+        assert!(mock.assert_request("eth_chainId", ()).is_err());
+
+        // the code at block 1 is cached once the node reports a head that finalizes it
+        let code = Bytes::from(vec![1, 2, 3]);
+        mock.push(U64::from(100)).unwrap();
+        mock.push(code.clone()).unwrap();
+        let block = Some(BlockNumber::Number(1.into()).into());
+        assert_eq!(provider.get_code(Address::zero(), block).await.unwrap(), code);
+        assert_eq!(provider.get_code(Address::zero(), block).await.unwrap(), code);
+        mock.assert_request("eth_getCode", [json!(Address::zero()), json!("0x1")]).unwrap();
+        mock.assert_request("eth_blockNumber", ()).unwrap();
+        assert!(mock.assert_request("eth_getCode", ()).is_err());
+
+        // requests at the latest block are never cached
+        mock.push(code.clone()).unwrap();
+        mock.push(code.clone()).unwrap();
+        provider.get_code(Address::zero(), None).await.unwrap();
+        provider.get_code(Address::zero(), None).await.unwrap();
+        mock.assert_request("eth_getCode", [json!(Address::zero()), json!("latest")]).unwrap();
+        mock.assert_request("eth_getCode", [json!(Address::zero()), json!("latest")]).unwrap();
+    }
+
+    #[tokio::test]
+    async fn does_not_cache_recent_blocks() {
+        let mock = MockProvider::new();
+        let client = CachingClient::new(mock.clone(), MemoryCache::new(100)).confirmations(10);
+
+        mock.push(U64::from(5)).unwrap();
+        let head: U64 = client.request("eth_blockNumber", ()).await.unwrap();
+        assert_eq!(head, U64::from(5));
+
+        // block 1 is not finalized at head 5, so the head is refreshed once
+        mock.push(U64::from(6)).unwrap();
+        mock.push(Bytes::default()).unwrap();
+        let _: Bytes =
+            client.request("eth_getCode", [json!(Address::zero()), json!("0x1")]).await.unwrap();
+        assert_eq!(client.head.load(Ordering::SeqCst), 6);
+        assert!(client.caches[0]
+            .get(r#"eth_getCode:["0x0000000000000000000000000000000000000000","0x1"]"#)
+            .is_none());
+    }
+
+    #[test]
+    fn evicts_least_recently_used() {
+        let cache = MemoryCache::new(2);
+        cache.insert("a", &json!(1));
+        cache.insert("b", &json!(2));
+        assert_eq!(cache.get("a"), Some(json!(1)));
+        cache.insert("c", &json!(3));
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.get("b"), None);
+        assert_eq!(cache.get("a"), Some(json!(1)));
+        assert_eq!(cache.get("c"), Some(json!(3)));
+    }
+
+    #[test]
+    fn disk_cache_persists() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = DiskCache::new(dir.path()).unwrap();
+        assert_eq!(cache.get("eth_chainId"), None);
+        cache.insert("eth_chainId", &json!("0x1"));
+
+        let cache = DiskCache::new(dir.path()).unwrap();
+        assert_eq!(cache.get("eth_chainId"), Some(json!("0x1")));
+    }
+
+    #[test]
+    fn disk_cache_concurrent_writes() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = DiskCache::new(dir.path()).unwrap();
+        let writers = (0..8)
+            .map(|i| {
+                let cache = cache.clone();
+                std::thread::spawn(move || {
+                    for _ in 0..20 {
+                        cache.insert("eth_chainId", &json!(i));
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        for writer in writers {
+            writer.join().unwrap();
+        }
+
+        assert!(cache.get("eth_chainId").is_some());
+        // no temporary file is left behind
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[tokio::test]
+    async fn requests_without_params_share_a_key() {
+        assert_eq!(cache_key("eth_chainId", None), "eth_chainId");
+        assert_eq!(cache_key("eth_chainId", Some(&Value::Null)), "eth_chainId");
+        assert_eq!(cache_key("eth_chainId", Some(&json!([]))), "eth_chainId");
+
+        // the response of a single request is served to the batch
+        let mock = MockProvider::new();
+        let client = CachingClient::new(mock.clone(), MemoryCache::new(100));
+        mock.push(U256::from(1)).unwrap();
+        let chain_id: U256 = client.request("eth_chainId", ()).await.unwrap();
+        assert_eq!(chain_id, U256::from(1));
+        let responses =
+            client.request_batch(vec![("eth_chainId".to_string(), json!([]))]).await.unwrap();
+        assert_eq!(responses[0].as_ref().unwrap(), &json!(chain_id));
+        mock.assert_request("eth_chainId", ()).unwrap();
+        assert!(mock.assert_request("eth_chainId", ()).is_err());
+    }
+
+    #[test]
+    fn immutability_of_requests() {
+        assert_eq!(immutability("eth_chainId", None), Immutability::Always);
+        assert_eq!(immutability("eth_blockNumber", None), Immutability::Never);
+        assert_eq!(
+            immutability("eth_getBlockByNumber", Some(&json!(["0x10", false]))),
+            Immutability::AtBlock(16)
+        );
+        assert_eq!(
+            immutability("eth_getBlockByNumber", Some(&json!(["latest", false]))),
+            Immutability::Never
+        );
+        assert_eq!(
+            immutability("eth_call", Some(&json!([{}, {"blockHash": "0x00"}]))),
+            Immutability::Always
+        );
+        assert_eq!(
+            immutability("eth_getStorageAt", Some(&json!(["0x00", "0x0", "0xa"]))),
+            Immutability::AtBlock(10)
+        );
+    }
+}
@@ -1,4 +1,5 @@
 // Code adapted from: https://github.com/althea-net/guac_rs/tree/master/web3/src/jsonrpc
+use super::jwt::{JwtAuth, JwtKey};
 use ethers_core::types::U256;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
@@ -66,6 +67,13 @@ pub struct Response<T> {
     pub data: ResponseData<T>,
 }
 
+/// The error object a node responds with instead of the array of responses when it rejects a
+/// whole batch, e.g. because it does not support batches. Its id is usually `null`.
+#[derive(Deserialize, Debug)]
+pub(crate) struct BatchError {
+    pub(crate) error: JsonRpcError,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(untagged)]
 pub enum ResponseData<R> {
@@ -94,13 +102,15 @@ impl ResponseData<serde_json::Value> {
     }
 }
 
-/// Basic or bearer authentication in http or websocket transport
+/// Basic, bearer or JWT authentication in http or websocket transport
 ///
 /// Use to inject username and password or an auth token into requests
 #[derive(Clone, Debug)]
 pub enum Authorization {
     Basic(String),
     Bearer(String),
+    /// A bearer token which is minted fresh for every request, as required by the Engine API
+    Jwt(JwtAuth),
 }
 
 impl Authorization {
@@ -112,6 +122,28 @@ impl Authorization {
     pub fn bearer(token: impl Into<String>) -> Self {
         Self::Bearer(token.into())
     }
+
+    /// Engine API authentication with the secret shared with the execution client
+    pub fn jwt(key: JwtKey) -> Self {
+        Self::Jwt(JwtAuth::new(key))
+    }
+
+    /// Returns true if the credentials change with every request
+    pub fn is_dynamic(&self) -> bool {
+        matches!(self, Authorization::Jwt(_))
+    }
+
+    /// Returns the value of the `Authorization` header, [`Authorization::Jwt`] mints a fresh
+    /// token on every call
+    pub fn header_value(&self) -> Result<http::HeaderValue, http::header::InvalidHeaderValue> {
+        let value = match self {
+            Authorization::Jwt(jwt) => format!("Bearer {}", jwt.generate_token()),
+            auth => auth.to_string(),
+        };
+        let mut value = http::HeaderValue::from_str(&value)?;
+        value.set_sensitive(true);
+        Ok(value)
+    }
 }
 
 impl fmt::Display for Authorization {
@@ -119,6 +151,8 @@ impl fmt::Display for Authorization {
         match self {
             Authorization::Basic(auth_secret) => write!(f, "Basic {}", auth_secret),
             Authorization::Bearer(token) => write!(f, "Bearer {}", token),
+            // tokens are only minted for requests, see `header_value`
+            Authorization::Jwt(_) => write!(f, "Bearer <jwt>"),
         }
     }
 }
@@ -149,4 +183,18 @@ mod tests {
             r#"{"id":300,"jsonrpc":"2.0","method":"method_name","params":1}"#
         );
     }
+
+    #[test]
+    fn formats_authorization_without_minting_tokens() {
+        let auth = Authorization::bearer("token");
+        assert_eq!(auth.to_string(), "Bearer token");
+        assert_eq!(auth.header_value().unwrap(), "Bearer token");
+        assert!(auth.header_value().unwrap().is_sensitive());
+
+        let auth = Authorization::jwt(JwtKey::from_slice(&[7; 32]).unwrap());
+        assert_eq!(auth.to_string(), "Bearer <jwt>");
+        let value = auth.header_value().unwrap();
+        let token = value.to_str().unwrap().strip_prefix("Bearer ").unwrap();
+        assert_eq!(token.split('.').count(), 3);
+    }
 }
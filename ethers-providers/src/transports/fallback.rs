@@ -0,0 +1,694 @@
+//! A [`JsonRpcClient`] implementation that sends every request to the highest priority healthy
+//! endpoint and fails over to the next one if it fails.
+use super::{is_retryable_rpc_error, JsonRpcClientWrapper, PubsubClientWrapper};
+use crate::{provider::ProviderError, JsonRpcClient, PubsubClient};
+
+use async_trait::async_trait;
+use ethers_core::types::U256;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use thiserror::Error;
+use tracing::trace;
+
+#[cfg(not(target_arch = "wasm32"))]
+use futures_timer::Delay;
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Instant;
+#[cfg(target_arch = "wasm32")]
+use wasm_timer::{Delay, Instant};
+
+/// The weight of the latest request in the moving averages of an endpoint's latency and
+/// error rate
+const EWMA_WEIGHT: f64 = 0.2;
+
+/// The number of requests an endpoint must have served before it is judged by its error rate
+const MIN_REQUESTS_FOR_ERROR_RATE: u64 = 10;
+
+/// A provider that sends requests to its endpoints in priority order.
+///
+/// Every request goes to the highest priority endpoint that is healthy. If that endpoint fails
+/// with a transport error, or with a JSON-RPC error that indicates rate limiting or a lagging
+/// node (see [`is_retryable_rpc_error`]), the request is sent to the next endpoint. All other
+/// JSON-RPC errors, e.g. reverts, are returned right away, since every node would return them.
+///
+/// An endpoint is marked unhealthy for the configured `cooldown` once it failed
+/// `failure_threshold` times in a row, its error rate exceeds `max_error_rate` or its latency
+/// exceeds `max_latency`. Unhealthy endpoints are skipped until their cooldown elapsed, after
+/// which the next request probes them. A successful probe brings the endpoint back in. If no
+/// endpoint is healthy, all endpoints are tried in priority order.
+///
+/// Unhealthy endpoints can also be probed periodically in the background, so they are brought
+/// back in as soon as they recover, see [`FallbackProvider::health_checks`].
+///
+/// # Example
+///
+/// ```no_run
+/// use ethers_core::types::U64;
+/// use ethers_providers::{FallbackProvider, Http, JsonRpcClient};
+/// use std::{str::FromStr, time::Duration};
+///
+/// # async fn foo() -> Result<(), Box<dyn std::error::Error>> {
+/// let provider = FallbackProvider::builder()
+///     .add_provider(Http::from_str("http://localhost:8545")?)
+///     .add_provider(Http::from_str("http://localhost:8546")?)
+///     .cooldown(Duration::from_secs(30))
+///     .build();
+/// let block_number: U64 = provider.request("eth_blockNumber", ()).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct FallbackProvider<T> {
+    /// The endpoints in priority order, shared with the background health checks
+    providers: Arc<Vec<Endpoint<T>>>,
+    cooldown: Duration,
+    failure_threshold: u32,
+    max_error_rate: f64,
+    max_latency: Option<Duration>,
+    /// The endpoint on which each subscription was installed, by subscription id
+    subscriptions: Mutex<HashMap<U256, usize>>,
+}
+
+impl FallbackProvider<Box<dyn JsonRpcClientWrapper>> {
+    /// Create a `FallbackProvider` for different `JsonRpcClient` types
+    pub fn dyn_rpc() -> FallbackProviderBuilder<Box<dyn JsonRpcClientWrapper>> {
+        Self::builder()
+    }
+}
+
+impl FallbackProvider<Box<dyn PubsubClientWrapper>> {
+    /// Create a `FallbackProvider` for different `PubsubClient` types
+    pub fn dyn_pub_sub() -> FallbackProviderBuilder<Box<dyn PubsubClientWrapper>> {
+        Self::builder()
+    }
+}
+
+impl<T> FallbackProvider<T> {
+    /// Convenience method for creating a `FallbackProviderBuilder` with same `JsonRpcClient`
+    /// types
+    pub fn builder() -> FallbackProviderBuilder<T> {
+        FallbackProviderBuilder::default()
+    }
+
+    /// Creates a `FallbackProvider` with the default configuration and the given endpoints in
+    /// priority order
+    pub fn new(providers: impl IntoIterator<Item = T>) -> Self {
+        Self::builder().add_providers(providers).build()
+    }
+
+    /// Returns the health and request statistics of every endpoint, in priority order
+    pub fn stats(&self) -> Vec<EndpointStats> {
+        let now = Instant::now();
+        self.providers.iter().map(|provider| provider.state.lock().unwrap().stats(now)).collect()
+    }
+
+    /// Returns the indices of the endpoints in the order in which they should be tried
+    fn candidates(&self) -> Vec<usize> {
+        let now = Instant::now();
+        let (available, unhealthy): (Vec<usize>, Vec<usize>) = (0..self.providers.len())
+            .partition(|idx| self.providers[*idx].state.lock().unwrap().is_available(now));
+        // if every endpoint is in its cooldown we try them anyway
+        if available.is_empty() {
+            unhealthy
+        } else {
+            available
+        }
+    }
+
+    fn record_success(&self, idx: usize, latency: Duration) {
+        let mut state = self.providers[idx].state.lock().unwrap();
+        state.record_success(latency);
+        if let Some(max_latency) = self.max_latency {
+            if state.latency.map(|latency| latency > max_latency).unwrap_or_default() {
+                trace!(endpoint = idx, latency = ?state.latency, "endpoint too slow");
+                state.unhealthy_until = Some(Instant::now() + self.cooldown);
+            }
+        }
+    }
+
+    fn record_failure(&self, idx: usize) {
+        let mut state = self.providers[idx].state.lock().unwrap();
+        state.record_failure();
+        let failing = state.consecutive_failures >= self.failure_threshold ||
+            (state.requests >= MIN_REQUESTS_FOR_ERROR_RATE &&
+                state.error_rate > self.max_error_rate);
+        if failing {
+            trace!(endpoint = idx, error_rate = state.error_rate, "endpoint unhealthy");
+            state.unhealthy_until = Some(Instant::now() + self.cooldown);
+        }
+    }
+}
+
+impl<T: JsonRpcClientWrapper> FallbackProvider<T> {
+    /// Sends the request to the endpoints in priority order, until one of them succeeds or
+    /// fails with an error that should not be retried on another endpoint.
+    async fn request_fallback(
+        &self,
+        method: &str,
+        params: Value,
+        candidates: Vec<usize>,
+    ) -> Result<(Value, usize), ProviderError> {
+        let mut errors = Vec::new();
+        for idx in candidates {
+            let start = Instant::now();
+            match self.providers[idx].inner.request(method, params.clone()).await {
+                Ok(value) => {
+                    self.record_success(idx, start.elapsed());
+                    return Ok((value, idx))
+                }
+                Err(err) if !should_fallback(&err) => {
+                    // the endpoint responded, so it is healthy
+                    self.record_success(idx, start.elapsed());
+                    return Err(err)
+                }
+                Err(err) => {
+                    trace!(endpoint = idx, method = method, "request failed: {}", err);
+                    self.record_failure(idx);
+                    errors.push(err);
+                }
+            }
+        }
+        Err(FallbackError::AllProvidersFailed { errors }.into())
+    }
+
+    /// Returns the endpoint the subscription was installed on
+    fn subscription_endpoint(&self, id: U256) -> Option<usize> {
+        self.subscriptions.lock().unwrap().get(&id).copied()
+    }
+
+    /// Probes every unhealthy endpoint with `eth_blockNumber`, regardless of its cooldown.
+    /// Endpoints that respond are healthy again, the cooldown of the others starts over.
+    pub async fn probe_unhealthy(&self) {
+        probe_unhealthy(&self.providers, self.cooldown).await
+    }
+
+    /// Returns a future which calls [`probe_unhealthy`](Self::probe_unhealthy) every
+    /// `interval`, until the provider is dropped.
+    ///
+    /// The future does nothing unless it is spawned, e.g. with `tokio::spawn`.
+    pub fn health_checks(&self, interval: Duration) -> impl Future<Output = ()> {
+        let providers = Arc::downgrade(&self.providers);
+        let cooldown = self.cooldown;
+        async move {
+            loop {
+                Delay::new(interval).await;
+                match providers.upgrade() {
+                    Some(providers) => probe_unhealthy(&providers, cooldown).await,
+                    None => return,
+                }
+            }
+        }
+    }
+}
+
+async fn probe_unhealthy<T: JsonRpcClientWrapper>(providers: &[Endpoint<T>], cooldown: Duration) {
+    for (idx, provider) in providers.iter().enumerate() {
+        if provider.state.lock().unwrap().unhealthy_until.is_none() {
+            continue
+        }
+        let start = Instant::now();
+        let res = provider.inner.request("eth_blockNumber", Value::Array(Vec::new())).await;
+        let mut state = provider.state.lock().unwrap();
+        match res {
+            Ok(_) => {
+                trace!(endpoint = idx, "endpoint recovered");
+                state.record_success(start.elapsed());
+            }
+            Err(err) => {
+                trace!(endpoint = idx, "health check failed: {}", err);
+                state.record_failure();
+                state.unhealthy_until = Some(Instant::now() + cooldown);
+            }
+        }
+    }
+}
+
+/// Builder for a [`FallbackProvider`]
+#[derive(Debug, Clone)]
+pub struct FallbackProviderBuilder<T> {
+    providers: Vec<T>,
+    cooldown: Duration,
+    failure_threshold: u32,
+    max_error_rate: f64,
+    max_latency: Option<Duration>,
+}
+
+impl<T> Default for FallbackProviderBuilder<T> {
+    fn default() -> Self {
+        Self {
+            providers: Vec::new(),
+            cooldown: Duration::from_secs(60),
+            failure_threshold: 3,
+            max_error_rate: 0.5,
+            max_latency: None,
+        }
+    }
+}
+
+impl<T> FallbackProviderBuilder<T> {
+    /// Adds an endpoint with a lower priority than all previously added endpoints
+    pub fn add_provider(mut self, provider: T) -> Self {
+        self.providers.push(provider);
+        self
+    }
+
+    /// Adds the endpoints in priority order, after all previously added endpoints
+    pub fn add_providers(mut self, providers: impl IntoIterator<Item = T>) -> Self {
+        for provider in providers {
+            self.providers.push(provider);
+        }
+        self
+    }
+
+    /// Sets how long an unhealthy endpoint is skipped before it is probed again (default: 60s)
+    pub fn cooldown(mut self, cooldown: Duration) -> Self {
+        self.cooldown = cooldown;
+        self
+    }
+
+    /// Sets after how many consecutive failures an endpoint is marked unhealthy (default: 3)
+    pub fn failure_threshold(mut self, failure_threshold: u32) -> Self {
+        self.failure_threshold = failure_threshold.max(1);
+        self
+    }
+
+    /// Sets the moving average of the error rate above which an endpoint is marked unhealthy
+    /// (default: 0.5)
+    ///
+    /// NOTE: this is clamped to `0.0..=1.0`
+    pub fn max_error_rate(mut self, max_error_rate: f64) -> Self {
+        self.max_error_rate = max_error_rate.clamp(0., 1.);
+        self
+    }
+
+    /// Sets the moving average of the latency above which an endpoint is marked unhealthy
+    /// (default: unlimited)
+    pub fn max_latency(mut self, max_latency: Duration) -> Self {
+        self.max_latency = Some(max_latency);
+        self
+    }
+
+    /// Creates the [`FallbackProvider`] with the endpoints in the order they were added
+    pub fn build(self) -> FallbackProvider<T> {
+        FallbackProvider {
+            providers: Arc::new(
+                self.providers
+                    .into_iter()
+                    .map(|inner| Endpoint { inner, state: Default::default() })
+                    .collect(),
+            ),
+            cooldown: self.cooldown,
+            failure_threshold: self.failure_threshold,
+            max_error_rate: self.max_error_rate,
+            max_latency: self.max_latency,
+            subscriptions: Default::default(),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Endpoint<T> {
+    inner: T,
+    state: Mutex<EndpointState>,
+}
+
+#[derive(Debug, Default)]
+struct EndpointState {
+    /// Moving average of the latency, `None` until the first successful request
+    latency: Option<Duration>,
+    /// Moving average of the error rate
+    error_rate: f64,
+    requests: u64,
+    errors: u64,
+    consecutive_failures: u32,
+    /// Set while the endpoint is unhealthy
+    unhealthy_until: Option<Instant>,
+}
+
+impl EndpointState {
+    /// Returns true if the endpoint is healthy or its cooldown elapsed, so it may be probed
+    fn is_available(&self, now: Instant) -> bool {
+        self.unhealthy_until.map(|until| until <= now).unwrap_or(true)
+    }
+
+    fn record_success(&mut self, latency: Duration) {
+        self.requests += 1;
+        self.consecutive_failures = 0;
+        self.error_rate *= 1. - EWMA_WEIGHT;
+        self.latency = Some(match self.latency {
+            Some(avg) => avg.mul_f64(1. - EWMA_WEIGHT) + latency.mul_f64(EWMA_WEIGHT),
+            None => latency,
+        });
+        self.unhealthy_until = None;
+    }
+
+    fn record_failure(&mut self) {
+        self.requests += 1;
+        self.errors += 1;
+        self.consecutive_failures += 1;
+        self.error_rate = self.error_rate * (1. - EWMA_WEIGHT) + EWMA_WEIGHT;
+    }
+
+    fn stats(&self, now: Instant) -> EndpointStats {
+        EndpointStats {
+            healthy: self.unhealthy_until.is_none(),
+            available: self.is_available(now),
+            latency: self.latency,
+            error_rate: self.error_rate,
+            requests: self.requests,
+            errors: self.errors,
+        }
+    }
+}
+
+/// A snapshot of the health and request statistics of an endpoint of a [`FallbackProvider`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EndpointStats {
+    /// Whether the endpoint is healthy
+    pub healthy: bool,
+    /// Whether requests are sent to the endpoint, which is the case if it is healthy or its
+    /// cooldown elapsed
+    pub available: bool,
+    /// The moving average of the latency, `None` if no request succeeded yet
+    pub latency: Option<Duration>,
+    /// The moving average of the error rate
+    pub error_rate: f64,
+    /// The total number of requests sent to the endpoint
+    pub requests: u64,
+    /// The total number of failed requests
+    pub errors: u64,
+}
+
+/// Returns true if the request should be sent to the next endpoint after failing with `err`
+fn should_fallback(err: &ProviderError) -> bool {
+    match err {
+        ProviderError::JsonRpcClientError(_) => match err.as_error_response() {
+            Some(err) => is_retryable_rpc_error(err),
+            None => true,
+        },
+        ProviderError::SerdeJson(_) | ProviderError::HexError(_) => false,
+        _ => true,
+    }
+}
+
+#[derive(Error, Debug)]
+/// Error thrown by the [`FallbackProvider`]
+pub enum FallbackError {
+    /// Thrown if every endpoint failed, contains the error of each endpoint that was tried
+    #[error("All providers failed")]
+    AllProvidersFailed { errors: Vec<ProviderError> },
+}
+
+impl From<FallbackError> for ProviderError {
+    fn from(src: FallbackError) -> Self {
+        ProviderError::JsonRpcClientError(Box::new(src))
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl<C> JsonRpcClient for FallbackProvider<C>
+where
+    C: JsonRpcClientWrapper,
+{
+    type Error = ProviderError;
+
+    async fn request<T: Serialize + Send + Sync, R: DeserializeOwned>(
+        &self,
+        method: &str,
+        params: T,
+    ) -> Result<R, Self::Error> {
+        let params = serde_json::to_value(params)?;
+
+        // subscriptions only exist on the endpoint they were installed on
+        let unsubscribe_from = if method == "eth_unsubscribe" {
+            serde_json::from_value::<[U256; 1]>(params.clone())
+                .ok()
+                .and_then(|[id]| Some((id, self.subscription_endpoint(id)?)))
+        } else {
+            None
+        };
+        let candidates = match unsubscribe_from {
+            Some((_, idx)) => vec![idx],
+            None => self.candidates(),
+        };
+
+        let (value, idx) = self.request_fallback(method, params, candidates).await?;
+        match (method, unsubscribe_from) {
+            ("eth_subscribe", _) => {
+                if let Ok(id) = serde_json::from_value::<U256>(value.clone()) {
+                    self.subscriptions.lock().unwrap().insert(id, idx);
+                }
+            }
+            (_, Some((id, _))) => {
+                self.subscriptions.lock().unwrap().remove(&id);
+            }
+            _ => {}
+        }
+        Ok(serde_json::from_value(value)?)
+    }
+
+    /// Subscriptions are supported if every endpoint supports them, since `eth_subscribe` may
+    /// be sent to any of them
+    fn supports_pubsub(&self) -> bool {
+        self.providers.iter().all(|provider| provider.inner.supports_pubsub())
+    }
+
+    /// Yields the notifications from the endpoint the subscription was installed on
+    fn subscribe_notifications(
+        &self,
+        id: U256,
+    ) -> Option<Result<crate::NotificationStream, Self::Error>> {
+        let idx = match self
+            .subscription_endpoint(id)
+            .or_else(|| self.candidates().first().copied())
+        {
+            Some(idx) => idx,
+            None => {
+                return Some(Err(FallbackError::AllProvidersFailed { errors: Vec::new() }.into()))
+            }
+        };
+        self.providers[idx].inner.subscribe_notifications(id)
+    }
+
+    fn unsubscribe_notifications(&self, id: U256) -> Result<(), Self::Error> {
+        match self.subscription_endpoint(id) {
+            Some(idx) => self.providers[idx].inner.unsubscribe_notifications(id),
+            None => {
+                for provider in &self.providers {
+                    provider.inner.unsubscribe_notifications(id)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl<C> PubsubClient for FallbackProvider<C>
+where
+    C: PubsubClientWrapper,
+{
+    type NotificationStream = Box<dyn futures_core::Stream<Item = Value> + Send + Unpin>;
+
+    fn subscribe<T: Into<U256>>(&self, id: T) -> Result<Self::NotificationStream, Self::Error> {
+        let id = id.into();
+        let idx = self
+            .subscription_endpoint(id)
+            .or_else(|| self.candidates().first().copied())
+            .ok_or_else(|| FallbackError::AllProvidersFailed { errors: Vec::new() })?;
+        self.providers[idx].inner.subscribe(id)
+    }
+
+    fn unsubscribe<T: Into<U256>>(&self, id: T) -> Result<(), Self::Error> {
+        let id = id.into();
+        match self.subscription_endpoint(id) {
+            Some(idx) => self.providers[idx].inner.unsubscribe(id),
+            None => {
+                for provider in &self.providers {
+                    provider.inner.unsubscribe(id)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+#[cfg(not(target_arch = "wasm32"))]
+mod tests {
+    use super::FallbackProvider;
+    use crate::{JsonRpcClient, Middleware, MockError, MockProvider, NotificationStream, Provider};
+    use async_trait::async_trait;
+    use ethers_core::types::{U256, U64};
+    use futures_util::StreamExt;
+    use serde::{de::DeserializeOwned, Serialize};
+    use serde_json::json;
+    use std::{
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+
+    /// A pubsub transport whose subscriptions yield its `tag`
+    #[derive(Debug, Default)]
+    struct Pubsub {
+        mock: MockProvider,
+        tag: u64,
+        unsubscribed: Mutex<Vec<U256>>,
+    }
+
+    #[async_trait]
+    impl JsonRpcClient for Pubsub {
+        type Error = MockError;
+
+        async fn request<T: Serialize + Send + Sync, R: DeserializeOwned>(
+            &self,
+            method: &str,
+            params: T,
+        ) -> Result<R, Self::Error> {
+            self.mock.request(method, params).await
+        }
+
+        fn supports_pubsub(&self) -> bool {
+            true
+        }
+
+        fn subscribe_notifications(
+            &self,
+            _id: U256,
+        ) -> Option<Result<NotificationStream, Self::Error>> {
+            Some(Ok(Box::pin(futures_util::stream::iter(vec![json!(self.tag)]))))
+        }
+
+        fn unsubscribe_notifications(&self, id: U256) -> Result<(), Self::Error> {
+            self.unsubscribed.lock().unwrap().push(id);
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn forwards_subscriptions_to_their_endpoint() {
+        // the first endpoint has no responses and thus fails the `eth_subscribe` request
+        let failing = Arc::new(Pubsub { tag: 1, ..Default::default() });
+        let healthy = Arc::new(Pubsub { tag: 2, ..Default::default() });
+        healthy.mock.push(U256::from(5)).unwrap();
+        let fallback = FallbackProvider::builder()
+            .add_providers([failing.clone(), healthy.clone()])
+            .failure_threshold(5)
+            .build();
+        assert!(fallback.supports_pubsub());
+        assert!(!FallbackProvider::new([MockProvider::new()]).supports_pubsub());
+
+        let id: U256 = fallback.request("eth_subscribe", ["newHeads"]).await.unwrap();
+        assert_eq!(id, 5.into());
+        let mut notifications = fallback.subscribe_notifications(id).unwrap().unwrap();
+        assert_eq!(notifications.next().await.unwrap(), json!(2));
+
+        fallback.unsubscribe_notifications(id).unwrap();
+        assert!(failing.unsubscribed.lock().unwrap().is_empty());
+        assert_eq!(*healthy.unsubscribed.lock().unwrap(), vec![id]);
+    }
+
+    #[tokio::test]
+    async fn falls_back_to_next_provider() {
+        // the first provider has no responses and thus fails every request
+        let failing = MockProvider::new();
+        let healthy = MockProvider::new();
+        let fallback = FallbackProvider::builder()
+            .add_providers([failing.clone(), healthy.clone()])
+            .failure_threshold(2)
+            .build();
+        let provider = Provider::fallback(fallback);
+
+        for block in 1..=3u64 {
+            healthy.push(U64::from(block)).unwrap();
+            assert_eq!(provider.get_block_number().await.unwrap(), U64::from(block));
+            healthy.assert_request("eth_blockNumber", ()).unwrap();
+        }
+
+        // the failing provider is skipped once it reached the failure threshold
+        failing.assert_request("eth_blockNumber", ()).unwrap();
+        failing.assert_request("eth_blockNumber", ()).unwrap();
+        assert!(failing.assert_request("eth_blockNumber", ()).is_err());
+
+        let stats = provider.as_ref().stats();
+        assert!(!stats[0].healthy);
+        assert_eq!(stats[0].errors, 2);
+        assert!(stats[1].healthy);
+        assert_eq!(stats[1].requests, 3);
+    }
+
+    #[tokio::test]
+    async fn probes_unhealthy_provider_after_cooldown() {
+        let flaky = MockProvider::new();
+        let backup = MockProvider::new();
+        let fallback = FallbackProvider::builder()
+            .add_providers([flaky.clone(), backup.clone()])
+            .failure_threshold(1)
+            .cooldown(Duration::from_millis(10))
+            .build();
+
+        backup.push(U64::from(1)).unwrap();
+        let block: U64 = fallback.request("eth_blockNumber", ()).await.unwrap();
+        assert_eq!(block, U64::from(1));
+        assert!(!fallback.stats()[0].available);
+
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(fallback.stats()[0].available);
+
+        // the probe succeeds and the provider is healthy again
+        flaky.push(U64::from(2)).unwrap();
+        let block: U64 = fallback.request("eth_blockNumber", ()).await.unwrap();
+        assert_eq!(block, U64::from(2));
+        assert!(fallback.stats()[0].healthy);
+    }
+
+    #[tokio::test]
+    async fn health_checks_probe_unhealthy_providers() {
+        let flaky = MockProvider::new();
+        let backup = MockProvider::new();
+        let fallback = FallbackProvider::builder()
+            .add_providers([flaky.clone(), backup.clone()])
+            .failure_threshold(1)
+            .cooldown(Duration::from_secs(60))
+            .build();
+
+        backup.push(U64::from(1)).unwrap();
+        let _: U64 = fallback.request("eth_blockNumber", ()).await.unwrap();
+        assert!(!fallback.stats()[0].healthy);
+        flaky.assert_request("eth_blockNumber", ()).unwrap();
+
+        // a failed probe keeps the provider unhealthy
+        fallback.probe_unhealthy().await;
+        flaky.assert_request("eth_blockNumber", ()).unwrap();
+        assert!(!fallback.stats()[0].healthy);
+
+        // the health checks bring it back in long before its cooldown elapsed
+        flaky.push(U64::from(2)).unwrap();
+        let checks = tokio::spawn(fallback.health_checks(Duration::from_millis(10)));
+        for _ in 0..100 {
+            if fallback.stats()[0].healthy {
+                break
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert!(fallback.stats()[0].healthy);
+        flaky.assert_request("eth_blockNumber", ()).unwrap();
+
+        // the health checks end with the provider
+        drop(fallback);
+        checks.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn all_providers_failed() {
+        let fallback = FallbackProvider::new([MockProvider::new(), MockProvider::new()]);
+        let err = fallback.request::<_, U64>("eth_blockNumber", ()).await.unwrap_err();
+        assert!(err.to_string().contains("All providers failed"));
+    }
+}
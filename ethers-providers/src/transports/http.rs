@@ -2,16 +2,22 @@
 use crate::{provider::ProviderError, JsonRpcClient};
 
 use async_trait::async_trait;
-use reqwest::{header::HeaderValue, Client, Error as ReqwestError};
+use reqwest::{
+    header::{HeaderMap, HeaderName, HeaderValue, AUTHORIZATION},
+    Client, Error as ReqwestError, RequestBuilder, StatusCode,
+};
 use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
 use std::{
+    collections::HashMap,
     str::FromStr,
     sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
 };
 use thiserror::Error;
 use url::Url;
 
-use super::common::{Authorization, JsonRpcError, Request, Response};
+use super::common::{Authorization, JsonRpcError, Request, Response, ResponseData};
 
 /// A low-level JSON-RPC Client over HTTP.
 ///
@@ -33,6 +39,24 @@ pub struct Provider {
     id: AtomicU64,
     client: Client,
     url: Url,
+    /// Headers sent with every request
+    headers: HeaderMap,
+    /// Credentials which change with every request
+    auth: Option<Authorization>,
+    timeouts: Timeouts,
+}
+
+/// The request timeouts of the HTTP client
+#[derive(Debug, Clone, Default)]
+struct Timeouts {
+    default: Option<Duration>,
+    methods: HashMap<String, Duration>,
+}
+
+impl Timeouts {
+    fn get(&self, method: &str) -> Option<Duration> {
+        self.methods.get(method).copied().or(self.default)
+    }
 }
 
 #[derive(Error, Debug)]
@@ -48,6 +72,19 @@ pub enum ClientError {
     #[error("Deserialization Error: {err}. Response: {text}")]
     /// Serde JSON Error
     SerdeJson { err: serde_json::Error, text: String },
+
+    #[error("HTTP Error: {status}. Response: {text}")]
+    /// Thrown if the server responded with a non-success status and a body which is not a
+    /// JSON-RPC response, e.g. `429 Too Many Requests`
+    HttpStatus { status: StatusCode, text: String },
+
+    #[error("Missing response for batch request with id {0}")]
+    /// Thrown if the server did not respond to every request of a batch
+    MissingBatchResponse(u64),
+
+    #[error("Invalid authorization header: {0}")]
+    /// Thrown if the credentials minted for a request are not a valid header value
+    InvalidAuthorization(#[from] http::header::InvalidHeaderValue),
 }
 
 impl From<ClientError> for ProviderError {
@@ -71,13 +108,80 @@ impl JsonRpcClient for Provider {
         let next_id = self.id.fetch_add(1, Ordering::SeqCst);
         let payload = Request::new(next_id, method, params);
 
-        let res = self.client.post(self.url.as_ref()).json(&payload).send().await?;
+        let res = self.post(self.timeouts.get(method))?.json(&payload).send().await?;
+        let status = res.status();
         let text = res.text().await?;
-        let res: Response<R> =
-            serde_json::from_str(&text).map_err(|err| ClientError::SerdeJson { err, text })?;
+        let res: Response<R> = serde_json::from_str(&text).map_err(|err| {
+            if status.is_success() {
+                ClientError::SerdeJson { err, text }
+            } else {
+                ClientError::HttpStatus { status, text }
+            }
+        })?;
 
         Ok(res.data.into_result()?)
     }
+
+    /// Sends all requests as a single JSON-RPC batch in one POST request
+    async fn request_batch(
+        &self,
+        requests: Vec<(String, Value)>,
+    ) -> Result<Vec<Result<Value, JsonRpcError>>, ClientError> {
+        if requests.is_empty() {
+            return Ok(Vec::new())
+        }
+        let first_id = self.id.fetch_add(requests.len() as u64, Ordering::SeqCst);
+        let payload = requests
+            .iter()
+            .enumerate()
+            .map(|(idx, (method, params))| Request::new(first_id + idx as u64, method, params))
+            .collect::<Vec<_>>();
+        // the batch may take as long as its slowest method
+        let timeout = requests.iter().filter_map(|(method, _)| self.timeouts.get(method)).max();
+
+        let res = self.post(timeout)?.json(&payload).send().await?;
+        let status = res.status();
+        let text = res.text().await?;
+        let responses: Vec<Response<Value>> = match serde_json::from_str(&text) {
+            Ok(responses) => responses,
+            Err(err) => {
+                // nodes which do not support batches respond with a single error
+                if let Ok(Response { data: ResponseData::Error { error }, .. }) =
+                    serde_json::from_str::<Response<Value>>(&text)
+                {
+                    return Err(error.into())
+                }
+                return Err(if status.is_success() {
+                    ClientError::SerdeJson { err, text }
+                } else {
+                    ClientError::HttpStatus { status, text }
+                })
+            }
+        };
+
+        order_batch_responses(first_id, requests.len(), responses)
+    }
+}
+
+/// Orders the responses of a batch by their ids, since the server may respond in any order
+fn order_batch_responses(
+    first_id: u64,
+    len: usize,
+    responses: Vec<Response<Value>>,
+) -> Result<Vec<Result<Value, JsonRpcError>>, ClientError> {
+    let mut ordered = (0..len).map(|_| None).collect::<Vec<_>>();
+    for response in responses {
+        if let Some(slot) =
+            response.id.checked_sub(first_id).and_then(|idx| ordered.get_mut(idx as usize))
+        {
+            *slot = Some(response.data.into_result());
+        }
+    }
+    ordered
+        .into_iter()
+        .enumerate()
+        .map(|(idx, res)| res.ok_or(ClientError::MissingBatchResponse(first_id + idx as u64)))
+        .collect()
 }
 
 impl Provider {
@@ -111,15 +215,35 @@ impl Provider {
         url: impl Into<Url>,
         auth: Authorization,
     ) -> Result<Self, HttpClientError> {
-        let mut auth_value = HeaderValue::from_str(&auth.to_string())?;
-        auth_value.set_sensitive(true);
-
-        let mut headers = reqwest::header::HeaderMap::new();
-        headers.insert(reqwest::header::AUTHORIZATION, auth_value);
-
-        let client = Client::builder().default_headers(headers).build()?;
+        Self::builder(url).auth(auth).build()
+    }
 
-        Ok(Self::new_with_client(url, client))
+    /// Returns a builder for a client with custom headers, timeouts or authentication
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ethers_providers::{Authorization, Http};
+    /// use std::time::Duration;
+    /// use url::Url;
+    ///
+    /// let url = Url::parse("http://localhost:8545").unwrap();
+    /// let provider = Http::builder(url)
+    ///     .header("x-api-key", "my-api-key")
+    ///     .auth(Authorization::bearer("token"))
+    ///     .timeout(Duration::from_secs(10))
+    ///     .method_timeout("debug_traceTransaction", Duration::from_secs(120))
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn builder(url: impl Into<Url>) -> HttpBuilder {
+        HttpBuilder {
+            url: url.into(),
+            client: None,
+            headers: Vec::new(),
+            auth: None,
+            timeouts: Timeouts::default(),
+        }
     }
 
     /// Allows to customize the provider by providing your own http client
@@ -135,7 +259,110 @@ impl Provider {
     /// let provider = Http::new_with_client(url, client);
     /// ```
     pub fn new_with_client(url: impl Into<Url>, client: reqwest::Client) -> Self {
-        Self { id: AtomicU64::new(0), client, url: url.into() }
+        Self {
+            id: AtomicU64::new(0),
+            client,
+            url: url.into(),
+            headers: HeaderMap::new(),
+            auth: None,
+            timeouts: Timeouts::default(),
+        }
+    }
+
+    /// Returns a POST request to the node with the headers and credentials of the client
+    fn post(&self, timeout: Option<Duration>) -> Result<RequestBuilder, ClientError> {
+        let mut request = self.client.post(self.url.as_ref()).headers(self.headers.clone());
+        if let Some(auth) = &self.auth {
+            request = request.header(AUTHORIZATION, auth.header_value()?);
+        }
+        Ok(with_timeout(request, timeout))
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn with_timeout(request: RequestBuilder, timeout: Option<Duration>) -> RequestBuilder {
+    match timeout {
+        Some(timeout) => request.timeout(timeout),
+        None => request,
+    }
+}
+
+// timeouts are not supported by the fetch API
+#[cfg(target_arch = "wasm32")]
+fn with_timeout(request: RequestBuilder, _timeout: Option<Duration>) -> RequestBuilder {
+    request
+}
+
+/// Builder for an HTTP client with custom headers, timeouts and authentication, see
+/// [`Http::builder`](Provider::builder)
+#[derive(Debug)]
+#[must_use = "builders do nothing unless you `build` them"]
+pub struct HttpBuilder {
+    url: Url,
+    client: Option<Client>,
+    headers: Vec<(String, String)>,
+    auth: Option<Authorization>,
+    timeouts: Timeouts,
+}
+
+impl HttpBuilder {
+    /// Adds a header which is sent with every request
+    pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// Sets the credentials sent with every request. [`Authorization::Jwt`] mints a fresh
+    /// token for every request.
+    pub fn auth(mut self, auth: Authorization) -> Self {
+        self.auth = Some(auth);
+        self
+    }
+
+    /// Sets the timeout of every request
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeouts.default = Some(timeout);
+        self
+    }
+
+    /// Sets the timeout of the requests of a method, which takes precedence over
+    /// [`HttpBuilder::timeout`]
+    pub fn method_timeout(mut self, method: impl Into<String>, timeout: Duration) -> Self {
+        self.timeouts.methods.insert(method.into(), timeout);
+        self
+    }
+
+    /// Sends the requests with a custom `reqwest` client
+    pub fn client(mut self, client: Client) -> Self {
+        self.client = Some(client);
+        self
+    }
+
+    /// Builds the client, fails if a header is invalid
+    pub fn build(self) -> Result<Provider, HttpClientError> {
+        let mut headers = HeaderMap::new();
+        for (name, value) in self.headers {
+            headers
+                .insert(HeaderName::from_bytes(name.as_bytes())?, HeaderValue::from_str(&value)?);
+        }
+
+        // static credentials are validated once and sent like any other header
+        let auth = match self.auth {
+            Some(auth) if !auth.is_dynamic() => {
+                headers.insert(AUTHORIZATION, auth.header_value()?);
+                None
+            }
+            auth => auth,
+        };
+
+        Ok(Provider {
+            id: AtomicU64::new(0),
+            client: self.client.unwrap_or_default(),
+            url: self.url,
+            headers,
+            auth,
+            timeouts: self.timeouts,
+        })
     }
 }
 
@@ -150,7 +377,14 @@ impl FromStr for Provider {
 
 impl Clone for Provider {
     fn clone(&self) -> Self {
-        Self { id: AtomicU64::new(0), client: self.client.clone(), url: self.url.clone() }
+        Self {
+            id: AtomicU64::new(0),
+            client: self.client.clone(),
+            url: self.url.clone(),
+            headers: self.headers.clone(),
+            auth: self.auth.clone(),
+            timeouts: self.timeouts.clone(),
+        }
     }
 }
 
@@ -161,7 +395,137 @@ pub enum HttpClientError {
     #[error(transparent)]
     InvalidHeader(#[from] http::header::InvalidHeaderValue),
 
+    /// Thrown if the name of a header is invalid
+    #[error(transparent)]
+    InvalidHeaderName(#[from] http::header::InvalidHeaderName),
+
     /// Thrown if unable to build client
     #[error(transparent)]
     ClientBuild(#[from] reqwest::Error),
 }
+
+#[cfg(test)]
+#[cfg(not(target_arch = "wasm32"))]
+mod tests {
+    use super::*;
+    use crate::{JwtAuth, JwtKey};
+    use ethers_core::types::U64;
+    use std::{
+        io::{Read, Write},
+        net::TcpListener,
+        sync::mpsc,
+    };
+
+    /// Responds to `requests` JSON-RPC requests with `0x1` and sends their headers
+    fn serve(listener: TcpListener, requests: usize, heads: mpsc::Sender<String>) {
+        for stream in listener.incoming().take(requests) {
+            let mut stream = stream.unwrap();
+            let mut buf = Vec::new();
+            let mut chunk = [0u8; 4096];
+            let end = loop {
+                let n = stream.read(&mut chunk).unwrap();
+                buf.extend_from_slice(&chunk[..n]);
+                if let Some(end) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+                    break end
+                }
+            };
+            let head = String::from_utf8_lossy(&buf[..end]).to_lowercase();
+            let len = head
+                .lines()
+                .find_map(|line| line.strip_prefix("content-length:"))
+                .map(|len| len.trim().parse::<usize>().unwrap())
+                .unwrap_or(0);
+            while buf.len() < end + 4 + len {
+                let n = stream.read(&mut chunk).unwrap();
+                buf.extend_from_slice(&chunk[..n]);
+            }
+            heads.send(head).unwrap();
+
+            let response = r#"{"jsonrpc":"2.0","id":0,"result":"0x1"}"#;
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                response.len(),
+                response
+            )
+            .unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn sends_headers_and_fresh_jwt() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = Url::parse(&format!("http://{}", listener.local_addr().unwrap())).unwrap();
+        let (tx, heads) = mpsc::channel();
+        let server = std::thread::spawn(move || serve(listener, 2, tx));
+
+        let jwt = JwtAuth::new(JwtKey::from_slice(&[7; 32]).unwrap());
+        let provider = Provider::builder(url)
+            .header("X-Api-Key", "my-api-key")
+            .auth(Authorization::Jwt(jwt))
+            .build()
+            .unwrap();
+
+        let mut tokens = Vec::new();
+        for i in 0..2 {
+            if i > 0 {
+                // tokens are issued with a precision of seconds
+                tokio::time::sleep(Duration::from_millis(1100)).await;
+            }
+            let block: U64 = provider.request("eth_blockNumber", ()).await.unwrap();
+            assert_eq!(block, U64::from(1));
+            let head = heads.recv().unwrap();
+            assert!(head.contains("x-api-key: my-api-key"));
+            let token =
+                head.lines().find_map(|line| line.strip_prefix("authorization: bearer ")).unwrap();
+            assert!(token.starts_with("eyjhbgcioijiuzi1niisinr5cci6ikpxvcj9."));
+            tokens.push(token.to_string());
+        }
+        server.join().unwrap();
+        // every request carries a freshly minted token
+        assert_ne!(tokens[0], tokens[1]);
+    }
+
+    #[tokio::test]
+    async fn times_out_per_method() {
+        // the listener accepts connections but never responds
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = Url::parse(&format!("http://{}", listener.local_addr().unwrap())).unwrap();
+        let provider = Provider::builder(url)
+            .timeout(Duration::from_secs(60))
+            .method_timeout("eth_blockNumber", Duration::from_millis(100))
+            .build()
+            .unwrap();
+
+        let err = provider.request::<_, U64>("eth_blockNumber", ()).await.unwrap_err();
+        match err {
+            ClientError::ReqwestError(err) => assert!(err.is_timeout()),
+            err => panic!("expected a timeout, got {:?}", err),
+        }
+    }
+
+    #[test]
+    fn orders_batch_responses() {
+        let responses: Vec<Response<Value>> = serde_json::from_str(
+            r#"[{"jsonrpc":"2.0","id":7,"error":{"code":-32000,"message":"failed"}},{"jsonrpc":"2.0","id":6,"result":"0x1"}]"#,
+        )
+        .unwrap();
+        let ordered = order_batch_responses(6, 2, responses).unwrap();
+        assert_eq!(ordered[0].as_ref().unwrap(), &Value::from("0x1"));
+        assert_eq!(ordered[1].as_ref().unwrap_err().message, "failed");
+
+        let responses: Vec<Response<Value>> =
+            serde_json::from_str(r#"[{"jsonrpc":"2.0","id":6,"result":"0x1"}]"#).unwrap();
+        let err = order_batch_responses(6, 2, responses).unwrap_err();
+        assert!(matches!(err, ClientError::MissingBatchResponse(7)));
+    }
+
+    #[test]
+    fn rejects_invalid_headers() {
+        let url = Url::parse("http://localhost:8545").unwrap();
+        let err = Provider::builder(url.clone()).header("invalid header", "value").build();
+        assert!(matches!(err, Err(HttpClientError::InvalidHeaderName(_))));
+        let err = Provider::builder(url).header("x-api-key", "invalid\nvalue").build();
+        assert!(matches!(err, Err(HttpClientError::InvalidHeader(_))));
+    }
+}
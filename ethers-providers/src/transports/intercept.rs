@@ -0,0 +1,362 @@
+//! A [`JsonRpcClient`] implementation that passes every request and response through a chain
+//! of user supplied [`Interceptor`]s.
+use super::common::JsonRpcError;
+use crate::{provider::ProviderError, JsonRpcClient, NotificationStream};
+
+use async_trait::async_trait;
+use ethers_core::types::U256;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+use std::{fmt::Debug, time::Duration};
+
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Instant;
+#[cfg(target_arch = "wasm32")]
+use wasm_timer::Instant;
+
+/// The JSON-RPC error code for internal errors, used for errors of batch entries which were
+/// replaced by an interceptor
+const INTERNAL_ERROR: i64 = -32603;
+
+/// An outgoing JSON-RPC request as seen by an [`Interceptor`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct InterceptedRequest {
+    /// The JSON-RPC method
+    pub method: String,
+    /// The params of the request, `None` if the request is sent without params
+    pub params: Option<Value>,
+}
+
+/// A hook into the requests and responses of an [`InterceptorClient`].
+///
+/// Requests pass through the interceptors in the order they were added, responses in reverse
+/// order. The first interceptor thus sees the request as it was made and the response as it
+/// is returned.
+///
+/// # Example
+///
+/// ```
+/// use ethers_providers::{InterceptedRequest, Interceptor, ProviderError};
+/// use serde_json::Value;
+/// use std::{
+///     collections::HashMap,
+///     sync::Mutex,
+///     time::Duration,
+/// };
+///
+/// /// Counts the requests per method
+/// #[derive(Debug, Default)]
+/// struct RequestCounter(Mutex<HashMap<String, u64>>);
+///
+/// impl Interceptor for RequestCounter {
+///     fn on_response(
+///         &self,
+///         request: &InterceptedRequest,
+///         _response: &mut Result<Value, ProviderError>,
+///         _elapsed: Duration,
+///     ) {
+///         *self.0.lock().unwrap().entry(request.method.clone()).or_default() += 1;
+///     }
+/// }
+/// ```
+pub trait Interceptor: Send + Sync + Debug {
+    /// Called before the request is sent, the method and params may be rewritten.
+    ///
+    /// Returning a response skips the remaining interceptors as well as the transport, e.g. to
+    /// serve a cached response. The response is still passed to the `on_response` hook of all
+    /// preceding interceptors.
+    fn on_request(&self, _request: &mut InterceptedRequest) -> Option<Value> {
+        None
+    }
+
+    /// Called with the response to `request`, which may be rewritten. `elapsed` is the time
+    /// since the request was made.
+    fn on_response(
+        &self,
+        _request: &InterceptedRequest,
+        _response: &mut Result<Value, ProviderError>,
+        _elapsed: Duration,
+    ) {
+    }
+}
+
+/// A [`JsonRpcClient`] which wraps another transport and passes every request, including the
+/// individual requests of a batch, through its [`Interceptor`]s.
+///
+/// # Example
+///
+/// ```no_run
+/// use ethers_providers::{Http, InterceptedRequest, Interceptor, InterceptorClient, Provider};
+/// use serde_json::Value;
+/// use std::str::FromStr;
+///
+/// /// Routes `eth_call`s to the pending block
+/// #[derive(Debug)]
+/// struct CallPending;
+///
+/// impl Interceptor for CallPending {
+///     fn on_request(&self, request: &mut InterceptedRequest) -> Option<Value> {
+///         if request.method == "eth_call" {
+///             if let Some(Value::Array(params)) = request.params.as_mut() {
+///                 params.truncate(1);
+///                 params.push("pending".into());
+///             }
+///         }
+///         None
+///     }
+/// }
+///
+/// # fn foo() -> Result<(), Box<dyn std::error::Error>> {
+/// let http = Http::from_str("http://localhost:8545")?;
+/// let provider = Provider::new(InterceptorClient::new(http).with_interceptor(CallPending));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct InterceptorClient<T> {
+    inner: T,
+    interceptors: Vec<Box<dyn Interceptor>>,
+}
+
+impl<T: JsonRpcClient> InterceptorClient<T> {
+    /// Creates a new `InterceptorClient` without any interceptors
+    pub fn new(inner: T) -> Self {
+        Self { inner, interceptors: Vec::new() }
+    }
+
+    /// Adds an interceptor, which sees requests after and responses before all previously
+    /// added interceptors
+    #[must_use]
+    pub fn with_interceptor(mut self, interceptor: impl Interceptor + 'static) -> Self {
+        self.interceptors.push(Box::new(interceptor));
+        self
+    }
+
+    /// Returns a reference to the wrapped transport
+    pub fn inner(&self) -> &T {
+        &self.inner
+    }
+
+    /// Passes the request through the interceptors. Returns the response and the index of the
+    /// interceptor which short-circuited the request, if any.
+    fn on_request(&self, request: &mut InterceptedRequest) -> Option<(usize, Value)> {
+        self.interceptors
+            .iter()
+            .enumerate()
+            .find_map(|(idx, interceptor)| Some((idx, interceptor.on_request(request)?)))
+    }
+
+    /// Passes the response through the first `called` interceptors, in reverse order
+    fn on_response(
+        &self,
+        request: &InterceptedRequest,
+        response: &mut Result<Value, ProviderError>,
+        called: usize,
+        elapsed: Duration,
+    ) {
+        for interceptor in self.interceptors[..called].iter().rev() {
+            interceptor.on_response(request, response, elapsed);
+        }
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl<T: JsonRpcClient> JsonRpcClient for InterceptorClient<T> {
+    type Error = ProviderError;
+
+    async fn request<A, R>(&self, method: &str, params: A) -> Result<R, Self::Error>
+    where
+        A: Debug + Serialize + Send + Sync,
+        R: DeserializeOwned,
+    {
+        let start = Instant::now();
+        // Zero-sized params are not serialized at all by the transports, so we keep them as
+        // `None` instead of `null`
+        let params =
+            if std::mem::size_of::<A>() == 0 { None } else { Some(serde_json::to_value(params)?) };
+        let mut request = InterceptedRequest { method: method.to_string(), params };
+
+        let (mut response, called) = match self.on_request(&mut request) {
+            Some((idx, response)) => (Ok(response), idx),
+            None => {
+                let response: Result<Value, T::Error> = match &request.params {
+                    Some(params) => self.inner.request(&request.method, params).await,
+                    None => self.inner.request(&request.method, ()).await,
+                };
+                (response.map_err(Into::into), self.interceptors.len())
+            }
+        };
+        self.on_response(&request, &mut response, called, start.elapsed());
+
+        Ok(serde_json::from_value(response?)?)
+    }
+
+    /// Passes every request of the batch through the interceptors. Requests which were not
+    /// short-circuited are sent as a single batch by the inner transport.
+    async fn request_batch(
+        &self,
+        requests: Vec<(String, Value)>,
+    ) -> Result<Vec<Result<Value, JsonRpcError>>, Self::Error> {
+        let start = Instant::now();
+        let mut intercepted = Vec::with_capacity(requests.len());
+        let mut batch = Vec::new();
+        for (method, params) in requests {
+            let mut request = InterceptedRequest { method, params: Some(params) };
+            let short_circuit = self.on_request(&mut request);
+            if short_circuit.is_none() {
+                let params = request.params.clone().unwrap_or_else(|| Value::Array(Vec::new()));
+                batch.push((request.method.clone(), params));
+            }
+            intercepted.push((request, short_circuit));
+        }
+
+        let mut responses = if batch.is_empty() {
+            Vec::new().into_iter()
+        } else {
+            self.inner.request_batch(batch).await.map_err(Into::<ProviderError>::into)?.into_iter()
+        };
+        let elapsed = start.elapsed();
+
+        let mut results = Vec::with_capacity(intercepted.len());
+        for (request, short_circuit) in intercepted {
+            let (mut response, called) = match short_circuit {
+                Some((idx, response)) => (Ok(response), idx),
+                None => {
+                    let response = responses.next().ok_or_else(|| {
+                        ProviderError::CustomError(format!(
+                            "missing batch response for {}",
+                            request.method
+                        ))
+                    })?;
+                    let response =
+                        response.map_err(|err| ProviderError::JsonRpcClientError(Box::new(err)));
+                    (response, self.interceptors.len())
+                }
+            };
+            self.on_response(&request, &mut response, called, elapsed);
+            results.push(response.map_err(into_json_rpc_error));
+        }
+        Ok(results)
+    }
+
+    fn supports_pubsub(&self) -> bool {
+        self.inner.supports_pubsub()
+    }
+
+    fn subscribe_notifications(&self, id: U256) -> Option<Result<NotificationStream, Self::Error>> {
+        self.inner.subscribe_notifications(id).map(|res| res.map_err(Into::into))
+    }
+
+    fn unsubscribe_notifications(&self, id: U256) -> Result<(), Self::Error> {
+        self.inner.unsubscribe_notifications(id).map_err(Into::into)
+    }
+}
+
+/// Converts the error of a batch entry back into a JSON-RPC error
+fn into_json_rpc_error(err: ProviderError) -> JsonRpcError {
+    match err.as_error_response() {
+        Some(err) => err.clone(),
+        None => JsonRpcError { code: INTERNAL_ERROR, message: err.to_string(), data: None },
+    }
+}
+
+#[cfg(test)]
+#[cfg(not(target_arch = "wasm32"))]
+mod tests {
+    use super::*;
+    use crate::{Middleware, MockProvider, Provider};
+    use ethers_core::types::U64;
+    use serde_json::json;
+    use std::sync::{Arc, Mutex};
+
+    /// Records the methods of all requests and responses it sees
+    #[derive(Debug, Default, Clone)]
+    struct Recorder {
+        requests: Arc<Mutex<Vec<String>>>,
+        responses: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl Interceptor for Recorder {
+        fn on_request(&self, request: &mut InterceptedRequest) -> Option<Value> {
+            self.requests.lock().unwrap().push(request.method.clone());
+            None
+        }
+
+        fn on_response(
+            &self,
+            request: &InterceptedRequest,
+            _response: &mut Result<Value, ProviderError>,
+            _elapsed: Duration,
+        ) {
+            self.responses.lock().unwrap().push(request.method.clone());
+        }
+    }
+
+    /// Rewrites `eth_blockNumber` requests to `eth_chainId`
+    #[derive(Debug)]
+    struct Rewrite;
+
+    impl Interceptor for Rewrite {
+        fn on_request(&self, request: &mut InterceptedRequest) -> Option<Value> {
+            if request.method == "eth_blockNumber" {
+                request.method = "eth_chainId".to_string();
+            }
+            None
+        }
+    }
+
+    /// Answers `eth_blockNumber` without reaching the transport
+    #[derive(Debug)]
+    struct Cached;
+
+    impl Interceptor for Cached {
+        fn on_request(&self, request: &mut InterceptedRequest) -> Option<Value> {
+            (request.method == "eth_blockNumber").then(|| json!("0xc"))
+        }
+    }
+
+    #[tokio::test]
+    async fn rewrites_requests() {
+        let mock = MockProvider::new();
+        let recorder = Recorder::default();
+        let client = InterceptorClient::new(mock.clone())
+            .with_interceptor(recorder.clone())
+            .with_interceptor(Rewrite);
+        let provider = Provider::new(client);
+
+        mock.push(U64::from(1)).unwrap();
+        assert_eq!(provider.get_block_number().await.unwrap(), U64::from(1));
+        mock.assert_request("eth_chainId", ()).unwrap();
+
+        // the recorder comes first, so it sees the original request
+        assert_eq!(*recorder.requests.lock().unwrap(), vec!["eth_blockNumber"]);
+        assert_eq!(*recorder.responses.lock().unwrap(), vec!["eth_chainId"]);
+    }
+
+    #[tokio::test]
+    async fn short_circuits_requests() {
+        let mock = MockProvider::new();
+        let recorder = Recorder::default();
+        let client = InterceptorClient::new(mock.clone())
+            .with_interceptor(Cached)
+            .with_interceptor(recorder.clone());
+        let provider = Provider::new(client);
+
+        assert_eq!(provider.get_block_number().await.unwrap(), U64::from(12));
+        assert!(mock.assert_request("eth_blockNumber", ()).is_err());
+        // the recorder comes after the cache, so it never sees the request
+        assert!(recorder.requests.lock().unwrap().is_empty());
+        assert!(recorder.responses.lock().unwrap().is_empty());
+
+        mock.push(U64::from(1)).unwrap();
+        let mut batch = provider.batch();
+        let block = batch.get_block_number().unwrap();
+        let chain_id = batch.add_request::<_, U64>("eth_chainId", ()).unwrap();
+        let response = batch.send().await.unwrap();
+        assert_eq!(response.get(&block).unwrap(), U64::from(12));
+        assert_eq!(response.get(&chain_id).unwrap(), U64::from(1));
+        mock.assert_request("eth_chainId", json!([])).unwrap();
+        assert_eq!(*recorder.requests.lock().unwrap(), vec!["eth_chainId"]);
+    }
+}
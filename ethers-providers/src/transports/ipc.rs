@@ -1,17 +1,23 @@
 use crate::{
     provider::ProviderError,
-    transports::common::{JsonRpcError, Notification, Request, Response},
-    JsonRpcClient, PubsubClient,
+    transports::{
+        common::{BatchError, JsonRpcError, Notification, Request, Response},
+        reconnect::{Handled, Reconnect},
+    },
+    JsonRpcClient, NotificationStream, PubsubClient,
 };
 use ethers_core::types::U256;
 
 use async_trait::async_trait;
 use futures_channel::mpsc;
+use futures_timer::Delay;
 use futures_util::stream::{Fuse, StreamExt};
 use oneshot::error::RecvError;
 use serde::{de::DeserializeOwned, Serialize};
 use std::{
-    collections::HashMap,
+    collections::{HashMap, VecDeque},
+    future::Future,
+    io,
     path::Path,
     sync::{
         atomic::{AtomicU64, Ordering},
@@ -25,28 +31,32 @@ use tokio::{
     sync::oneshot,
 };
 use tokio_util::io::ReaderStream;
-use tracing::{error, warn};
+use tracing::{debug, error, warn};
 
 /// Unix Domain Sockets (IPC) transport.
+///
+/// The transport can drive any `AsyncRead + AsyncWrite` stream, see [`Ipc::new`].
 #[derive(Debug, Clone)]
 pub struct Ipc {
     id: Arc<AtomicU64>,
     messages_tx: mpsc::UnboundedSender<TransportMessage>,
 }
 
-type Pending = oneshot::Sender<serde_json::Value>;
+type Pending = oneshot::Sender<Result<serde_json::Value, JsonRpcError>>;
 type Subscription = mpsc::UnboundedSender<serde_json::Value>;
 
 #[derive(Debug)]
 enum TransportMessage {
     Request { id: u64, request: String, sender: Pending },
+    BatchRequest { requests: Vec<(u64, Pending)>, request: String },
     Subscribe { id: U256, sink: Subscription },
     Unsubscribe { id: U256 },
 }
 
 impl Ipc {
-    /// Creates a new IPC transport from a Async Reader / Writer
-    fn new<S: AsyncRead + AsyncWrite + Send + 'static>(stream: S) -> Self {
+    /// Creates a new IPC transport from an async reader / writer, e.g. a connected `UnixStream`
+    /// or one end of an in-memory [`tokio::io::duplex`] pipe.
+    pub fn new<S: AsyncRead + AsyncWrite + Send + 'static>(stream: S) -> Self {
         let id = Arc::new(AtomicU64::new(1));
         let (messages_tx, messages_rx) = mpsc::unbounded();
 
@@ -61,6 +71,56 @@ impl Ipc {
         Ok(Self::new(ipc))
     }
 
+    /// Creates a new IPC transport which re-dials the socket at `path` if the connection drops,
+    /// giving up after `reconnects` consecutive failed attempts.
+    ///
+    /// Requests which were in flight when the connection dropped are sent again and active
+    /// subscriptions are re-issued on the new connection. The subscription ids handed out by
+    /// this client stay the same across reconnects, so existing `SubscriptionStream`s keep
+    /// receiving notifications.
+    ///
+    /// ```no_run
+    /// # async fn foo() -> Result<(), Box<dyn std::error::Error>> {
+    /// use ethers_providers::{Ipc, Middleware, Provider, StreamExt};
+    ///
+    /// let ipc = Ipc::connect_with_reconnects("/tmp/geth.ipc", 10).await?;
+    /// let provider = Provider::new(ipc);
+    /// let mut blocks = provider.subscribe_blocks().await?;
+    /// while let Some(block) = blocks.next().await {
+    ///     println!("{:?}", block.hash);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(unix)]
+    pub async fn connect_with_reconnects<P: AsRef<Path>>(
+        path: P,
+        reconnects: usize,
+    ) -> Result<Self, IpcError> {
+        let path = path.as_ref().to_path_buf();
+        let connect = move || UnixStream::connect(path.clone());
+        Self::with_reconnects(connect, reconnects).await
+    }
+
+    /// Creates a new IPC transport over the streams opened by `connect`, which is called again
+    /// to re-dial the node if the connection drops. See [`Ipc::connect_with_reconnects`].
+    pub async fn with_reconnects<F, Fut, S>(connect: F, reconnects: usize) -> Result<Self, IpcError>
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = io::Result<S>> + Send + 'static,
+        S: AsyncRead + AsyncWrite + Send + 'static,
+    {
+        let stream = connect().await?;
+
+        let id = Arc::new(AtomicU64::new(1));
+        let (messages_tx, messages_rx) = mpsc::unbounded();
+        IpcServer::new(stream, messages_rx)
+            .with_reconnect(Reconnect::new(connect, reconnects, id.clone()))
+            .spawn();
+
+        Ok(Self { id, messages_tx })
+    }
+
     fn send(&self, msg: TransportMessage) -> Result<(), IpcError> {
         self.messages_tx
             .unbounded_send(msg)
@@ -93,11 +153,57 @@ impl JsonRpcClient for Ipc {
         self.send(payload)?;
 
         // Wait for the response from the IPC server.
-        let res = receiver.await?;
+        let res = receiver.await??;
 
         // Parse JSON response.
         Ok(serde_json::from_value(res)?)
     }
+
+    async fn request_batch(
+        &self,
+        requests: Vec<(String, serde_json::Value)>,
+    ) -> Result<Vec<Result<serde_json::Value, JsonRpcError>>, IpcError> {
+        if requests.is_empty() {
+            return Ok(Vec::new())
+        }
+        let first_id = self.id.fetch_add(requests.len() as u64, Ordering::SeqCst);
+        let payload = requests
+            .iter()
+            .enumerate()
+            .map(|(idx, (method, params))| Request::new(first_id + idx as u64, method, params))
+            .collect::<Vec<_>>();
+
+        // Initialize a response channel for every request of the batch
+        let (senders, receivers): (Vec<_>, Vec<_>) = (0..requests.len() as u64)
+            .map(|idx| {
+                let (sender, receiver) = oneshot::channel();
+                ((first_id + idx, sender), receiver)
+            })
+            .unzip();
+
+        self.send(TransportMessage::BatchRequest {
+            requests: senders,
+            request: serde_json::to_string(&payload)?,
+        })?;
+
+        let mut responses = Vec::with_capacity(receivers.len());
+        for receiver in receivers {
+            responses.push(receiver.await?);
+        }
+        Ok(responses)
+    }
+
+    fn supports_pubsub(&self) -> bool {
+        true
+    }
+
+    fn subscribe_notifications(&self, id: U256) -> Option<Result<NotificationStream, IpcError>> {
+        Some(PubsubClient::subscribe(self, id).map(|rx| -> NotificationStream { Box::pin(rx) }))
+    }
+
+    fn unsubscribe_notifications(&self, id: U256) -> Result<(), IpcError> {
+        PubsubClient::unsubscribe(self, id)
+    }
 }
 
 impl PubsubClient for Ipc {
@@ -120,13 +226,17 @@ struct IpcServer<T> {
     requests: Fuse<mpsc::UnboundedReceiver<TransportMessage>>,
     pending: HashMap<u64, Pending>,
     subscriptions: HashMap<U256, Subscription>,
+    /// The request ids of the batches without a response, in the order they were sent
+    batches: VecDeque<Vec<u64>>,
+    /// Set if the server re-dials the node when the connection drops
+    reconnect: Option<Reconnect<T, io::Error>>,
 }
 
 impl<T> IpcServer<T>
 where
     T: AsyncRead + AsyncWrite,
 {
-    /// Instantiates the Websocket Server
+    /// Instantiates the IPC Server
     pub fn new(ipc: T, requests: mpsc::UnboundedReceiver<TransportMessage>) -> Self {
         let (socket_reader, socket_writer) = tokio::io::split(ipc);
         let socket_reader = ReaderStream::new(socket_reader).fuse();
@@ -136,9 +246,17 @@ where
             requests: requests.fuse(),
             pending: HashMap::default(),
             subscriptions: HashMap::default(),
+            batches: VecDeque::default(),
+            reconnect: None,
         }
     }
 
+    /// Enables reconnecting to the node when the connection drops
+    fn with_reconnect(mut self, reconnect: Reconnect<T, io::Error>) -> Self {
+        self.reconnect = Some(reconnect);
+        self
+    }
+
     /// Spawns the event loop
     fn spawn(mut self)
     where
@@ -147,9 +265,25 @@ where
         let f = async move {
             let mut read_buffer = Vec::new();
             loop {
-                let closed = self.process(&mut read_buffer).await.expect("WS Server panic");
-                if closed && self.pending.is_empty() {
-                    break
+                match self.process(&mut read_buffer).await {
+                    Ok(closed) => {
+                        if closed && self.pending.is_empty() {
+                            break
+                        }
+                    }
+                    Err(err) if self.reconnect.is_some() && is_disconnect(&err) => {
+                        warn!("IPC connection lost, reconnecting: {}", err);
+                        // drop the partial message of the previous connection
+                        read_buffer.clear();
+                        if let Err(err) = self.reconnect().await {
+                            error!("Could not reconnect: {}", err);
+                            break
+                        }
+                    }
+                    Err(err) => {
+                        error!("IPC server error: {}", err);
+                        break
+                    }
                 }
             }
         };
@@ -173,7 +307,7 @@ where
                     error!("IPC read error: {:?}", err);
                     return Err(err.into());
                 },
-                None => {},
+                None => return Err(IpcError::UnexpectedClose),
             },
             // finished
             complete => {},
@@ -189,11 +323,43 @@ where
                     warn!("Replacing a pending request with id {:?}", id);
                 }
 
-                if let Err(err) = self.socket_writer.write(request.as_bytes()).await {
+                let request = match self.reconnect.as_mut() {
+                    Some(reconnect) => reconnect.track_request(id, request)?,
+                    None => request,
+                };
+
+                if let Err(err) = self.socket_writer.write_all(request.as_bytes()).await {
                     error!("IPC connection error: {:?}", err);
-                    self.pending.remove(&id);
+                    // the request is sent again once the connection is restored
+                    if self.reconnect.is_none() {
+                        self.pending.remove(&id);
+                    }
                 }
             }
+            TransportMessage::BatchRequest { requests, request } => {
+                let ids = requests.iter().map(|(id, _)| *id).collect::<Vec<_>>();
+                for (id, sender) in requests {
+                    if self.pending.insert(id, sender).is_some() {
+                        warn!("Replacing a pending request with id {:?}", id);
+                    }
+                }
+
+                if let (Some(reconnect), Some(first_id)) = (self.reconnect.as_mut(), ids.first()) {
+                    reconnect.track_batch(*first_id, request.clone());
+                }
+
+                if let Err(err) = self.socket_writer.write_all(request.as_bytes()).await {
+                    error!("IPC connection error: {:?}", err);
+                    // the batch is sent again once the connection is restored
+                    if self.reconnect.is_none() {
+                        for id in ids {
+                            self.pending.remove(&id);
+                        }
+                        return Ok(())
+                    }
+                }
+                self.batches.push_back(ids);
+            }
             TransportMessage::Subscribe { id, sink } => {
                 if self.subscriptions.insert(id, sink).is_some() {
                     warn!("Replacing already-registered subscription with id {:?}", id);
@@ -209,6 +375,43 @@ where
         Ok(())
     }
 
+    /// Re-dials the node, then sends all in flight requests again and re-issues the active
+    /// subscriptions
+    async fn reconnect(&mut self) -> Result<(), IpcError> {
+        let reconnect = self.reconnect.as_mut().expect("reconnecting is enabled");
+        let mut attempt = 0;
+        'connect: loop {
+            if attempt > 0 {
+                Delay::new(Reconnect::<T, io::Error>::backoff(attempt)).await;
+            }
+            attempt += 1;
+
+            let stream = match reconnect.connect().await {
+                Ok(stream) => stream,
+                Err(err) if attempt < reconnect.max_attempts => {
+                    warn!("Reconnect attempt {} failed: {}", attempt, err);
+                    continue
+                }
+                Err(err) => return Err(err.into()),
+            };
+            let (socket_reader, socket_writer) = tokio::io::split(stream);
+            self.socket_reader = ReaderStream::new(socket_reader).fuse();
+            self.socket_writer = socket_writer;
+            debug!("reconnected after {} attempt(s)", attempt);
+
+            for message in reconnect.resubscribe()? {
+                if let Err(err) = self.socket_writer.write_all(message.as_bytes()).await {
+                    if attempt < reconnect.max_attempts {
+                        warn!("Reconnect attempt {} failed: {}", attempt, err);
+                        continue 'connect
+                    }
+                    return Err(err.into())
+                }
+            }
+            return Ok(())
+        }
+    }
+
     fn handle_socket(
         &mut self,
         read_buffer: &mut Vec<u8>,
@@ -224,21 +427,17 @@ where
 
             // Iterate through these elements, and handle responses/notifications
             while let Some(Ok(value)) = de.next() {
-                if let Ok(notification) =
-                    serde_json::from_value::<Notification<serde_json::Value>>(value.clone())
-                {
-                    // Send notify response if okay.
-                    if let Err(e) = self.notify(notification) {
-                        error!("Failed to send IPC notification: {}", e)
-                    }
-                } else if let Ok(response) =
-                    serde_json::from_value::<Response<serde_json::Value>>(value)
-                {
-                    if let Err(e) = self.respond(response) {
-                        error!("Failed to send IPC response: {}", e)
+                match value {
+                    // the responses of a batch request
+                    serde_json::Value::Array(values) => {
+                        if let Some(id) = values.first().and_then(|value| value["id"].as_u64()) {
+                            self.batches.retain(|ids| !ids.contains(&id));
+                        }
+                        for value in values {
+                            self.handle_value(value);
+                        }
                     }
-                } else {
-                    warn!("JSON from IPC stream is not a response or notification");
+                    value => self.handle_value(value),
                 }
             }
 
@@ -253,10 +452,59 @@ where
         Ok(())
     }
 
+    /// Handles a single response or notification
+    fn handle_value(&mut self, value: serde_json::Value) {
+        if let Ok(notification) =
+            serde_json::from_value::<Notification<serde_json::Value>>(value.clone())
+        {
+            // Send notify response if okay.
+            if let Err(e) = self.notify(notification) {
+                error!("Failed to send IPC notification: {}", e)
+            }
+        } else if let Ok(response) =
+            serde_json::from_value::<Response<serde_json::Value>>(value.clone())
+        {
+            if let Err(e) = self.respond(response) {
+                error!("Failed to send IPC response: {}", e)
+            }
+        } else if let Ok(BatchError { error }) = serde_json::from_value::<BatchError>(value) {
+            self.reject_batch(error);
+        } else {
+            warn!("JSON from IPC stream is not a response or notification");
+        }
+    }
+
+    /// Fails every request of the oldest batch without a response with the error the node
+    /// rejected a whole batch with
+    fn reject_batch(&mut self, error: JsonRpcError) {
+        while let Some(ids) = self.batches.pop_front() {
+            let senders = ids.iter().filter_map(|id| self.pending.remove(id)).collect::<Vec<_>>();
+            // every request of the batch was answered already
+            if senders.is_empty() {
+                continue
+            }
+            if let (Some(reconnect), Some(first_id)) = (self.reconnect.as_mut(), ids.first()) {
+                reconnect.reject_batch(*first_id);
+            }
+            for sender in senders {
+                let _ = sender.send(Err(error.clone()));
+            }
+            return
+        }
+        warn!("Received an error without a pending batch: {}", error);
+    }
+
     /// Sends notification through the channel based on the ID of the subscription.
     /// This handles streaming responses.
     fn notify(&mut self, notification: Notification<serde_json::Value>) -> Result<(), IpcError> {
-        let id = notification.params.subscription;
+        let id = match self.reconnect.as_ref() {
+            Some(reconnect) => match reconnect.client_id(notification.params.subscription) {
+                Some(id) => id,
+                // the subscription was not re-issued yet
+                None => return Ok(()),
+            },
+            None => notification.params.subscription,
+        };
         if let Some(tx) = self.subscriptions.get(&id) {
             tx.unbounded_send(notification.params.result).map_err(|_| {
                 IpcError::ChannelError(format!("Subscription receiver {} dropped", id))
@@ -270,10 +518,22 @@ where
     /// This handles RPC calls with only one response, and the channel entry is dropped after
     /// sending.
     fn respond(&mut self, output: Response<serde_json::Value>) -> Result<(), IpcError> {
+        let output = match self.reconnect.as_mut() {
+            Some(reconnect) => match reconnect.handle_response(output)? {
+                Handled::Forward(output) => output,
+                Handled::Resubscribed => return Ok(()),
+                Handled::ResubscribeFailed(id) => {
+                    // end the stream instead of silently never yielding again
+                    self.subscriptions.remove(&id);
+                    return Ok(())
+                }
+            },
+            None => output,
+        };
         let id = output.id;
 
-        // Converts output into result, to send data if valid response.
-        let value = output.data.into_value()?;
+        // Converts output into result, to forward JSON-RPC errors to the caller
+        let value = output.data.into_result();
 
         let response_tx = self.pending.remove(&id).ok_or_else(|| {
             IpcError::ChannelError("No response channel exists for the response ID".to_string())
@@ -307,6 +567,15 @@ pub enum IpcError {
 
     #[error(transparent)]
     Canceled(#[from] RecvError),
+
+    /// The node closed the connection
+    #[error("IPC connection closed unexpectedly")]
+    UnexpectedClose,
+}
+
+/// Returns true if the error means that the connection to the node was lost
+fn is_disconnect(err: &IpcError) -> bool {
+    matches!(err, IpcError::IoError(_) | IpcError::UnexpectedClose)
 }
 
 impl From<IpcError> for ProviderError {
@@ -323,7 +592,9 @@ mod test {
         types::{Block, TxHash, U256},
         utils::Geth,
     };
+    use serde_json::json;
     use tempfile::NamedTempFile;
+    use tokio::io::{AsyncReadExt, DuplexStream};
 
     #[tokio::test]
     async fn request() {
@@ -360,4 +631,142 @@ mod test {
         let offset = blocks[0] - block_num;
         assert_eq!(blocks, &[block_num + offset, block_num + offset + 1, block_num + offset + 2])
     }
+
+    /// Reads a request sent by the client
+    async fn read_request(stream: &mut DuplexStream) -> serde_json::Value {
+        let mut buf = vec![0; 1024];
+        let len = stream.read(&mut buf).await.unwrap();
+        serde_json::from_slice(&buf[..len]).unwrap()
+    }
+
+    #[tokio::test]
+    async fn request_over_duplex() {
+        let (client, mut server) = tokio::io::duplex(1024);
+        let ipc = Ipc::new(client);
+        tokio::spawn(async move {
+            let request = read_request(&mut server).await;
+            assert_eq!(request["method"], "eth_blockNumber");
+            let response = json!({"jsonrpc": "2.0", "id": request["id"], "result": "0x7"});
+            server.write_all(response.to_string().as_bytes()).await.unwrap();
+            futures_util::future::pending::<()>().await;
+        });
+
+        let block_num: U256 = ipc.request("eth_blockNumber", ()).await.unwrap();
+        assert_eq!(block_num, 7.into());
+    }
+
+    #[tokio::test]
+    async fn batch_over_duplex() {
+        let (client, mut server) = tokio::io::duplex(1024);
+        let ipc = Ipc::new(client);
+        tokio::spawn(async move {
+            // answers the first batch in reverse order
+            let request = read_request(&mut server).await;
+            let responses = request
+                .as_array()
+                .unwrap()
+                .iter()
+                .rev()
+                .map(|request| json!({"jsonrpc": "2.0", "id": request["id"], "result": request["method"]}))
+                .collect::<Vec<_>>();
+            server.write_all(json!(responses).to_string().as_bytes()).await.unwrap();
+
+            // rejects the second batch as a whole
+            read_request(&mut server).await;
+            let response = json!({
+                "jsonrpc": "2.0",
+                "id": null,
+                "error": {"code": -32600, "message": "batches are not supported"}
+            });
+            server.write_all(response.to_string().as_bytes()).await.unwrap();
+            futures_util::future::pending::<()>().await;
+        });
+
+        let requests = vec![
+            ("eth_chainId".to_string(), json!([])),
+            ("eth_blockNumber".to_string(), json!([])),
+        ];
+        let responses = ipc.request_batch(requests.clone()).await.unwrap();
+        assert_eq!(responses[0].as_ref().unwrap(), &json!("eth_chainId"));
+        assert_eq!(responses[1].as_ref().unwrap(), &json!("eth_blockNumber"));
+
+        let responses = ipc.request_batch(requests).await.unwrap();
+        assert_eq!(responses.len(), 2);
+        for response in responses {
+            assert_eq!(response.unwrap_err().message, "batches are not supported");
+        }
+    }
+
+    /// Answers the `eth_subscribe` request of the client with `sub_id` and sends one
+    /// notification for the subscription
+    async fn serve_subscription(stream: &mut DuplexStream, sub_id: &str, item: u64) {
+        let request = read_request(stream).await;
+        assert_eq!(request["method"], "eth_subscribe");
+
+        let response = json!({"jsonrpc": "2.0", "id": request["id"], "result": sub_id});
+        stream.write_all(response.to_string().as_bytes()).await.unwrap();
+        let notification = json!({
+            "jsonrpc": "2.0",
+            "method": "eth_subscription",
+            "params": {"subscription": sub_id, "result": item}
+        });
+        stream.write_all(notification.to_string().as_bytes()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn resubscribes_after_reconnect() {
+        let (first, mut first_server) = tokio::io::duplex(1024);
+        let (second, mut second_server) = tokio::io::duplex(1024);
+        let streams = Arc::new(std::sync::Mutex::new(vec![second, first]));
+        let connect = move || {
+            let stream = streams.lock().unwrap().pop();
+            async move { stream.ok_or_else(|| io::Error::from(io::ErrorKind::NotConnected)) }
+        };
+        tokio::spawn(async move {
+            serve_subscription(&mut first_server, "0x1", 1).await;
+            drop(first_server);
+            // the node assigns a new id to the re-issued subscription
+            serve_subscription(&mut second_server, "0x5", 2).await;
+            futures_util::future::pending::<()>().await;
+        });
+
+        let ipc = Ipc::with_reconnects(connect, 3).await.unwrap();
+        let mut stream = ipc.subscribe(1).unwrap();
+        let sub_id: U256 = ipc.request("eth_subscribe", ["newHeads"]).await.unwrap();
+        assert_eq!(sub_id, 1.into());
+
+        assert_eq!(stream.next().await.unwrap(), json!(1));
+        assert_eq!(stream.next().await.unwrap(), json!(2));
+    }
+
+    #[tokio::test]
+    async fn streams_from_subscription() {
+        let (client, mut server) = tokio::io::duplex(1024);
+        let tx_hash = TxHash::repeat_byte(1);
+        tokio::spawn(async move {
+            let request = read_request(&mut server).await;
+            assert_eq!(request["method"], "eth_subscribe");
+            assert_eq!(request["params"], json!(["newPendingTransactions"]));
+            let response = json!({"jsonrpc": "2.0", "id": request["id"], "result": "0x3"});
+            server.write_all(response.to_string().as_bytes()).await.unwrap();
+
+            // the client registered the subscription before sending its next request
+            let request = read_request(&mut server).await;
+            let response = json!({"jsonrpc": "2.0", "id": request["id"], "result": "0x1"});
+            server.write_all(response.to_string().as_bytes()).await.unwrap();
+            let notification = json!({
+                "jsonrpc": "2.0",
+                "method": "eth_subscription",
+                "params": {"subscription": "0x3", "result": tx_hash}
+            });
+            server.write_all(notification.to_string().as_bytes()).await.unwrap();
+            futures_util::future::pending::<()>().await;
+        });
+
+        let provider = crate::Provider::new(Ipc::new(client));
+        let mut stream = provider.stream_pending_txs().await.unwrap();
+        assert_eq!(stream.source(), crate::WatchSource::Subscription(3.into()));
+        let _: U256 = provider.as_ref().request("eth_chainId", ()).await.unwrap();
+        assert_eq!(stream.next().await.unwrap(), tx_hash);
+    }
 }
@@ -0,0 +1,187 @@
+//! JWT authentication of the Engine API of execution clients, see
+//! <https://github.com/ethereum/execution-apis/blob/main/src/engine/authentication.md>
+use hmac::{Hmac, Mac, NewMac};
+use serde::Serialize;
+use sha2::Sha256;
+use std::{
+    fmt,
+    time::{SystemTime, UNIX_EPOCH},
+};
+use thiserror::Error;
+
+/// The length of a JWT secret in bytes
+pub const JWT_SECRET_LENGTH: usize = 32;
+
+/// The base64url encoded header of HS256 tokens, `{"alg":"HS256","typ":"JWT"}`
+const JWT_HEADER: &str = "eyJhbGciOiJIUzI1NiIsInR5cCI6IkpXVCJ9";
+
+/// Error thrown when parsing a JWT secret
+#[derive(Error, Debug)]
+pub enum JwtError {
+    /// Thrown if the secret is not hex encoded
+    #[error(transparent)]
+    InvalidHex(#[from] hex::FromHexError),
+
+    /// Thrown if the secret is not 32 bytes long
+    #[error("invalid JWT secret length {0}, expected {}", JWT_SECRET_LENGTH)]
+    InvalidLength(usize),
+
+    /// Thrown if the secret file could not be read
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+/// The 256 bit secret shared with the execution client
+#[derive(Clone, PartialEq, Eq)]
+pub struct JwtKey([u8; JWT_SECRET_LENGTH]);
+
+impl JwtKey {
+    /// Creates a key from the raw secret
+    pub fn from_slice(secret: &[u8]) -> Result<Self, JwtError> {
+        if secret.len() != JWT_SECRET_LENGTH {
+            return Err(JwtError::InvalidLength(secret.len()))
+        }
+        let mut key = [0; JWT_SECRET_LENGTH];
+        key.copy_from_slice(secret);
+        Ok(Self(key))
+    }
+
+    /// Parses a hex encoded secret, with or without `0x` prefix
+    pub fn from_hex(secret: &str) -> Result<Self, JwtError> {
+        let secret = secret.trim();
+        let secret = secret.strip_prefix("0x").unwrap_or(secret);
+        Self::from_slice(&hex::decode(secret)?)
+    }
+
+    /// Reads a hex encoded secret from a file, like the `jwt.hex` file written by execution
+    /// clients
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn from_file(path: impl AsRef<std::path::Path>) -> Result<Self, JwtError> {
+        Self::from_hex(&std::fs::read_to_string(path)?)
+    }
+
+    /// Returns the raw secret
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl fmt::Debug for JwtKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("JwtKey(<redacted>)")
+    }
+}
+
+/// The claims of the tokens minted by a [`JwtAuth`]
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Claims {
+    /// The time the token was issued at, in seconds since the unix epoch
+    pub iat: u64,
+    /// The id of the consensus client
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    /// The version of the consensus client
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub clv: Option<String>,
+}
+
+/// Mints HS256 JSON Web Tokens to authenticate with the Engine API of an execution client.
+///
+/// Execution clients reject tokens whose `iat` claim is not within a few seconds of their
+/// clock, so a fresh token is minted for every request (or connection, for WebSockets).
+///
+/// ```no_run
+/// use ethers_providers::{Authorization, Http, JwtAuth, JwtKey};
+/// use url::Url;
+///
+/// # fn foo() -> Result<(), Box<dyn std::error::Error>> {
+/// let key = JwtKey::from_file("/tmp/jwt.hex")?;
+/// let http = Http::builder(Url::parse("http://localhost:8551")?)
+///     .auth(Authorization::Jwt(JwtAuth::new(key)))
+///     .build()?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct JwtAuth {
+    key: JwtKey,
+    id: Option<String>,
+    clv: Option<String>,
+}
+
+impl JwtAuth {
+    /// Creates a token generator with the secret
+    pub fn new(key: JwtKey) -> Self {
+        Self { key, id: None, clv: None }
+    }
+
+    /// Sets the `id` claim of the tokens
+    #[must_use]
+    pub fn id(mut self, id: impl Into<String>) -> Self {
+        self.id = Some(id.into());
+        self
+    }
+
+    /// Sets the `clv` (client version) claim of the tokens
+    #[must_use]
+    pub fn client_version(mut self, clv: impl Into<String>) -> Self {
+        self.clv = Some(clv.into());
+        self
+    }
+
+    /// Mints a token issued now
+    pub fn generate_token(&self) -> String {
+        let iat = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+        self.encode(&Claims { iat, id: self.id.clone(), clv: self.clv.clone() })
+    }
+
+    /// Mints a token with the given claims
+    pub fn encode(&self, claims: &Claims) -> String {
+        let claims = serde_json::to_vec(claims).expect("claims are serializable");
+        let message = format!("{}.{}", JWT_HEADER, base64url(&claims));
+
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(self.key.as_bytes()).expect("HMAC accepts any key size");
+        mac.update(message.as_bytes());
+        let signature = mac.finalize().into_bytes();
+
+        format!("{}.{}", message, base64url(&signature))
+    }
+}
+
+fn base64url(data: &[u8]) -> String {
+    base64::encode_config(data, base64::URL_SAFE_NO_PAD)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRET: &str = "0x7365637265747365637265747365637265747365637265747365637265747365";
+
+    #[test]
+    fn parses_secrets() {
+        let key = JwtKey::from_hex(&format!("{}\n", SECRET)).unwrap();
+        assert_eq!(key.as_bytes(), b"secretsecretsecretsecretsecretse");
+        assert!(matches!(JwtKey::from_hex("0x0102"), Err(JwtError::InvalidLength(2))));
+        assert!(matches!(JwtKey::from_hex("0xzz"), Err(JwtError::InvalidHex(_))));
+        assert_eq!(format!("{:?}", key), "JwtKey(<redacted>)");
+    }
+
+    #[test]
+    fn encodes_tokens() {
+        let auth = JwtAuth::new(JwtKey::from_hex(SECRET).unwrap());
+        let token = auth.encode(&Claims { iat: 1_700_000_000, id: None, clv: None });
+        assert_eq!(
+            token,
+            "eyJhbGciOiJIUzI1NiIsInR5cCI6IkpXVCJ9.eyJpYXQiOjE3MDAwMDAwMDB9.\
+             M2nY84VaM-AUfRGDPC_vdyUsN1rvFs8UaGBU76h2vK4"
+        );
+
+        let token = auth.generate_token();
+        let claims = token.split('.').nth(1).unwrap();
+        let claims = base64::decode_config(claims, base64::URL_SAFE_NO_PAD).unwrap();
+        let claims: serde_json::Value = serde_json::from_slice(&claims).unwrap();
+        assert!(claims["iat"].as_u64().unwrap() > 1_700_000_000);
+    }
+}
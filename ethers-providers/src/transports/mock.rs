@@ -1,3 +1,4 @@
+use super::common::JsonRpcError;
 use crate::{JsonRpcClient, ProviderError};
 
 use async_trait::async_trait;
@@ -6,15 +7,43 @@ use serde_json::Value;
 use std::{
     borrow::Borrow,
     collections::VecDeque,
+    fmt,
     sync::{Arc, Mutex},
 };
 use thiserror::Error;
 
 #[derive(Clone, Debug)]
 /// Mock transport used in test environments.
+///
+/// Responses are either registered per method with [`MockProvider::expect`], or pushed to a
+/// queue with [`MockProvider::push`] from which they are popped from the back regardless of
+/// the method. Registered responses take precedence over the queue.
+///
+/// ```
+/// # async fn foo() -> Result<(), Box<dyn std::error::Error>> {
+/// use ethers_core::types::U64;
+/// use ethers_providers::{JsonRpcError, Middleware, Provider};
+///
+/// let (provider, mock) = Provider::mocked();
+/// mock.expect("eth_blockNumber").times(1).returns(U64::from(1))?;
+/// mock.expect("eth_blockNumber").returns(U64::from(2))?;
+/// mock.expect("eth_chainId").returns_error(JsonRpcError {
+///     code: -32601,
+///     message: "the method eth_chainId does not exist".to_string(),
+///     data: None,
+/// });
+///
+/// assert_eq!(provider.get_block_number().await?, U64::from(1));
+/// assert_eq!(provider.get_block_number().await?, U64::from(2));
+/// assert_eq!(provider.get_block_number().await?, U64::from(2));
+/// assert!(provider.get_chainid().await.is_err());
+/// # Ok(())
+/// # }
+/// ```
 pub struct MockProvider {
     requests: Arc<Mutex<VecDeque<(String, Value)>>>,
-    responses: Arc<Mutex<VecDeque<Value>>>,
+    responses: Arc<Mutex<VecDeque<MockResponse>>>,
+    expectations: Arc<Mutex<Vec<MethodResponse>>>,
 }
 
 impl Default for MockProvider {
@@ -28,19 +57,41 @@ impl Default for MockProvider {
 impl JsonRpcClient for MockProvider {
     type Error = MockError;
 
-    /// Pushes the `(method, input)` to the back of the `requests` queue,
-    /// pops the responses from the back of the `responses` queue
+    /// Pushes the `(method, input)` to the back of the `requests` queue. Responds with the first
+    /// matching response registered for the method, or pops the response from the back of the
+    /// `responses` queue
     async fn request<T: Serialize + Send + Sync, R: DeserializeOwned>(
         &self,
         method: &str,
         input: T,
     ) -> Result<R, MockError> {
-        self.requests.lock().unwrap().push_back((method.to_owned(), serde_json::to_value(input)?));
-        let mut data = self.responses.lock().unwrap();
-        let element = data.pop_back().ok_or(MockError::EmptyResponses)?;
-        let res: R = serde_json::from_value(element)?;
+        let params = serde_json::to_value(input)?;
+        self.requests.lock().unwrap().push_back((method.to_owned(), params.clone()));
+        let response = match self.expected_response(method, &params) {
+            Some(response) => response,
+            None => self.responses.lock().unwrap().pop_back().ok_or(MockError::EmptyResponses)?,
+        };
 
-        Ok(res)
+        match response {
+            MockResponse::Value(value) => Ok(serde_json::from_value(value)?),
+            MockResponse::Error(err) => Err(MockError::JsonRpcError(err)),
+        }
+    }
+
+    /// Responds to the requests one after another, JSON-RPC errors are returned per request
+    async fn request_batch(
+        &self,
+        requests: Vec<(String, Value)>,
+    ) -> Result<Vec<Result<Value, JsonRpcError>>, MockError> {
+        let mut responses = Vec::with_capacity(requests.len());
+        for (method, params) in requests {
+            match self.request(&method, params).await {
+                Ok(value) => responses.push(Ok(value)),
+                Err(MockError::JsonRpcError(err)) => responses.push(Err(err)),
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(responses)
     }
 }
 
@@ -62,15 +113,149 @@ impl MockProvider {
         Self {
             requests: Arc::new(Mutex::new(VecDeque::new())),
             responses: Arc::new(Mutex::new(VecDeque::new())),
+            expectations: Arc::new(Mutex::new(Vec::new())),
         }
     }
 
     /// Pushes the data to the responses
     pub fn push<T: Serialize + Send + Sync, K: Borrow<T>>(&self, data: K) -> Result<(), MockError> {
         let value = serde_json::to_value(data.borrow())?;
-        self.responses.lock().unwrap().push_back(value);
+        self.responses.lock().unwrap().push_back(MockResponse::Value(value));
         Ok(())
     }
+
+    /// Pushes a JSON-RPC error to the responses
+    pub fn push_error(&self, err: JsonRpcError) {
+        self.responses.lock().unwrap().push_back(MockResponse::Error(err));
+    }
+
+    /// Returns a builder for a response to the requests of `method`, which is registered with
+    /// [`Expectation::returns`] or [`Expectation::returns_error`].
+    ///
+    /// Registered responses are matched in the order they were registered, and are returned
+    /// for every matching request unless limited with [`Expectation::times`].
+    pub fn expect(&self, method: impl Into<String>) -> Expectation<'_> {
+        Expectation { mock: self, method: method.into(), matcher: None, times: None }
+    }
+
+    /// Removes all responses registered with [`MockProvider::expect`]
+    pub fn clear_expectations(&self) {
+        self.expectations.lock().unwrap().clear();
+    }
+
+    /// Returns the first registered response matching the request and consumes one of its
+    /// uses
+    fn expected_response(&self, method: &str, params: &Value) -> Option<MockResponse> {
+        let mut expectations = self.expectations.lock().unwrap();
+        let idx = expectations.iter().position(|expected| expected.matches(method, params))?;
+        let expected = &mut expectations[idx];
+        let response = expected.response.clone();
+        if let Some(remaining) = expected.remaining.as_mut() {
+            *remaining -= 1;
+            if *remaining == 0 {
+                expectations.remove(idx);
+            }
+        }
+        Some(response)
+    }
+}
+
+/// A response of the [`MockProvider`]
+#[derive(Clone, Debug)]
+enum MockResponse {
+    Value(Value),
+    Error(JsonRpcError),
+}
+
+type ParamsMatcher = Arc<dyn Fn(&Value) -> bool + Send + Sync>;
+
+/// A response registered for the requests of a method
+#[derive(Clone)]
+struct MethodResponse {
+    method: String,
+    matcher: Option<ParamsMatcher>,
+    /// The number of requests left to respond to, `None` if unlimited
+    remaining: Option<usize>,
+    response: MockResponse,
+}
+
+impl MethodResponse {
+    fn matches(&self, method: &str, params: &Value) -> bool {
+        self.method == method &&
+            self.remaining != Some(0) &&
+            self.matcher.as_ref().map_or(true, |matcher| matcher(params))
+    }
+}
+
+impl fmt::Debug for MethodResponse {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MethodResponse")
+            .field("method", &self.method)
+            .field("remaining", &self.remaining)
+            .field("response", &self.response)
+            .finish()
+    }
+}
+
+/// Builder for a response of the [`MockProvider`] to the requests of a method, see
+/// [`MockProvider::expect`]
+#[must_use = "responses are only registered by `returns` or `returns_error`"]
+pub struct Expectation<'a> {
+    mock: &'a MockProvider,
+    method: String,
+    matcher: Option<ParamsMatcher>,
+    times: Option<usize>,
+}
+
+impl<'a> Expectation<'a> {
+    /// Only responds to requests with exactly these params
+    pub fn with_params<T: Serialize>(self, params: T) -> Result<Self, MockError> {
+        let params = serde_json::to_value(params)?;
+        Ok(self.matching(move |value| *value == params))
+    }
+
+    /// Only responds to requests whose params satisfy the predicate
+    pub fn matching(mut self, predicate: impl Fn(&Value) -> bool + Send + Sync + 'static) -> Self {
+        self.matcher = Some(Arc::new(predicate));
+        self
+    }
+
+    /// Only responds to the next `n` matching requests
+    pub fn times(mut self, n: usize) -> Self {
+        self.times = Some(n);
+        self
+    }
+
+    /// Registers the value as the response
+    pub fn returns<T: Serialize>(self, value: T) -> Result<(), MockError> {
+        let value = serde_json::to_value(value)?;
+        self.register(MockResponse::Value(value));
+        Ok(())
+    }
+
+    /// Registers the JSON-RPC error as the response
+    pub fn returns_error(self, err: JsonRpcError) {
+        self.register(MockResponse::Error(err))
+    }
+
+    fn register(self, response: MockResponse) {
+        let expected = MethodResponse {
+            method: self.method,
+            matcher: self.matcher,
+            remaining: self.times,
+            response,
+        };
+        self.mock.expectations.lock().unwrap().push(expected);
+    }
+}
+
+impl<'a> fmt::Debug for Expectation<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Expectation")
+            .field("method", &self.method)
+            .field("times", &self.times)
+            .finish()
+    }
 }
 
 #[derive(Error, Debug)]
@@ -84,6 +269,10 @@ pub enum MockError {
 
     #[error("empty responses array, please push some responses")]
     EmptyResponses,
+
+    /// The JSON-RPC error registered as the response
+    #[error(transparent)]
+    JsonRpcError(JsonRpcError),
 }
 
 impl From<MockError> for ProviderError {
@@ -130,6 +319,83 @@ mod tests {
         };
     }
 
+    #[tokio::test]
+    async fn responds_by_method() {
+        let mock = MockProvider::new();
+        mock.expect("eth_blockNumber").times(2).returns(U64::from(1)).unwrap();
+        mock.expect("eth_blockNumber").returns(U64::from(2)).unwrap();
+        mock.expect("eth_chainId").returns(U64::from(5)).unwrap();
+        mock.push(U64::from(100)).unwrap();
+
+        let block: U64 = mock.request("eth_blockNumber", ()).await.unwrap();
+        assert_eq!(block.as_u64(), 1);
+        let chain_id: U64 = mock.request("eth_chainId", ()).await.unwrap();
+        assert_eq!(chain_id.as_u64(), 5);
+        for expected in [1, 2, 2] {
+            let block: U64 = mock.request("eth_blockNumber", ()).await.unwrap();
+            assert_eq!(block.as_u64(), expected);
+        }
+
+        // methods without a registered response fall back to the queue
+        let gas_price: U64 = mock.request("eth_gasPrice", ()).await.unwrap();
+        assert_eq!(gas_price.as_u64(), 100);
+
+        mock.clear_expectations();
+        let err = mock.request::<_, U64>("eth_chainId", ()).await.unwrap_err();
+        assert!(matches!(err, MockError::EmptyResponses));
+    }
+
+    #[tokio::test]
+    async fn responds_by_params() {
+        let mock = MockProvider::new();
+        mock.expect("eth_getBalance")
+            .with_params(["0x01", "latest"])
+            .unwrap()
+            .returns(U64::from(1))
+            .unwrap();
+        mock.expect("eth_getBalance")
+            .matching(|params| params[1] == "pending")
+            .returns(U64::from(2))
+            .unwrap();
+
+        // the order of the requests does not matter
+        let balance: U64 = mock.request("eth_getBalance", ["0x02", "pending"]).await.unwrap();
+        assert_eq!(balance.as_u64(), 2);
+        let balance: U64 = mock.request("eth_getBalance", ["0x01", "latest"]).await.unwrap();
+        assert_eq!(balance.as_u64(), 1);
+        assert!(mock.request::<_, U64>("eth_getBalance", ["0x02", "latest"]).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn responds_with_errors() {
+        let (provider, mock) = crate::Provider::mocked();
+        let error = JsonRpcError { code: -32000, message: "nonce too low".to_string(), data: None };
+        mock.expect("eth_gasPrice").times(1).returns_error(error.clone());
+        mock.push_error(error);
+
+        let err = provider.get_gas_price().await.unwrap_err();
+        assert_eq!(err.as_error_response().unwrap().message, "nonce too low");
+        let err = provider.get_block_number().await.unwrap_err();
+        assert_eq!(err.as_error_response().unwrap().code, -32000);
+
+        // errors are returned per request of a batch
+        mock.expect("eth_chainId").returns(U64::from(1)).unwrap();
+        mock.expect("eth_gasPrice").returns_error(JsonRpcError {
+            code: -32601,
+            message: "method not found".to_string(),
+            data: None,
+        });
+        let responses = mock
+            .request_batch(vec![
+                ("eth_chainId".to_string(), Value::Null),
+                ("eth_gasPrice".to_string(), Value::Null),
+            ])
+            .await
+            .unwrap();
+        assert_eq!(responses[0].as_ref().unwrap(), &serde_json::json!("0x1"));
+        assert_eq!(responses[1].as_ref().unwrap_err().code, -32601);
+    }
+
     #[tokio::test]
     async fn composes_with_provider() {
         let (provider, mock) = crate::Provider::mocked();
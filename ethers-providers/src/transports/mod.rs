@@ -1,5 +1,8 @@
 mod common;
-pub use common::Authorization;
+pub use common::{Authorization, JsonRpcError};
+
+mod jwt;
+pub use jwt::{Claims, JwtAuth, JwtError, JwtKey, JWT_SECRET_LENGTH};
 
 // only used with WS
 #[cfg(feature = "ws")]
@@ -19,13 +22,17 @@ macro_rules! if_not_wasm {
     )*}
 }
 
+// restores the sessions of the pubsub transports after reconnecting
+#[cfg(any(feature = "ws", all(target_family = "unix", feature = "ipc")))]
+mod reconnect;
+
 #[cfg(all(target_family = "unix", feature = "ipc"))]
 mod ipc;
 #[cfg(all(target_family = "unix", feature = "ipc"))]
 pub use ipc::Ipc;
 
 mod http;
-pub use self::http::{ClientError as HttpClientError, Provider as Http};
+pub use self::http::{ClientError as HttpClientError, HttpBuilder, Provider as Http};
 
 #[cfg(feature = "ws")]
 mod ws;
@@ -36,5 +43,122 @@ mod quorum;
 pub(crate) use quorum::JsonRpcClientWrapper;
 pub use quorum::{Quorum, QuorumProvider, WeightedProvider};
 
+mod fallback;
+pub use fallback::{EndpointStats, FallbackError, FallbackProvider, FallbackProviderBuilder};
+
+mod retry;
+pub use retry::{
+    is_retryable_rpc_error, HttpRateLimitRetryPolicy, RetryClient, RetryClientBuilder,
+    RetryClientError, RetryPolicy,
+};
+
+mod intercept;
+pub use intercept::{InterceptedRequest, Interceptor, InterceptorClient};
+
+mod cache;
+#[cfg(not(target_arch = "wasm32"))]
+pub use cache::DiskCache;
+pub use cache::{CachingClient, MemoryCache, ResponseCache};
+
 mod mock;
-pub use mock::{MockError, MockProvider};
+pub use mock::{Expectation, MockError, MockProvider};
+
+#[cfg(not(target_arch = "wasm32"))]
+mod replay;
+#[cfg(not(target_arch = "wasm32"))]
+pub use replay::{
+    RecordedRequest, RecordedResponse, RecordingProvider, ReplayError, ReplayProvider,
+};
+
+use crate::ProviderError;
+
+/// Returns the JSON-RPC error the node responded with, if any
+pub(crate) fn json_rpc_error<'a>(
+    err: &'a (dyn std::error::Error + Send + Sync + 'static),
+) -> Option<&'a JsonRpcError> {
+    if let Some(err) = err.downcast_ref::<JsonRpcError>() {
+        return Some(err)
+    }
+    if let Some(HttpClientError::JsonRpcError(err)) = err.downcast_ref::<HttpClientError>() {
+        return Some(err)
+    }
+    if let Some(MockError::JsonRpcError(err)) = err.downcast_ref::<MockError>() {
+        return Some(err)
+    }
+    // the errors of the wrapping transports contain the errors of the transports they wrap
+    if let Some(err) = err.downcast_ref::<ProviderError>() {
+        return err.as_error_response()
+    }
+    match err.downcast_ref::<RetryClientError>() {
+        Some(RetryClientError::ProviderError(err)) |
+        Some(RetryClientError::RetriesExhausted { source: err, .. }) => {
+            return err.as_error_response()
+        }
+        Some(RetryClientError::SerdeJson(_)) => return None,
+        None => {}
+    }
+    if let Some(FallbackError::AllProvidersFailed { errors }) = err.downcast_ref::<FallbackError>()
+    {
+        return errors.last()?.as_error_response()
+    }
+    // only if every endpoint failed, otherwise the responses just did not agree
+    if let Some(quorum::QuorumError::NoQuorumReached { values, errors }) =
+        err.downcast_ref::<quorum::QuorumError>()
+    {
+        return if values.is_empty() { errors.first()?.as_error_response() } else { None }
+    }
+    ws_json_rpc_error(err)
+        .or_else(|| ipc_json_rpc_error(err))
+        .or_else(|| replay_json_rpc_error(err))
+}
+
+#[cfg(feature = "ws")]
+fn ws_json_rpc_error<'a>(
+    err: &'a (dyn std::error::Error + Send + Sync + 'static),
+) -> Option<&'a JsonRpcError> {
+    match err.downcast_ref::<WsClientError>() {
+        Some(WsClientError::JsonRpcError(err)) => Some(err),
+        _ => None,
+    }
+}
+
+#[cfg(not(feature = "ws"))]
+fn ws_json_rpc_error<'a>(
+    _err: &'a (dyn std::error::Error + Send + Sync + 'static),
+) -> Option<&'a JsonRpcError> {
+    None
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn replay_json_rpc_error<'a>(
+    err: &'a (dyn std::error::Error + Send + Sync + 'static),
+) -> Option<&'a JsonRpcError> {
+    match err.downcast_ref::<ReplayError>() {
+        Some(ReplayError::JsonRpcError(err)) => Some(err),
+        _ => None,
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn replay_json_rpc_error<'a>(
+    _err: &'a (dyn std::error::Error + Send + Sync + 'static),
+) -> Option<&'a JsonRpcError> {
+    None
+}
+
+#[cfg(all(target_family = "unix", feature = "ipc"))]
+fn ipc_json_rpc_error<'a>(
+    err: &'a (dyn std::error::Error + Send + Sync + 'static),
+) -> Option<&'a JsonRpcError> {
+    match err.downcast_ref::<ipc::IpcError>() {
+        Some(ipc::IpcError::JsonRpcError(err)) => Some(err),
+        _ => None,
+    }
+}
+
+#[cfg(not(all(target_family = "unix", feature = "ipc")))]
+fn ipc_json_rpc_error<'a>(
+    _err: &'a (dyn std::error::Error + Send + Sync + 'static),
+) -> Option<&'a JsonRpcError> {
+    None
+}
@@ -337,6 +337,18 @@ impl From<QuorumError> for ProviderError {
 #[cfg_attr(not(target_arch = "wasm32"), async_trait)]
 pub trait JsonRpcClientWrapper: Send + Sync + fmt::Debug {
     async fn request(&self, method: &str, params: Value) -> Result<Value, ProviderError>;
+
+    /// See [`JsonRpcClient::supports_pubsub`]
+    fn supports_pubsub(&self) -> bool;
+
+    /// See [`JsonRpcClient::subscribe_notifications`]
+    fn subscribe_notifications(
+        &self,
+        id: U256,
+    ) -> Option<Result<crate::NotificationStream, ProviderError>>;
+
+    /// See [`JsonRpcClient::unsubscribe_notifications`]
+    fn unsubscribe_notifications(&self, id: U256) -> Result<(), ProviderError>;
 }
 type NotificationStream = Box<dyn futures_core::Stream<Item = Value> + Send + Unpin + 'static>;
 
@@ -354,6 +366,21 @@ impl<C: JsonRpcClient> JsonRpcClientWrapper for C {
     async fn request(&self, method: &str, params: Value) -> Result<Value, ProviderError> {
         Ok(JsonRpcClient::request(self, method, params).await.map_err(C::Error::into)?)
     }
+
+    fn supports_pubsub(&self) -> bool {
+        JsonRpcClient::supports_pubsub(self)
+    }
+
+    fn subscribe_notifications(
+        &self,
+        id: U256,
+    ) -> Option<Result<crate::NotificationStream, ProviderError>> {
+        JsonRpcClient::subscribe_notifications(self, id).map(|res| res.map_err(C::Error::into))
+    }
+
+    fn unsubscribe_notifications(&self, id: U256) -> Result<(), ProviderError> {
+        JsonRpcClient::unsubscribe_notifications(self, id).map_err(C::Error::into)
+    }
 }
 #[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
 #[cfg_attr(not(target_arch = "wasm32"), async_trait)]
@@ -361,6 +388,21 @@ impl JsonRpcClientWrapper for Box<dyn JsonRpcClientWrapper> {
     async fn request(&self, method: &str, params: Value) -> Result<Value, ProviderError> {
         self.as_ref().request(method, params).await
     }
+
+    fn supports_pubsub(&self) -> bool {
+        self.as_ref().supports_pubsub()
+    }
+
+    fn subscribe_notifications(
+        &self,
+        id: U256,
+    ) -> Option<Result<crate::NotificationStream, ProviderError>> {
+        self.as_ref().subscribe_notifications(id)
+    }
+
+    fn unsubscribe_notifications(&self, id: U256) -> Result<(), ProviderError> {
+        self.as_ref().unsubscribe_notifications(id)
+    }
 }
 
 #[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
@@ -369,6 +411,21 @@ impl JsonRpcClientWrapper for Box<dyn PubsubClientWrapper> {
     async fn request(&self, method: &str, params: Value) -> Result<Value, ProviderError> {
         self.as_ref().request(method, params).await
     }
+
+    fn supports_pubsub(&self) -> bool {
+        self.as_ref().supports_pubsub()
+    }
+
+    fn subscribe_notifications(
+        &self,
+        id: U256,
+    ) -> Option<Result<crate::NotificationStream, ProviderError>> {
+        self.as_ref().subscribe_notifications(id)
+    }
+
+    fn unsubscribe_notifications(&self, id: U256) -> Result<(), ProviderError> {
+        self.as_ref().unsubscribe_notifications(id)
+    }
 }
 
 impl<C: PubsubClient> PubsubClientWrapper for C
@@ -424,6 +481,35 @@ where
         let value = QuorumRequest::new(self, requests).await?;
         Ok(serde_json::from_value(value)?)
     }
+
+    /// Subscriptions are supported if every provider supports them
+    fn supports_pubsub(&self) -> bool {
+        self.providers.iter().all(|provider| provider.inner.supports_pubsub())
+    }
+
+    /// Yields the notifications on which the providers reached quorum
+    fn subscribe_notifications(
+        &self,
+        id: U256,
+    ) -> Option<Result<crate::NotificationStream, Self::Error>> {
+        let mut notifications = Vec::with_capacity(self.providers.len());
+        for provider in &self.providers {
+            let weight = provider.weight;
+            let fut = match provider.inner.subscribe_notifications(id)? {
+                Ok(stream) => stream.map(move |val| (val, weight)),
+                Err(err) => return Some(Err(err)),
+            };
+            notifications.push(Box::pin(fut) as WeightedNotificationStream);
+        }
+        Some(Ok(Box::pin(QuorumStream::new(self.quorum_weight, notifications))))
+    }
+
+    fn unsubscribe_notifications(&self, id: U256) -> Result<(), Self::Error> {
+        for provider in &self.providers {
+            provider.inner.unsubscribe_notifications(id)?;
+        }
+        Ok(())
+    }
 }
 
 // A stream that returns a value and the weight of its provider
@@ -528,8 +614,72 @@ where
 #[cfg(not(target_arch = "wasm32"))]
 mod tests {
     use super::{Quorum, QuorumProvider, WeightedProvider};
-    use crate::{Middleware, MockProvider, Provider};
-    use ethers_core::types::U64;
+    use crate::{JsonRpcClient, Middleware, MockError, MockProvider, NotificationStream, Provider};
+    use async_trait::async_trait;
+    use ethers_core::types::{U256, U64};
+    use futures_util::StreamExt;
+    use serde::{de::DeserializeOwned, Serialize};
+    use serde_json::json;
+    use std::sync::{Arc, Mutex};
+
+    /// A pubsub transport whose subscriptions yield `0x1`
+    #[derive(Debug, Default)]
+    struct Pubsub {
+        mock: MockProvider,
+        unsubscribed: Mutex<Vec<U256>>,
+    }
+
+    #[async_trait]
+    impl JsonRpcClient for Pubsub {
+        type Error = MockError;
+
+        async fn request<T: Serialize + Send + Sync, R: DeserializeOwned>(
+            &self,
+            method: &str,
+            params: T,
+        ) -> Result<R, Self::Error> {
+            self.mock.request(method, params).await
+        }
+
+        fn supports_pubsub(&self) -> bool {
+            true
+        }
+
+        fn subscribe_notifications(
+            &self,
+            _id: U256,
+        ) -> Option<Result<NotificationStream, Self::Error>> {
+            Some(Ok(Box::pin(futures_util::stream::iter(vec![json!("0x1")]))))
+        }
+
+        fn unsubscribe_notifications(&self, id: U256) -> Result<(), Self::Error> {
+            self.unsubscribed.lock().unwrap().push(id);
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn forwards_subscriptions() {
+        let providers = vec![Arc::new(Pubsub::default()), Arc::new(Pubsub::default())];
+        let quorum = QuorumProvider::builder()
+            .add_providers(providers.iter().cloned().map(WeightedProvider::new))
+            .quorum(Quorum::All)
+            .build();
+        assert!(quorum.supports_pubsub());
+        let rpc_only = QuorumProvider::builder()
+            .add_provider(WeightedProvider::new(MockProvider::new()))
+            .build();
+        assert!(!rpc_only.supports_pubsub());
+
+        let id = U256::from(3);
+        let mut notifications = quorum.subscribe_notifications(id).unwrap().unwrap();
+        assert_eq!(notifications.next().await.unwrap(), json!("0x1"));
+
+        quorum.unsubscribe_notifications(id).unwrap();
+        for provider in providers {
+            assert_eq!(*provider.unsubscribed.lock().unwrap(), vec![id]);
+        }
+    }
 
     async fn test_quorum(q: Quorum) {
         let num = 5u64;
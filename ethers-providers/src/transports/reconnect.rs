@@ -0,0 +1,215 @@
+//! Session bookkeeping of the pubsub transports which re-dial the node when the connection drops
+use super::common::{Request, Response, ResponseData};
+use ethers_core::types::U256;
+use std::{
+    collections::BTreeMap,
+    future::Future,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+type ConnectFuture<S, E> = Pin<Box<dyn Future<Output = Result<S, E>> + Send>>;
+type Connect<S, E> = Box<dyn Fn() -> ConnectFuture<S, E> + Send + Sync>;
+
+/// The delay before the first reconnection attempt, doubled with every failed attempt
+const RECONNECT_INITIAL_BACKOFF: Duration = Duration::from_millis(100);
+/// The maximum delay between two reconnection attempts
+const RECONNECT_MAX_BACKOFF: Duration = Duration::from_secs(10);
+
+/// The parts of an outgoing request which are inspected to restore subscriptions after a
+/// reconnect
+#[derive(Debug, serde::Deserialize)]
+struct RequestInfo {
+    method: String,
+    #[serde(default)]
+    params: serde_json::Value,
+}
+
+/// A response after it was recorded by [`Reconnect`]
+pub(crate) enum Handled {
+    /// The response to a request of the client, which is forwarded to it
+    Forward(Response<serde_json::Value>),
+    /// The response to a subscription re-issued on the new connection
+    Resubscribed,
+    /// The subscription with the client side id could not be re-issued, its stream should end
+    ResubscribeFailed(U256),
+}
+
+/// Everything a server needs to restore the session on a new connection.
+///
+/// Subscriptions are handed out to the client under a client side id, which initially is the
+/// id assigned by the node. After a reconnect the subscriptions are re-issued and the new
+/// server side ids are mapped to the client side ids.
+pub(crate) struct Reconnect<S, E> {
+    connect: Connect<S, E>,
+    /// The maximum number of consecutive failed reconnection attempts
+    pub(crate) max_attempts: usize,
+    /// The request id counter shared with the client
+    id: Arc<AtomicU64>,
+    /// Requests without a response, by their (first) request id
+    in_flight: BTreeMap<u64, String>,
+    /// `eth_subscribe` requests of the client without a response, by request id
+    subscribe_requests: BTreeMap<u64, serde_json::Value>,
+    /// `eth_subscribe` requests re-issued after a reconnect, by request id
+    resubscribe_requests: BTreeMap<u64, U256>,
+    /// The `eth_subscribe` params of the active subscriptions, by client side id
+    subscriptions: BTreeMap<U256, serde_json::Value>,
+    /// The client side ids of the active subscriptions, by server side id
+    server_ids: BTreeMap<U256, U256>,
+}
+
+impl<S, E> Reconnect<S, E> {
+    pub(crate) fn new<F, Fut>(connect: F, max_attempts: usize, id: Arc<AtomicU64>) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<S, E>> + Send + 'static,
+    {
+        Self {
+            connect: Box::new(move || -> ConnectFuture<S, E> { Box::pin(connect()) }),
+            max_attempts,
+            id,
+            in_flight: BTreeMap::default(),
+            subscribe_requests: BTreeMap::default(),
+            resubscribe_requests: BTreeMap::default(),
+            subscriptions: BTreeMap::default(),
+            server_ids: BTreeMap::default(),
+        }
+    }
+
+    /// Dials a new connection
+    pub(crate) fn connect(&self) -> ConnectFuture<S, E> {
+        (self.connect)()
+    }
+
+    /// Records an outgoing request so it can be sent again after a reconnect, and translates the
+    /// client side subscription id of `eth_unsubscribe` requests.
+    pub(crate) fn track_request(
+        &mut self,
+        id: u64,
+        request: String,
+    ) -> Result<String, serde_json::Error> {
+        let info: RequestInfo = serde_json::from_str(&request)?;
+        let request = match info.method.as_str() {
+            "eth_subscribe" => {
+                self.subscribe_requests.insert(id, info.params);
+                request
+            }
+            "eth_unsubscribe" => {
+                let client_id: U256 = match serde_json::from_value::<[U256; 1]>(info.params) {
+                    Ok([client_id]) => client_id,
+                    Err(_) => return Ok(request),
+                };
+                self.subscriptions.remove(&client_id);
+                let server_id = self
+                    .server_ids
+                    .iter()
+                    .find(|(_, client)| **client == client_id)
+                    .map(|(server, _)| *server);
+                match server_id {
+                    Some(server_id) => {
+                        self.server_ids.remove(&server_id);
+                        serde_json::to_string(&Request::new(id, "eth_unsubscribe", [server_id]))?
+                    }
+                    None => request,
+                }
+            }
+            _ => request,
+        };
+        self.in_flight.insert(id, request.clone());
+        Ok(request)
+    }
+
+    /// Records an outgoing batch request, by the id of its first request
+    pub(crate) fn track_batch(&mut self, first_id: u64, request: String) {
+        self.in_flight.insert(first_id, request);
+    }
+
+    /// Records that the node rejected the batch request with the id of its first request
+    pub(crate) fn reject_batch(&mut self, first_id: u64) {
+        self.in_flight.remove(&first_id);
+    }
+
+    /// Returns the client side id of a subscription, or `None` if the notification belongs to
+    /// a subscription which was not re-issued yet.
+    pub(crate) fn client_id(&self, server_id: U256) -> Option<U256> {
+        match self.server_ids.get(&server_id) {
+            Some(client_id) => Some(*client_id),
+            None if self.subscriptions.contains_key(&server_id) => None,
+            // subscriptions which were not created via `eth_subscribe` are not remapped
+            None => Some(server_id),
+        }
+    }
+
+    /// Returns a client side id for a new subscription, which is the server side id unless a
+    /// subscription of a previous connection already uses it.
+    fn new_client_id(&self, server_id: U256) -> U256 {
+        let mut client_id = server_id;
+        while self.subscriptions.contains_key(&client_id) {
+            client_id = client_id.overflowing_add(U256::one()).0;
+        }
+        client_id
+    }
+
+    /// Records a response. The subscription id of a new subscription is replaced by its client
+    /// side id before the response is forwarded to the client.
+    pub(crate) fn handle_response(
+        &mut self,
+        mut resp: Response<serde_json::Value>,
+    ) -> Result<Handled, serde_json::Error> {
+        self.in_flight.remove(&resp.id);
+
+        if let Some(client_id) = self.resubscribe_requests.remove(&resp.id) {
+            return match resp.data.into_result().map(serde_json::from_value::<U256>) {
+                Ok(Ok(server_id)) => {
+                    if self.subscriptions.contains_key(&client_id) {
+                        self.server_ids.insert(server_id, client_id);
+                    }
+                    Ok(Handled::Resubscribed)
+                }
+                res => {
+                    tracing::error!("Could not resubscribe {:?}: {:?}", client_id, res);
+                    self.subscriptions.remove(&client_id);
+                    Ok(Handled::ResubscribeFailed(client_id))
+                }
+            }
+        }
+
+        if let Some(params) = self.subscribe_requests.remove(&resp.id) {
+            if let ResponseData::Success { result } = &mut resp.data {
+                if let Ok(server_id) = serde_json::from_value::<U256>(result.clone()) {
+                    let client_id = self.new_client_id(server_id);
+                    self.subscriptions.insert(client_id, params);
+                    self.server_ids.insert(server_id, client_id);
+                    *result = serde_json::to_value(client_id)?;
+                }
+            }
+        }
+        Ok(Handled::Forward(resp))
+    }
+
+    /// Returns the messages to send on a new connection: the requests which were in flight and
+    /// the `eth_subscribe` requests re-issuing the active subscriptions
+    pub(crate) fn resubscribe(&mut self) -> Result<Vec<String>, serde_json::Error> {
+        // the ids of the previous connection are void
+        self.server_ids.clear();
+        self.resubscribe_requests.clear();
+
+        let mut messages = self.in_flight.values().cloned().collect::<Vec<_>>();
+        for (client_id, params) in self.subscriptions.iter() {
+            let id = self.id.fetch_add(1, Ordering::SeqCst);
+            self.resubscribe_requests.insert(id, *client_id);
+            messages.push(serde_json::to_string(&Request::new(id, "eth_subscribe", params))?);
+        }
+        Ok(messages)
+    }
+
+    /// Returns the delay before the `attempt`th reconnection attempt
+    pub(crate) fn backoff(attempt: usize) -> Duration {
+        let exp = attempt.saturating_sub(1).min(16) as u32;
+        (RECONNECT_INITIAL_BACKOFF * 2u32.pow(exp)).min(RECONNECT_MAX_BACKOFF)
+    }
+}
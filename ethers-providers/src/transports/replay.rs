@@ -0,0 +1,378 @@
+//! [`JsonRpcClient`] implementations which record the requests to a real node and replay them,
+//! to run tests deterministically and offline.
+use super::common::JsonRpcError;
+use crate::{provider::ProviderError, JsonRpcClient, NotificationStream};
+
+use async_trait::async_trait;
+use ethers_core::types::U256;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt::Debug,
+    fs,
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    path::Path,
+    sync::Mutex,
+};
+use thiserror::Error;
+
+/// A request recorded by a [`RecordingProvider`] together with the node's response
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RecordedRequest {
+    /// The JSON-RPC method
+    pub method: String,
+    /// The parameters of the request, `null` for requests without parameters
+    pub params: Value,
+    /// The result or the JSON-RPC error the node responded with
+    #[serde(flatten)]
+    pub response: RecordedResponse,
+}
+
+/// The response to a [`RecordedRequest`]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "lowercase")]
+pub enum RecordedResponse {
+    /// The request succeeded
+    Result(Value),
+    /// The node responded with a JSON-RPC error
+    Error(JsonRpcError),
+}
+
+impl RecordedRequest {
+    fn key(&self) -> String {
+        request_key(&self.method, &self.params)
+    }
+}
+
+/// Returns the key by which recorded requests are matched
+fn request_key(method: &str, params: &Value) -> String {
+    format!("{}:{}", method, params)
+}
+
+/// A [`JsonRpcClient`] which wraps another transport and writes every request with its
+/// response to a file, one JSON object per line. The file can be served by a
+/// [`ReplayProvider`].
+///
+/// Responses which are JSON-RPC errors are recorded as well, other errors of the transport are
+/// only returned.
+///
+/// # Example
+///
+/// ```no_run
+/// use ethers_providers::{Http, Middleware, Provider, RecordingProvider};
+/// use std::str::FromStr;
+///
+/// # async fn foo() -> Result<(), Box<dyn std::error::Error>> {
+/// let http = Http::from_str("http://localhost:8545")?;
+/// let provider = Provider::new(RecordingProvider::new(http, "tests/fixtures/rpc.jsonl")?);
+/// let block = provider.get_block_number().await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct RecordingProvider<T> {
+    inner: T,
+    file: Mutex<BufWriter<fs::File>>,
+}
+
+impl<T: JsonRpcClient> RecordingProvider<T> {
+    /// Creates a provider which records to `path`, replacing the file if it exists
+    pub fn new(inner: T, path: impl AsRef<Path>) -> io::Result<Self> {
+        let file = fs::File::create(path)?;
+        Ok(Self { inner, file: Mutex::new(BufWriter::new(file)) })
+    }
+
+    /// Creates a provider which appends its records to `path`
+    pub fn append(inner: T, path: impl AsRef<Path>) -> io::Result<Self> {
+        let file = fs::OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self { inner, file: Mutex::new(BufWriter::new(file)) })
+    }
+
+    /// Returns a reference to the wrapped transport
+    pub fn inner(&self) -> &T {
+        &self.inner
+    }
+
+    /// Appends the request and its response to the file
+    fn record(
+        &self,
+        method: &str,
+        params: Value,
+        response: &Result<Value, JsonRpcError>,
+    ) -> Result<(), ProviderError> {
+        let response = match response {
+            Ok(value) => RecordedResponse::Result(value.clone()),
+            Err(err) => RecordedResponse::Error(err.clone()),
+        };
+        let record = RecordedRequest { method: method.to_string(), params, response };
+
+        let mut file = self.file.lock().unwrap();
+        serde_json::to_writer(&mut *file, &record)?;
+        file.write_all(b"\n")
+            .and_then(|_| file.flush())
+            .map_err(|err| ProviderError::CustomError(format!("could not record request: {}", err)))
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl<T: JsonRpcClient> JsonRpcClient for RecordingProvider<T> {
+    type Error = ProviderError;
+
+    async fn request<A, R>(&self, method: &str, params: A) -> Result<R, Self::Error>
+    where
+        A: Debug + Serialize + Send + Sync,
+        R: DeserializeOwned,
+    {
+        let value = serde_json::to_value(&params)?;
+        let res = self
+            .inner
+            .request::<_, Value>(method, params)
+            .await
+            .map_err(Into::<ProviderError>::into);
+        match res {
+            Ok(response) => {
+                self.record(method, value, &Ok(response.clone()))?;
+                Ok(serde_json::from_value(response)?)
+            }
+            Err(err) => {
+                if let Some(rpc_err) = err.as_error_response() {
+                    self.record(method, value, &Err(rpc_err.clone()))?;
+                }
+                Err(err)
+            }
+        }
+    }
+
+    async fn request_batch(
+        &self,
+        requests: Vec<(String, Value)>,
+    ) -> Result<Vec<Result<Value, JsonRpcError>>, Self::Error> {
+        let responses = self
+            .inner
+            .request_batch(requests.clone())
+            .await
+            .map_err(Into::<ProviderError>::into)?;
+        for ((method, params), response) in requests.into_iter().zip(&responses) {
+            self.record(&method, params, response)?;
+        }
+        Ok(responses)
+    }
+
+    fn supports_pubsub(&self) -> bool {
+        self.inner.supports_pubsub()
+    }
+
+    fn subscribe_notifications(&self, id: U256) -> Option<Result<NotificationStream, Self::Error>> {
+        self.inner.subscribe_notifications(id).map(|res| res.map_err(Into::into))
+    }
+
+    fn unsubscribe_notifications(&self, id: U256) -> Result<(), Self::Error> {
+        self.inner.unsubscribe_notifications(id).map_err(Into::into)
+    }
+}
+
+/// Errors of the [`ReplayProvider`]
+#[derive(Error, Debug)]
+pub enum ReplayError {
+    /// The request was not recorded
+    #[error("request was not recorded: {method} {params}")]
+    NotRecorded {
+        /// The JSON-RPC method
+        method: String,
+        /// The parameters of the request
+        params: Value,
+    },
+
+    /// The recorded response is a JSON-RPC error
+    #[error(transparent)]
+    JsonRpcError(#[from] JsonRpcError),
+
+    /// The request or the response could not be (de)serialized
+    #[error(transparent)]
+    SerdeJson(#[from] serde_json::Error),
+}
+
+impl From<ReplayError> for ProviderError {
+    fn from(src: ReplayError) -> Self {
+        ProviderError::JsonRpcClientError(Box::new(src))
+    }
+}
+
+/// A [`JsonRpcClient`] which serves the responses recorded by a [`RecordingProvider`], by
+/// matching the method and the parameters of each request.
+///
+/// Responses to a request which was recorded several times are served in the order they were
+/// recorded, once they are used up the last one is repeated. This keeps polling requests like
+/// `eth_blockNumber` deterministic. Requests which were not recorded fail with
+/// [`ReplayError::NotRecorded`].
+///
+/// # Example
+///
+/// ```no_run
+/// use ethers_providers::{Middleware, Provider, ReplayProvider};
+///
+/// # async fn foo() -> Result<(), Box<dyn std::error::Error>> {
+/// let provider = Provider::new(ReplayProvider::from_file("tests/fixtures/rpc.jsonl")?);
+/// let block = provider.get_block_number().await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct ReplayProvider {
+    responses: Mutex<HashMap<String, VecDeque<RecordedResponse>>>,
+}
+
+impl ReplayProvider {
+    /// Creates a provider which serves the given records
+    pub fn new(records: impl IntoIterator<Item = RecordedRequest>) -> Self {
+        let mut responses: HashMap<_, VecDeque<_>> = HashMap::new();
+        for record in records {
+            responses.entry(record.key()).or_default().push_back(record.response);
+        }
+        Self { responses: Mutex::new(responses) }
+    }
+
+    /// Creates a provider which serves the records of a file written by a
+    /// [`RecordingProvider`]
+    pub fn from_file(path: impl AsRef<Path>) -> io::Result<Self> {
+        let file = BufReader::new(fs::File::open(path)?);
+        let mut records = Vec::new();
+        for line in file.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue
+            }
+            records.push(serde_json::from_str(&line)?);
+        }
+        Ok(Self::new(records))
+    }
+
+    /// Returns the next recorded response to the request
+    fn response(&self, method: &str, params: &Value) -> Result<Value, ReplayError> {
+        let mut responses = self.responses.lock().unwrap();
+        let not_recorded =
+            || ReplayError::NotRecorded { method: method.to_string(), params: params.clone() };
+        let queue = responses.get_mut(&request_key(method, params)).ok_or_else(not_recorded)?;
+        let response = if queue.len() > 1 { queue.pop_front() } else { queue.front().cloned() };
+        match response.ok_or_else(not_recorded)? {
+            RecordedResponse::Result(value) => Ok(value),
+            RecordedResponse::Error(err) => Err(ReplayError::JsonRpcError(err)),
+        }
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl JsonRpcClient for ReplayProvider {
+    type Error = ReplayError;
+
+    async fn request<A, R>(&self, method: &str, params: A) -> Result<R, Self::Error>
+    where
+        A: Debug + Serialize + Send + Sync,
+        R: DeserializeOwned,
+    {
+        let params = serde_json::to_value(params)?;
+        let response = self.response(method, &params)?;
+        Ok(serde_json::from_value(response)?)
+    }
+
+    async fn request_batch(
+        &self,
+        requests: Vec<(String, Value)>,
+    ) -> Result<Vec<Result<Value, JsonRpcError>>, Self::Error> {
+        requests
+            .iter()
+            .map(|(method, params)| match self.response(method, params) {
+                Ok(value) => Ok(Ok(value)),
+                Err(ReplayError::JsonRpcError(err)) => Ok(Err(err)),
+                Err(err) => Err(err),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Middleware, MockProvider, Provider};
+    use ethers_core::types::{Address, U256, U64};
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn replays_recorded_requests() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("rpc.jsonl");
+
+        let mock = MockProvider::new();
+        let provider = Provider::new(RecordingProvider::new(mock.clone(), &path).unwrap());
+        // responses are popped from the back
+        mock.push(U64::from(2)).unwrap();
+        mock.push(U256::from(100)).unwrap();
+        mock.push(U64::from(1)).unwrap();
+        assert_eq!(provider.get_block_number().await.unwrap(), U64::from(1));
+        assert_eq!(provider.get_balance(Address::zero(), None).await.unwrap(), U256::from(100));
+        assert_eq!(provider.get_block_number().await.unwrap(), U64::from(2));
+
+        // requests are matched by method and params instead of their order
+        let provider = Provider::new(ReplayProvider::from_file(&path).unwrap());
+        assert_eq!(provider.get_balance(Address::zero(), None).await.unwrap(), U256::from(100));
+        assert_eq!(provider.get_block_number().await.unwrap(), U64::from(1));
+        assert_eq!(provider.get_block_number().await.unwrap(), U64::from(2));
+        // the last response is repeated
+        assert_eq!(provider.get_block_number().await.unwrap(), U64::from(2));
+
+        let err = provider.get_chainid().await.unwrap_err();
+        assert_eq!(err.to_string(), "request was not recorded: eth_chainId null");
+    }
+
+    #[tokio::test]
+    async fn replays_errors_and_batches() {
+        let error = JsonRpcError {
+            code: 3,
+            message: "execution reverted".to_string(),
+            data: Some(json!("0x")),
+        };
+        let provider = Provider::new(ReplayProvider::new(vec![
+            RecordedRequest {
+                method: "eth_chainId".to_string(),
+                params: Value::Null,
+                response: RecordedResponse::Result(json!("0x1")),
+            },
+            RecordedRequest {
+                method: "eth_call".to_string(),
+                params: json!([{}, "latest"]),
+                response: RecordedResponse::Error(error.clone()),
+            },
+        ]));
+
+        let client = provider.as_ref();
+        let err = client.request::<_, Value>("eth_call", [json!({}), json!("latest")]).await;
+        let err = ProviderError::from(err.unwrap_err());
+        assert_eq!(err.as_error_response().unwrap().message, error.message);
+
+        let responses = client
+            .request_batch(vec![
+                ("eth_chainId".to_string(), Value::Null),
+                ("eth_call".to_string(), json!([{}, "latest"])),
+            ])
+            .await
+            .unwrap();
+        assert_eq!(responses[0].as_ref().unwrap(), &json!("0x1"));
+        assert_eq!(responses[1].as_ref().unwrap_err().code, 3);
+    }
+
+    #[test]
+    fn serializes_records() {
+        let record = RecordedRequest {
+            method: "eth_blockNumber".to_string(),
+            params: Value::Null,
+            response: RecordedResponse::Result(json!("0x1")),
+        };
+        assert_eq!(
+            serde_json::to_string(&record).unwrap(),
+            r#"{"method":"eth_blockNumber","params":null,"result":"0x1"}"#
+        );
+    }
+}
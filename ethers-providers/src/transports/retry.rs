@@ -0,0 +1,597 @@
+//! A [`JsonRpcClient`] implementation that retries requests filtered by a [`RetryPolicy`]
+//! with an exponential backoff.
+use super::{common::JsonRpcError, http::ClientError};
+use crate::{provider::ProviderError, JsonRpcClient, NotificationStream};
+
+use async_trait::async_trait;
+use ethers_core::types::U256;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+use std::{
+    collections::hash_map::RandomState,
+    fmt::Debug,
+    future::Future,
+    hash::{BuildHasher, Hasher},
+    sync::atomic::{AtomicU32, Ordering},
+    time::Duration,
+};
+use thiserror::Error;
+use tracing::trace;
+
+#[cfg(not(target_arch = "wasm32"))]
+use futures_timer::Delay;
+#[cfg(target_arch = "wasm32")]
+use wasm_timer::Delay;
+
+/// The average compute unit cost of a single request, used to translate the
+/// `compute_units_per_second` budget into a number of requests per second.
+///
+/// Providers weigh their methods differently (e.g. `eth_getBlockByNumber` is 16 CU and
+/// `eth_getStorageAt` is 17 CU on Alchemy), so this is only an approximation.
+const AVG_COST: u64 = 17;
+
+/// Defines which errors returned by the inner [`JsonRpcClient`] are considered transient
+/// and should be retried by the [`RetryClient`].
+pub trait RetryPolicy<E>: Send + Sync + Debug {
+    /// Whether to retry the request based on the given `error`
+    fn should_retry(&self, error: &E) -> bool;
+
+    /// Providers may include the backoff they expect in the error response directly
+    fn backoff_hint(&self, _error: &E) -> Option<Duration> {
+        None
+    }
+}
+
+/// A [`RetryPolicy`] for the [`Http`](crate::Http) transport which retries on timeouts,
+/// connection errors, HTTP 429/502/503/504 and the JSON-RPC errors nodes return when they are
+/// rate limited or lagging behind their load balancer.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct HttpRateLimitRetryPolicy;
+
+impl RetryPolicy<ClientError> for HttpRateLimitRetryPolicy {
+    fn should_retry(&self, error: &ClientError) -> bool {
+        match error {
+            ClientError::ReqwestError(err) => {
+                is_transport_error(err) || err.status().map(is_retryable_status).unwrap_or_default()
+            }
+            ClientError::HttpStatus { status, .. } => is_retryable_status(*status),
+            ClientError::JsonRpcError(err) => is_retryable_rpc_error(err),
+            ClientError::SerdeJson { .. } |
+            ClientError::MissingBatchResponse(_) |
+            ClientError::InvalidAuthorization(_) => false,
+        }
+    }
+
+    fn backoff_hint(&self, error: &ClientError) -> Option<Duration> {
+        if let ClientError::JsonRpcError(JsonRpcError { data: Some(data), .. }) = error {
+            // infura includes the backoff in the `rate` object of the error data:
+            // `{"rate": {"allowed_rps": 1, "backoff_seconds": 30, "current_rps": 1.1}}`
+            let backoff_seconds = data.get("rate")?.get("backoff_seconds")?;
+            if let Some(secs) = backoff_seconds.as_u64() {
+                return Some(Duration::from_secs(secs))
+            }
+            if let Some(secs) = backoff_seconds.as_f64() {
+                return Some(Duration::from_secs_f64(secs.max(0.)))
+            }
+        }
+        None
+    }
+}
+
+/// Returns true if the request failed before a response was received
+#[cfg(not(target_arch = "wasm32"))]
+fn is_transport_error(err: &reqwest::Error) -> bool {
+    err.is_timeout() || err.is_connect()
+}
+
+/// Returns true if the request failed before a response was received
+#[cfg(target_arch = "wasm32")]
+fn is_transport_error(err: &reqwest::Error) -> bool {
+    err.is_timeout()
+}
+
+/// Returns true if the HTTP status indicates a transient failure
+fn is_retryable_status(status: reqwest::StatusCode) -> bool {
+    matches!(status.as_u16(), 429 | 502 | 503 | 504)
+}
+
+/// Returns true if the JSON-RPC error is one of the known "rate limited" or "lagging node"
+/// errors returned by nodes and node providers.
+pub fn is_retryable_rpc_error(err: &JsonRpcError) -> bool {
+    // alchemy throws it this way
+    if err.code == 429 {
+        return true
+    }
+    // infura: "daily request count exceeded, request rate limited"
+    if err.code == -32005 {
+        return true
+    }
+    let msg = err.message.to_lowercase();
+    // geth behind a load balancer may not have seen the requested block yet
+    msg.contains("header not found") ||
+        msg.contains("rate limit") ||
+        msg.contains("too many requests") ||
+        // alchemy: "Your app has exceeded its compute units per second capacity"
+        msg.contains("compute units per second")
+}
+
+/// A [`JsonRpcClient`] which wraps another transport and retries failed requests with an
+/// exponential backoff, as long as the [`RetryPolicy`] deems the error to be transient.
+///
+/// The backoff before the `n`-th retry is `initial_backoff * 2^(n - 1)`, capped at
+/// `max_backoff` and increased by a random `jitter` fraction. If the node provider told us how
+/// long to back off, that duration is used instead. When a `compute_units_per_second` budget is
+/// set, requests which queued up behind many others additionally wait for the budget to free
+/// up.
+///
+/// # Example
+///
+/// ```no_run
+/// use ethers_core::types::U64;
+/// use ethers_providers::{Http, HttpRateLimitRetryPolicy, Middleware, Provider, RetryClientBuilder};
+/// use std::{str::FromStr, time::Duration};
+///
+/// # async fn foo() -> Result<(), Box<dyn std::error::Error>> {
+/// let http = Http::from_str("http://localhost:8545")?;
+/// let client = RetryClientBuilder::default()
+///     .max_retries(10)
+///     .initial_backoff(Duration::from_millis(500))
+///     .compute_units_per_second(330)
+///     .build(http, Box::new(HttpRateLimitRetryPolicy));
+/// let provider = Provider::new(client);
+/// let block_number: U64 = provider.get_block_number().await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct RetryClient<T: JsonRpcClient> {
+    inner: T,
+    policy: Box<dyn RetryPolicy<T::Error>>,
+    /// Number of requests currently being processed by this client
+    requests_enqueued: AtomicU32,
+    max_retries: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    jitter: f64,
+    /// Available compute units per second, `None` if unlimited
+    compute_units_per_second: Option<u64>,
+}
+
+impl<T: JsonRpcClient> RetryClient<T> {
+    /// Creates a new `RetryClient` with the default configuration, see [`RetryClientBuilder`]
+    pub fn new(inner: T, policy: Box<dyn RetryPolicy<T::Error>>) -> Self {
+        RetryClientBuilder::default().build(inner, policy)
+    }
+
+    /// Returns a reference to the wrapped transport
+    pub fn inner(&self) -> &T {
+        &self.inner
+    }
+
+    /// Returns the backoff before the `retry`-th retry, ignoring any hints from the provider
+    fn backoff(&self, retry: u32) -> Duration {
+        let factor = 2u32.saturating_pow(retry.saturating_sub(1));
+        let backoff = self
+            .initial_backoff
+            .checked_mul(factor)
+            .unwrap_or(self.max_backoff)
+            .min(self.max_backoff);
+        if self.jitter > 0. {
+            backoff + backoff.mul_f64(self.jitter * random_fraction())
+        } else {
+            backoff
+        }
+    }
+
+    /// Awaits the request created by `f` until it succeeds, fails with an error the policy does
+    /// not retry, or the retries are exhausted.
+    async fn retry<F, Fut, O>(&self, method: &str, mut f: F) -> Result<O, RetryClientError>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<O, T::Error>>,
+    {
+        let guard = QueueGuard::new(&self.requests_enqueued);
+        let ahead_in_queue = guard.ahead_in_queue;
+
+        let mut retry = 0;
+        loop {
+            // the response is dropped at the end of the match, so that `O` and the inner
+            // error type are not held across the backoff
+            let (should_retry, backoff_hint, err): (bool, Option<Duration>, ProviderError) =
+                match f().await {
+                    Ok(res) => return Ok(res),
+                    Err(err) => {
+                        (self.policy.should_retry(&err), self.policy.backoff_hint(&err), err.into())
+                    }
+                };
+
+            if !should_retry {
+                return Err(RetryClientError::ProviderError(err))
+            }
+
+            retry += 1;
+            if retry > self.max_retries {
+                trace!(method = method, "request failed after {} retries", self.max_retries);
+                return Err(RetryClientError::RetriesExhausted {
+                    retries: self.max_retries,
+                    source: err,
+                })
+            }
+
+            let mut backoff = backoff_hint.unwrap_or_else(|| self.backoff(retry));
+            if let Some(compute_units_per_second) = self.compute_units_per_second {
+                let queued = self.requests_enqueued.load(Ordering::SeqCst) as u64;
+                backoff += Duration::from_secs(compute_unit_offset_in_secs(
+                    AVG_COST,
+                    compute_units_per_second,
+                    queued,
+                    ahead_in_queue,
+                ));
+            }
+
+            trace!(method = method, retry = retry, backoff = ?backoff, "retrying request: {}", err);
+            let _ = Delay::new(backoff).await;
+        }
+    }
+}
+
+/// Builder for a [`RetryClient`]
+#[derive(Debug, Clone, Copy)]
+pub struct RetryClientBuilder {
+    max_retries: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    jitter: f64,
+    compute_units_per_second: Option<u64>,
+}
+
+impl Default for RetryClientBuilder {
+    fn default() -> Self {
+        Self {
+            max_retries: 10,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(30),
+            jitter: 0.1,
+            compute_units_per_second: None,
+        }
+    }
+}
+
+impl RetryClientBuilder {
+    /// Sets how many times a request is retried before giving up (default: 10)
+    pub fn max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Sets the backoff before the first retry, which doubles with every retry
+    /// (default: 100ms)
+    pub fn initial_backoff(mut self, initial_backoff: Duration) -> Self {
+        self.initial_backoff = initial_backoff;
+        self
+    }
+
+    /// Sets the maximum backoff between two retries (default: 30s)
+    pub fn max_backoff(mut self, max_backoff: Duration) -> Self {
+        self.max_backoff = max_backoff;
+        self
+    }
+
+    /// Sets the fraction of the backoff which is randomly added on top of it, so that
+    /// requests failing at the same time do not all retry at the same time (default: 0.1)
+    ///
+    /// NOTE: this is clamped to `0.0..=1.0`
+    pub fn jitter(mut self, jitter: f64) -> Self {
+        self.jitter = jitter.clamp(0., 1.);
+        self
+    }
+
+    /// Sets the compute units per second available at the node provider, which is used to
+    /// delay retries of requests when many requests are queued up (default: unlimited)
+    pub fn compute_units_per_second(mut self, compute_units_per_second: u64) -> Self {
+        self.compute_units_per_second = Some(compute_units_per_second);
+        self
+    }
+
+    /// Creates the `RetryClient` wrapping the `inner` transport
+    pub fn build<T: JsonRpcClient>(
+        self,
+        inner: T,
+        policy: Box<dyn RetryPolicy<T::Error>>,
+    ) -> RetryClient<T> {
+        RetryClient {
+            inner,
+            policy,
+            requests_enqueued: AtomicU32::new(0),
+            max_retries: self.max_retries,
+            initial_backoff: self.initial_backoff,
+            max_backoff: self.max_backoff,
+            jitter: self.jitter,
+            compute_units_per_second: self.compute_units_per_second,
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+/// Error thrown by the [`RetryClient`]
+pub enum RetryClientError {
+    /// Thrown if the inner transport failed with an error which should not be retried
+    #[error(transparent)]
+    ProviderError(ProviderError),
+
+    /// Thrown if the request still failed after the maximum number of retries
+    #[error("request failed after {retries} retries: {source}")]
+    RetriesExhausted { retries: u32, source: ProviderError },
+
+    /// Thrown if the params could not be serialized
+    #[error(transparent)]
+    SerdeJson(#[from] serde_json::Error),
+}
+
+impl From<RetryClientError> for ProviderError {
+    fn from(src: RetryClientError) -> Self {
+        ProviderError::JsonRpcClientError(Box::new(src))
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl<T: JsonRpcClient> JsonRpcClient for RetryClient<T> {
+    type Error = RetryClientError;
+
+    async fn request<A, R>(&self, method: &str, params: A) -> Result<R, Self::Error>
+    where
+        A: Debug + Serialize + Send + Sync,
+        R: DeserializeOwned,
+    {
+        // The params are cached as a json value across retries. Zero-sized params are not
+        // serialized at all by the transports, so we keep them as `()` instead of `null`
+        let params =
+            if std::mem::size_of::<A>() == 0 { None } else { Some(serde_json::to_value(params)?) };
+
+        let params = &params;
+        self.retry(method, move || async move {
+            match params {
+                Some(params) => self.inner.request(method, params).await,
+                None => self.inner.request(method, ()).await,
+            }
+        })
+        .await
+    }
+
+    /// Sends the whole batch again if it failed with a transient error. Errors of individual
+    /// requests within the batch are not retried.
+    async fn request_batch(
+        &self,
+        requests: Vec<(String, Value)>,
+    ) -> Result<Vec<Result<Value, JsonRpcError>>, Self::Error> {
+        let requests = &requests;
+        self.retry("batch", move || self.inner.request_batch(requests.clone())).await
+    }
+
+    fn supports_pubsub(&self) -> bool {
+        self.inner.supports_pubsub()
+    }
+
+    fn subscribe_notifications(&self, id: U256) -> Option<Result<NotificationStream, Self::Error>> {
+        self.inner
+            .subscribe_notifications(id)
+            .map(|res| res.map_err(|err| RetryClientError::ProviderError(err.into())))
+    }
+
+    fn unsubscribe_notifications(&self, id: U256) -> Result<(), Self::Error> {
+        self.inner
+            .unsubscribe_notifications(id)
+            .map_err(|err| RetryClientError::ProviderError(err.into()))
+    }
+}
+
+/// Tracks the number of requests in flight for the lifetime of a request, even if the
+/// request future is dropped before completion.
+struct QueueGuard<'a> {
+    counter: &'a AtomicU32,
+    ahead_in_queue: u64,
+}
+
+impl<'a> QueueGuard<'a> {
+    fn new(counter: &'a AtomicU32) -> Self {
+        let ahead_in_queue = counter.fetch_add(1, Ordering::SeqCst) as u64;
+        Self { counter, ahead_in_queue }
+    }
+}
+
+impl Drop for QueueGuard<'_> {
+    fn drop(&mut self) {
+        self.counter.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Calculates the number of seconds a request has to wait for the compute unit budget, given
+/// the number of currently queued requests and the number of requests ahead of it.
+fn compute_unit_offset_in_secs(
+    avg_cost: u64,
+    compute_units_per_second: u64,
+    current_queued_requests: u64,
+    ahead_in_queue: u64,
+) -> u64 {
+    let request_capacity_per_second = (compute_units_per_second / avg_cost.max(1)).max(1);
+    if current_queued_requests > request_capacity_per_second {
+        current_queued_requests.min(ahead_in_queue) / request_capacity_per_second
+    } else {
+        0
+    }
+}
+
+/// Returns a random number in `0.0..1.0`, which is good enough for jittering the backoff.
+///
+/// Every `RandomState` is seeded differently, which saves us a dependency on `rand`.
+fn random_fraction() -> f64 {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u8(0);
+    (hasher.finish() >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+#[cfg(not(target_arch = "wasm32"))]
+mod tests {
+    use super::*;
+    use crate::{Middleware, MockError, MockProvider, Provider};
+    use ethers_core::types::U64;
+    use serde_json::json;
+
+    /// A transport which fails the first `failures` requests
+    #[derive(Debug)]
+    struct FlakyProvider {
+        failures: AtomicU32,
+        mock: MockProvider,
+    }
+
+    #[async_trait]
+    impl JsonRpcClient for FlakyProvider {
+        type Error = MockError;
+
+        async fn request<A, R>(&self, method: &str, params: A) -> Result<R, MockError>
+        where
+            A: Debug + Serialize + Send + Sync,
+            R: DeserializeOwned,
+        {
+            if self.failures.load(Ordering::SeqCst) > 0 {
+                self.failures.fetch_sub(1, Ordering::SeqCst);
+                return Err(MockError::EmptyResponses)
+            }
+            self.mock.request(method, params).await
+        }
+    }
+
+    #[derive(Debug)]
+    struct AlwaysRetry;
+
+    impl RetryPolicy<MockError> for AlwaysRetry {
+        fn should_retry(&self, _error: &MockError) -> bool {
+            true
+        }
+    }
+
+    #[derive(Debug)]
+    struct NeverRetry;
+
+    impl RetryPolicy<MockError> for NeverRetry {
+        fn should_retry(&self, _error: &MockError) -> bool {
+            false
+        }
+    }
+
+    fn flaky(failures: u32) -> (FlakyProvider, MockProvider) {
+        let mock = MockProvider::new();
+        (FlakyProvider { failures: AtomicU32::new(failures), mock: mock.clone() }, mock)
+    }
+
+    #[tokio::test]
+    async fn retries_until_success() {
+        let (flaky, mock) = flaky(3);
+        mock.push(U64::from(12)).unwrap();
+        let client = RetryClientBuilder::default()
+            .max_retries(3)
+            .initial_backoff(Duration::from_millis(1))
+            .build(flaky, Box::new(AlwaysRetry));
+        let provider = Provider::new(client);
+
+        let block = provider.get_block_number().await.unwrap();
+        assert_eq!(block.as_u64(), 12);
+        mock.assert_request("eth_blockNumber", ()).unwrap();
+        assert_eq!(provider.as_ref().requests_enqueued.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn gives_up_after_max_retries() {
+        let (flaky, mock) = flaky(3);
+        mock.push(U64::from(12)).unwrap();
+        let client = RetryClientBuilder::default()
+            .max_retries(2)
+            .initial_backoff(Duration::from_millis(1))
+            .build(flaky, Box::new(AlwaysRetry));
+
+        let err = client.request::<_, U64>("eth_blockNumber", ()).await.unwrap_err();
+        match err {
+            RetryClientError::RetriesExhausted { retries, .. } => assert_eq!(retries, 2),
+            err => panic!("expected exhausted retries, got {:?}", err),
+        }
+    }
+
+    #[tokio::test]
+    async fn does_not_retry_permanent_errors() {
+        let (flaky, mock) = flaky(1);
+        mock.push(U64::from(12)).unwrap();
+        let client = RetryClient::new(flaky, Box::new(NeverRetry));
+
+        let err = client.request::<_, U64>("eth_blockNumber", ()).await.unwrap_err();
+        assert!(matches!(err, RetryClientError::ProviderError(_)));
+    }
+
+    #[test]
+    fn detects_rate_limit_errors() {
+        let policy = HttpRateLimitRetryPolicy;
+        let infura: JsonRpcError = serde_json::from_value(json!({
+            "code": -32005,
+            "message": "daily request count exceeded, request rate limited",
+            "data": {"rate": {"allowed_rps": 1, "backoff_seconds": 30, "current_rps": 1.1}}
+        }))
+        .unwrap();
+        let infura = ClientError::JsonRpcError(infura);
+        assert!(policy.should_retry(&infura));
+        assert_eq!(policy.backoff_hint(&infura), Some(Duration::from_secs(30)));
+
+        let alchemy = ClientError::JsonRpcError(JsonRpcError {
+            code: 429,
+            message: "Your app has exceeded its compute units per second capacity.".to_string(),
+            data: None,
+        });
+        assert!(policy.should_retry(&alchemy));
+        assert_eq!(policy.backoff_hint(&alchemy), None);
+
+        let header = ClientError::JsonRpcError(JsonRpcError {
+            code: -32000,
+            message: "header not found".to_string(),
+            data: None,
+        });
+        assert!(policy.should_retry(&header));
+
+        let revert = ClientError::JsonRpcError(JsonRpcError {
+            code: 3,
+            message: "execution reverted".to_string(),
+            data: None,
+        });
+        assert!(!policy.should_retry(&revert));
+
+        let too_many = ClientError::HttpStatus {
+            status: reqwest::StatusCode::TOO_MANY_REQUESTS,
+            text: "Too Many Requests".to_string(),
+        };
+        assert!(policy.should_retry(&too_many));
+    }
+
+    #[test]
+    fn exponential_backoff() {
+        let (flaky, _) = flaky(0);
+        let client = RetryClientBuilder::default()
+            .initial_backoff(Duration::from_millis(100))
+            .max_backoff(Duration::from_secs(1))
+            .jitter(0.)
+            .build(flaky, Box::new(AlwaysRetry));
+        assert_eq!(client.backoff(1), Duration::from_millis(100));
+        assert_eq!(client.backoff(2), Duration::from_millis(200));
+        assert_eq!(client.backoff(4), Duration::from_millis(800));
+        assert_eq!(client.backoff(5), Duration::from_secs(1));
+        assert_eq!(client.backoff(64), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn compute_unit_offset() {
+        // 330 CU/s at 17 CU per request allows 19 requests per second
+        assert_eq!(compute_unit_offset_in_secs(17, 330, 10, 5), 0);
+        assert_eq!(compute_unit_offset_in_secs(17, 330, 100, 5), 0);
+        assert_eq!(compute_unit_offset_in_secs(17, 330, 100, 40), 2);
+        // budget smaller than a single request
+        assert_eq!(compute_unit_offset_in_secs(17, 1, 10, 5), 5);
+    }
+}
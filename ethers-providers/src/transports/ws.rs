@@ -1,7 +1,10 @@
 use crate::{
     provider::ProviderError,
-    transports::common::{JsonRpcError, Notification, Request, Response},
-    JsonRpcClient, PubsubClient,
+    transports::{
+        common::{BatchError, JsonRpcError, Notification, Request, Response},
+        reconnect::{Handled, Reconnect},
+    },
+    JsonRpcClient, NotificationStream, PubsubClient,
 };
 use ethers_core::types::U256;
 
@@ -13,7 +16,7 @@ use futures_util::{
 };
 use serde::{de::DeserializeOwned, Serialize};
 use std::{
-    collections::{btree_map::Entry, BTreeMap},
+    collections::{btree_map::Entry, BTreeMap, VecDeque},
     fmt::{self, Debug},
     sync::{
         atomic::{AtomicU64, Ordering},
@@ -22,6 +25,11 @@ use std::{
 };
 use thiserror::Error;
 
+#[cfg(not(target_arch = "wasm32"))]
+use futures_timer::Delay;
+#[cfg(target_arch = "wasm32")]
+use wasm_timer::Delay;
+
 if_wasm! {
     use wasm_bindgen::prelude::*;
     use wasm_bindgen_futures::spawn_local;
@@ -73,6 +81,8 @@ type Subscription = mpsc::UnboundedSender<serde_json::Value>;
 enum Instruction {
     /// JSON-RPC request
     Request { id: u64, request: String, sender: Pending },
+    /// JSON-RPC batch request, with the id and response channel of every request in the batch
+    BatchRequest { requests: Vec<(u64, Pending)>, request: String },
     /// Create a new subscription
     Subscribe { id: U256, sink: Subscription },
     /// Cancel an existing subscription
@@ -84,6 +94,8 @@ enum Instruction {
 enum Incoming {
     Notification(Notification<serde_json::Value>),
     Response(Response<serde_json::Value>),
+    Batch(Vec<Response<serde_json::Value>>),
+    BatchError(BatchError),
 }
 
 /// A JSON-RPC Client over Websockets.
@@ -144,20 +156,82 @@ impl Ws {
         Ok(Self::new(ws))
     }
 
-    /// Initializes a new WebSocket Client with authentication
+    /// Initializes a new WebSocket Client with authentication. [`Authorization::Jwt`] mints a
+    /// fresh token for the handshake, so it can be used with the Engine API.
     #[cfg(not(target_arch = "wasm32"))]
     pub async fn connect_with_auth(
         uri: impl AsRef<str> + Unpin,
         auth: Authorization,
     ) -> Result<Self, ClientError> {
-        let mut request: HttpRequest<()> =
-            HttpRequest::builder().method("GET").uri(Uri::from_str(uri.as_ref())?).body(())?;
+        Self::connect(auth_request(uri.as_ref(), &auth)?).await
+    }
 
-        let mut auth_value = http::HeaderValue::from_str(&auth.to_string())?;
-        auth_value.set_sensitive(true);
+    /// Initializes a new WebSocket Client which re-dials the node if the connection drops,
+    /// giving up after `reconnects` consecutive failed attempts.
+    ///
+    /// Requests which were in flight when the connection dropped are sent again and active
+    /// subscriptions are re-issued on the new connection. The subscription ids handed out by
+    /// this client stay the same across reconnects, so existing `SubscriptionStream`s keep
+    /// receiving notifications.
+    ///
+    /// ```no_run
+    /// # async fn foo() -> Result<(), Box<dyn std::error::Error>> {
+    /// use ethers_providers::{Middleware, Provider, StreamExt, Ws};
+    ///
+    /// let ws = Ws::connect_with_reconnects("wss://localhost:8545", 10).await?;
+    /// let provider = Provider::new(ws);
+    /// let mut blocks = provider.subscribe_blocks().await?;
+    /// while let Some(block) = blocks.next().await {
+    ///     println!("{:?}", block.hash);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(not(target_arch = "wasm32"))]
+    pub async fn connect_with_reconnects(
+        url: impl AsRef<str>,
+        reconnects: usize,
+    ) -> Result<Self, ClientError> {
+        Self::connect_reconnecting(url.as_ref().to_string(), None, reconnects).await
+    }
 
-        request.headers_mut().insert(http::header::AUTHORIZATION, auth_value);
-        Self::connect(request).await
+    /// Initializes a new WebSocket Client with authentication, which re-dials the node if the
+    /// connection drops. See [`Ws::connect_with_reconnects`].
+    #[cfg(not(target_arch = "wasm32"))]
+    pub async fn connect_with_auth_and_reconnects(
+        url: impl AsRef<str>,
+        auth: Authorization,
+        reconnects: usize,
+    ) -> Result<Self, ClientError> {
+        Self::connect_reconnecting(url.as_ref().to_string(), Some(auth), reconnects).await
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    async fn connect_reconnecting(
+        url: String,
+        auth: Option<Authorization>,
+        reconnects: usize,
+    ) -> Result<Self, ClientError> {
+        let connect = move || {
+            let url = url.clone();
+            let auth = auth.clone();
+            async move {
+                let (ws, _) = match auth {
+                    Some(auth) => connect_async(auth_request(&url, &auth)?).await?,
+                    None => connect_async(url.as_str()).await?,
+                };
+                Ok::<_, ClientError>(ws)
+            }
+        };
+        let ws = connect().await?;
+
+        let id = Arc::new(AtomicU64::new(0));
+        let (sink, stream) = mpsc::unbounded();
+        WsServer::new(ws, stream)
+            .with_reconnect(Reconnect::new(connect, reconnects, id.clone()))
+            .spawn();
+
+        Ok(Self { id, instructions: sink })
     }
 
     fn send(&self, msg: Instruction) -> Result<(), ClientError> {
@@ -197,6 +271,52 @@ impl JsonRpcClient for Ws {
         // parse it
         Ok(serde_json::from_value(res)?)
     }
+
+    async fn request_batch(
+        &self,
+        requests: Vec<(String, serde_json::Value)>,
+    ) -> Result<Vec<Result<serde_json::Value, JsonRpcError>>, ClientError> {
+        if requests.is_empty() {
+            return Ok(Vec::new())
+        }
+        let first_id = self.id.fetch_add(requests.len() as u64, Ordering::SeqCst);
+        let payload = requests
+            .iter()
+            .enumerate()
+            .map(|(idx, (method, params))| Request::new(first_id + idx as u64, method, params))
+            .collect::<Vec<_>>();
+
+        // one response channel per request, the server matches the responses by id
+        let (senders, receivers): (Vec<_>, Vec<_>) = (0..requests.len() as u64)
+            .map(|idx| {
+                let (sender, receiver) = oneshot::channel();
+                ((first_id + idx, sender), receiver)
+            })
+            .unzip();
+
+        self.send(Instruction::BatchRequest {
+            requests: senders,
+            request: serde_json::to_string(&payload)?,
+        })?;
+
+        let mut responses = Vec::with_capacity(receivers.len());
+        for receiver in receivers {
+            responses.push(receiver.await?);
+        }
+        Ok(responses)
+    }
+
+    fn supports_pubsub(&self) -> bool {
+        true
+    }
+
+    fn subscribe_notifications(&self, id: U256) -> Option<Result<NotificationStream, ClientError>> {
+        Some(PubsubClient::subscribe(self, id).map(|rx| -> NotificationStream { Box::pin(rx) }))
+    }
+
+    fn unsubscribe_notifications(&self, id: U256) -> Result<(), ClientError> {
+        PubsubClient::unsubscribe(self, id)
+    }
 }
 
 impl PubsubClient for Ws {
@@ -219,6 +339,11 @@ struct WsServer<S> {
 
     pending: BTreeMap<u64, Pending>,
     subscriptions: BTreeMap<U256, Subscription>,
+    /// The request ids of the batches without a response, in the order they were sent
+    batches: VecDeque<Vec<u64>>,
+
+    /// Set if the server re-dials the node when the connection drops
+    reconnect: Option<Reconnect<S, ClientError>>,
 }
 
 impl<S> WsServer<S>
@@ -234,9 +359,17 @@ where
             instructions: requests.fuse(),
             pending: BTreeMap::default(),
             subscriptions: BTreeMap::default(),
+            batches: VecDeque::default(),
+            reconnect: None,
         }
     }
 
+    /// Enables reconnecting to the node when the connection drops
+    fn with_reconnect(mut self, reconnect: Reconnect<S, ClientError>) -> Self {
+        self.reconnect = Some(reconnect);
+        self
+    }
+
     /// Returns whether the all work has been completed.
     ///
     /// If this method returns `true`, then the `instructions` channel has been closed and all
@@ -257,6 +390,13 @@ where
                     break
                 }
                 match self.tick().await {
+                    Err(err) if self.reconnect.is_some() && is_disconnect(&err) => {
+                        warn!("WS connection lost, reconnecting: {}", err);
+                        if let Err(err) = self.reconnect().await {
+                            error!("Could not reconnect: {}", err);
+                            break
+                        }
+                    }
                     Err(ClientError::UnexpectedClose) => {
                         error!("{}", ClientError::UnexpectedClose);
                         break
@@ -287,10 +427,49 @@ where
             warn!("Replacing a pending request with id {:?}", id);
         }
 
+        let request = match self.reconnect.as_mut() {
+            Some(reconnect) => reconnect.track_request(id, request)?,
+            None => request,
+        };
+
+        if let Err(e) = self.ws.send(Message::Text(request)).await {
+            error!("WS connection error: {:?}", e);
+            // the request is sent again once the connection is restored
+            if self.reconnect.is_none() {
+                self.pending.remove(&id);
+            }
+        }
+        Ok(())
+    }
+
+    // dispatch an RPC batch request
+    async fn service_batch_request(
+        &mut self,
+        requests: Vec<(u64, Pending)>,
+        request: String,
+    ) -> Result<(), ClientError> {
+        let ids = requests.iter().map(|(id, _)| *id).collect::<Vec<_>>();
+        for (id, sender) in requests {
+            if self.pending.insert(id, sender).is_some() {
+                warn!("Replacing a pending request with id {:?}", id);
+            }
+        }
+
+        if let (Some(reconnect), Some(first_id)) = (self.reconnect.as_mut(), ids.first()) {
+            reconnect.track_batch(*first_id, request.clone());
+        }
+
         if let Err(e) = self.ws.send(Message::Text(request)).await {
             error!("WS connection error: {:?}", e);
-            self.pending.remove(&id);
+            // the batch is sent again once the connection is restored
+            if self.reconnect.is_none() {
+                for id in ids {
+                    self.pending.remove(&id);
+                }
+                return Ok(())
+            }
         }
+        self.batches.push_back(ids);
         Ok(())
     }
 
@@ -316,6 +495,9 @@ where
             Instruction::Request { id, request, sender } => {
                 self.service_request(id, request, sender).await
             }
+            Instruction::BatchRequest { requests, request } => {
+                self.service_batch_request(requests, request).await
+            }
             Instruction::Subscribe { id, sink } => self.service_subscribe(id, sink).await,
             Instruction::Unsubscribe { id } => self.service_unsubscribe(id).await,
         }
@@ -331,15 +513,26 @@ where
         match serde_json::from_str::<Incoming>(&inner) {
             Err(err) => return Err(ClientError::JsonError(err)),
 
-            Ok(Incoming::Response(resp)) => {
-                if let Some(request) = self.pending.remove(&resp.id) {
-                    if !request.is_canceled() {
-                        request.send(resp.data.into_result()).map_err(to_client_error)?;
-                    }
+            Ok(Incoming::Response(resp)) => self.handle_response(resp)?,
+            Ok(Incoming::Batch(responses)) => {
+                if let Some(id) = responses.first().map(|resp| resp.id) {
+                    self.batches.retain(|ids| !ids.contains(&id));
+                }
+                for resp in responses {
+                    self.handle_response(resp)?;
                 }
             }
+            Ok(Incoming::BatchError(BatchError { error })) => self.reject_batch(error),
             Ok(Incoming::Notification(notification)) => {
-                let id = notification.params.subscription;
+                let id = match self.reconnect.as_ref() {
+                    Some(reconnect) => {
+                        match reconnect.client_id(notification.params.subscription) {
+                            Some(id) => id,
+                            None => return Ok(()),
+                        }
+                    }
+                    None => notification.params.subscription,
+                };
                 if let Entry::Occupied(stream) = self.subscriptions.entry(id) {
                     if let Err(err) = stream.get().unbounded_send(notification.params.result) {
                         if err.is_disconnected() {
@@ -354,6 +547,83 @@ where
         Ok(())
     }
 
+    fn handle_response(&mut self, resp: Response<serde_json::Value>) -> Result<(), ClientError> {
+        let resp = match self.reconnect.as_mut() {
+            Some(reconnect) => match reconnect.handle_response(resp)? {
+                Handled::Forward(resp) => resp,
+                Handled::Resubscribed => return Ok(()),
+                Handled::ResubscribeFailed(client_id) => {
+                    // end the stream instead of silently never yielding again
+                    self.subscriptions.remove(&client_id);
+                    return Ok(())
+                }
+            },
+            None => resp,
+        };
+
+        if let Some(request) = self.pending.remove(&resp.id) {
+            if !request.is_canceled() {
+                request.send(resp.data.into_result()).map_err(to_client_error)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Fails every request of the oldest batch without a response with the error the node
+    /// rejected a whole batch with
+    fn reject_batch(&mut self, error: JsonRpcError) {
+        while let Some(ids) = self.batches.pop_front() {
+            let senders = ids.iter().filter_map(|id| self.pending.remove(id)).collect::<Vec<_>>();
+            // every request of the batch was answered already
+            if senders.is_empty() {
+                continue
+            }
+            if let (Some(reconnect), Some(first_id)) = (self.reconnect.as_mut(), ids.first()) {
+                reconnect.reject_batch(*first_id);
+            }
+            for sender in senders {
+                let _ = sender.send(Err(error.clone()));
+            }
+            return
+        }
+        warn!("Received an error without a pending batch: {}", error);
+    }
+
+    /// Re-dials the node, then sends all in flight requests again and re-issues the active
+    /// subscriptions
+    async fn reconnect(&mut self) -> Result<(), ClientError> {
+        let reconnect = self.reconnect.as_mut().expect("reconnecting is enabled");
+        let mut attempt = 0;
+        'connect: loop {
+            if attempt > 0 {
+                let _ = Delay::new(Reconnect::<S, ClientError>::backoff(attempt)).await;
+            }
+            attempt += 1;
+
+            let ws = match reconnect.connect().await {
+                Ok(ws) => ws,
+                Err(err) if attempt < reconnect.max_attempts => {
+                    warn!("Reconnect attempt {} failed: {}", attempt, err);
+                    continue
+                }
+                Err(err) => return Err(err),
+            };
+            self.ws = ws.fuse();
+            debug!("reconnected after {} attempt(s)", attempt);
+
+            for message in reconnect.resubscribe()? {
+                if let Err(err) = self.ws.send(Message::Text(message)).await {
+                    if attempt < reconnect.max_attempts {
+                        warn!("Reconnect attempt {} failed: {}", attempt, err);
+                        continue 'connect
+                    }
+                    return Err(err.into())
+                }
+            }
+            return Ok(())
+        }
+    }
+
     #[cfg(target_arch = "wasm32")]
     async fn handle(&mut self, resp: Message) -> Result<(), ClientError> {
         match resp {
@@ -419,6 +689,34 @@ where
     }
 }
 
+/// Creates the handshake request for a websocket connection with authentication
+#[cfg(not(target_arch = "wasm32"))]
+fn auth_request(uri: &str, auth: &Authorization) -> Result<HttpRequest<()>, ClientError> {
+    let mut request: HttpRequest<()> =
+        HttpRequest::builder().method("GET").uri(Uri::from_str(uri)?).body(())?;
+
+    request.headers_mut().insert(http::header::AUTHORIZATION, auth.header_value()?);
+    Ok(request)
+}
+
+/// Returns true if the error means that the connection to the node was lost
+#[cfg(not(target_arch = "wasm32"))]
+fn is_disconnect(err: &ClientError) -> bool {
+    matches!(
+        err,
+        ClientError::UnexpectedClose | ClientError::WsClosed(_) | ClientError::TungsteniteError(_)
+    )
+}
+
+/// Returns true if the error means that the connection to the node was lost
+#[cfg(target_arch = "wasm32")]
+fn is_disconnect(err: &ClientError) -> bool {
+    matches!(
+        err,
+        ClientError::UnexpectedClose | ClientError::WsClosed | ClientError::TungsteniteError(_)
+    )
+}
+
 // TrySendError is private :(
 fn to_client_error<T: Debug>(err: T) -> ClientError {
     ClientError::ChannelError(format!("{:?}", err))
@@ -494,6 +792,7 @@ mod tests {
         types::{Block, TxHash, U256},
         utils::Ganache,
     };
+    use serde_json::json;
 
     #[tokio::test]
     async fn request() {
@@ -536,4 +835,100 @@ mod tests {
         let resp = WsServer::new(ws, stream).handle_text(malformed_data).await;
         assert!(resp.is_err(), "Deserialization should not fail silently");
     }
+
+    /// Accepts a connection, answers its `eth_subscribe` request with `sub_id` and sends one
+    /// notification for the subscription
+    async fn serve_subscription(
+        listener: &tokio::net::TcpListener,
+        sub_id: &str,
+        item: u64,
+    ) -> tokio_tungstenite::WebSocketStream<tokio::net::TcpStream> {
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+        let request: serde_json::Value = match ws.next().await.unwrap().unwrap() {
+            Message::Text(text) => serde_json::from_str(&text).unwrap(),
+            msg => panic!("unexpected message {:?}", msg),
+        };
+        assert_eq!(request["method"], "eth_subscribe");
+
+        let response = json!({"jsonrpc": "2.0", "id": request["id"], "result": sub_id});
+        ws.send(Message::Text(response.to_string())).await.unwrap();
+        let notification = json!({
+            "jsonrpc": "2.0",
+            "method": "eth_subscription",
+            "params": {"subscription": sub_id, "result": item}
+        });
+        ws.send(Message::Text(notification.to_string())).await.unwrap();
+        ws
+    }
+
+    #[tokio::test]
+    async fn resubscribes_after_reconnect() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let mut ws = serve_subscription(&listener, "0x1", 1).await;
+            ws.close(None).await.unwrap();
+            // the node assigns a new id to the re-issued subscription
+            let _ws = serve_subscription(&listener, "0x5", 2).await;
+            futures_util::future::pending::<()>().await;
+        });
+
+        let ws = Ws::connect_with_reconnects(url, 3).await.unwrap();
+        let mut stream = ws.subscribe(1).unwrap();
+        let sub_id: U256 = ws.request("eth_subscribe", ["newHeads"]).await.unwrap();
+        assert_eq!(sub_id, 1.into());
+
+        assert_eq!(stream.next().await.unwrap(), json!(1));
+        assert_eq!(stream.next().await.unwrap(), json!(2));
+    }
+
+    #[tokio::test]
+    async fn batch_request() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+
+            // answers the first batch in reverse order
+            let request: serde_json::Value = match ws.next().await.unwrap().unwrap() {
+                Message::Text(text) => serde_json::from_str(&text).unwrap(),
+                msg => panic!("unexpected message {:?}", msg),
+            };
+            let responses = request
+                .as_array()
+                .unwrap()
+                .iter()
+                .rev()
+                .map(|request| json!({"jsonrpc": "2.0", "id": request["id"], "result": request["method"]}))
+                .collect::<Vec<_>>();
+            ws.send(Message::Text(json!(responses).to_string())).await.unwrap();
+
+            // rejects the second batch as a whole
+            ws.next().await.unwrap().unwrap();
+            let response = json!({
+                "jsonrpc": "2.0",
+                "id": null,
+                "error": {"code": -32600, "message": "batches are not supported"}
+            });
+            ws.send(Message::Text(response.to_string())).await.unwrap();
+            futures_util::future::pending::<()>().await;
+        });
+
+        let ws = Ws::connect(url).await.unwrap();
+        let requests = vec![
+            ("eth_chainId".to_string(), json!([])),
+            ("eth_blockNumber".to_string(), json!([])),
+        ];
+        let responses = ws.request_batch(requests.clone()).await.unwrap();
+        assert_eq!(responses[0].as_ref().unwrap(), &json!("eth_chainId"));
+        assert_eq!(responses[1].as_ref().unwrap(), &json!("eth_blockNumber"));
+
+        let responses = ws.request_batch(requests).await.unwrap();
+        assert_eq!(responses.len(), 2);
+        for response in responses {
+            assert_eq!(response.unwrap_err().message, "batches are not supported");
+        }
+    }
 }
@@ -0,0 +1,78 @@
+//! Streams which use `eth_subscribe` if the transport supports it and poll a filter otherwise
+use crate::{JsonRpcClient, Provider, ProviderError};
+
+use ethers_core::types::U256;
+
+use futures_core::stream::Stream;
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+#[cfg(target_arch = "wasm32")]
+pub(crate) type BoxStream<'a, T> = Pin<Box<dyn Stream<Item = T> + 'a>>;
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) type BoxStream<'a, T> = Pin<Box<dyn Stream<Item = T> + Send + 'a>>;
+
+/// How a [`WatchStream`] receives its items
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchSource {
+    /// The notifications of the `eth_subscribe` subscription with this id
+    Subscription(U256),
+    /// Polling the filter with this id via `eth_getFilterChanges`
+    Filter(U256),
+}
+
+#[must_use = "streams do nothing unless polled"]
+/// Streams new blocks, pending transactions or logs, either from a subscription if the transport
+/// supports `eth_subscribe` or by polling a filter otherwise.
+///
+/// Created by [`Provider::stream_blocks`], [`Provider::stream_pending_txs`] and
+/// [`Provider::stream_logs`].
+pub struct WatchStream<'a, P: JsonRpcClient, R> {
+    source: WatchSource,
+    provider: &'a Provider<P>,
+    inner: BoxStream<'a, R>,
+}
+
+impl<'a, P: JsonRpcClient, R> WatchStream<'a, P, R> {
+    pub(crate) fn new(
+        source: WatchSource,
+        provider: &'a Provider<P>,
+        inner: BoxStream<'a, R>,
+    ) -> Self {
+        Self { source, provider, inner }
+    }
+
+    /// Returns whether the items are received from a subscription or by polling a filter
+    pub fn source(&self) -> WatchSource {
+        self.source
+    }
+
+    /// Cancels the subscription or uninstalls the filter on the node
+    pub async fn unsubscribe(self) -> Result<bool, ProviderError> {
+        let (method, id) = match self.source {
+            WatchSource::Subscription(id) => ("eth_unsubscribe", id),
+            WatchSource::Filter(id) => ("eth_uninstallFilter", id),
+        };
+        (*self.provider).as_ref().request(method, [id]).await.map_err(Into::into)
+    }
+}
+
+impl<'a, P: JsonRpcClient, R> Stream for WatchStream<'a, P, R> {
+    type Item = R;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.inner.as_mut().poll_next(cx)
+    }
+}
+
+impl<P: JsonRpcClient, R> Drop for WatchStream<'_, P, R> {
+    fn drop(&mut self) {
+        // stops forwarding the notifications, like `SubscriptionStream` the subscription stays
+        // active on the node unless `unsubscribe` is called
+        if let WatchSource::Subscription(id) = self.source {
+            let _ = (*self.provider).as_ref().unsubscribe_notifications(id);
+        }
+    }
+}